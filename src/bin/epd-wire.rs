@@ -0,0 +1,81 @@
+//! Converts an image into a frame in the wire format of a panel
+//!
+//! ```sh
+//! epd-wire <panel> <image.png|pbm|pgm> <frame.bin|frame.rs> [--threshold <0-255>] [--dither]
+//! ```
+//!
+//! A `.rs` output defines a static named after the file, to be loaded with
+//! `epd_waveshare_async::wire::WireFrame::expect`.
+use std::path::Path;
+use std::process::ExitCode;
+
+use epd_waveshare_async::convert::{self, Quantize};
+use epd_waveshare_async::wire::{Panel, PANELS};
+
+fn usage() -> ExitCode {
+    eprintln!(
+        "usage: epd-wire <panel> <image.png|pbm|pgm> <frame.bin|frame.rs> [--threshold <0-255>] [--dither]"
+    );
+    let panels: Vec<&str> = PANELS.iter().map(|panel| panel.id).collect();
+    eprintln!("panels: {}", panels.join(", "));
+    ExitCode::FAILURE
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut positional = Vec::new();
+    let mut quantize = Quantize::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dither" => quantize = Quantize::Dither,
+            "--threshold" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(threshold) => quantize = Quantize::Threshold(threshold),
+                None => return usage(),
+            },
+            _ => positional.push(arg),
+        }
+    }
+    let [panel, input, output] = positional[..] else {
+        return usage();
+    };
+    let Some(panel) = Panel::find(panel) else {
+        eprintln!("unknown panel {}", panel);
+        return usage();
+    };
+
+    match convert_file(panel, Path::new(input), Path::new(output), quantize) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn convert_file(
+    panel: &Panel,
+    input: &Path,
+    output: &Path,
+    quantize: Quantize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let image = convert::decode(&std::fs::read(input)?)?;
+    let buffer = convert::pack(&image, panel, quantize)?;
+    let frame = convert::encode_frame(panel, &buffer);
+
+    if output
+        .extension()
+        .is_some_and(|extension| extension == "rs")
+    {
+        let name = output
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("frame")
+            .to_uppercase()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        std::fs::write(output, convert::to_rust_source(&name, &frame))?;
+    } else {
+        std::fs::write(output, &frame)?;
+    }
+    Ok(())
+}
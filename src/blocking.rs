@@ -0,0 +1,363 @@
+//! Blocking API of the drivers, for applications without an executor
+//!
+//! [`WaveshareDisplay`] and [`WaveshareConstructor`] have the methods of the async
+//! [`WaveshareDisplay`](crate::traits::WaveshareDisplay) and
+//! [`WaveshareConstructor`](crate::traits::WaveshareConstructor) without `async`. They are
+//! implemented for every driver whose SPI device is a blocking [`SpiDevice`] wrapped in
+//! [`Blocking`], every call runs the async driver with [`block_on`].
+//! The command sequences are the same, so is the wire traffic.
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::blocking::{WaveshareConstructor, WaveshareDisplay};
+//! use epd_waveshare_async::{epd2in7b::Epd2in7b, executor::Blocking, PollingBusy};
+//!
+//! // `spi` is a blocking `embedded_hal::spi::SpiDevice`, `delay` a blocking `DelayNs`
+//! let busy = PollingBusy::new(busy_pin, Blocking(delay), 1_000);
+//! let mut epd = Epd2in7b::new(&mut spi, busy, dc, rst, None)?;
+//! epd.update_and_display_frame(&mut spi, display.buffer())?;
+//! epd.sleep(&mut spi)?;
+//! ```
+//!
+//! The busy pin has to be polled, see the constraints of the [`executor`](crate::executor).
+//! Import either these traits or the async ones, their methods have the same names. The rest of
+//! the async API stays available through [`block_on`] and [`Blocking::from_mut`].
+
+use core::fmt::{Debug, Display};
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice;
+
+use crate::executor::{block_on, Blocking};
+use crate::interface::{BusySignal, ResetControl};
+use crate::traits::{self, PartialAlignment, RefreshLut};
+
+/// The blocking counterpart of [`WaveshareDisplay`](crate::traits::WaveshareDisplay), see the
+/// [module documentation](self)
+pub trait WaveshareDisplay<SPI, BUSY, DC, RST>: Sized
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Error of the driver
+    type Error;
+    /// The Color Type used by the Display
+    type DisplayColor;
+
+    /// Let the device enter deep-sleep mode to save power
+    fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Wakes the device up from sleep
+    fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
+    fn set_background_color(&mut self, color: Self::DisplayColor);
+
+    /// Get current background color
+    fn background_color(&self) -> &Self::DisplayColor;
+
+    /// Get the width of the display
+    fn width(&self) -> u32;
+
+    /// Get the height of the display
+    fn height(&self) -> u32;
+
+    /// Transmit a full frame to the SRAM of the EPD
+    fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error>;
+
+    /// Transmits partial data to the SRAM of the EPD, (x,y) is the top left corner
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>;
+
+    /// Alignment the windows of [`update_partial_frame`](WaveshareDisplay::update_partial_frame)
+    /// need, see [`partial_alignment`](crate::traits::WaveshareDisplay::partial_alignment)
+    fn partial_alignment(&self) -> PartialAlignment;
+
+    /// Displays the frame data from SRAM
+    fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Provide a combined update&display and save some time (skipping a busy check in between)
+    fn update_and_display_frame(&mut self, spi: &mut SPI, buffer: &[u8])
+        -> Result<(), Self::Error>;
+
+    /// Clears the frame buffer on the EPD with the declared background color
+    fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Selects the look-up table of the next refreshes, see
+    /// [`set_lut`](crate::traits::WaveshareDisplay::set_lut)
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error>;
+
+    /// Wait until the display has stopped processing data
+    fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Checks the busy signal once, see [`is_busy`](crate::traits::WaveshareDisplay::is_busy)
+    fn is_busy(&mut self) -> bool;
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareDisplay<SPI, BUSY, DC, RST> for D
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: traits::WaveshareDisplay<Blocking<SPI>, BUSY, DC, RST>,
+{
+    type Error = D::Error;
+    type DisplayColor = D::DisplayColor;
+
+    fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::sleep(
+            self,
+            Blocking::from_mut(spi),
+        ))
+    }
+
+    fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::wake_up(
+            self,
+            Blocking::from_mut(spi),
+        ))
+    }
+
+    fn set_background_color(&mut self, color: Self::DisplayColor) {
+        traits::WaveshareDisplay::set_background_color(self, color)
+    }
+
+    fn background_color(&self) -> &Self::DisplayColor {
+        traits::WaveshareDisplay::background_color(self)
+    }
+
+    fn width(&self) -> u32 {
+        traits::WaveshareDisplay::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        traits::WaveshareDisplay::height(self)
+    }
+
+    fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::update_frame(
+            self,
+            Blocking::from_mut(spi),
+            buffer,
+        ))
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::update_partial_frame(
+            self,
+            Blocking::from_mut(spi),
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        ))
+    }
+
+    fn partial_alignment(&self) -> PartialAlignment {
+        traits::WaveshareDisplay::partial_alignment(self)
+    }
+
+    fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::display_frame(
+            self,
+            Blocking::from_mut(spi),
+        ))
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::update_and_display_frame(
+            self,
+            Blocking::from_mut(spi),
+            buffer,
+        ))
+    }
+
+    fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::clear_frame(
+            self,
+            Blocking::from_mut(spi),
+        ))
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::set_lut(
+            self,
+            Blocking::from_mut(spi),
+            refresh_rate,
+        ))
+    }
+
+    fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        block_on(traits::WaveshareDisplay::wait_until_idle(
+            self,
+            Blocking::from_mut(spi),
+        ))
+    }
+
+    fn is_busy(&mut self) -> bool {
+        traits::WaveshareDisplay::is_busy(self)
+    }
+}
+
+/// The blocking counterpart of [`WaveshareConstructor`](crate::traits::WaveshareConstructor)
+pub trait WaveshareConstructor<SPI, BUSY, DC, RST>: WaveshareDisplay<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Creates and initialises a new driver, see [`new`](crate::traits::WaveshareConstructor::new)
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error>;
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareConstructor<SPI, BUSY, DC, RST> for D
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: traits::WaveshareConstructor<Blocking<SPI>, BUSY, DC, RST>,
+{
+    fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        block_on(D::new(Blocking::from_mut(spi), busy, dc, rst, delay_us))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use crate::epd2in7b::{self, Epd2in7b};
+    use crate::executor::{block_on, Blocking};
+    use crate::test_utils::{blank_buffer, NoopPin, RecordingSpi};
+    use crate::PollingBusy;
+    use core::convert::Infallible;
+    use embedded_hal::spi::Operation;
+    use std::vec::Vec;
+
+    /// Blocking SPI device recording like [`RecordingSpi`]
+    #[derive(Default)]
+    struct BlockingSpi(RecordingSpi);
+
+    impl embedded_hal::spi::ErrorType for BlockingSpi {
+        type Error = Infallible;
+    }
+
+    impl embedded_hal::spi::SpiDevice for BlockingSpi {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            block_on(embedded_hal_async::spi::SpiDevice::transaction(
+                &mut self.0,
+                operations,
+            ))
+        }
+    }
+
+    struct IdlePin;
+
+    impl embedded_hal::digital::ErrorType for IdlePin {
+        type Error = Infallible;
+    }
+
+    impl embedded_hal::digital::InputPin for IdlePin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(false)
+        }
+    }
+
+    struct NoDelay;
+
+    impl embedded_hal::delay::DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    /// Bytes the async driver sends for the same calls
+    fn async_traffic(buffer: &[u8]) -> Vec<u8> {
+        use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+
+        let mut spi = RecordingSpi::default();
+        let busy = PollingBusy::new(IdlePin, Blocking(NoDelay), 1_000);
+        let mut epd = block_on(Epd2in7b::new(&mut spi, busy, NoopPin, NoopPin, Some(0))).unwrap();
+        block_on(epd.update_and_display_frame(&mut spi, buffer)).unwrap();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        spi.take()
+    }
+
+    #[test]
+    fn same_traffic_as_the_async_driver() {
+        use super::{WaveshareConstructor, WaveshareDisplay};
+
+        let buffer = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+
+        let mut spi = BlockingSpi::default();
+        let busy = PollingBusy::new(IdlePin, Blocking(NoDelay), 1_000);
+        let mut epd = Epd2in7b::new(&mut spi, busy, NoopPin, NoopPin, Some(0)).unwrap();
+        epd.update_and_display_frame(&mut spi, &buffer).unwrap();
+        epd.sleep(&mut spi).unwrap();
+
+        assert_eq!(spi.0.take(), async_traffic(&buffer));
+    }
+}
@@ -0,0 +1,439 @@
+//! Calibration of custom look-up tables for a single panel
+//!
+//! Panels vary from unit to unit, quick LUTs that look fine on one panel ghost on another.
+//! [`LutSweep`] shows a known alternating pattern with every candidate LUT and asks a scoring
+//! callback how good the result looks. The callback can wait for an operator to enter a score,
+//! or compute one itself, e.g. from a camera or by reading back the RAM of controllers that
+//! support it.
+//!
+//! The resulting [`SweepReport`] names the best candidate and converts to a few bytes, so
+//! production tooling can store the choice for every unit.
+//!
+//! ```rust, ignore
+//! let candidates = [slow_lut, medium_lut, fast_lut];
+//! let mut sweep = LutSweep::new(|candidate| read_score_from_uart(candidate));
+//! let report = sweep
+//!     .run_partial_lut_sweep(&mut epd, &mut spi, &candidates, CalibrationPattern::default(), &mut buffer)
+//!     .await?;
+//! eeprom.write(&report.to_bytes());
+//! ```
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::buffer_len;
+use crate::cancel::{CancelToken, Outcome};
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusySignal, ResetControl};
+use crate::traits::WaveshareDisplay;
+
+/// Most candidates a single sweep can compare
+pub const MAX_CANDIDATES: usize = 16;
+/// Highest score, higher scores returned by the callback are clamped
+pub const MAX_SCORE: u8 = 10;
+/// Number of times the pattern and its inverse are shown with every candidate
+pub const DEFAULT_CYCLES: u8 = 3;
+
+/// Displays that can load a look-up table chosen at runtime
+pub trait LoadLut<SPI, BUSY, DC, RST>:
+    WaveshareDisplay<SPI, BUSY, DC, RST, Error = ErrorKind<SPI, BUSY, DC, RST>>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Complete set of look-up tables of the controller
+    type Lut;
+
+    /// Uploads `lut`, it stays in use until the next `set_lut` or `wake_up`
+    async fn load_lut(&mut self, spi: &mut SPI, lut: &Self::Lut) -> Result<(), Self::Error>;
+}
+
+/// Pattern shown with every candidate, alternating with its inverse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationPattern {
+    /// Checkerboard with square cells of the given size in pixels
+    Checkerboard(u8),
+    /// Vertical stripes of the given width in pixels
+    Stripes(u8),
+}
+
+impl Default for CalibrationPattern {
+    fn default() -> Self {
+        CalibrationPattern::Checkerboard(8)
+    }
+}
+
+impl CalibrationPattern {
+    /// Whether the pixel at `x`, `y` is black in the non-inverted pattern
+    fn is_black(self, x: u32, y: u32) -> bool {
+        match self {
+            CalibrationPattern::Checkerboard(size) => {
+                let size = u32::from(size.max(1));
+                (x / size + y / size).is_multiple_of(2)
+            }
+            CalibrationPattern::Stripes(width) => (x / u32::from(width.max(1))).is_multiple_of(2),
+        }
+    }
+
+    /// Draws the pattern into a full frame `buffer` of a `width` x `height` display
+    pub fn draw(self, buffer: &mut [u8], width: u32, height: u32, inverted: bool) {
+        let row_len = (width as usize).div_ceil(8);
+        for (y, row) in buffer.chunks_mut(row_len).take(height as usize).enumerate() {
+            for (i, byte) in row.iter_mut().enumerate() {
+                *byte = Color::White.get_byte_value();
+                for bit in 0..8 {
+                    let x = (i * 8 + bit) as u32;
+                    if x < width && self.is_black(x, y as u32) != inverted {
+                        *byte &= !(0x80 >> bit);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Result of a [`LutSweep`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SweepReport {
+    count: u8,
+    best: u8,
+    scores: [u8; MAX_CANDIDATES],
+}
+
+impl SweepReport {
+    /// Version of the byte format written by [`to_bytes`](Self::to_bytes)
+    pub const FORMAT_VERSION: u8 = 1;
+    /// Length of the byte format
+    pub const SERIALIZED_LEN: usize = 3 + MAX_CANDIDATES;
+
+    /// Index of the candidate with the highest score, the first one on ties
+    pub fn best(&self) -> usize {
+        self.best as usize
+    }
+
+    /// Score of every candidate, in the order of the candidates
+    pub fn scores(&self) -> &[u8] {
+        &self.scores[..self.count as usize]
+    }
+
+    /// Converts the report to `[version, count, best, scores...]`, unused scores are zero
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut bytes = [0; Self::SERIALIZED_LEN];
+        bytes[0] = Self::FORMAT_VERSION;
+        bytes[1] = self.count;
+        bytes[2] = self.best;
+        bytes[3..].copy_from_slice(&self.scores);
+        bytes
+    }
+
+    /// Reads a report written by [`to_bytes`](Self::to_bytes), `None` if it isn't valid
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::SERIALIZED_LEN || bytes[0] != Self::FORMAT_VERSION {
+            return None;
+        }
+        let (count, best) = (bytes[1], bytes[2]);
+        if count == 0 || count as usize > MAX_CANDIDATES || best >= count {
+            return None;
+        }
+        let mut scores = [0; MAX_CANDIDATES];
+        scores.copy_from_slice(&bytes[3..]);
+        if scores.iter().any(|&score| score > MAX_SCORE) {
+            return None;
+        }
+        Some(SweepReport {
+            count,
+            best,
+            scores,
+        })
+    }
+}
+
+/// Compares candidate look-up tables with the help of a scoring callback
+///
+/// The callback gets the index of the candidate that was just shown and returns a score
+/// from 0 (unusable) to [`MAX_SCORE`].
+pub struct LutSweep<SCORE> {
+    score: SCORE,
+    cycles: u8,
+}
+
+impl<SCORE> LutSweep<SCORE>
+where
+    SCORE: FnMut(usize) -> u8,
+{
+    /// Creates a sweep showing the pattern [`DEFAULT_CYCLES`] times per candidate
+    pub fn new(score: SCORE) -> Self {
+        LutSweep {
+            score,
+            cycles: DEFAULT_CYCLES,
+        }
+    }
+
+    /// Changes how often the pattern and its inverse are shown with every candidate
+    ///
+    /// Ghosting usually gets worse with every quick refresh, more cycles make it easier to see.
+    pub fn with_cycles(mut self, cycles: u8) -> Self {
+        self.cycles = cycles.max(1);
+        self
+    }
+
+    /// Shows `pattern` with every candidate LUT and collects the scores
+    ///
+    /// `buffer` is used to draw the pattern and needs to hold a full frame. Returns
+    /// `ErrorKind::InvalidArgument` without touching the display if there are no or more than
+    /// [`MAX_CANDIDATES`] candidates, or if the buffer has the wrong size.
+    ///
+    /// The last candidate stays loaded, load the chosen one or call `set_lut` afterwards.
+    pub async fn run_partial_lut_sweep<EPD, SPI, BUSY, DC, RST>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        candidates: &[EPD::Lut],
+        pattern: CalibrationPattern,
+        buffer: &mut [u8],
+    ) -> Result<SweepReport, ErrorKind<SPI, BUSY, DC, RST>>
+    where
+        EPD: LoadLut<SPI, BUSY, DC, RST>,
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+    {
+        let report = self
+            .run_partial_lut_sweep_cancellable(
+                epd,
+                spi,
+                candidates,
+                pattern,
+                buffer,
+                &CancelToken::never(),
+            )
+            .await?;
+        match report {
+            Outcome::Done(report) => Ok(report),
+            Outcome::Cancelled { .. } => unreachable!("the sweep can't be cancelled"),
+        }
+    }
+
+    /// Like [`run_partial_lut_sweep`](Self::run_partial_lut_sweep), checking `cancel` before
+    /// every candidate
+    ///
+    /// A cancelled sweep loads the default look-up table again with `set_lut` and reports the
+    /// number of scored candidates, resume with the remaining ones.
+    pub async fn run_partial_lut_sweep_cancellable<EPD, SPI, BUSY, DC, RST>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        candidates: &[EPD::Lut],
+        pattern: CalibrationPattern,
+        buffer: &mut [u8],
+        cancel: &CancelToken<'_>,
+    ) -> Result<Outcome<SweepReport>, ErrorKind<SPI, BUSY, DC, RST>>
+    where
+        EPD: LoadLut<SPI, BUSY, DC, RST>,
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+    {
+        let (width, height) = (epd.width(), epd.height());
+        if candidates.is_empty()
+            || candidates.len() > MAX_CANDIDATES
+            || buffer.len() != buffer_len(width as usize, height as usize)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        let mut report = SweepReport {
+            count: candidates.len() as u8,
+            best: 0,
+            scores: [0; MAX_CANDIDATES],
+        };
+        for (i, lut) in candidates.iter().enumerate() {
+            if cancel.is_cancelled() {
+                if i > 0 {
+                    epd.set_lut(spi, None).await?;
+                }
+                return Ok(Outcome::Cancelled { completed: i });
+            }
+            epd.load_lut(spi, lut).await?;
+            for _ in 0..self.cycles {
+                for inverted in [false, true] {
+                    pattern.draw(buffer, width, height, inverted);
+                    epd.update_frame(spi, buffer).await?;
+                    epd.display_frame(spi).await?;
+                }
+            }
+
+            let score = (self.score)(i).min(MAX_SCORE);
+            report.scores[i] = score;
+            if score > report.scores[report.best as usize] {
+                report.best = i as u8;
+            }
+        }
+        Ok(Outcome::Done(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in7b::{self, CustomLut, Epd2in7b};
+    use crate::test_utils::*;
+    use crate::traits::WaveshareConstructor;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn pattern_and_inverse() {
+        let mut buffer = [0u8; 2 * 4];
+        CalibrationPattern::Checkerboard(2).draw(&mut buffer, 16, 4, false);
+        assert_eq!(buffer, [0x33, 0x33, 0x33, 0x33, 0xCC, 0xCC, 0xCC, 0xCC]);
+        CalibrationPattern::Stripes(4).draw(&mut buffer, 16, 4, true);
+        assert_eq!(buffer, [0xF0; 8]);
+    }
+
+    #[test]
+    fn report_roundtrip() {
+        let mut scores = [0; MAX_CANDIDATES];
+        scores[..3].copy_from_slice(&[3, 8, 8]);
+        let report = SweepReport {
+            count: 3,
+            best: 1,
+            scores,
+        };
+        let bytes = report.to_bytes();
+        assert_eq!(bytes[..6], [SweepReport::FORMAT_VERSION, 3, 1, 3, 8, 8]);
+        assert_eq!(SweepReport::from_bytes(&bytes), Some(report));
+
+        let mut corrupted = bytes;
+        corrupted[2] = 3;
+        assert_eq!(SweepReport::from_bytes(&corrupted), None);
+        assert_eq!(SweepReport::from_bytes(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn sweep_scores_every_candidate() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let mut buffer = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+        let candidates: [CustomLut; 3] = core::array::from_fn(|i| {
+            let mut lut = CustomLut::FULL;
+            lut.vcom[..4].copy_from_slice(&[0xC0, 0xDE, 0x00, i as u8]);
+            lut
+        });
+        spi.take();
+
+        let mut shown = [0; 3];
+        let mut sweep = LutSweep::new(|i| {
+            shown[i] += 1;
+            [4, 12, 7][i]
+        })
+        .with_cycles(2);
+        let report = block_on(sweep.run_partial_lut_sweep(
+            &mut epd,
+            &mut spi,
+            &candidates,
+            CalibrationPattern::Stripes(8),
+            &mut buffer,
+        ))
+        .unwrap();
+
+        assert_eq!(shown, [1, 1, 1]);
+        assert_eq!(report.scores(), [4, MAX_SCORE, 7]);
+        assert_eq!(report.best(), 1);
+        let written = spi.take();
+        for i in 0..3 {
+            assert!(contains(&written, &[0xC0, 0xDE, 0x00, i]));
+        }
+        assert!(contains(&written, &[0x00, 0xFF, 0x00, 0xFF]));
+    }
+
+    #[test]
+    fn cancelled_sweep_restores_the_lut() {
+        let candidates: [CustomLut; 3] = core::array::from_fn(|i| {
+            let mut lut = CustomLut::FULL;
+            lut.vcom[..4].copy_from_slice(&[0xC0, 0xDE, 0x00, i as u8]);
+            lut
+        });
+        for checkpoint in 0..candidates.len() {
+            let mut spi = RecordingSpi::default();
+            let mut epd =
+                block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+            let mut buffer = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+            spi.take();
+
+            let flag = AtomicBool::new(checkpoint == 0);
+            let mut sweep = LutSweep::new(|i| {
+                flag.store(i + 1 == checkpoint, Ordering::Relaxed);
+                5
+            })
+            .with_cycles(1);
+            let outcome = block_on(sweep.run_partial_lut_sweep_cancellable(
+                &mut epd,
+                &mut spi,
+                &candidates,
+                CalibrationPattern::default(),
+                &mut buffer,
+                &CancelToken::new(&flag),
+            ))
+            .unwrap();
+
+            assert_eq!(
+                outcome,
+                Outcome::Cancelled {
+                    completed: checkpoint
+                }
+            );
+            let written = spi.take();
+            for i in 0..candidates.len() {
+                let loaded = contains(&written, &[0xC0, 0xDE, 0x00, i as u8]);
+                assert_eq!(loaded, i < checkpoint);
+            }
+            // nothing was changed before the first candidate
+            assert_eq!(contains(&written, &CustomLut::FULL.vcom), checkpoint > 0);
+        }
+    }
+
+    #[test]
+    fn sweep_rejects_invalid_arguments() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let mut buffer = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+        spi.take();
+
+        let mut sweep = LutSweep::new(|_| panic!("nothing was shown"));
+        let pattern = CalibrationPattern::default();
+        assert!(matches!(
+            block_on(sweep.run_partial_lut_sweep(&mut epd, &mut spi, &[], pattern, &mut buffer)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(matches!(
+            block_on(sweep.run_partial_lut_sweep(
+                &mut epd,
+                &mut spi,
+                &[CustomLut::FULL],
+                pattern,
+                &mut buffer[1..]
+            )),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+    }
+}
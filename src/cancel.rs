@@ -0,0 +1,179 @@
+//! Cooperative cancellation of long running routines
+//!
+//! Multi-pass refreshes and calibration sweeps can take minutes on big panels. Their
+//! `_cancellable` variants take a [`CancelToken`] and check it at safe checkpoints, between
+//! two refresh cycles or two candidates, never while data is transmitted. A cancelled routine
+//! finishes the current step, restores the configuration it changed and returns
+//! [`Outcome::Cancelled`] with the number of completed steps, so it can be resumed later.
+//!
+//! ```rust, ignore
+//! static STOP: AtomicBool = AtomicBool::new(false);
+//!
+//! // set from the button interrupt or the battery monitor
+//! STOP.store(true, Ordering::Relaxed);
+//!
+//! match epd.display_frame_repeated_cancellable(&mut spi, 3, &CancelToken::new(&STOP)).await? {
+//!     Outcome::Done(()) => {}
+//!     Outcome::Cancelled { completed } => remaining = 3 - completed,
+//! }
+//! ```
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Tells a long running routine to stop at its next checkpoint
+///
+/// Wraps a flag that can be set from anywhere, e.g. an interrupt handler. The flag is only
+/// loaded and stored, which works on targets without compare-and-swap too.
+#[derive(Debug, Clone, Copy)]
+pub struct CancelToken<'a> {
+    flag: Option<&'a AtomicBool>,
+}
+
+impl<'a> CancelToken<'a> {
+    /// Token that cancels once `flag` is set
+    pub const fn new(flag: &'a AtomicBool) -> Self {
+        CancelToken { flag: Some(flag) }
+    }
+
+    /// Token that never cancels
+    pub const fn never() -> Self {
+        CancelToken { flag: None }
+    }
+
+    /// Whether the routine should stop at the next checkpoint
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
+
+/// Result of a routine that can be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome<T> {
+    /// The routine ran to the end
+    Done(T),
+    /// The routine stopped at a checkpoint after `completed` steps
+    Cancelled {
+        /// Number of steps that were completed, e.g. refresh passes or candidates
+        completed: usize,
+    },
+}
+
+impl<T> Outcome<T> {
+    /// Whether the routine was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Outcome::Cancelled { .. })
+    }
+
+    /// The result of the routine, `None` if it was cancelled
+    pub fn done(self) -> Option<T> {
+        match self {
+            Outcome::Done(value) => Some(value),
+            Outcome::Cancelled { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9bc::{command::Command, Epd2in9bc};
+    use crate::interface::BusySignal;
+    use crate::test_utils::*;
+    use crate::traits::{WaveshareConstructor, WaveshareThreeColorDisplay};
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /// Busy signal that sets the flag once it was waited for `after` times
+    struct CancellingBusy<'a> {
+        flag: &'a AtomicBool,
+        after: &'a Cell<u32>,
+    }
+
+    impl BusySignal for CancellingBusy<'_> {
+        type Error = Infallible;
+
+        async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+            match self.after.get() {
+                0 => {}
+                1 => {
+                    self.after.set(0);
+                    self.flag.store(true, Ordering::Relaxed);
+                }
+                n => self.after.set(n - 1),
+            }
+            Ok(())
+        }
+
+        fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn never_cancels() {
+        assert!(!CancelToken::never().is_cancelled());
+        let flag = AtomicBool::new(false);
+        let token = CancelToken::new(&flag);
+        assert!(!token.is_cancelled());
+        flag.store(true, Ordering::Relaxed);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn repeated_refresh_stops_between_passes() {
+        let refresh = Command::DisplayRefresh as u8;
+        for passes in 0..3 {
+            let flag = AtomicBool::new(false);
+            let after = Cell::new(0);
+            let busy = CancellingBusy {
+                flag: &flag,
+                after: &after,
+            };
+            let mut spi = RecordingSpi::default();
+            let mut epd =
+                block_on(Epd2in9bc::new(&mut spi, busy, NoopPin, NoopPin, Some(0))).unwrap();
+            spi.take();
+
+            // every pass waits for the busy line once, in display_frame
+            after.set(passes);
+            flag.store(passes == 0, Ordering::Relaxed);
+            let outcome = block_on(epd.display_frame_repeated_cancellable(
+                &mut spi,
+                3,
+                &CancelToken::new(&flag),
+            ))
+            .unwrap();
+
+            assert_eq!(
+                outcome,
+                Outcome::Cancelled {
+                    completed: passes as usize
+                }
+            );
+            let written = spi.take();
+            assert_eq!(
+                written.iter().filter(|&&byte| byte == refresh).count(),
+                passes as usize
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_refresh_without_cancellation() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        let outcome =
+            block_on(epd.display_frame_repeated_cancellable(&mut spi, 2, &CancelToken::never()))
+                .unwrap();
+        assert_eq!(outcome, Outcome::Done(()));
+        assert_eq!(spi.take(), [Command::DisplayRefresh as u8; 2]);
+    }
+}
@@ -86,6 +86,13 @@ pub trait ColorType {
     /// * .1 are the bits used to set the color in the byte (eg: 0x80 in BiColor)
     ///      this is u16 because we set 2 bytes in case of split buffer
     fn bitmask(&self, bwrbit: bool, pos: u32) -> (u8, u16);
+
+    /// Decode the color of the pixel at position `pos` in the line
+    ///
+    /// `bits` holds the byte containing the pixel, in the same layout as the bits returned by
+    /// [`bitmask`](ColorType::bitmask): the chromatic byte of a split buffer goes into the
+    /// upper 8 bits.
+    fn from_bits(bits: u16, pos: u32) -> Self;
 }
 
 impl ColorType for Color {
@@ -98,6 +105,14 @@ impl ColorType for Color {
             Color::White => (!bit, bit as u16),
         }
     }
+
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        if bits & 0x80 >> (pos % 8) != 0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
 }
 
 impl ColorType for TriColor {
@@ -105,18 +120,18 @@ impl ColorType for TriColor {
     const BUFFER_COUNT: usize = 2;
     fn bitmask(&self, bwrbit: bool, pos: u32) -> (u8, u16) {
         let bit = 0x80 >> (pos % 8);
-        match self {
-            TriColor::Black => (!bit, 0u16),
-            TriColor::White => (!bit, bit as u16),
-            TriColor::Chromatic => (
-                !bit,
-                if bwrbit {
-                    (bit as u16) << 8
-                } else {
-                    (bit as u16) << 8 | bit as u16
-                },
-            ),
-        }
+        let (black, chromatic) = self.to_byte_pair();
+        // without BWRBIT the chromatic pixels are white in the black/white buffer
+        let black = if bwrbit { black } else { black | chromatic };
+        (
+            !bit,
+            (u16::from(chromatic * bit) << 8) | u16::from(black * bit),
+        )
+    }
+
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let bit = 0x80 >> (pos % 8);
+        TriColor::from_byte_pair(bits as u8 & bit, (bits >> 8) as u8 & bit)
     }
 }
 
@@ -124,10 +139,33 @@ impl ColorType for OctColor {
     const BITS_PER_PIXEL_PER_BUFFER: usize = 4;
     const BUFFER_COUNT: usize = 1;
     fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
-        let mask = !(0xF0 >> (pos % 2));
+        let mask = !(0xF0 >> (pos % 2 * 4));
         let bits = self.get_nibble() as u16;
         (mask, if pos % 2 == 1 { bits } else { bits << 4 })
     }
+
+    /// Nibbles outside of the palette decode as white
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let nibble = if pos % 2 == 1 { bits } else { bits >> 4 };
+        OctColor::from_nibble(nibble as u8 & 0x0F).unwrap_or(OctColor::White)
+    }
+}
+
+/// Four gray levels packed with two bits per pixel, as described in [`crate::gray`]
+#[cfg(feature = "graphics")]
+impl ColorType for embedded_graphics_core::pixelcolor::Gray2 {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        use embedded_graphics_core::pixelcolor::GrayColor;
+        let shift = 6 - pos % 4 * 2;
+        (!(0b11 << shift), u16::from(self.luma()) << shift)
+    }
+
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let shift = 6 - pos % 4 * 2;
+        Self::new((bits >> shift) as u8 & 0b11)
+    }
 }
 
 #[cfg(feature = "graphics")]
@@ -198,16 +236,16 @@ impl PixelColor for OctColor {
 
 impl OctColor {
     /// Gets the Nibble representation of the Color as needed by the display
-    pub fn get_nibble(self) -> u8 {
+    pub const fn get_nibble(self) -> u8 {
         self as u8
     }
     /// Converts two colors into a single byte for the Display
-    pub fn colors_byte(a: OctColor, b: OctColor) -> u8 {
+    pub const fn colors_byte(a: OctColor, b: OctColor) -> u8 {
         a.get_nibble() << 4 | b.get_nibble()
     }
 
     ///Take the nibble (lower 4 bits) and convert to an OctColor if possible
-    pub fn from_nibble(nibble: u8) -> Result<OctColor, OutOfColorRangeParseError> {
+    pub const fn from_nibble(nibble: u8) -> Result<OctColor, OutOfColorRangeParseError> {
         match nibble & 0xf {
             0x00 => Ok(OctColor::Black),
             0x01 => Ok(OctColor::White),
@@ -227,7 +265,7 @@ impl OctColor {
         Ok((high, low))
     }
     /// Converts to limited range of RGB values.
-    pub fn rgb(self) -> (u8, u8, u8) {
+    pub const fn rgb(self) -> (u8, u8, u8) {
         match self {
             OctColor::White => (0xff, 0xff, 0xff),
             OctColor::Black => (0x00, 0x00, 0x00),
@@ -244,7 +282,7 @@ impl OctColor {
 
 impl Color {
     /// Get the color encoding of the color for one bit
-    pub fn get_bit_value(self) -> u8 {
+    pub const fn get_bit_value(self) -> u8 {
         match self {
             Color::White => 1u8,
             Color::Black => 0u8,
@@ -252,7 +290,7 @@ impl Color {
     }
 
     /// Gets a full byte of black or white pixels
-    pub fn get_byte_value(self) -> u8 {
+    pub const fn get_byte_value(self) -> u8 {
         match self {
             Color::White => 0xff,
             Color::Black => 0x00,
@@ -274,7 +312,7 @@ impl Color {
     /// Returns the inverse of the given color.
     ///
     /// Black returns White and White returns Black
-    pub fn inverse(self) -> Color {
+    pub const fn inverse(self) -> Color {
         match self {
             Color::White => Color::Black,
             Color::Black => Color::White,
@@ -335,7 +373,7 @@ impl From<Color> for embedded_graphics_core::pixelcolor::Rgb888 {
 
 impl TriColor {
     /// Get the color encoding of the color for one bit
-    pub fn get_bit_value(self) -> u8 {
+    pub const fn get_bit_value(self) -> u8 {
         match self {
             TriColor::White => 1u8,
             TriColor::Black | TriColor::Chromatic => 0u8,
@@ -343,12 +381,39 @@ impl TriColor {
     }
 
     /// Gets a full byte of black or white pixels
-    pub fn get_byte_value(self) -> u8 {
+    pub const fn get_byte_value(self) -> u8 {
         match self {
             TriColor::White => 0xff,
             TriColor::Black | TriColor::Chromatic => 0x00,
         }
     }
+
+    /// Encodes the color as `(black_channel_bit, chromatic_channel_bit)`
+    ///
+    /// Uses the polarity of the buffers of a tri-color `Display`: a set bit in the black/white
+    /// channel is white, a set bit in the chromatic channel is chromatic. Chromatic pixels are
+    /// black in the black/white channel. Drivers of panels with an inverted chromatic RAM flip
+    /// the chromatic channel when sending it.
+    pub const fn to_byte_pair(self) -> (u8, u8) {
+        match self {
+            TriColor::Black => (0, 0),
+            TriColor::White => (1, 0),
+            TriColor::Chromatic => (0, 1),
+        }
+    }
+
+    /// Decodes a pixel from the bits of both channels, see [`to_byte_pair`](Self::to_byte_pair)
+    ///
+    /// Any non-zero value counts as a set bit, a set chromatic bit takes precedence.
+    pub const fn from_byte_pair(black: u8, chromatic: u8) -> TriColor {
+        if chromatic != 0 {
+            TriColor::Chromatic
+        } else if black != 0 {
+            TriColor::White
+        } else {
+            TriColor::Black
+        }
+    }
 }
 
 #[cfg(feature = "graphics")]
@@ -396,6 +461,21 @@ impl From<TriColor> for embedded_graphics_core::pixelcolor::Rgb888 {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn gray2_bits() {
+        use embedded_graphics_core::pixelcolor::{Gray2, GrayColor};
+
+        assert_eq!(
+            Gray2::new(0b10).bitmask(false, 1),
+            (0b1100_1111, 0b0010_0000)
+        );
+        assert_eq!(Gray2::WHITE.bitmask(false, 7), (0b1111_1100, 0b0000_0011));
+        for pos in 0..4 {
+            assert_eq!(Gray2::from_bits(0b0001_1011, pos), Gray2::new(pos as u8));
+        }
+    }
+
     #[test]
     fn from_u8() {
         assert_eq!(Color::Black, Color::from(0u8));
@@ -433,4 +513,51 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn const_accessors() {
+        const WHITE: u8 = Color::White.get_byte_value();
+        const INVERSE: Color = Color::White.inverse();
+        const CHROMATIC_BIT: u8 = TriColor::Chromatic.get_bit_value();
+        const RED_GREEN: u8 = OctColor::colors_byte(OctColor::Red, OctColor::Green);
+
+        assert_eq!(WHITE, 0xff);
+        assert_eq!(INVERSE, Color::Black);
+        assert_eq!(CHROMATIC_BIT, 0);
+        assert_eq!(RED_GREEN, 0x42);
+    }
+
+    #[test]
+    fn tricolor_byte_pair() {
+        for color in [TriColor::Black, TriColor::White, TriColor::Chromatic] {
+            let (black, chromatic) = color.to_byte_pair();
+            assert_eq!(TriColor::from_byte_pair(black, chromatic), color);
+        }
+        assert_eq!(TriColor::from_byte_pair(0xff, 0x80), TriColor::Chromatic);
+        assert_eq!(TriColor::Chromatic.bitmask(true, 1), (0xbf, 0x4000));
+        assert_eq!(TriColor::Chromatic.bitmask(false, 1), (0xbf, 0x4040));
+        assert_eq!(TriColor::White.bitmask(true, 7), (0xfe, 0x0001));
+    }
+
+    #[test]
+    fn bits_roundtrip() {
+        for pos in 0..8 {
+            for color in [Color::Black, Color::White] {
+                let (mask, bits) = color.bitmask(false, pos);
+                assert_eq!(Color::from_bits(bits | u16::from(mask & 0x55), pos), color);
+            }
+            for bwrbit in [false, true] {
+                for color in [TriColor::Black, TriColor::White, TriColor::Chromatic] {
+                    let (_, bits) = color.bitmask(bwrbit, pos);
+                    assert_eq!(TriColor::from_bits(bits, pos), color);
+                }
+            }
+            let (mask, bits) = OctColor::Orange.bitmask(false, pos);
+            assert_eq!(
+                OctColor::from_bits(bits | u16::from(mask & 0x77), pos),
+                OctColor::Orange
+            );
+        }
+        assert_eq!(OctColor::from_bits(0x0F, 1), OctColor::White);
+    }
 }
@@ -0,0 +1,541 @@
+//! The blocking API of epd-waveshare 0.5 on top of the async drivers
+//!
+//! Code written for epd-waveshare 0.5 passes the chip select pin to the constructor and a
+//! blocking SPI bus and delay to every call. The wrappers here keep that shape, so such code
+//! can move to this crate first and to the async API later, one call at a time:
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::compat::Epd2in9bc;
+//!
+//! let mut epd = Epd2in9bc::new(&mut spi, cs, busy, dc, rst, &mut delay, None)?;
+//! epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//! epd.sleep(&mut spi, &mut delay)?;
+//! ```
+//!
+//! Every call wraps the bus, the chip select pin and the delay into an `ExclusiveDevice` of
+//! `embedded-hal-bus` and runs the async driver with [`block_on`]. The busy pin only needs
+//! [`InputPin`], it is polled with the delay in between. The wire traffic is the same as that
+//! of the async driver.
+//!
+//! The old methods are deprecated, each names its replacement in [`WaveshareDisplay`]. The
+//! new API takes an [`SpiDevice`](embedded_hal_async::spi::SpiDevice) that owns the chip
+//! select, wrap a blocking one in [`Blocking`](crate::executor::Blocking).
+//!
+//! `embedded-hal-bus` always builds its atomic bus sharing, which uses compare-and-swap. On
+//! targets without it, like `thumbv6m-none-eabi`, the application has to enable the
+//! `critical-section` or `unsafe-assume-single-core` feature of `portable-atomic` for this
+//! module.
+
+use core::convert::Infallible;
+use core::fmt::{Debug, Display};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::{Operation, SpiBus};
+use embedded_hal_async::spi::SpiDevice;
+use embedded_hal_bus::spi::{DeviceError, ExclusiveDevice};
+
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::executor::{block_on, Blocking};
+use crate::interface::{BusySignal, BusyWait, ResetControl};
+use crate::traits::{RefreshLut, WaveshareConstructor, WaveshareDisplay};
+
+/// Error of the old API, the error of the SPI bus or chip select and a summary of the rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// Error of the SPI bus or the chip select pin
+    Spi(E),
+    /// Error of the busy, DC or reset pin
+    Pin,
+    /// Any other error of the driver, like a buffer of the wrong size
+    Driver,
+}
+
+impl<E> Error<E> {
+    fn from_kind<SPI, BUSY, DC, RST>(kind: ErrorKind<SPI, BUSY, DC, RST>) -> Self
+    where
+        SPI: SpiDevice<Error = E>,
+        SPI::Error: Copy,
+        BUSY: BusySignal,
+        DC: OutputPin,
+        DC::Error: Copy,
+        RST: ResetControl,
+        RST::Error: Copy,
+    {
+        match kind {
+            ErrorKind::SpiError(err) => Error::Spi(err),
+            ErrorKind::BusyError(_) | ErrorKind::DcError(_) | ErrorKind::RstError(_) => Error::Pin,
+            _ => Error::Driver,
+        }
+    }
+}
+
+impl<E: Display> Display for Error<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Spi(err) => Display::fmt(err, f),
+            Error::Pin => write!(f, "A pin of the display failed"),
+            Error::Driver => write!(f, "The driver failed"),
+        }
+    }
+}
+
+/// Busy pin polled with the delay of the SPI device
+pub struct PolledBusy<P>(P);
+
+impl<P> BusySignal for PolledBusy<P>
+where
+    P: InputPin,
+    P::Error: Copy + Debug + Display,
+{
+    type Error = P::Error;
+
+    async fn wait_idle(&mut self, is_busy_low: bool) -> Result<(), Self::Error> {
+        // only used by the constructor, before polling is switched on
+        while self.is_busy(is_busy_low) {}
+        Ok(())
+    }
+
+    fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        (is_busy_low && self.0.is_low().unwrap_or(false))
+            || (!is_busy_low && self.0.is_high().unwrap_or(false))
+    }
+}
+
+/// SPI device of a driver between two calls, nothing is sent through it
+pub struct Detached;
+
+impl embedded_hal::spi::ErrorType for Detached {
+    type Error = Infallible;
+}
+
+impl SpiDevice for Detached {
+    async fn transaction(
+        &mut self,
+        _operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Error of the old API for a bus and chip select pin
+type BusError<BUS, CS> = Error<
+    DeviceError<
+        <BUS as embedded_hal::spi::ErrorType>::Error,
+        <CS as embedded_hal::digital::ErrorType>::Error,
+    >,
+>;
+
+/// The SPI device of a driver during one call
+type Device<'a, BUS, CS, DELAY> = Blocking<ExclusiveDevice<&'a mut BUS, &'a mut CS, &'a mut DELAY>>;
+
+fn device<'a, BUS, CS, DELAY>(
+    spi: &'a mut BUS,
+    cs: &'a mut CS,
+    delay: &'a mut DELAY,
+) -> Result<Device<'a, BUS, CS, DELAY>, BusError<BUS, CS>>
+where
+    BUS: SpiBus,
+    CS: OutputPin,
+    DELAY: DelayNs,
+{
+    ExclusiveDevice::new(spi, cs, delay)
+        .map(Blocking)
+        .map_err(|err| Error::Spi(DeviceError::Cs(err)))
+}
+
+/// Runs `$call` with the driver attached to the bus of this call
+macro_rules! attached {
+    ($self:ident, $spi:ident, $delay:ident, |$epd:ident, $device:ident| $call:expr) => {{
+        let mut $device = device($spi, &mut $self.cs, $delay)?;
+        let mut $epd = $self
+            .epd
+            .take()
+            .expect("a previous call panicked")
+            .rebind_spi::<Device<'_, BUS, CS, DELAY>>();
+        let result = block_on($call);
+        $self.epd = Some($epd.rebind_spi());
+        result.map_err(Error::from_kind)
+    }};
+}
+
+macro_rules! compat_drivers {
+    ($($name:ident: $driver:ident;)*) => {$(
+        #[doc = concat!(
+            "[`", stringify!($name), "`](crate::", stringify!($driver), "::", stringify!($name),
+            ") with the blocking API of epd-waveshare 0.5"
+        )]
+        pub struct $name<CS, BUSY, DC, RST> {
+            epd: Option<crate::$driver::$name<Detached, PolledBusy<BUSY>, DC, RST>>,
+            cs: CS,
+        }
+
+        impl<CS, BUSY, DC, RST> $name<CS, BUSY, DC, RST>
+        where
+            CS: OutputPin,
+            CS::Error: Copy + Debug + Display,
+            BUSY: InputPin,
+            BUSY::Error: Copy + Debug + Display,
+            DC: OutputPin,
+            DC::Error: Copy + Debug + Display,
+            RST: OutputPin,
+            RST::Error: Copy + Debug + Display,
+        {
+            /// Creates and initialises the driver, `delay_us` is the busy poll interval
+            #[deprecated(note = "use `WaveshareConstructor::new` with an `SpiDevice` owning the chip select")]
+            pub fn new<BUS, DELAY>(
+                spi: &mut BUS,
+                cs: CS,
+                busy: BUSY,
+                dc: DC,
+                rst: RST,
+                delay: &mut DELAY,
+                delay_us: Option<u32>,
+            ) -> Result<Self, BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                let mut compat = $name { epd: None, cs };
+                let mut device = device(spi, &mut compat.cs, delay)?;
+                let mut epd = block_on(crate::$driver::$name::new(
+                    &mut device,
+                    PolledBusy(busy),
+                    dc,
+                    rst,
+                    delay_us,
+                ))
+                .map_err(Error::from_kind)?;
+                epd.set_wait_strategy(BusyWait::Poll);
+                compat.epd = Some(epd.rebind_spi());
+                Ok(compat)
+            }
+
+            /// Transmits a full frame
+            #[deprecated(note = "use `WaveshareDisplay::update_frame`")]
+            pub fn update_frame<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                buffer: &[u8],
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.update_frame(&mut device, buffer))
+            }
+
+            /// Transmits the part of a frame inside the given window
+            #[deprecated(note = "use `WaveshareDisplay::update_partial_frame`")]
+            #[allow(clippy::too_many_arguments)]
+            pub fn update_partial_frame<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+                buffer: &[u8],
+                x: u32,
+                y: u32,
+                width: u32,
+                height: u32,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| {
+                    epd.update_partial_frame(&mut device, buffer, x, y, width, height)
+                })
+            }
+
+            /// Displays the transmitted frame
+            #[deprecated(note = "use `WaveshareDisplay::display_frame`")]
+            pub fn display_frame<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.display_frame(&mut device))
+            }
+
+            /// Transmits and displays a full frame
+            #[deprecated(note = "use `WaveshareDisplay::update_and_display_frame`")]
+            pub fn update_and_display_frame<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                buffer: &[u8],
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| {
+                    epd.update_and_display_frame(&mut device, buffer)
+                })
+            }
+
+            /// Clears the frame with the background color
+            #[deprecated(note = "use `WaveshareDisplay::clear_frame`")]
+            pub fn clear_frame<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.clear_frame(&mut device))
+            }
+
+            /// Selects the look-up table of the next refreshes
+            #[deprecated(note = "use `WaveshareDisplay::set_lut`")]
+            pub fn set_lut<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+                refresh_rate: Option<RefreshLut>,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| {
+                    epd.set_lut(&mut device, refresh_rate)
+                })
+            }
+
+            /// Waits until the display is idle
+            #[deprecated(note = "use `WaveshareDisplay::wait_until_idle`")]
+            pub fn wait_until_idle<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.wait_until_idle(&mut device))
+            }
+
+            /// Puts the display into deep sleep
+            #[deprecated(note = "use `WaveshareDisplay::sleep`")]
+            pub fn sleep<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.sleep(&mut device))
+            }
+
+            /// Wakes the display up from deep sleep
+            #[deprecated(note = "use `WaveshareDisplay::wake_up`")]
+            pub fn wake_up<BUS, DELAY>(
+                &mut self,
+                spi: &mut BUS,
+                delay: &mut DELAY,
+            ) -> Result<(), BusError<BUS, CS>>
+            where
+                BUS: SpiBus,
+                BUS::Error: Copy + Debug + Display,
+                DELAY: DelayNs,
+            {
+                attached!(self, spi, delay, |epd, device| epd.wake_up(&mut device))
+            }
+
+            /// Sets the color used by `clear_frame`
+            pub fn set_background_color(&mut self, color: Color) {
+                self.driver().set_background_color(color)
+            }
+
+            /// The color used by `clear_frame`
+            pub fn background_color(&self) -> &Color {
+                self.epd.as_ref().expect("a previous call panicked").background_color()
+            }
+
+            /// Width of the display
+            pub fn width(&self) -> u32 {
+                crate::$driver::WIDTH
+            }
+
+            /// Height of the display
+            pub fn height(&self) -> u32 {
+                crate::$driver::HEIGHT
+            }
+
+            /// Returns the chip select pin and the async driver, e.g. for moving to the new API
+            pub fn release(self) -> (CS, crate::$driver::$name<Detached, PolledBusy<BUSY>, DC, RST>) {
+                (self.cs, self.epd.expect("a previous call panicked"))
+            }
+
+            fn driver(&mut self) -> &mut crate::$driver::$name<Detached, PolledBusy<BUSY>, DC, RST> {
+                self.epd.as_mut().expect("a previous call panicked")
+            }
+        }
+    )*};
+}
+
+compat_drivers! {
+    Epd1in54: epd1in54;
+    Epd2in9bc: epd2in9bc;
+    Epd4in2: epd4in2;
+    Epd7in5: epd7in5_v2;
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::{blank_buffer, IdleBusy, NoopPin, RecordingSpi};
+    use std::vec::Vec;
+
+    /// Blocking bus recording every written byte
+    #[derive(Default)]
+    struct RecordingBus {
+        written: Vec<u8>,
+    }
+
+    impl embedded_hal::spi::ErrorType for RecordingBus {
+        type Error = Infallible;
+    }
+
+    impl SpiBus for RecordingBus {
+        fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            words.fill(0);
+            Ok(())
+        }
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.written.extend_from_slice(words);
+            Ok(())
+        }
+
+        fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+            read.fill(0);
+            self.write(write)
+        }
+
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+            self.written.extend_from_slice(words);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingDelay {
+        delays: Vec<u32>,
+    }
+
+    impl DelayNs for RecordingDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.delays.push(ns);
+        }
+    }
+
+    /// Busy pin at the level of an idle display
+    struct IdlePin {
+        high: bool,
+    }
+
+    impl embedded_hal::digital::ErrorType for IdlePin {
+        type Error = Infallible;
+    }
+
+    impl InputPin for IdlePin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.high)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.high)
+        }
+    }
+
+    macro_rules! same_traffic {
+        ($name:ident: $driver:ident, idle_high: $high:expr) => {{
+            let buffer = blank_buffer(crate::$driver::WIDTH, crate::$driver::HEIGHT);
+
+            let mut spi = RecordingSpi::default();
+            let mut epd = block_on(crate::$driver::$name::new(
+                &mut spi, IdleBusy, NoopPin, NoopPin, None,
+            ))
+            .unwrap();
+            block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+            block_on(epd.sleep(&mut spi)).unwrap();
+
+            let mut bus = RecordingBus::default();
+            let mut delay = RecordingDelay::default();
+            let mut old = $name::new(
+                &mut bus,
+                NoopPin,
+                IdlePin { high: $high },
+                NoopPin,
+                NoopPin,
+                &mut delay,
+                None,
+            )
+            .unwrap();
+            old.update_and_display_frame(&mut bus, &buffer, &mut delay)
+                .unwrap();
+            old.sleep(&mut bus, &mut delay).unwrap();
+
+            assert_eq!(bus.written, spi.written, stringify!($name));
+            assert_eq!(delay.delays, spi.delays, stringify!($name));
+            assert_eq!(old.width(), crate::$driver::WIDTH);
+            assert_eq!(old.height(), crate::$driver::HEIGHT);
+        }};
+    }
+
+    #[test]
+    fn old_calls_send_the_same_bytes() {
+        same_traffic!(Epd1in54: epd1in54, idle_high: false);
+        same_traffic!(Epd2in9bc: epd2in9bc, idle_high: true);
+        same_traffic!(Epd4in2: epd4in2, idle_high: true);
+        same_traffic!(Epd7in5: epd7in5_v2, idle_high: true);
+    }
+
+    #[test]
+    fn background_color_is_kept_between_calls() {
+        let mut bus = RecordingBus::default();
+        let mut delay = RecordingDelay::default();
+        let mut epd = Epd2in9bc::new(
+            &mut bus,
+            NoopPin,
+            IdlePin { high: true },
+            NoopPin,
+            NoopPin,
+            &mut delay,
+            None,
+        )
+        .unwrap();
+        epd.set_background_color(Color::Black);
+        epd.clear_frame(&mut bus, &mut delay).unwrap();
+        assert_eq!(epd.background_color(), &Color::Black);
+    }
+}
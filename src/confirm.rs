@@ -0,0 +1,72 @@
+//! Expected reaction of the busy line to state changing commands
+//!
+//! The controllers have no checksum on their SPI input. A command corrupted on a long cable is
+//! either ignored or taken for a different one, and the display ends up in an unknown state.
+//! The commands below make the controller busy right after they are latched, so a busy line
+//! that doesn't assert within the given time means the command most likely didn't land, see
+//! [`SafeCommands`](crate::SafeCommands).
+//!
+//! Registers of the SSD controllers can be read back instead, the drivers supporting that
+//! verify them with their own tables, see
+//! [`verify_register_writes`](crate::traits::WaveshareDisplay::verify_register_writes).
+
+/// A command the busy line asserts after
+pub(crate) struct BusyExpectation {
+    /// Address of the command
+    pub(crate) command: u8,
+    /// Time the busy line may take to assert, in µs
+    pub(crate) within_us: u32,
+}
+
+/// UC8151 / IL0373 family, e.g. the 2in9bc and the 2in13bc
+///
+/// BUSY_N is driven low while the charge pumps switch on or off and during the refresh
+/// (UC8151C datasheet, command table: PON, POF and DRF "BUSY_N flag low"). The flag follows
+/// the command within a few clock cycles, 1 ms leaves room for slow repeater boards.
+pub(crate) const UC81XX: &[BusyExpectation] = &[
+    // POF
+    BusyExpectation {
+        command: 0x02,
+        within_us: 1_000,
+    },
+    // PON
+    BusyExpectation {
+        command: 0x04,
+        within_us: 1_000,
+    },
+    // DRF
+    BusyExpectation {
+        command: 0x12,
+        within_us: 1_000,
+    },
+];
+
+/// SSD16xx family, e.g. the 2in13 V2
+///
+/// BUSY goes high while the update sequence of Master Activation runs (SSD1680 datasheet,
+/// command 0x20: "BUSY pad will output high during operation").
+pub(crate) const SSD16XX: &[BusyExpectation] = &[
+    // Master Activation
+    BusyExpectation {
+        command: 0x20,
+        within_us: 1_000,
+    },
+];
+
+/// Command both families react to with busy levels of opposite polarity, DRF of the UC81xx
+/// and SWRESET of the SSD16xx, which tells them apart in the sanity checks of the drivers
+///
+/// Sent right after the hardware reset, when the charge pumps of a UC81xx are still off and
+/// its refresh doesn't drive the panel.
+pub(crate) const FAMILY_PROBE: BusyExpectation = BusyExpectation {
+    command: 0x12,
+    within_us: 1_000,
+};
+
+/// Time the busy line may take to assert after `command`, `None` if it doesn't react to it
+pub(crate) fn busy_within(expectations: &[BusyExpectation], command: u8) -> Option<u32> {
+    expectations
+        .iter()
+        .find(|expectation| expectation.command == command)
+        .map(|expectation| expectation.within_us)
+}
@@ -0,0 +1,473 @@
+//! Host side conversion of images to [wire frames](crate::wire), used by the `epd-wire` tool
+//!
+//! The conversion uses the same code as the drivers: the pixels are packed by a
+//! [`VarDisplay`] and transformed by [`Panel::wire_bytes`].
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::{convert, wire};
+//!
+//! let image = convert::decode(&std::fs::read("splash.png")?)?;
+//! let buffer = convert::pack(&image, &wire::EPD4IN2, convert::Quantize::Dither)?;
+//! std::fs::write("splash.bin", convert::encode_frame(&wire::EPD4IN2, &buffer))?;
+//! ```
+extern crate std;
+
+use embedded_graphics_core::prelude::*;
+use std::format;
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+use crate::color::Color;
+use crate::graphics::VarDisplay;
+use crate::gray::GrayMap;
+use crate::wire::Panel;
+
+/// Grayscale image, 0 is black and 255 white
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrayImage {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Pixels row by row
+    pub pixels: Vec<u8>,
+}
+
+/// How gray values are mapped to black and white
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantize {
+    /// Pixels darker than the threshold are black
+    Threshold(u8),
+    /// Floyd-Steinberg dithering
+    Dither,
+}
+
+impl Default for Quantize {
+    fn default() -> Self {
+        Quantize::Threshold(128)
+    }
+}
+
+/// Errors of the conversion
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The image file is broken or uses an unsupported format
+    Format(String),
+    /// The image doesn't have the size of the panel
+    Size {
+        /// Width and height of the panel
+        expected: (u32, u32),
+        /// Width and height of the image
+        actual: (u32, u32),
+    },
+}
+
+impl core::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConvertError::Format(message) => write!(f, "unsupported image: {}", message),
+            ConvertError::Size { expected, actual } => write!(
+                f,
+                "the image has {}x{} pixels, the panel {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+fn format_error(message: &str) -> ConvertError {
+    ConvertError::Format(String::from(message))
+}
+
+/// Decodes a PNG, PBM or PGM image
+pub fn decode(bytes: &[u8]) -> Result<GrayImage, ConvertError> {
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        decode_png(bytes)
+    } else if bytes.first() == Some(&b'P') {
+        decode_netpbm(bytes)
+    } else {
+        Err(format_error("neither PNG, PBM nor PGM"))
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<GrayImage, ConvertError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|error| ConvertError::Format(format!("{}", error)))?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut data)
+        .map_err(|error| ConvertError::Format(format!("{}", error)))?;
+
+    let channels = info.color_type.samples();
+    let pixels = data[..info.buffer_size()]
+        .chunks(info.line_size)
+        .flat_map(|line| line.chunks(channels).take(info.width as usize))
+        .map(|pixel| match pixel {
+            [gray] => *gray,
+            // transparent pixels are white
+            [gray, alpha] => blend(*gray, *alpha),
+            [r, g, b] => luma(*r, *g, *b),
+            [r, g, b, alpha] => blend(luma(*r, *g, *b), *alpha),
+            _ => 255,
+        })
+        .collect();
+    Ok(GrayImage {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+fn blend(gray: u8, alpha: u8) -> u8 {
+    ((gray as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255) as u8
+}
+
+fn decode_netpbm(bytes: &[u8]) -> Result<GrayImage, ConvertError> {
+    let mut rest = bytes;
+    let mut token = || -> Result<&[u8], ConvertError> {
+        loop {
+            match rest.first() {
+                Some(b'#') => {
+                    let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                    rest = &rest[end..];
+                }
+                Some(byte) if byte.is_ascii_whitespace() => rest = &rest[1..],
+                Some(_) => break,
+                None => return Err(format_error("truncated header")),
+            }
+        }
+        let end = rest
+            .iter()
+            .position(|byte| byte.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        Ok(token)
+    };
+    let number = |token: &[u8]| -> Result<u32, ConvertError> {
+        core::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| format_error("invalid number in the header"))
+    };
+
+    let magic = token()?;
+    let width = number(token()?)?;
+    let height = number(token()?)?;
+    let max = match magic {
+        b"P1" | b"P4" => 1,
+        b"P2" | b"P5" => number(token()?)?,
+        _ => {
+            return Err(format_error(
+                "only P1, P2, P4 and P5 netpbm images are supported",
+            ))
+        }
+    };
+    if max == 0 || max > 255 {
+        return Err(format_error("only 8 bit gray values are supported"));
+    }
+    let count = width as usize * height as usize;
+
+    let pixels: Vec<u8> = match magic {
+        // in PBM images 1 is black
+        b"P1" => rest
+            .iter()
+            .filter(|byte| matches!(byte, b'0' | b'1'))
+            .map(|&byte| if byte == b'1' { 0 } else { 255 })
+            .collect(),
+        b"P2" => {
+            let mut pixels = Vec::with_capacity(count);
+            for token in rest
+                .split(|byte| byte.is_ascii_whitespace())
+                .filter(|token| !token.is_empty())
+            {
+                pixels.push((number(token)? * 255 / max) as u8);
+            }
+            pixels
+        }
+        // a single whitespace separates the header from the binary data
+        b"P4" => rest
+            .get(1..)
+            .unwrap_or_default()
+            .chunks(width.div_ceil(8) as usize)
+            .flat_map(|row| {
+                (0..width as usize).map(move |x| {
+                    let bit = row.get(x / 8).map_or(0, |byte| byte & (0x80 >> (x % 8)));
+                    if bit != 0 {
+                        0
+                    } else {
+                        255
+                    }
+                })
+            })
+            .collect(),
+        _ => rest
+            .get(1..)
+            .unwrap_or_default()
+            .iter()
+            .map(|&value| (value as u32 * 255 / max) as u8)
+            .collect(),
+    };
+    if pixels.len() < count {
+        return Err(format_error("truncated image data"));
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels: pixels[..count].to_vec(),
+    })
+}
+
+/// Packs `image` into a 1 bit per pixel buffer for `panel`
+pub fn pack(image: &GrayImage, panel: &Panel, quantize: Quantize) -> Result<Vec<u8>, ConvertError> {
+    if (image.width, image.height) != (panel.width, panel.height) {
+        return Err(ConvertError::Size {
+            expected: (panel.width, panel.height),
+            actual: (image.width, image.height),
+        });
+    }
+
+    let mut buffer = vec![0; panel.buffer_len()];
+    let mut display = VarDisplay::<Color>::new(panel.width, panel.height, &mut buffer, false)
+        .map_err(|_| format_error("buffer too small"))?;
+    let width = image.width as usize;
+    // quantization error spread to the following pixels
+    let mut error = vec![0i16; image.pixels.len() + width + 2];
+    for (index, &gray) in image.pixels.iter().enumerate() {
+        let (x, y) = (index % width, index / width);
+        let color = match quantize {
+            Quantize::Threshold(threshold) => {
+                if gray < threshold {
+                    Color::Black
+                } else {
+                    Color::White
+                }
+            }
+            Quantize::Dither => {
+                let value = (gray as i16 + error[index]).clamp(0, 255);
+                let (color, quantized) = if value < 128 {
+                    (Color::Black, 0)
+                } else {
+                    (Color::White, 255)
+                };
+                let diff = value - quantized;
+                if x + 1 < width {
+                    error[index + 1] += diff * 7 / 16;
+                    error[index + width + 1] += diff / 16;
+                }
+                if x > 0 {
+                    error[index + width - 1] += diff * 3 / 16;
+                }
+                error[index + width] += diff * 5 / 16;
+                color
+            }
+        };
+        display.set_pixel(Pixel(Point::new(x as i32, y as i32), color));
+    }
+    Ok(buffer)
+}
+
+/// Gray map splitting the pixels of `image` into four levels of about the same size
+///
+/// The thresholds are the quartiles of the histogram of the image. Images with too few
+/// distinct values for three different thresholds get [`GrayMap::LINEAR`].
+pub fn gray_map_from_histogram(image: &GrayImage) -> GrayMap {
+    let mut histogram = [0usize; 256];
+    for &gray in &image.pixels {
+        histogram[gray as usize] += 1;
+    }
+
+    let total = image.pixels.len();
+    let mut thresholds = [0u8; 3];
+    let (mut darker, mut quartile) = (0, 0);
+    for (value, &count) in histogram.iter().enumerate() {
+        // the first value after a quarter of the pixels starts the next level
+        while quartile < 3 && darker > 0 && darker >= total * (quartile + 1) / 4 {
+            thresholds[quartile] = value as u8;
+            quartile += 1;
+        }
+        darker += count;
+    }
+    GrayMap::new(thresholds).unwrap_or(GrayMap::LINEAR)
+}
+
+/// The header and the bytes sent to `panel` for `buffer`
+pub fn encode_frame(panel: &Panel, buffer: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(crate::wire::HEADER_LEN + panel.wire_len());
+    frame.extend_from_slice(&panel.header());
+    frame.extend(panel.wire_bytes(buffer));
+    frame
+}
+
+/// Rust source defining the static `name` holding `frame`, for use with
+/// [`WireFrame::expect`](crate::wire::WireFrame::expect)
+pub fn to_rust_source(name: &str, frame: &[u8]) -> String {
+    let mut source = format!(
+        "// Generated by epd-wire, format version {}\n#[rustfmt::skip]\npub static {}: [u8; {}] = [\n",
+        crate::wire::FORMAT_VERSION,
+        name,
+        frame.len()
+    );
+    for line in frame.chunks(16) {
+        source.push_str("   ");
+        for byte in line {
+            source.push_str(&format!(" 0x{:02X},", byte));
+        }
+        source.push('\n');
+    }
+    source.push_str("];\n");
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{block_on, contains, IdleBusy, NoopPin, RecordingSpi};
+    use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+    use crate::wire::{WireFrame, EPD4IN2, EPD7IN5};
+
+    /// Horizontal gradient as binary PGM
+    fn gradient_pgm(width: u32, height: u32) -> Vec<u8> {
+        let mut pgm = format!("P5\n# fixture\n{} {}\n255\n", width, height).into_bytes();
+        for _ in 0..height {
+            pgm.extend((0..width).map(|x| (x * 255 / (width - 1)) as u8));
+        }
+        pgm
+    }
+
+    #[test]
+    fn gray_map_from_quartiles() {
+        let ramp = GrayImage {
+            width: 256,
+            height: 1,
+            pixels: (0..=255).collect(),
+        };
+        assert_eq!(gray_map_from_histogram(&ramp), GrayMap::LINEAR);
+
+        // mostly dark pixels move the thresholds into the shadows
+        let dark = GrayImage {
+            width: 8,
+            height: 1,
+            pixels: vec![10, 10, 20, 20, 30, 30, 200, 200],
+        };
+        let map = gray_map_from_histogram(&dark);
+        assert_eq!(map.thresholds(), [11, 21, 31]);
+        let levels: Vec<u8> = [10, 20, 30, 200].iter().map(|&v| map.level(v)).collect();
+        assert_eq!(levels, [0b00, 0b01, 0b10, 0b11]);
+
+        let flat = GrayImage {
+            width: 4,
+            height: 1,
+            pixels: vec![90; 4],
+        };
+        assert_eq!(gray_map_from_histogram(&flat), GrayMap::LINEAR);
+    }
+
+    #[test]
+    fn decode_netpbm_images() {
+        let pbm = decode(b"P1\n# comment\n3 2\n1 0 1\n0 1 0\n").unwrap();
+        assert_eq!((pbm.width, pbm.height), (3, 2));
+        assert_eq!(pbm.pixels, [0, 255, 0, 255, 0, 255]);
+
+        let pbm = decode(b"P4 9 1\n\x80\x80").unwrap();
+        assert_eq!(pbm.pixels, [0, 255, 255, 255, 255, 255, 255, 255, 0]);
+
+        let pgm = decode(b"P2 2 1 15 0 15").unwrap();
+        assert_eq!(pgm.pixels, [0, 255]);
+
+        assert!(decode(b"P5 2 2 255\n\x00").is_err());
+        assert!(decode(b"P6 1 1 255\n\x00\x00\x00").is_err());
+        assert!(decode(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn quantize_gradient() {
+        let image = GrayImage {
+            width: 8,
+            height: 1,
+            pixels: vec![0, 40, 80, 120, 136, 176, 216, 255],
+        };
+        let panel = Panel {
+            width: 8,
+            height: 1,
+            ..EPD4IN2
+        };
+        assert_eq!(pack(&image, &panel, Quantize::default()).unwrap(), [0x0F]);
+        assert_eq!(
+            pack(&image, &panel, Quantize::Threshold(200)).unwrap(),
+            [0x03]
+        );
+        assert!(matches!(
+            pack(&image, &EPD4IN2, Quantize::default()),
+            Err(ConvertError::Size { .. })
+        ));
+
+        // half of a mid gray area ends up black
+        let gray = GrayImage {
+            width: 16,
+            height: 16,
+            pixels: vec![128; 256],
+        };
+        let panel = Panel {
+            width: 16,
+            height: 16,
+            ..EPD4IN2
+        };
+        let dithered = pack(&gray, &panel, Quantize::Dither).unwrap();
+        let white: u32 = dithered.iter().map(|byte| byte.count_ones()).sum();
+        assert!((120..=136).contains(&white), "{} white pixels", white);
+    }
+
+    #[test]
+    fn converter_matches_the_drivers() {
+        use crate::epd4in2::Epd4in2;
+        use crate::epd7in5::Epd7in5;
+
+        let image = decode(&gradient_pgm(EPD7IN5.width, EPD7IN5.height)).unwrap();
+        let buffer = pack(&image, &EPD7IN5, Quantize::Dither).unwrap();
+        let frame = encode_frame(&EPD7IN5, &buffer);
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+        block_on(epd.update_frame(&mut spi, &buffer)).unwrap();
+        let sent = spi.take();
+        block_on(epd.update_frame_wire(&mut spi, WireFrame::parse(&frame).unwrap())).unwrap();
+        assert_eq!(spi.take(), sent);
+        assert!(contains(&sent, &frame[crate::wire::HEADER_LEN..]));
+
+        let image = decode(&gradient_pgm(EPD4IN2.width, EPD4IN2.height)).unwrap();
+        let buffer = pack(&image, &EPD4IN2, Quantize::Dither).unwrap();
+        let frame = encode_frame(&EPD4IN2, &buffer);
+        let frame = WireFrame::parse(&frame).unwrap().check(&EPD4IN2).unwrap();
+
+        let mut epd =
+            block_on(Epd4in2::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+        block_on(epd.update_frame(&mut spi, frame.data())).unwrap();
+        assert!(contains(&spi.take(), &buffer));
+
+        // wrong panel
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        assert!(block_on(epd.update_frame_wire(&mut spi, frame)).is_err());
+    }
+
+    #[test]
+    fn rust_source() {
+        let source = to_rust_source("SPLASH", &[0x45, 0x50, 0x44]);
+        assert!(source.contains("pub static SPLASH: [u8; 3] = [\n    0x45, 0x50, 0x44,\n];\n"));
+    }
+}
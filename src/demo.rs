@@ -0,0 +1,442 @@
+//! A test image for checking the wiring of a new display
+//!
+//! Every driver has a `demo` method that draws the name of its module and the resolution, a
+//! border around the panel and, on three color panels, a stripe in the chromatic color, and
+//! shows it with a full update and a refresh:
+//!
+//! ```rust, ignore
+//! let mut epd = Epd2in9bc::new(&mut spi, busy, dc, rst, None).await?;
+//! let stats = epd.demo(&mut spi, || now_us()).await?;
+//! ```
+//!
+//! The demo goes through the same code as a frame of the application, `update_frame` or
+//! `update_color_frame` and then `display_frame`, with the driver's usual transformations. If
+//! the demo works but the application doesn't, the wiring is fine.
+//!
+//! The frame is drawn into a [`Display`] on the stack, of the size of the panel, with the
+//! `FONT_6X10` of embedded-graphics.
+use core::convert::Infallible;
+use core::fmt::{Debug, Write};
+use embedded_graphics::mono_font::{ascii::FONT_6X10, MonoTextStyle};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::color::{Color, ColorType, OctColor, TriColor};
+use crate::graphics::Display;
+use crate::interface::{BusySignal, ResetControl};
+use crate::traits::{WaveshareDisplay, WaveshareThreeColorDisplay};
+
+/// Time the demo took, in the unit of the clock passed to it, e.g. microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DemoStats {
+    /// Sending the frame
+    pub transfer: u64,
+    /// Refreshing the panel, until it wasn't busy anymore
+    pub refresh: u64,
+}
+
+/// Colors the demo draws with
+pub trait DemoColor: ColorType + PixelColor {
+    /// Background
+    const PAPER: Self;
+    /// Text and border
+    const INK: Self;
+    /// Stripe, if the panel has another color
+    const ACCENT: Option<Self>;
+}
+
+impl DemoColor for Color {
+    const PAPER: Self = Color::White;
+    const INK: Self = Color::Black;
+    const ACCENT: Option<Self> = None;
+}
+
+impl DemoColor for TriColor {
+    const PAPER: Self = TriColor::White;
+    const INK: Self = TriColor::Black;
+    const ACCENT: Option<Self> = Some(TriColor::Chromatic);
+}
+
+impl DemoColor for OctColor {
+    const PAPER: Self = OctColor::White;
+    const INK: Self = OctColor::Black;
+    const ACCENT: Option<Self> = Some(OctColor::Red);
+}
+
+/// Runs the demo on a driver taking the buffer of `display` with `update_frame`
+pub async fn run<
+    EPD,
+    SPI,
+    BUSY,
+    DC,
+    RST,
+    const W: u32,
+    const H: u32,
+    const B: bool,
+    const N: usize,
+    COLOR,
+>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    display: &mut Display<W, H, B, N, COLOR>,
+    name: &str,
+    clock: impl Fn() -> u64,
+) -> Result<DemoStats, EPD::Error>
+where
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST>,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + core::fmt::Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + core::fmt::Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + core::fmt::Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + core::fmt::Display,
+    COLOR: DemoColor,
+{
+    draw(display, name, COLOR::INK, COLOR::ACCENT);
+    let start = clock();
+    epd.update_frame(spi, display.buffer()).await?;
+    show(epd, spi, &clock, start).await
+}
+
+/// Runs the demo on a three color driver taking both planes of `display` with
+/// `update_color_frame`
+pub async fn run_tricolor<
+    EPD,
+    SPI,
+    BUSY,
+    DC,
+    RST,
+    const W: u32,
+    const H: u32,
+    const B: bool,
+    const N: usize,
+>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    display: &mut Display<W, H, B, N, TriColor>,
+    name: &str,
+    clock: impl Fn() -> u64,
+) -> Result<DemoStats, EPD::Error>
+where
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + core::fmt::Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + core::fmt::Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + core::fmt::Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + core::fmt::Display,
+{
+    draw(display, name, TriColor::INK, TriColor::ACCENT);
+    let start = clock();
+    epd.update_color_frame(spi, display.bw_buffer(), display.chromatic_buffer())
+        .await?;
+    show(epd, spi, &clock, start).await
+}
+
+/// Runs the demo on a three color driver whose planes are two black/white displays, the
+/// stripe is drawn in black into `chromatic`
+pub async fn run_planes<
+    EPD,
+    SPI,
+    BUSY,
+    DC,
+    RST,
+    const W: u32,
+    const H: u32,
+    const B: bool,
+    const N: usize,
+>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    black: &mut Display<W, H, B, N, Color>,
+    chromatic: &mut Display<W, H, B, N, Color>,
+    name: &str,
+    clock: impl Fn() -> u64,
+) -> Result<DemoStats, EPD::Error>
+where
+    EPD: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + core::fmt::Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + core::fmt::Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + core::fmt::Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + core::fmt::Display,
+{
+    draw(black, name, Color::Black, None);
+    let _ = chromatic.clear(Color::White);
+    let _ = stripe(W)
+        .into_styled(PrimitiveStyle::with_fill(Color::Black))
+        .draw(chromatic);
+    let start = clock();
+    epd.update_color_frame(spi, black.buffer(), chromatic.buffer())
+        .await?;
+    show(epd, spi, &clock, start).await
+}
+
+/// Refreshes and measures, the transfer started at `start`
+async fn show<EPD, SPI, BUSY, DC, RST>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    clock: &impl Fn() -> u64,
+    start: u64,
+) -> Result<DemoStats, EPD::Error>
+where
+    EPD: WaveshareDisplay<SPI, BUSY, DC, RST>,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + core::fmt::Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + core::fmt::Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + core::fmt::Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + core::fmt::Display,
+{
+    let sent = clock();
+    epd.display_frame(spi).await?;
+    epd.wait_until_idle(spi).await?;
+    let done = clock();
+    Ok(DemoStats {
+        transfer: sent.wrapping_sub(start),
+        refresh: done.wrapping_sub(sent),
+    })
+}
+
+/// Draws the test image
+fn draw<D, COLOR>(display: &mut D, name: &str, ink: COLOR, accent: Option<COLOR>)
+where
+    D: DrawTarget<Color = COLOR, Error = Infallible> + OriginDimensions,
+    COLOR: DemoColor,
+{
+    let size = display.size();
+    let _ = display.clear(COLOR::PAPER);
+    let _ = Rectangle::new(Point::zero(), size)
+        .into_styled(PrimitiveStyle::with_stroke(ink, 1))
+        .draw(display);
+
+    let style = MonoTextStyle::new(&FONT_6X10, ink);
+    let _ = Text::with_baseline(name, Point::new(4, 4), style, Baseline::Top).draw(display);
+    let mut resolution = Line::default();
+    let _ = write!(resolution, "{}x{}", size.width, size.height);
+    let _ = Text::with_baseline(resolution.as_str(), Point::new(4, 16), style, Baseline::Top)
+        .draw(display);
+
+    if let Some(accent) = accent {
+        let _ = stripe(size.width)
+            .into_styled(PrimitiveStyle::with_fill(accent))
+            .draw(display);
+    }
+}
+
+/// Area of the chromatic stripe, below the text
+fn stripe(width: u32) -> Rectangle {
+    Rectangle::new(Point::new(4, 30), Size::new(width.saturating_sub(8), 8))
+}
+
+/// Text of a line, long enough for the resolution
+#[derive(Default)]
+struct Line {
+    bytes: [u8; 24],
+    len: usize,
+}
+
+impl Line {
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(core::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Implements `demo` for a driver, see the [module documentation](self)
+///
+/// `$display` is the display type of the driver, `planes` draws the chromatic stripe into a
+/// second black/white display and `tricolor` into the chromatic plane of a [`TriColor`]
+/// display.
+macro_rules! impl_demo {
+    ($epd:ident $(<$lt:lifetime>)?, $display:ident, $name:literal) => {
+        $crate::demo::impl_demo!(@impl $epd $(<$lt>)?, |epd, spi, clock| {
+            $crate::demo::run(epd, spi, &mut $display::default(), $name, clock).await
+        });
+    };
+    ($epd:ident $(<$lt:lifetime>)?, $display:ident, $name:literal, tricolor) => {
+        $crate::demo::impl_demo!(@impl $epd $(<$lt>)?, |epd, spi, clock| {
+            $crate::demo::run_tricolor(epd, spi, &mut $display::default(), $name, clock).await
+        });
+    };
+    ($epd:ident $(<$lt:lifetime>)?, $display:ident, $name:literal, planes) => {
+        $crate::demo::impl_demo!(@impl $epd $(<$lt>)?, |epd, spi, clock| {
+            $crate::demo::run_planes(
+                epd,
+                spi,
+                &mut $display::default(),
+                &mut $display::default(),
+                $name,
+                clock,
+            )
+            .await
+        });
+    };
+    (@impl $epd:ident $(<$lt:lifetime>)?, |$this:ident, $spi:ident, $clock:ident| $run:block) => {
+        impl<$($lt,)? SPI, BUSY, DC, RST> $epd<$($lt,)? SPI, BUSY, DC, RST>
+        where
+            SPI: SpiDevice,
+            SPI::Error: Copy + Debug + Display,
+            BUSY: BusySignal,
+            BUSY::Error: Copy + Debug + Display,
+            DC: OutputPin,
+            DC::Error: Copy + Debug + Display,
+            RST: ResetControl,
+            RST::Error: Copy + Debug + Display,
+        {
+            /// Shows a test image with the name of the driver and the resolution, see the
+            /// [`demo`](crate::demo) module
+            ///
+            /// `clock` returns the current time, the [`DemoStats`](crate::demo::DemoStats) are
+            /// in its unit.
+            pub async fn demo(
+                &mut self,
+                $spi: &mut SPI,
+                $clock: impl Fn() -> u64,
+            ) -> Result<
+                $crate::demo::DemoStats,
+                <Self as $crate::traits::ErrorType<SPI, BUSY, DC, RST>>::Error,
+            > {
+                let $this = self;
+                $run
+            }
+        }
+    };
+}
+pub(crate) use impl_demo;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9bc::{Display2in9bc, Epd2in9bc};
+    use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+    use crate::traits::WaveshareConstructor;
+    use core::cell::Cell;
+
+    #[test]
+    fn demo_image() {
+        let mut display = Display2in9bc::default();
+        draw(&mut display, "epd2in9bc", Color::Black, None);
+        let buffer = display.buffer();
+        // border on the first row and in the first and last column
+        assert!(buffer[..16].iter().all(|&byte| byte == 0x00));
+        assert_eq!(buffer[16 * 100], 0x7F);
+        assert_eq!(buffer[16 * 101 - 1], 0xFE);
+        // some text in the top left corner
+        assert!(buffer[16 * 4..16 * 26]
+            .iter()
+            .any(|&byte| byte != 0xFF && byte != 0x7F));
+
+        let mut resolution = Line::default();
+        write!(resolution, "{}x{}", u32::MAX, u32::MAX).unwrap();
+        assert_eq!(resolution.as_str(), "4294967295x4294967295");
+        assert!(write!(resolution, "too long").is_err());
+    }
+
+    #[test]
+    fn demo_stats() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let now = Cell::new(0);
+        let stats = block_on(epd.demo(&mut spi, || now.replace(now.get() + 10))).unwrap();
+        assert_eq!(
+            stats,
+            DemoStats {
+                transfer: 10,
+                refresh: 10
+            }
+        );
+    }
+
+    crate::golden::golden_demo!(golden_epd1in02, crate::epd1in02::Epd1in02, "epd1in02");
+    crate::golden::golden_demo!(golden_epd1in54, crate::epd1in54::Epd1in54, "epd1in54");
+    crate::golden::golden_demo!(
+        golden_epd1in54_v2,
+        crate::epd1in54_v2::Epd1in54,
+        "epd1in54_v2"
+    );
+    crate::golden::golden_demo!(golden_epd1in54b, crate::epd1in54b::Epd1in54b, "epd1in54b");
+    crate::golden::golden_demo!(golden_epd1in54c, crate::epd1in54c::Epd1in54c, "epd1in54c");
+    // recorded with the V3 look-up tables of the default features
+    #[cfg(not(feature = "epd2in13_v2"))]
+    crate::golden::golden_demo!(
+        golden_epd2in13_v2,
+        crate::epd2in13_v2::Epd2in13,
+        "epd2in13_v2"
+    );
+    crate::golden::golden_demo!(
+        golden_epd2in13_v3,
+        crate::epd2in13_v3::Epd2in13V3,
+        "epd2in13_v3"
+    );
+    crate::golden::golden_demo!(
+        golden_epd2in13bc,
+        crate::epd2in13bc::Epd2in13bc,
+        "epd2in13bc"
+    );
+    crate::golden::golden_demo!(golden_epd2in66b, crate::epd2in66b::Epd2in66b, "epd2in66b");
+    crate::golden::golden_demo!(golden_epd2in7b, crate::epd2in7b::Epd2in7b, "epd2in7b");
+    crate::golden::golden_demo!(
+        golden_epd2in7b_v2,
+        crate::epd2in7b_v2::Epd2in7bV2,
+        "epd2in7b_v2"
+    );
+    crate::golden::golden_demo!(golden_epd2in9, crate::epd2in9::Epd2in9, "epd2in9");
+    crate::golden::golden_demo!(golden_epd2in9_v2, crate::epd2in9_v2::Epd2in9, "epd2in9_v2");
+    crate::golden::golden_demo!(golden_epd2in9bc, crate::epd2in9bc::Epd2in9bc, "epd2in9bc");
+    crate::golden::golden_demo!(golden_epd2in9d, crate::epd2in9d::Epd2in9d, "epd2in9d");
+    crate::golden::golden_demo!(golden_epd3in7, crate::epd3in7::Epd3in7, "epd3in7");
+    crate::golden::golden_demo!(golden_epd3in71, crate::epd3in71::Epd3in71, "epd3in71");
+    crate::golden::golden_demo!(golden_epd4in2, crate::epd4in2::Epd4in2, "epd4in2");
+    crate::golden::golden_demo!(golden_epd4in2b, crate::epd4in2b::Epd4in2b, "epd4in2b");
+    crate::golden::golden_demo!(golden_epd5in65f, crate::epd5in65f::Epd5in65f, "epd5in65f");
+    crate::golden::golden_demo!(
+        golden_epd5in83_v2,
+        crate::epd5in83_v2::Epd5in83,
+        "epd5in83_v2"
+    );
+    crate::golden::golden_demo!(
+        golden_epd5in83b_v2,
+        crate::epd5in83b_v2::Epd5in83,
+        "epd5in83b_v2"
+    );
+    crate::golden::golden_demo!(golden_epd7in3f, crate::epd7in3f::Epd7in3f, "epd7in3f");
+    crate::golden::golden_demo!(golden_epd7in5, crate::epd7in5::Epd7in5, "epd7in5");
+    crate::golden::golden_demo!(golden_epd7in5_hd, crate::epd7in5_hd::Epd7in5, "epd7in5_hd");
+    crate::golden::golden_demo!(golden_epd7in5_v2, crate::epd7in5_v2::Epd7in5, "epd7in5_v2");
+    crate::golden::golden_demo!(
+        golden_epd7in5b_v2,
+        crate::epd7in5b_v2::Epd7in5,
+        "epd7in5b_v2"
+    );
+}
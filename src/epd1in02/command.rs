@@ -0,0 +1,74 @@
+//! SPI Commands for the Waveshare 1.02" E-Ink Display
+//!
+//! The panel has an UC8175 controller, see its datasheet for the parameters.
+use crate::traits;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// LUT from register or OTP, gate scan and source shift direction, booster switch, soft reset
+    PanelSetting = 0x00,
+    /// Internal or external power, VGH/VGL and VDH/VDL levels
+    PowerSetting = 0x01,
+    /// Turns off the charge pump, the source and gate drivers and VCOM
+    PowerOff = 0x02,
+    PowerOffSequenceSetting = 0x03,
+    /// Turns on the power, BUSY_N is low until it is stable
+    PowerOn = 0x04,
+    /// Frequency and drive strength of the charge pump, this controller has no booster soft start
+    ChargePumpSetting = 0x06,
+    /// Deep sleep, the only parameter is the check code 0xA5
+    DeepSleep = 0x07,
+    /// Old data, compared with the new data by the look-up tables
+    DataStartTransmission1 = 0x10,
+    DataStop = 0x11,
+    /// Refreshes the display with the data in SRAM and the look-up tables
+    DisplayRefresh = 0x12,
+    /// New data
+    DataStartTransmission2 = 0x13,
+    AutoSequence = 0x17,
+
+    /// Look-up table for pixels ending up white
+    LutWhite = 0x23,
+    /// Look-up table for pixels ending up black
+    LutBlack = 0x24,
+    /// XON and options of the look-up tables
+    LutOption = 0x2A,
+
+    /// Frame rate
+    PllControl = 0x30,
+    TemperatureSensorCalibration = 0x40,
+    TemperatureSensorSelection = 0x41,
+    TemperatureSensorWrite = 0x42,
+    TemperatureSensorRead = 0x43,
+    /// Border output and the interval between VCOM and the data
+    VcomAndDataIntervalSetting = 0x50,
+    LowPowerDetection = 0x51,
+    /// Non-overlap periods of gate and source
+    TconSetting = 0x60,
+    /// Horizontal and vertical resolution, one byte each
+    ResolutionSetting = 0x61,
+    /// Reading the status makes the BUSY_N pin reflect the current state
+    GetStatus = 0x71,
+    AutoMeasurementVcom = 0x80,
+    ReadVcomValue = 0x81,
+    VcmDcSetting = 0x82,
+    /// Window of the partial update, start and end of x and y and the gate scan
+    PartialWindow = 0x90,
+    PartialIn = 0x91,
+    PartialOut = 0x92,
+    ProgramMode = 0xA0,
+    ActiveProgramming = 0xA1,
+    ReadOtp = 0xA2,
+    /// Not in the datasheet, sent first by the reference init code
+    VendorSetting = 0xD2,
+    /// Power saving of the source and gate drivers, mandatory on this panel
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
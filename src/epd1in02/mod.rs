@@ -0,0 +1,688 @@
+//! A simple Driver for the Waveshare 1.02" E-Ink Display via SPI
+//!
+//! The 80x128 panel has an UC8175 controller. Unlike the bigger UC81xx panels it has no booster
+//! soft start, sends the resolution as one byte per axis and needs the power saving register.
+//! The look-up tables are loaded from the registers: the full ones for
+//! [`update_frame`](WaveshareDisplay::update_frame), the quick ones for partial updates.
+//!
+//! Partial windows start and end on a byte boundary, x and the width are multiples of 8 pixels
+//! as reported by [`partial_alignment`](WaveshareDisplay::partial_alignment). Windows can be
+//! grown to that with [`PartialAlignment::expand`](crate::traits::PartialAlignment::expand).
+//! The controller compares the new data with the old data in the first transmission, for clean
+//! partial refreshes send the previous content of the window with
+//! [`update_partial_old_frame`](QuickRefresh::update_partial_old_frame) first.
+//!
+//! The 1.9" segment panel isn't covered by this driver: it is driven over I2C with the state of
+//! its segments instead of a frame of pixels, which doesn't fit [`WaveshareDisplay`].
+//!
+//! Build with the help of documentation/code from [Waveshare](https://www.waveshare.com/wiki/1.02inch_e-Paper_Module).
+//!
+//! # Example
+//!
+//! A battery label in the bottom 8 rows, updated without refreshing the rest of the display:
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!use embedded_graphics::{
+//!    mono_font::{ascii::FONT_5X8, MonoTextStyle}, prelude::*, text::{Baseline, Text},
+//!};
+//!use epd_waveshare::{epd1in02::*, graphics::VarDisplay, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!
+//!let mut epd = Epd1in02::new(&mut spi, busy_in, dc, rst, None)?;
+//!let style = MonoTextStyle::new(&FONT_5X8, Color::Black);
+//!
+//!let mut old = [Color::White.get_byte_value(); WIDTH as usize / 8 * 8];
+//!let mut new = old;
+//!for text in ["100%", "99%", "98%"] {
+//!    let mut label = VarDisplay::new(WIDTH, 8, &mut new, false).unwrap();
+//!    let _ = label.clear(Color::White);
+//!    let _ = Text::with_baseline(text, Point::zero(), style, Baseline::Top).draw(&mut label);
+//!
+//!    epd.update_partial_old_frame(&mut spi, &old, 0, HEIGHT - 8, WIDTH, 8)?;
+//!    epd.update_partial_new_frame(&mut spi, &new, 0, HEIGHT - 8, WIDTH, 8)?;
+//!    epd.display_frame(&mut spi)?;
+//!    old = new;
+//!}
+//!
+//!epd.sleep(&mut spi)?;
+//!# Ok(())
+//!# }
+//!```
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::color::Color;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::buffer_len;
+
+mod constants;
+use self::constants::*;
+
+/// Width of epd1in02 in pixels
+pub const WIDTH: u32 = 80;
+/// Height of epd1in02 in pixels
+pub const HEIGHT: u32 = 128;
+/// Default Background Color (white)
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Full size buffer for use with the 1in02 EPD
+#[cfg(feature = "graphics")]
+pub type Display1in02 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd1in02 driver
+pub struct Epd1in02<SPI, BUSY, DC, RST> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    config: UserConfig,
+    /// Look-up tables in the registers of the controller
+    refresh: RefreshLut,
+    /// A partial window is set, the next refresh only changes it
+    partial: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // Based on the reference code from:
+        // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in02d.c
+        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.partial = false;
+
+        self.cmd_with_data(spi, Command::VendorSetting, &[0x3F])
+            .await?;
+
+        // LUT from the registers
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x6F])
+            .await?;
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2B, 0x2B])
+            .await?;
+
+        self.cmd_with_data(spi, Command::ChargePumpSetting, &[0x3F])
+            .await?;
+
+        self.cmd_with_data(spi, Command::LutOption, &[0x00, 0x00])
+            .await?;
+
+        // 50Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x13])
+            .await?;
+
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x57])
+            .await?;
+
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])
+            .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, HEIGHT as u8],
+        )
+        .await?;
+
+        // -1V
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])
+            .await?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x33])
+            .await?;
+
+        self.set_lut(spi, Some(RefreshLut::Full)).await?;
+
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type DisplayColor = Color;
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // floating border
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])
+            .await?;
+
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.config.background_color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.config.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
+    /// Sends the frame for a full refresh, with the full look-up tables
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        self.set_lut(spi, Some(RefreshLut::Full)).await?;
+
+        // the full look-up tables don't depend on the old data
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
+            .await?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    /// Sends the new data of a window, the next refresh only changes the window
+    ///
+    /// Uses the quick look-up tables, x and `width` have to be multiples of 8.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.update_partial_new_frame(spi, buffer, x, y, width, height)
+            .await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        let color = self.config.background_color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, !color, NUM_DISPLAY_BITS)
+            .await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        let (white, black) = match self.refresh {
+            RefreshLut::Full => (&LUT_WHITE, &LUT_BLACK),
+            RefreshLut::Quick => (&LUT_WHITE_QUICK, &LUT_BLACK_QUICK),
+        };
+        self.cmd_with_data(spi, Command::LutWhite, white).await?;
+        self.cmd_with_data(spi, Command::LutBlack, black).await
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface
+            .wait_until_idle_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
+            .await
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd1in02 {
+            interface,
+            config: UserConfig::default(),
+            refresh: RefreshLut::Full,
+            partial: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> QuickRefresh<SPI, BUSY, DC, RST> for Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// To be followed immediately by `update_new_frame`.
+    async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await
+    }
+
+    /// To be used immediately after `update_old_frame`.
+    async fn update_new_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.display_frame(spi).await
+    }
+
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_new_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if buffer.len() != buffer_len(width as usize, height as usize) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.enter_partial(spi, x, y, width, height).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await
+    }
+
+    /// Always call `update_partial_old_frame` before this, with buffer-updating code
+    /// between the calls.
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if buffer.len() != buffer_len(width as usize, height as usize) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.enter_partial(spi, x, y, width, height).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.enter_partial(spi, x, y, width, height).await?;
+        let color = self.config.background_color.get_byte_value();
+        let len = width / 8 * height;
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface.data_x_times(spi, !color, len).await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface.data_x_times(spi, color, len).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> Epd1in02<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Loads the quick look-up tables and sets the partial window
+    async fn enter_partial(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if width == 0
+            || height == 0
+            || !self.partial_alignment().is_aligned(x, y, width, height)
+            || x.saturating_add(width) > WIDTH
+            || y.saturating_add(height) > HEIGHT
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        if self.refresh != RefreshLut::Quick {
+            self.set_lut(spi, Some(RefreshLut::Quick)).await?;
+        }
+        if !self.partial {
+            self.command(spi, Command::PartialIn).await?;
+            self.partial = true;
+        }
+        // start and end of x and y, gates scan inside of the window only
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                x as u8,
+                (x + width - 1) as u8,
+                y as u8,
+                (y + height - 1) as u8,
+                0x00,
+            ],
+        )
+        .await
+    }
+
+    async fn leave_partial(&mut self, spi: &mut SPI) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if self.partial {
+            self.command(spi, Command::PartialOut).await?;
+            self.partial = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd1in02, Display1in02, "epd1in02");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{block_on, contains, IdleBusy, NoopPin, RecordingSpi};
+    use crate::traits::PartialAlignment;
+
+    extern crate std;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 80);
+        assert_eq!(HEIGHT, 128);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_sends_the_uc8175_registers() {
+        let mut spi = RecordingSpi::default();
+        let _epd = block_on(Epd1in02::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let written = spi.take();
+        assert!(contains(&written, &[Command::PowerSaving as u8, 0x33]));
+        assert!(contains(
+            &written,
+            &[Command::ResolutionSetting as u8, 80, 128]
+        ));
+        assert!(contains(
+            &written,
+            &[Command::LutWhite as u8, 0x60, 0x5A, 0x5A]
+        ));
+        assert!(contains(
+            &written,
+            &[Command::ChargePumpSetting as u8, 0x3F]
+        ));
+        assert_eq!(written.last(), Some(&(Command::PowerOn as u8)));
+    }
+
+    #[test]
+    fn update_sends_old_and_new_data() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd1in02::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let buffer = [0x0F; NUM_DISPLAY_BITS as usize];
+        spi.take();
+
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        let written = spi.take();
+        let mut expected = std::vec![Command::LutWhite as u8];
+        expected.extend_from_slice(&LUT_WHITE);
+        expected.push(Command::LutBlack as u8);
+        expected.extend_from_slice(&LUT_BLACK);
+        expected.push(Command::DataStartTransmission1 as u8);
+        expected.extend_from_slice(&[0xFF; NUM_DISPLAY_BITS as usize]);
+        expected.push(Command::DataStartTransmission2 as u8);
+        expected.extend_from_slice(&buffer);
+        expected.extend_from_slice(&[Command::PowerOn as u8, Command::DisplayRefresh as u8]);
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn partial_update_of_an_8x8_window() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd1in02::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.update_partial_old_frame(&mut spi, &[0xFF; 8], 8, 120, 8, 8)).unwrap();
+        block_on(epd.update_partial_new_frame(&mut spi, &[0x00; 8], 8, 120, 8, 8)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let written = spi.take();
+
+        let mut expected = std::vec![Command::LutWhite as u8];
+        expected.extend_from_slice(&LUT_WHITE_QUICK);
+        expected.push(Command::LutBlack as u8);
+        expected.extend_from_slice(&LUT_BLACK_QUICK);
+        let window = [Command::PartialWindow as u8, 8, 15, 120, 127, 0x00];
+        expected.push(Command::PartialIn as u8);
+        expected.extend_from_slice(&window);
+        expected.push(Command::DataStartTransmission1 as u8);
+        expected.extend_from_slice(&[0xFF; 8]);
+        expected.extend_from_slice(&window);
+        expected.push(Command::DataStartTransmission2 as u8);
+        expected.extend_from_slice(&[0x00; 8]);
+        expected.extend_from_slice(&[
+            Command::PowerOn as u8,
+            Command::DisplayRefresh as u8,
+            Command::PartialOut as u8,
+        ]);
+        assert_eq!(written, expected);
+
+        // windows end on byte boundaries
+        assert_eq!(epd.partial_alignment(), PartialAlignment::BYTE);
+        for (x, width) in [(4, 8), (8, 4), (0, 0), (72, 16)] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &[0x00; 8], x, 0, width, 8)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd1in02, "epd1in02");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd1in02,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::DataStartTransmission2 as u8, 0x00, 0x00][..]]
+    );
+}
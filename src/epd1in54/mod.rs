@@ -49,13 +49,17 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy::DEFAULT;
 //const DPI: u16 = 184;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::{
     traits::{ErrorType, InternalWiAdditions},
@@ -67,9 +71,9 @@ use crate::{
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{RefreshLut, WaveshareConstructor, WaveshareDisplay};
 
 /// Full size buffer for use with the 1in54b EPD
 #[cfg(feature = "graphics")]
@@ -85,21 +89,49 @@ pub type Display1in54 = crate::graphics::Display<
 pub struct Epd1in54<SPI, BUSY, DC, RST> {
     /// SPI
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Color
-    background_color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+}
+
+#[cfg(feature = "compat-0-5")]
+impl<SPI, BUSY, DC, RST> Epd1in54<SPI, BUSY, DC, RST> {
+    /// The same driver for another type of SPI device, see [`crate::compat`]
+    pub(crate) fn rebind_spi<S>(self) -> Epd1in54<S, BUSY, DC, RST> {
+        Epd1in54 {
+            interface: self.interface.rebind_spi(),
+            config: self.config,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -109,11 +141,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54<SP
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -172,11 +204,11 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
@@ -188,24 +220,32 @@ where
         HEIGHT
     }
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
 
-        let mut epd = Epd1in54 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
 
-        epd.init(spi).await?;
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
 
-        Ok(epd)
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -271,12 +311,22 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.use_full_frame(spi).await?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
@@ -285,11 +335,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.background_color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.background_color
+        &self.config.background_color
     }
 
     async fn set_lut(
@@ -298,9 +348,9 @@ where
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
         if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+            self.config.refresh = refresh_lut;
         }
-        match self.refresh {
+        match self.config.refresh {
             RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE).await,
             RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE).await,
         }
@@ -309,19 +359,61 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd1in54 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd1in54<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     pub(crate) async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -407,6 +499,9 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd1in54, Display1in54, "epd1in54");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -417,4 +512,23 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd1in54, "epd1in54");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd1in54,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            [
+                &[Command::WriteLutRegister as u8][..],
+                &LUT_PARTIAL_UPDATE[..]
+            ]
+            .concat(),
+            [Command::WriteRam as u8, 0x00, 0x00].to_vec(),
+        ]
+    );
 }
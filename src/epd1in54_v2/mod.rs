@@ -10,27 +10,27 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy::DEFAULT;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
-use embedded_hal::{
-    delay::*,
-    digital::{InputPin, OutputPin},
-};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::{
     color::Color,
-    error::ErrorKind,
-    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay},
-    type_a::{
-        command::Command,
-        constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
-    },
+    error::{ErrorKind, InitError},
+    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay},
+    type_a::command::Command,
 };
 
-use crate::interface::DisplayInterface;
+use self::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
 
 #[cfg(feature = "graphics")]
 pub use crate::epd1in54::Display1in54;
@@ -39,22 +39,38 @@ pub use crate::epd1in54::Display1in54;
 pub struct Epd1in54<SPI, BUSY, DC, RST> {
     /// SPI
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Color
-    background_color: Color,
+    config: UserConfig,
+}
 
-    /// Refresh LUT
-    refresh: RefreshLut,
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -64,11 +80,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54<SP
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -133,11 +149,11 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
@@ -149,24 +165,32 @@ where
         HEIGHT
     }
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
 
-        let mut epd = Epd1in54 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
 
-        epd.init(spi).await?;
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
 
-        Ok(epd)
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -198,6 +222,13 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
+        if width == 0
+            || height == 0
+            || x.saturating_add(width) > WIDTH
+            || y.saturating_add(height) > HEIGHT
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
         self.wait_until_idle(spi).await?;
         self.set_ram_area(spi, x, y, x + width, y + height).await?;
         self.set_ram_counter(spi, x, y).await?;
@@ -209,11 +240,11 @@ where
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        if self.refresh == RefreshLut::Full {
+        if self.config.refresh == RefreshLut::Full {
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])
                 .await?;
-        } else if self.refresh == RefreshLut::Quick {
+        } else if self.config.refresh == RefreshLut::Quick {
             self.interface
                 .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xCF])
                 .await?;
@@ -234,12 +265,22 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.use_full_frame(spi).await?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
@@ -252,11 +293,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.background_color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.background_color
+        &self.config.background_color
     }
 
     async fn set_lut(
@@ -265,15 +306,15 @@ where
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
         if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+            self.config.refresh = refresh_lut;
         }
-        match self.refresh {
+        match self.config.refresh {
             RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE).await,
             RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE).await,
         }?;
 
         // Additional configuration required only for partial updates
-        if self.refresh == RefreshLut::Quick {
+        if self.config.refresh == RefreshLut::Quick {
             self.interface
                 .cmd_with_data(
                     spi,
@@ -298,19 +339,61 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd1in54 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd1in54<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     pub(crate) async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -331,8 +414,9 @@ where
         end_y: u32,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         self.wait_until_idle(spi).await?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        if start_x >= end_x || start_y >= end_y {
+            return Err(ErrorKind::InvalidArgument);
+        }
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -388,7 +472,7 @@ where
         buffer: &[u8],
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         self.wait_until_idle(spi).await?;
-        assert!(buffer.len() == 159);
+        debug_assert_eq!(buffer.len(), 159);
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, &buffer[0..153])
@@ -417,9 +501,17 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd1in54, Display1in54, "epd1in54_v2");
+
 #[cfg(test)]
 mod tests {
+    extern crate std;
+
     use super::*;
+    use crate::test_utils::{block_on, contains, IdleBusy, NoopPin, RecordingSpi};
+    use std::vec;
+    use std::vec::Vec;
 
     #[test]
     fn epd_size() {
@@ -427,4 +519,66 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn partial_frame_out_of_range() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd1in54::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let buffer = [0xFF; 8];
+
+        for (x, y, width, height) in [
+            (0, 0, 0, 8),
+            (0, 0, 8, 0),
+            (WIDTH - 7, 0, 8, 8),
+            (0, u32::MAX, 8, 8),
+        ] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &buffer, x, y, width, height)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 8, 1, 8, 1)).unwrap();
+    }
+
+    /// The bytes `set_lut_helper` sends for a table of the module
+    fn lut_commands(lut: &[u8; 159]) -> Vec<u8> {
+        let mut expected = vec![Command::WriteLutRegister as u8];
+        expected.extend_from_slice(&lut[..153]);
+        expected.extend([Command::WriteLutRegisterEnd as u8, lut[153]]);
+        expected.extend([Command::GateDrivingVoltage as u8, lut[154]]);
+        expected.extend([
+            Command::SourceDrivingVoltage as u8,
+            lut[155],
+            lut[156],
+            lut[157],
+        ]);
+        expected.extend([Command::WriteVcomRegister as u8, lut[158]]);
+        expected
+    }
+
+    #[test]
+    fn sends_the_v2_look_up_tables() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd1in54::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        assert!(contains(&spi.take(), &lut_commands(&LUT_FULL_UPDATE)));
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        let expected = lut_commands(&LUT_PARTIAL_UPDATE);
+        assert_eq!(spi.take()[..expected.len()], expected);
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd1in54,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            lut_commands(&LUT_PARTIAL_UPDATE),
+            vec![Command::WriteRam as u8, 0x00, 0x00],
+        ]
+    );
 }
@@ -1,15 +1,13 @@
 //! A simple Driver for the Waveshare 1.54" (B) E-Ink Display via SPI
 use core::fmt::{Debug, Display};
-use embedded_hal::{
-    delay::*,
-    digital::{InputPin, OutputPin},
-};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
 use crate::traits::{
-    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 //The Lookup Tables for the Display
@@ -45,18 +43,37 @@ pub type Display1in54b = crate::graphics::Display<
 /// Epd1in54b driver
 pub struct Epd1in54b<SPI, BUSY, DC, RST> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    color: Color,
+    config: UserConfig,
+    chromatic_passes: ChromaticPasses,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and to clear the chromatic layer in
+    /// `update_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -66,11 +83,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54b<S
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -121,11 +138,11 @@ impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn update_color_frame(
@@ -168,37 +185,33 @@ where
         self.interface.data(spi, chromatic).await?;
         Ok(())
     }
+
+    async fn refresh_and_wait(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.display_frame(spi).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
 }
 
 impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd1in54b { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.interface
@@ -225,11 +238,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -240,6 +253,34 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
@@ -257,7 +298,7 @@ where
         //NOTE: Example code has a delay here
 
         // Clear the read layer
-        let color = self.color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
         let nbits = WIDTH * (HEIGHT / 8);
 
         self.interface
@@ -268,17 +309,17 @@ where
         //NOTE: Example code has a delay here
     }
 
-    #[allow(unused)]
+    /// Partial updates aren't supported, returns [`ErrorKind::InvalidArgument`]
     async fn update_partial_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!()
+        Err(ErrorKind::InvalidArgument)
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -295,11 +336,21 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         // Clear the black
         self.interface
@@ -352,19 +403,61 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd1in54b {
+            interface,
+            config: UserConfig::default(),
+            chromatic_passes: ChromaticPasses::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd1in54b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -387,7 +480,7 @@ where
         command: Command,
         data: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd_with_data(spi, command, data)
+        self.interface.cmd_with_data(spi, command, data).await
     }
 
     async fn send_resolution(
@@ -416,6 +509,9 @@ fn expand_bits(bits: u8) -> [u8; 2] {
     [(x >> 8) as u8, (x & 0xFF) as u8]
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd1in54b, Display1in54b, "epd1in54b", planes);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -426,4 +522,13 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd1in54b,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::DataStartTransmission1 as u8, 0x00, 0x00][..]]
+    );
 }
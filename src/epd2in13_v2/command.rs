@@ -188,7 +188,7 @@ impl BorderWaveForm {
 }
 
 #[allow(dead_code)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DeepSleepMode {
     // Sleeps and keeps access to RAM and controller
     Normal = 0x00,
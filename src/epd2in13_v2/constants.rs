@@ -1,6 +1,6 @@
 #[rustfmt::skip]
 
-#[cfg(feature = "epd2in13_v2")]
+#[cfg(any(feature = "epd2in13_v2", not(feature = "epd2in13_v3")))]
 // Original Waveforms from Waveshare
 pub(crate) const LUT_FULL_UPDATE: [u8; 70] =[
     0x80,0x60,0x40,0x00,0x00,0x00,0x00,             // LUT0: BB:     VS 0 ~7
@@ -18,7 +18,7 @@ pub(crate) const LUT_FULL_UPDATE: [u8; 70] =[
     0x00,0x00,0x00,0x00,0x00,                       //  TP6 A~D RP6
 ];
 
-#[cfg(feature = "epd2in13_v2")]
+#[cfg(any(feature = "epd2in13_v2", not(feature = "epd2in13_v3")))]
 #[rustfmt::skip]
 pub(crate) const LUT_PARTIAL_UPDATE: [u8; 70] =[
     0x00,0x00,0x00,0x00,0x00,0x00,0x00,             // LUT0: BB:     VS 0 ~7
@@ -36,7 +36,7 @@ pub(crate) const LUT_PARTIAL_UPDATE: [u8; 70] =[
     0x00,0x00,0x00,0x00,0x00,                       //  TP6 A~D RP6
 ];
 
-#[cfg(feature = "epd2in13_v3")]
+#[cfg(all(feature = "epd2in13_v3", not(feature = "epd2in13_v2")))]
 #[rustfmt::skip]
 // Original Waveforms from Waveshare
 pub(crate) const LUT_PARTIAL_UPDATE: [u8; 159] =[
@@ -61,7 +61,7 @@ pub(crate) const LUT_PARTIAL_UPDATE: [u8; 159] =[
 	0x22,0x17,0x41,0x00,0x32,0x36,
 ];
 
-#[cfg(feature = "epd2in13_v3")]
+#[cfg(all(feature = "epd2in13_v3", not(feature = "epd2in13_v2")))]
 #[rustfmt::skip]
 pub(crate) const LUT_FULL_UPDATE: [u8; 159] =[
 	0x80,0x4A,0x40,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,0x0,
@@ -15,14 +15,17 @@
 //! - [Controller Datasheet SS1780](http://www.e-paper-display.com/download_detail/downloadsId=682.html)
 //!
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::confirm;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, SafeCommands, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::{
@@ -34,14 +37,6 @@ use self::command::{
 pub(crate) mod constants;
 
 use self::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
-#[cfg(all(feature = "epd2in13_v2", feature = "epd2in13_v3"))]
-compile_error!(
-    "feature \"epd2in13_v2\" and feature \"epd2in13_v3\" cannot be enabled at the same time"
-);
-#[cfg(not(any(feature = "epd2in13_v2", feature = "epd2in13_v3")))]
-compile_error!(
-    "One of feature \"epd2in13_v2\" and feature \"epd2in13_v3\" needs to be enabled as a feature"
-);
 
 /// Full size buffer for use with the 2in13 v2 and v3 EPD
 #[cfg(feature = "graphics")]
@@ -61,58 +56,101 @@ pub const HEIGHT: u32 = 250;
 
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy::DEFAULT;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// How often a register is written again if it reads back wrong, see
+/// [`Epd2in13::verify_register_writes`]
+pub const REGISTER_VERIFY_RETRIES: u8 = 2;
+
+/// Registers that are verified after writing them, with the mask of the bits that read back
+const VERIFIED_REGISTERS: [(Command, &[u8]); 6] = [
+    (Command::DriverOutputControl, &[0xFF, 0x01, 0x07]),
+    (Command::DataEntryModeSetting, &[0x07]),
+    (Command::SetRamXAddressStartEndPosition, &[0x3F, 0x3F]),
+    (
+        Command::SetRamYAddressStartEndPosition,
+        &[0xFF, 0x01, 0xFF, 0x01],
+    ),
+    (Command::WriteVcomRegister, &[0xFF]),
+    (Command::DisplayUpdateControl2, &[0xFF]),
+];
+
 /// Epd2in13 (V2 & V3) driver
 ///
-/// To use this driver for V2 of the display, feature \"epd2in13_v3\" needs to be disabled and feature \"epd2in13_v2\" enabled.
+/// Uses the look-up tables of the V3 of the display with feature \"epd2in13_v3\", which is
+/// enabled by default, and those of the V2 with feature \"epd2in13_v2\" or without either.
+/// The V2 tables win if both are enabled. [`epd2in13_v3`](crate::epd2in13_v3) drives the V3
+/// with the waveforms of its controller instead.
 pub struct Epd2in13<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
 
-    sleep_mode: DeepSleepMode,
+    config: UserConfig,
+    verify_registers: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+    /// Mode entered by `sleep`, see [`Epd2in13::set_deep_sleep_mode`]
+    pub deep_sleep_mode: DeepSleepMode,
+}
 
-    /// Background Color
-    background_color: Color,
-    refresh: RefreshLut,
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            deep_sleep_mode: DeepSleepMode::Mode1,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in13<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST>
-    for Epd2in13<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in13<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // HW reset
         self.interface.reset(spi, 10_000, 10_000).await?;
 
-        if self.refresh == RefreshLut::Quick {
+        if self.config.refresh == RefreshLut::Quick {
             self.set_vcom_register(spi, (-9).vcom()).await?;
             self.wait_until_idle(spi).await?;
 
-            self.set_lut(spi, Some(self.refresh)).await?;
+            self.set_lut(spi, Some(self.config.refresh)).await?;
 
             // Python code does this, not sure why
             // self.cmd_with_data(spi, Command::WriteOtpSelection, &[0, 0, 0, 0, 0x40, 0, 0])?;
@@ -187,7 +225,7 @@ where
 
             self.set_gate_line_width(spi, 10).await?;
 
-            self.set_lut(spi, Some(self.refresh)).await?;
+            self.set_lut(spi, Some(self.config.refresh)).await?;
         }
 
         self.wait_until_idle(spi).await?;
@@ -199,32 +237,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in13<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let mut epd = Epd2in13 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
-            sleep_mode: DeepSleepMode::Mode1,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi).await?;
-        Ok(epd)
-    }
-
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.init(spi).await
     }
@@ -244,7 +264,8 @@ where
         .await?;
         self.command(spi, Command::MasterActivation).await?;
 
-        self.set_sleep_mode(spi, self.sleep_mode).await?;
+        self.set_sleep_mode(spi, self.config.deep_sleep_mode)
+            .await?;
         Ok(())
     }
 
@@ -255,7 +276,7 @@ where
 
         self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
 
-        if self.refresh == RefreshLut::Full {
+        if self.config.refresh == RefreshLut::Full {
             // Always keep the base buffer equal to current if not doing partial refresh.
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
             self.set_ram_address_counters(spi, 0, 0).await?;
@@ -286,14 +307,14 @@ where
         // RAM content). Using this function will most probably make the actual
         // display incorrect as the controler will compare with something
         // incorrect.
-        assert!(self.refresh == RefreshLut::Full);
+        assert!(self.config.refresh == RefreshLut::Full);
 
         self.set_ram_area(spi, x, y, x + width, y + height).await?;
         self.set_ram_address_counters(spi, x, y).await?;
 
         self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
 
-        if self.refresh == RefreshLut::Full {
+        if self.config.refresh == RefreshLut::Full {
             // Always keep the base buffer equals to current if not doing partial refresh.
             self.set_ram_area(spi, x, y, x + width, y + height).await?;
             self.set_ram_address_counters(spi, x, y).await?;
@@ -308,7 +329,7 @@ where
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        if self.refresh == RefreshLut::Full {
+        if self.config.refresh == RefreshLut::Full {
             self.set_display_update_control_2(
                 spi,
                 DisplayUpdateControl2::new()
@@ -337,14 +358,39 @@ where
         self.update_frame(spi, buffer).await?;
         self.display_frame(spi).await?;
 
-        if self.refresh == RefreshLut::Quick {
+        if self.config.refresh == RefreshLut::Quick {
             self.set_partial_base_buffer(spi, buffer).await?;
         }
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        if self.config.refresh == RefreshLut::Quick {
+            // the base of the next quick refresh is written once the refresh has finished
+            return self.update_and_display_frame(spi, buffer).await;
+        }
+        self.update_frame(spi, buffer).await?;
+        self.set_display_update_control_2(
+            spi,
+            DisplayUpdateControl2::new()
+                .enable_clock()
+                .enable_analog()
+                .display()
+                .disable_analog()
+                .disable_clock(),
+        )
+        .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let color = self.background_color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
         self.set_ram_address_counters(spi, 0, 0).await?;
@@ -359,7 +405,7 @@ where
             .await?;
 
         // Always keep the base buffer equals to current if not doing partial refresh.
-        if self.refresh == RefreshLut::Full {
+        if self.config.refresh == RefreshLut::Full {
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
             self.set_ram_address_counters(spi, 0, 0).await?;
 
@@ -376,11 +422,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.background_color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.background_color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -391,6 +437,49 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    /// Reads critical registers back after writing them
+    ///
+    /// Guards against corrupted register writes, e.g. from EMI on long cables, which would
+    /// leave wrong scan or waveform settings until the next init. The driver output, data
+    /// entry, RAM window, VCOM and update control registers are read back after every write and
+    /// written again up to [`REGISTER_VERIFY_RETRIES`] times, before failing with
+    /// `ErrorKind::RegisterVerifyFailed`. Frame data isn't verified.
+    ///
+    /// Needs the data line of the controller connected for reading. Off by default, which
+    /// costs no extra transactions.
+    fn verify_register_writes(&mut self, verify: bool) -> Result<(), Self::Error> {
+        self.verify_registers = verify;
+        Ok(())
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn set_lut(
         &mut self,
         spi: &mut SPI,
@@ -409,19 +498,59 @@ where
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await?;
         Ok(())
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in13<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let mut epd = Epd2in13 {
+            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            config: UserConfig::default(),
+            verify_registers: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in13<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     /// When using partial refresh, the controller uses the provided buffer for
     /// comparison with new buffer.
     pub async fn set_partial_base_buffer(
@@ -438,9 +567,17 @@ where
         Ok(())
     }
 
+    /// Checks that every display update reaches the controller
+    ///
+    /// See [`SafeCommands`], `None` turns the checks off, which is the default. Together with
+    /// [`verify_register_writes`](Self::verify_register_writes) for the registers.
+    pub fn set_safe_commands(&mut self, safe_commands: Option<SafeCommands>) {
+        self.interface.set_safe_commands(safe_commands);
+    }
+
     /// Selects which sleep mode will be used when triggering the deep sleep.
     pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
-        self.sleep_mode = mode;
+        self.config.deep_sleep_mode = mode;
     }
 
     /// Sets the refresh mode. When changing mode, the screen will be
@@ -450,8 +587,8 @@ where
         spi: &mut SPI,
         refresh: RefreshLut,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        if self.refresh != refresh {
-            self.refresh = refresh;
+        if self.config.refresh != refresh {
+            self.config.refresh = refresh;
             self.init(spi).await?;
         }
         Ok(())
@@ -634,7 +771,9 @@ where
         spi: &mut SPI,
         command: Command,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd(spi, command).await
+        self.interface
+            .cmd_confirmed(spi, command, IS_BUSY_LOW, confirm::SSD16XX)
+            .await
     }
 
     async fn cmd_with_data(
@@ -643,13 +782,44 @@ where
         command: Command,
         data: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd_with_data(spi, command, data).await
+        self.interface.cmd_with_data(spi, command, data).await?;
+        if !self.verify_registers {
+            return Ok(());
+        }
+        let Some((_, mask)) = VERIFIED_REGISTERS
+            .iter()
+            .find(|(register, _)| *register as u8 == command as u8)
+        else {
+            return Ok(());
+        };
+
+        for attempt in 0..=REGISTER_VERIFY_RETRIES {
+            let mut readback = [0u8; 4];
+            let readback = &mut readback[..mask.len()];
+            self.interface.cmd_read(spi, command, readback).await?;
+            let matches = readback
+                .iter()
+                .zip(data)
+                .zip(mask.iter())
+                .all(|((read, written), mask)| (read ^ written) & mask == 0);
+            if matches {
+                return Ok(());
+            }
+            if attempt < REGISTER_VERIFY_RETRIES {
+                self.interface.cmd_with_data(spi, command, data).await?;
+            }
+        }
+        Err(ErrorKind::RegisterVerifyFailed(command as u8))
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in13, Display2in13, "epd2in13_v2");
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{blank_buffer, block_on, contains, IdleBusy, NoopPin, ScriptedSpi};
 
     #[test]
     fn epd_size() {
@@ -657,4 +827,93 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    const X_RANGE: [u8; 3] = [Command::SetRamXAddressStartEndPosition as u8, 0x00, 0x0F];
+    const Y_RANGE: [u8; 5] = [
+        Command::SetRamYAddressStartEndPosition as u8,
+        0x00,
+        0x00,
+        0xF9,
+        0x00,
+    ];
+
+    #[test]
+    fn register_writes_are_only_read_back_when_enabled() {
+        let buffer = blank_buffer(WIDTH, HEIGHT);
+        let mut spi = ScriptedSpi::default();
+        let mut epd =
+            block_on(Epd2in13::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.set_partial_base_buffer(&mut spi, &buffer)).unwrap();
+        assert_eq!(spi.reads, 0);
+    }
+
+    #[test]
+    fn corrupted_register_is_written_again() {
+        let buffer = blank_buffer(WIDTH, HEIGHT);
+        let mut spi = ScriptedSpi::default();
+        let mut epd =
+            block_on(Epd2in13::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        epd.verify_register_writes(true).unwrap();
+        spi.take();
+
+        // a flipped bit in the end of the X range, and reserved bits the controller ignores
+        spi.answer(&[0x00, 0x0E]);
+        spi.answer(&[0x00, 0x0F]);
+        spi.answer(&[0x00, 0xFE, 0xF9, 0xFE]);
+        block_on(epd.set_partial_base_buffer(&mut spi, &buffer)).unwrap();
+
+        assert_eq!(spi.reads, 3);
+        let written = spi.take();
+        let x_range_at = |from: usize| {
+            written[from..]
+                .windows(X_RANGE.len())
+                .position(|window| window == X_RANGE)
+                .map(|i| i + from)
+        };
+        let first = x_range_at(0).unwrap();
+        assert_eq!(written[first + 3], X_RANGE[0]);
+        assert!(x_range_at(first + 4).is_some());
+        assert!(contains(&written, &Y_RANGE));
+    }
+
+    #[test]
+    fn persistent_mismatch_fails() {
+        let buffer = blank_buffer(WIDTH, HEIGHT);
+        let mut spi = ScriptedSpi::default();
+        let mut epd =
+            block_on(Epd2in13::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        epd.verify_register_writes(true).unwrap();
+        spi.take();
+
+        for _ in 0..=REGISTER_VERIFY_RETRIES {
+            spi.answer(&[0x01, 0x0F]);
+        }
+        assert!(matches!(
+            block_on(epd.set_partial_base_buffer(&mut spi, &buffer)),
+            Err(ErrorKind::RegisterVerifyFailed(0x44))
+        ));
+        assert_eq!(spi.reads, usize::from(REGISTER_VERIFY_RETRIES) + 1);
+        assert!(!contains(&spi.take(), &Y_RANGE));
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in13,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_refresh(&mut spi, RefreshLut::Quick)).unwrap();
+            epd.set_deep_sleep_mode(DeepSleepMode::Mode2);
+        },
+        [
+            [
+                &[Command::WriteLutRegister as u8][..],
+                &LUT_PARTIAL_UPDATE[..]
+            ]
+            .concat(),
+            [Command::WriteRam as u8, 0x00, 0x00].to_vec(),
+            [Command::DeepSleepMode as u8, DeepSleepMode::Mode2 as u8].to_vec(),
+        ]
+    );
 }
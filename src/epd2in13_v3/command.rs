@@ -0,0 +1,49 @@
+//! SPI Commands for the Waveshare 2.13" V3 E-Ink Display
+//!
+//! The panel has an SSD1680 controller, see its datasheet for the parameters.
+use crate::traits;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Number of gates, gate scanning sequence and direction
+    DriverOutputControl = 0x01,
+    GateDrivingVoltage = 0x03,
+    SourceDrivingVoltage = 0x04,
+    /// 0x00 for normal mode, 0x01 and 0x03 for the deep sleep modes
+    DeepSleepMode = 0x10,
+    /// Direction of the address counter, 0x03 increments x and then y
+    DataEntryModeSetting = 0x11,
+    /// Resets the commands and parameters to their defaults, BUSY is high while it runs
+    SwReset = 0x12,
+    /// 0x80 selects the built-in temperature sensor
+    TemperatureSensorSelection = 0x18,
+    TemperatureSensorControlWrite = 0x1A,
+    /// Runs the sequence selected with `DisplayUpdateControl2`
+    MasterActivation = 0x20,
+    /// RAM content options, the second byte selects the source output mode
+    DisplayUpdateControl1 = 0x21,
+    /// Sequence of the next `MasterActivation`, 0xF7 refreshes with the full waveform from the
+    /// OTP, 0xFF with the partial one
+    DisplayUpdateControl2 = 0x22,
+    /// New data
+    WriteRam = 0x24,
+    /// Old data, compared with the new data by the partial waveform
+    WriteRamRed = 0x26,
+    WriteVcomRegister = 0x2C,
+    WriteLutRegister = 0x32,
+    /// Waveform of the border
+    BorderWaveformControl = 0x3C,
+    SetRamXAddressStartEndPosition = 0x44,
+    SetRamYAddressStartEndPosition = 0x45,
+    SetRamXAddressCounter = 0x4E,
+    SetRamYAddressCounter = 0x4F,
+    Nop = 0x7F,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
@@ -0,0 +1,656 @@
+//! A simple Driver for the Waveshare 2.13" E-Ink Display V3 via SPI
+//!
+//! The 122x250 panel has an SSD1680 controller. Unlike the 2.13" V2 driver it doesn't write
+//! look-up tables: full refreshes run the waveform from the OTP of the controller with the
+//! update sequence 0xF7, partial refreshes the partial one with 0xFF.
+//!
+//! [`update_frame`](WaveshareDisplay::update_frame) writes the frame to both RAMs of the
+//! controller, the partial waveform compares the new data of a window sent with
+//! [`update_partial_frame`](WaveshareDisplay::update_partial_frame) with it. The next
+//! [`display_frame`](WaveshareDisplay::display_frame) only changes the differing pixels. Windows
+//! start and end on a byte boundary, x and the width are multiples of 8 pixels.
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13_V3.c)
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!use embedded_graphics::{
+//!    prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd2in13_v3::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!
+//!let mut epd = Epd2in13V3::new(&mut spi, busy_in, dc, rst, None)?;
+//!let mut display = Display2in13V3::default();
+//!
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 249))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!epd.update_and_display_frame(&mut spi, display.buffer())?;
+//!
+//!// the top 8 rows, refreshed with the partial waveform
+//!let window = [Color::Black.get_byte_value(); 16 * 8];
+//!epd.update_partial_frame(&mut spi, &window, 0, 0, 128, 8)?;
+//!epd.display_frame(&mut spi)?;
+//!
+//!epd.sleep(&mut spi)?;
+//!# Ok(())
+//!# }
+//!```
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Width of epd2in13_v3 in pixels
+pub const WIDTH: u32 = 122;
+/// Height of epd2in13_v3 in pixels
+pub const HEIGHT: u32 = 250;
+/// Default Background Color (white)
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+const NUM_DISPLAY_BITS: u32 = WIDTH.div_ceil(8) * HEIGHT;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Update sequence of a full refresh: clock and analog on, temperature and waveform from the
+/// OTP, display mode 1
+const FULL_UPDATE: u8 = 0xF7;
+/// Update sequence of a partial refresh, the same with display mode 2
+const PARTIAL_UPDATE: u8 = 0xFF;
+
+/// Full size buffer for use with the 2in13 V3 EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13V3 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd2in13V3 driver
+pub struct Epd2in13V3<SPI, BUSY, DC, RST> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    config: UserConfig,
+    /// A partial window is written, the next refresh uses the partial waveform
+    partial: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in13V3<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in13V3<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // Based on the reference code from:
+        // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13_V3.c
+        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.partial = false;
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // 250 gates, scanning from G0 upwards
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )
+        .await?;
+
+        // x increments, then y
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+        self.use_full_frame(spi).await?;
+
+        // the border follows the look-up table of white pixels
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])
+            .await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])
+            .await?;
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in13V3<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type DisplayColor = Color;
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // deep sleep mode 1, the RAM is kept
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.config.background_color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.config.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
+    /// Writes the frame to both RAMs, as the base of the following partial refreshes
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        if buffer.len() != NUM_DISPLAY_BITS as usize {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+        self.cmd_with_data(spi, Command::WriteRamRed, buffer).await
+    }
+
+    /// Writes the new data of a window, the next refresh uses the partial waveform
+    ///
+    /// x and `width` have to be multiples of 8.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if width == 0
+            || height == 0
+            || !self.partial_alignment().is_aligned(x, y, width, height)
+            || x.saturating_add(width) > WIDTH.next_multiple_of(8)
+            || y.saturating_add(height) > HEIGHT
+            || buffer.len() != buffer_len(width as usize, height as usize)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.partial = true;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.activate(spi).await?;
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.activate(spi).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.leave_partial(spi).await?;
+        let color = self.config.background_color.get_byte_value();
+
+        self.command(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await?;
+        self.command(spi, Command::WriteRamRed).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    /// Selects the waveform of whole frames, the quick one is the partial waveform
+    ///
+    /// Both are stored in the OTP of the controller, nothing is sent.
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.config.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in13V3<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd2in13V3 {
+            interface,
+            config: UserConfig::default(),
+            partial: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> Epd2in13V3<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Starts the refresh, with the partial waveform after a partial update
+    async fn activate(&mut self, spi: &mut SPI) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let sequence = if self.partial || self.config.refresh == RefreshLut::Quick {
+            PARTIAL_UPDATE
+        } else {
+            FULL_UPDATE
+        };
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])
+            .await?;
+        self.command(spi, Command::MasterActivation).await
+    }
+
+    /// Sets the RAM window back to the whole frame
+    async fn leave_partial(&mut self, spi: &mut SPI) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if self.partial {
+            self.use_full_frame(spi).await?;
+            self.partial = false;
+        }
+        Ok(())
+    }
+
+    async fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        // x is positioned in bytes
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )
+        .await?;
+
+        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await
+    }
+
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in13V3, Display2in13V3, "epd2in13_v3");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{block_on, contains, IdleBusy, NoopPin, RecordingSpi};
+
+    extern crate std;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(NUM_DISPLAY_BITS, 16 * 250);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_size() {
+        let display = Display2in13V3::default();
+        assert_eq!(display.buffer().len(), NUM_DISPLAY_BITS as usize);
+    }
+
+    #[test]
+    fn init_uses_the_ssd1680_setup() {
+        let mut spi = RecordingSpi::default();
+        let _epd = block_on(Epd2in13V3::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let written = spi.take();
+        assert!(contains(
+            &written,
+            &[Command::DriverOutputControl as u8, 0xF9, 0x00, 0x00]
+        ));
+        assert!(contains(
+            &written,
+            &[Command::DataEntryModeSetting as u8, 0x03]
+        ));
+        assert!(contains(
+            &written,
+            &[Command::SetRamXAddressStartEndPosition as u8, 0, 15]
+        ));
+        assert!(contains(
+            &written,
+            &[Command::SetRamYAddressStartEndPosition as u8, 0, 0, 249, 0]
+        ));
+        assert!(!contains(&written, &[Command::WriteLutRegister as u8]));
+    }
+
+    #[test]
+    fn full_refresh_uses_the_otp_waveform() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in13V3::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let buffer = [0x0F; NUM_DISPLAY_BITS as usize];
+        spi.take();
+
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        let mut expected = std::vec![Command::WriteRam as u8];
+        expected.extend_from_slice(&buffer);
+        expected.push(Command::WriteRamRed as u8);
+        expected.extend_from_slice(&buffer);
+        expected.extend_from_slice(&[
+            Command::DisplayUpdateControl2 as u8,
+            0xF7,
+            Command::MasterActivation as u8,
+        ]);
+        assert_eq!(spi.take(), expected);
+
+        assert!(matches!(
+            block_on(epd.update_frame(&mut spi, &buffer[1..])),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn partial_refresh_of_a_window() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in13V3::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        block_on(epd.update_partial_frame(&mut spi, &[0x00; 16], 8, 240, 16, 8)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let mut expected = std::vec![
+            Command::SetRamXAddressStartEndPosition as u8,
+            1,
+            2,
+            Command::SetRamYAddressStartEndPosition as u8,
+            240,
+            0,
+            247,
+            0,
+            Command::SetRamXAddressCounter as u8,
+            1,
+            Command::SetRamYAddressCounter as u8,
+            240,
+            0,
+            Command::WriteRam as u8,
+        ];
+        expected.extend_from_slice(&[0x00; 16]);
+        expected.extend_from_slice(&[
+            Command::DisplayUpdateControl2 as u8,
+            0xFF,
+            Command::MasterActivation as u8,
+            // back to the whole frame
+            Command::SetRamXAddressStartEndPosition as u8,
+            0,
+            15,
+            Command::SetRamYAddressStartEndPosition as u8,
+            0,
+            0,
+            249,
+            0,
+            Command::SetRamXAddressCounter as u8,
+            0,
+            Command::SetRamYAddressCounter as u8,
+            0,
+            0,
+        ]);
+        assert_eq!(spi.take(), expected);
+
+        // windows end on byte boundaries and inside of the panel
+        for (x, y, width) in [(4, 0, 8), (8, 0, 4), (0, 0, 0), (120, 0, 16), (0, 246, 8)] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &[0x00; 8], x, y, width, 8)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd2in13V3, "epd2in13_v3");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in13V3,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            &[Command::DisplayUpdateControl2 as u8, PARTIAL_UPDATE][..],
+            &[Command::WriteRam as u8, 0x00, 0x00][..],
+        ]
+    );
+}
@@ -51,13 +51,15 @@
 //!# }
 //!```
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::confirm;
+use crate::error::{ErrorKind, InitError, MismatchDiagnosis};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, SafeCommands, Timing};
 use crate::traits::{
-    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in13bc in pixels
@@ -71,13 +73,46 @@ pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 
 const IS_BUSY_LOW: bool = true;
-const VCOM_DATA_INTERVAL: u8 = 0x07;
-const WHITE_BORDER: u8 = 0x70;
-const BLACK_BORDER: u8 = 0x30;
-const CHROMATIC_BORDER: u8 = 0xb0;
-const FLOATING_BORDER: u8 = 0xF0;
+
+/// Diagnosis of a panel whose busy line idles low, like the one of an SSD controller
+const SSD_PANEL: MismatchDiagnosis = MismatchDiagnosis {
+    suspected: "SSD1680 (2.13\" b V4)",
+    driver: Some("epd2in13_v2 with the epd2in13_v3 feature"),
+};
+const _: () = assert!(Command::DisplayRefresh as u8 == confirm::FAMILY_PROBE.command);
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Values of the `VcomAndDataIntervalSetting` register
+pub mod consts {
+    use crate::color::TriColor;
+
+    /// VCOM and data interval, lower nibble of the register
+    pub const VCOM_DATA_INTERVAL: u8 = 0x07;
+    /// Border bits for a white border
+    pub const WHITE_BORDER: u8 = 0x70;
+    /// Border bits for a black border
+    pub const BLACK_BORDER: u8 = 0x30;
+    /// Border bits for a chromatic border
+    pub const CHROMATIC_BORDER: u8 = 0xb0;
+    /// Border bits for a floating border, used before going to sleep
+    pub const FLOATING_BORDER: u8 = 0xF0;
+
+    /// Complete register value for a border of the given color
+    pub const fn border_register(color: TriColor) -> u8 {
+        let border = match color {
+            TriColor::Black => BLACK_BORDER,
+            TriColor::White => WHITE_BORDER,
+            TriColor::Chromatic => CHROMATIC_BORDER,
+        };
+        border | VCOM_DATA_INTERVAL
+    }
+
+    // Values sent by the Waveshare reference driver on init and sleep
+    const _: () = assert!(border_register(TriColor::White) == 0x77);
+    const _: () = assert!(FLOATING_BORDER | VCOM_DATA_INTERVAL == 0xF7);
+}
+use self::consts::*;
+
 use crate::color::TriColor;
 
 pub(crate) mod command;
@@ -97,18 +132,40 @@ pub type Display2in13bc = crate::graphics::Display<
 /// Epd2in13bc driver
 pub struct Epd2in13bc<SPI, BUSY, DC, RST> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    color: TriColor,
+    config: UserConfig,
+    chromatic_passes: ChromaticPasses,
+}
+
+/// Settings chosen by the user
+///
+/// The display forgets its registers in deep sleep, `init` sends these values again on every
+/// wake up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used to clear the chromatic layer in `update_frame`
+    pub background_color: TriColor,
+    /// Color of the outer border
+    pub border_color: TriColor,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            border_color: TriColor::White,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in13bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -118,17 +175,20 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in13bc<
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Values taken from datasheet and sample code
 
         self.interface.reset(spi, 10_000, 10_000).await?;
+        if self.interface.check_panel() {
+            self.probe_panel(spi).await?;
+        }
 
         // start the booster
         self.interface
@@ -147,7 +207,7 @@ where
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[WHITE_BORDER | VCOM_DATA_INTERVAL],
+            &[border_register(self.config.border_color)],
         )
         .await?;
 
@@ -168,11 +228,11 @@ impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn update_color_frame(
@@ -216,37 +276,28 @@ where
         self.wait_until_idle(spi).await?;
         Ok(())
     }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
 }
 
 impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in13bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = TriColor;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in13bc { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Section 8.2 from datasheet
         self.interface
@@ -271,11 +322,11 @@ where
     }
 
     fn set_background_color(&mut self, color: TriColor) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &TriColor {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -286,6 +337,34 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -294,7 +373,7 @@ where
         self.interface.data(spi, buffer).await?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
@@ -337,6 +416,17 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.send_resolution(spi).await?;
 
@@ -374,17 +464,49 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    async fn sanity_check(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.probe_panel(spi).await?;
+        self.init(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in13bc<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        Self::try_init(spi, DisplayInterface::new(busy, dc, rst, delay_us)).await
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in13bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn command(
@@ -392,7 +514,9 @@ where
         spi: &mut SPI,
         command: Command,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd(spi, command).await
+        self.interface
+            .cmd_confirmed(spi, command, IS_BUSY_LOW, confirm::UC81XX)
+            .await
     }
 
     async fn send_data(
@@ -426,22 +550,118 @@ where
         self.send_data(spi, &[h as u8]).await
     }
 
+    /// Creates the driver like [`try_new`](WaveshareConstructor::try_new), with the
+    /// [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation
+    ///
+    /// The check runs before the driver first waits for the busy line, so a panel of the wrong
+    /// generation fails with [`ErrorKind::PanelMismatch`] instead of hanging.
+    pub async fn try_new_checked(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_check_panel(true);
+        Self::try_init(spi, interface).await
+    }
+
+    /// Runs the [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation, off
+    /// by default
+    pub fn set_check_panel(&mut self, check: bool) {
+        self.interface.set_check_panel(check);
+    }
+
+    async fn try_init(
+        spi: &mut SPI,
+        interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut epd = Epd2in13bc {
+            interface,
+            config: UserConfig::default(),
+            chromatic_passes: ChromaticPasses::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+
+    /// Fails if the busy line idles at the level of the other controller family, call right
+    /// after the reset
+    async fn probe_panel(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let idle_high = self
+            .interface
+            .busy_reaction(
+                spi,
+                Command::DisplayRefresh,
+                confirm::FAMILY_PROBE.within_us,
+            )
+            .await?;
+        if idle_high == Some(!IS_BUSY_LOW) {
+            return Err(ErrorKind::PanelMismatch(SSD_PANEL));
+        }
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
+    /// Checks that power on, power off and the refresh reach the controller
+    ///
+    /// See [`SafeCommands`], `None` turns the checks off, which is the default.
+    pub fn set_safe_commands(&mut self, safe_commands: Option<SafeCommands>) {
+        self.interface.set_safe_commands(safe_commands);
+    }
+
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     /// Set the outer border of the display to the chosen color.
+    ///
+    /// The color is kept across `sleep` and `wake_up`.
     pub async fn set_border_color(
         &mut self,
         spi: &mut SPI,
         color: TriColor,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let border = match color {
-            TriColor::Black => BLACK_BORDER,
-            TriColor::White => WHITE_BORDER,
-            TriColor::Chromatic => CHROMATIC_BORDER,
-        };
+        self.config.border_color = color;
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[border | VCOM_DATA_INTERVAL],
+            &[border_register(color)],
         )
         .await
     }
 }
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in13bc, Display2in13bc, "epd2in13bc", tricolor);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::test_utils::user_config_survives_sleep!(
+        border_and_background_survive_sleep,
+        Epd2in13bc,
+        |epd, spi| {
+            block_on(epd.set_border_color(&mut spi, TriColor::Chromatic)).unwrap();
+            epd.set_background_color(TriColor::Black);
+        },
+        [
+            &[
+                Command::VcomAndDataIntervalSetting as u8,
+                border_register(TriColor::Chromatic),
+            ][..],
+            &[Command::DataStartTransmission2 as u8, 0x00, 0x00][..],
+        ]
+    );
+}
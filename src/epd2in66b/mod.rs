@@ -167,14 +167,15 @@
 //!```
 use core::fmt::{Debug, Display};
 
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::color::TriColor;
-use crate::interface::DisplayInterface;
-use crate::prelude::ErrorKind;
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::prelude::{ErrorKind, InitError};
 use crate::traits::{
-    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 pub(crate) mod command;
@@ -204,33 +205,50 @@ pub type Display2in66b = crate::graphics::Display<
 /// The EPD 2in66-B driver.
 pub struct Epd2in66b<SPI, BUSY, DC, RST> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    background: TriColor,
+    config: UserConfig,
+    chromatic_passes: ChromaticPasses,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: TriColor,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST>
-    for Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -259,11 +277,11 @@ impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn update_color_frame(
@@ -295,39 +313,29 @@ where
         self.interface.cmd(spi, Command::WriteRedRAM).await?;
         self.interface.data(spi, chromatic).await
     }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
 }
 
 impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = TriColor;
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let mut epd = Self {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
-            background: DEFAULT_BACKGROUND_COLOR,
-        };
-        epd.init(spi).await?;
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface
             .cmd_with_data(
@@ -343,11 +351,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
-        self.background = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Self::DisplayColor {
-        &self.background
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -358,6 +366,34 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.set_cursor(spi, 0, 0).await?;
         self.update_achromatic_frame(spi, buffer).await?;
@@ -395,8 +431,19 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let (white, red) = match self.background {
+        let (white, red) = match self.config.background_color {
             TriColor::Black => (StartWith::Zero, StartWith::Zero),
             TriColor::White => (StartWith::One, StartWith::Zero),
             TriColor::Chromatic => (StartWith::Zero, StartWith::One),
@@ -417,6 +464,43 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(false)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>>
+    where
+        Self: Sized,
+    {
+        let mut epd = Self {
+            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            config: UserConfig::default(),
+            chromatic_passes: ChromaticPasses::default(),
+        };
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+        Ok(epd)
+    }
 }
 
 // Helper functions that enforce some type and value constraints. Meant to help with code readability. They caught some of my silly errors -> yay rust!.
@@ -424,13 +508,18 @@ impl<SPI, BUSY, DC, RST> Epd2in66b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn wait_until_idle(
         &mut self,
         spi: &mut SPI,
@@ -440,6 +529,7 @@ where
 
     async fn hw_reset(
         &mut self,
+        spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         // The initial delay is taken from other code here, the 2 ms comes from the SSD1675B datasheet.
         self.interface.reset(spi, 20_000, 2_000).await?;
@@ -559,3 +649,23 @@ where
         self.wait_until_idle(spi).await
     }
 }
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in66b, Display2in66b, "epd2in66b", tricolor);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in66b,
+        |epd, spi| {
+            epd.set_background_color(TriColor::Black);
+        },
+        [&[
+            Command::BlackWhiteRAMTestPattern as u8,
+            StartWith::Zero as u8 | PatH::H296 as u8 | PatW::W160 as u8,
+        ][..]]
+    );
+}
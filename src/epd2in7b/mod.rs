@@ -2,18 +2,18 @@
 //!
 //! [Documentation](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT_(B))
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
 use crate::traits::{
-    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 // The Lookup Tables for the Display
-mod constants;
-use crate::epd2in7b::constants::*;
+use crate::lut::lut_defaults::*;
 
 /// Width of the display
 pub const WIDTH: u32 = 176;
@@ -21,6 +21,10 @@ pub const WIDTH: u32 = 176;
 pub const HEIGHT: u32 = 264;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy::DEFAULT;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = true;
 
@@ -29,35 +33,104 @@ use crate::color::Color;
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
+use crate::strided::StridedSlice;
+use crate::wire::{WireBytes, WireOrder, WireTransform};
 
 /// Full size buffer for use with the 2in7B EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
+///
+/// Its [`bw_buffer`](crate::graphics::Display::bw_buffer) and
+/// [`chromatic_buffer`](crate::graphics::Display::chromatic_buffer) are what
+/// [`update_color_frame`](WaveshareThreeColorDisplay::update_color_frame) takes, the driver
+/// inverts the black/white plane for the controller. Chromatic pixels are white in the
+/// black/white plane.
 #[cfg(feature = "graphics")]
 pub type Display2in7b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    crate::color::TriColor,
 >;
 
+/// Complete set of custom look-up tables, see [`Epd2in7b::write_lut_direct`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomLut {
+    /// VCOM table
+    pub vcom: [u8; 44],
+    /// White to white table
+    pub ww: [u8; 42],
+    /// Black to white table
+    pub bw: [u8; 42],
+    /// White to black table
+    pub wb: [u8; 42],
+    /// Black to black table
+    pub bb: [u8; 42],
+}
+
+impl CustomLut {
+    /// The tables loaded by `set_lut`, a starting point for own tables
+    pub const FULL: CustomLut = CustomLut {
+        vcom: LUT_VCOM_DC,
+        ww: LUT_WW,
+        bw: LUT_BW,
+        wb: LUT_WB,
+        bb: LUT_BB,
+    };
+
+    /// The tables loaded by `set_lut` for [`RefreshLut::Quick`]
+    pub const QUICK: CustomLut = CustomLut {
+        vcom: LUT_VCOM_DC_QUICK,
+        ww: LUT_WW_QUICK,
+        bw: LUT_BW_QUICK,
+        wb: LUT_WB_QUICK,
+        bb: LUT_BB_QUICK,
+    };
+}
+
 /// Epd2in7b driver
 pub struct Epd2in7b<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: Color,
+    config: UserConfig,
+    /// Look-up tables currently loaded in the controller, `None` if unknown or custom
+    lut: Option<RefreshLut>,
+    /// Refreshes done by `update_and_display_color_frame`
+    chromatic_passes: ChromaticPasses,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and to clear the chromatic layer in
+    /// `update_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+    /// Order the frames are sent in, see [`Epd2in7b::set_wire_order`]
+    pub wire_order: WireOrder,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            wire_order: WireOrder::default(),
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in7b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -67,16 +140,17 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in7b<SP
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // reset the device
         self.interface.reset(spi, 10_000, 2_000).await?;
+        self.invalidate_lut();
 
         // power on
         self.command(spi, Command::PowerOn).await?;
@@ -143,31 +217,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in7b<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in7b { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.init(spi).await
     }
@@ -183,6 +240,7 @@ where
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
             .await?;
+        self.invalidate_lut();
         Ok(())
     }
 
@@ -190,11 +248,15 @@ where
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
             .await?;
-        self.send_buffer_helper(spi, buffer).await?;
+        self.send_buffer_helper(spi, buffer, WIDTH).await?;
 
         // Clear chromatic layer since we won't be using it here
         self.interface
-            .data_x_times(spi, !self.color.get_byte_value(), WIDTH / 8 * HEIGHT)
+            .data_x_times(
+                spi,
+                !self.config.background_color.get_byte_value(),
+                WIDTH / 8 * HEIGHT,
+            )
             .await?;
 
         self.interface.cmd(spi, Command::DataStop).await?;
@@ -214,17 +276,41 @@ where
             .cmd(spi, Command::PartialDataStartTransmission1)
             .await?;
 
-        self.send_data(spi, &[(x >> 8) as u8]).await?;
-        self.send_data(spi, &[(x & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(y >> 8) as u8]).await?;
-        self.send_data(spi, &[(y & 0xff) as u8]).await?;
-        self.send_data(spi, &[(width >> 8) as u8]).await?;
-        self.send_data(spi, &[(width & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(height >> 8) as u8]).await?;
-        self.send_data(spi, &[(height & 0xff) as u8]).await?;
-        self.wait_until_idle(spi).await?;
+        self.send_window(spi, x, y, width, height).await?;
+
+        self.send_buffer_helper(spi, buffer, width).await?;
 
-        self.send_buffer_helper(spi, buffer).await?;
+        self.interface.cmd(spi, Command::DataStop).await
+    }
+
+    /// Walks the rows of the window, they need to be `width` pixels wide unless the window is
+    /// contiguous
+    async fn update_partial_frame_strided(
+        &mut self,
+        spi: &mut SPI,
+        window: StridedSlice<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if let Some(buffer) = window.as_contiguous() {
+            return self
+                .update_partial_frame(spi, buffer, x, y, width, height)
+                .await;
+        }
+        // the wire order reverses the rows of the window
+        if window.row_len() != width.div_ceil(8) as usize || window.row_count() != height as usize {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.interface
+            .cmd(spi, Command::PartialDataStartTransmission1)
+            .await?;
+        self.send_window(spi, x, y, width, height).await?;
+
+        let bytes = WireBytes::strided(WireTransform::Packed, window, self.config.wire_order);
+        self.send_inverted(spi, bytes).await?;
 
         self.interface.cmd(spi, Command::DataStop).await
     }
@@ -245,10 +331,20 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.config.background_color.get_byte_value();
         self.interface
             .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)
             .await?;
@@ -266,11 +362,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -281,28 +377,93 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.invalidate_lut();
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
+    /// Only uploads the look-up tables if they aren't loaded already,
+    /// use [`Epd2in7b::force_set_lut`] to always send them.
+    ///
+    /// The quick tables only drive black and white, chromatic pixels need the full ones. `None`
+    /// keeps the tables selected before, `wake_up` loads them again.
     async fn set_lut(
         &mut self,
         spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
+        refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)
-            .await?;
-        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)
-            .await?;
-        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW)
-            .await?;
-        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB)
-            .await?;
-        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB)
-            .await?;
-        Ok(())
+        if self.lut == Some(refresh_rate.unwrap_or(self.config.refresh)) {
+            return Ok(());
+        }
+        self.force_set_lut(spi, refresh_rate).await
     }
 
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in7b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd2in7b {
+            interface,
+            config: UserConfig::default(),
+            lut: None,
+            chromatic_passes: ChromaticPasses::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
@@ -310,11 +471,11 @@ impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn update_color_frame(
@@ -339,7 +500,7 @@ where
             .cmd(spi, Command::DataStartTransmission1)
             .await?;
 
-        self.send_buffer_helper(spi, achromatic).await?;
+        self.send_buffer_helper(spi, achromatic, WIDTH).await?;
 
         self.interface.cmd(spi, Command::DataStop).await
     }
@@ -356,26 +517,58 @@ where
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
 
-        self.send_buffer_helper(spi, chromatic).await?;
+        self.send_chromatic_helper(spi, chromatic, WIDTH).await?;
 
         self.interface.cmd(spi, Command::DataStop).await?;
         self.wait_until_idle(spi).await?;
 
         Ok(())
     }
+
+    /// Update partial chromatic/red frame
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.interface
+            .cmd(spi, Command::PartialDataStartTransmission2)
+            .await?;
+        self.send_window(spi, x, y, width, height).await?;
+
+        self.send_chromatic_helper(spi, chromatic, width).await
+    }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in7b<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -392,31 +585,61 @@ where
         self.interface.data(spi, data).await
     }
 
+    /// Sends a buffer with rows of `width` pixels in the configured order
     async fn send_buffer_helper(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
+        width: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         // Based on the waveshare implementation, all data for color values is flipped. This helper
         // method makes that transmission easier
-        for b in buffer.iter() {
-            self.send_data(spi, &[!b]).await?;
+        let row_len = width.div_ceil(8) as usize;
+        let bytes = WireBytes::ordered(
+            WireTransform::Packed,
+            buffer,
+            row_len,
+            self.config.wire_order,
+        );
+        self.send_inverted(spi, bytes).await
+    }
+
+    /// Sends a chromatic buffer with rows of `width` pixels in the configured order
+    ///
+    /// Unlike the black/white data it isn't flipped, the controller takes a set bit as chromatic
+    /// like [`TriColor`](crate::color::TriColor) does.
+    async fn send_chromatic_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        width: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let row_len = width.div_ceil(8) as usize;
+        for b in WireBytes::ordered(
+            WireTransform::Packed,
+            buffer,
+            row_len,
+            self.config.wire_order,
+        ) {
+            self.send_data(spi, &[b]).await?;
         }
         Ok(())
     }
 
-    async fn cmd_with_data(
+    async fn send_inverted(
         &mut self,
         spi: &mut SPI,
-        command: Command,
-        data: &[u8],
+        bytes: WireBytes<'_>,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd_with_data(spi, command, data).await
-
+        for b in bytes {
+            self.send_data(spi, &[!b]).await?;
+        }
+        Ok(())
     }
 
-    /// Refresh display for partial frame
-    pub async fn display_partial_frame(
+    /// Sends the position and size of a partial window, moved to where the configured order puts
+    /// it, and waits for the controller
+    async fn send_window(
         &mut self,
         spi: &mut SPI,
         x: u32,
@@ -424,7 +647,10 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.command(spi, Command::PartialDisplayRefresh).await?;
+        let (x, y) =
+            self.config
+                .wire_order
+                .remap_window(WIDTH, HEIGHT, x & 0xf8, y, width & 0xf8, height);
         self.send_data(spi, &[(x >> 8) as u8]).await?;
         self.send_data(spi, &[(x & 0xf8) as u8]).await?;
         self.send_data(spi, &[(y >> 8) as u8]).await?;
@@ -433,78 +659,175 @@ where
         self.send_data(spi, &[(width & 0xf8) as u8]).await?;
         self.send_data(spi, &[(height >> 8) as u8]).await?;
         self.send_data(spi, &[(height & 0xff) as u8]).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    /// Sends the rows and bytes of every frame in `order`, for panels mounted the other way round
+    ///
+    /// Applies to full frames and partial windows alike, see the [`wire`](crate::wire) module.
+    /// The windows passed to the partial updates stay in the coordinates of the buffer, the
+    /// driver moves them.
+    pub fn set_wire_order(&mut self, order: WireOrder) {
+        self.config.wire_order = order;
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)
+            .await?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)
+            .await?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)
+            .await?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)
+            .await?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)
+            .await?;
         Ok(())
     }
 
-    /// Update black/achromatic frame
+    /// Load custom look-up tables for full control over the refresh waveform
+    ///
+    /// The VCOM table needs to be 44 bytes long, all the other tables 42 bytes
+    /// (see the defaults in `lut.rs` for the layout).
+    /// Returns [`ErrorKind::InvalidArgument`] without sending anything if a table has the wrong length.
+    ///
+    /// The tables are lost on the next [`set_lut`](WaveshareDisplay::set_lut) or
+    /// [`wake_up`](WaveshareDisplay::wake_up).
     #[allow(clippy::too_many_arguments)]
-    pub async fn update_partial_achromatic_frame(
+    pub async fn write_lut_direct(
         &mut self,
         spi: &mut SPI,
-        achromatic: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
+        vcom_lut: &[u8],
+        ww_lut: &[u8],
+        bw_lut: &[u8],
+        wb_lut: &[u8],
+        bb_lut: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface
-            .cmd(spi, Command::PartialDataStartTransmission1)
+        if vcom_lut.len() != LUT_VCOM_DC.len()
+            || [ww_lut, bw_lut, wb_lut, bb_lut]
+                .iter()
+                .any(|lut| lut.len() != LUT_WW.len())
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.set_lut_helper(spi, vcom_lut, ww_lut, bw_lut, wb_lut, bb_lut)
             .await?;
-        self.send_data(spi, &[(x >> 8) as u8]).await?;
-        self.send_data(spi, &[(x & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(y >> 8) as u8]).await?;
-        self.send_data(spi, &[(y & 0xff) as u8]).await?;
-        self.send_data(spi, &[(width >> 8) as u8]).await?;
-        self.send_data(spi, &[(width & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(height >> 8) as u8]).await?;
-        self.send_data(spi, &[(height & 0xff) as u8]).await?;
-        self.wait_until_idle(spi).await?;
+        self.lut = None;
+        Ok(())
+    }
 
-        for b in achromatic.iter() {
-            // Flipping based on waveshare implementation
-            self.send_data(spi, &[!b]).await?;
+    /// Upload the look-up tables even if the driver thinks they are already loaded
+    ///
+    /// Only needed if the controller was reset behind the back of the driver.
+    pub async fn force_set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.config.refresh = refresh_lut;
         }
+        let lut = match self.config.refresh {
+            RefreshLut::Full => &CustomLut::FULL,
+            RefreshLut::Quick => &CustomLut::QUICK,
+        };
+        self.set_lut_helper(spi, &lut.vcom, &lut.ww, &lut.bw, &lut.wb, &lut.bb)
+            .await?;
+        self.lut = Some(self.config.refresh);
+        Ok(())
+    }
 
+    /// Forget about the loaded look-up tables.
+    ///
+    /// The controller loses them on:
+    /// - a hardware reset (done by `init` and therefore `wake_up`)
+    /// - deep sleep, which can only be left through a hardware reset
+    fn invalidate_lut(&mut self) {
+        self.lut = None;
+    }
+
+    /// Refresh display for partial frame
+    pub async fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.command(spi, Command::PartialDisplayRefresh).await?;
+        self.send_window(spi, x, y, width, height).await?;
         Ok(())
     }
 
-    /// Update partial chromatic/red frame
+    /// Update black/achromatic frame
     #[allow(clippy::too_many_arguments)]
-    pub async fn update_partial_chromatic_frame(
+    pub async fn update_partial_achromatic_frame(
         &mut self,
         spi: &mut SPI,
-        chromatic: &[u8],
+        achromatic: &[u8],
         x: u32,
         y: u32,
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface
-            .cmd(spi, Command::PartialDataStartTransmission2)
+            .cmd(spi, Command::PartialDataStartTransmission1)
             .await?;
-        self.send_data(spi, &[(x >> 8) as u8]).await?;
-        self.send_data(spi, &[(x & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(y >> 8) as u8]).await?;
-        self.send_data(spi, &[(y & 0xff) as u8]).await?;
-        self.send_data(spi, &[(width >> 8) as u8]).await?;
-        self.send_data(spi, &[(width & 0xf8) as u8]).await?;
-        self.send_data(spi, &[(height >> 8) as u8]).await?;
-        self.send_data(spi, &[(height & 0xff) as u8]).await?;
-        self.wait_until_idle(spi).await?;
+        self.send_window(spi, x, y, width, height).await?;
 
-        for b in chromatic.iter() {
-            // Flipping based on waveshare implementation
-            self.send_data(spi, &[!b]).await?;
-        }
+        self.send_buffer_helper(spi, achromatic, width).await
+    }
+}
 
-        Ok(())
+#[cfg(feature = "calibration")]
+impl<SPI, BUSY, DC, RST> crate::calibration::LoadLut<SPI, BUSY, DC, RST>
+    for Epd2in7b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type Lut = CustomLut;
+
+    async fn load_lut(&mut self, spi: &mut SPI, lut: &CustomLut) -> Result<(), Self::Error> {
+        self.write_lut_direct(spi, &lut.vcom, &lut.ww, &lut.bw, &lut.wb, &lut.bb)
+            .await
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in7b, Display2in7b, "epd2in7b", tricolor);
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{block_on, contains, IdleBusy, NoopPin, RecordingSpi};
 
     #[test]
     fn epd_size() {
@@ -512,4 +835,209 @@ mod tests {
         assert_eq!(HEIGHT, 264);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn lut_upload_is_lazy() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC));
+
+        // same mode is a no-op
+        block_on(epd.set_lut(&mut spi, None)).unwrap();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert!(!contains(&spi.take(), &LUT_VCOM_DC));
+
+        // mode change uploads once
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC_QUICK));
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        block_on(epd.set_lut(&mut spi, None)).unwrap();
+        assert!(spi.take().is_empty());
+
+        // forcing always uploads
+        block_on(epd.force_set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC_QUICK));
+    }
+
+    #[test]
+    fn quick_lut_survives_wake_up() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        spi.take();
+
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        let written = spi.take();
+        for table in [&LUT_WW_QUICK, &LUT_BW_QUICK, &LUT_WB_QUICK, &LUT_BB_QUICK] {
+            assert!(contains(&written, table));
+        }
+        assert!(!contains(&written, &LUT_WW));
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC));
+    }
+
+    #[test]
+    fn lut_reupload_after_invalidation() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // deep sleep and the reset on wake up clear the tables
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC));
+
+        // custom tables replace the built-in ones
+        let vcom = [0u8; 44];
+        let lut = [0u8; 42];
+        block_on(epd.write_lut_direct(&mut spi, &vcom, &lut, &lut, &lut, &lut)).unwrap();
+        spi.take();
+        block_on(epd.set_lut(&mut spi, None)).unwrap();
+        assert!(contains(&spi.take(), &LUT_VCOM_DC));
+    }
+
+    #[test]
+    fn partial_windows_follow_the_wire_order() {
+        use crate::wire::{ByteOrderInRow, RowOrder};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        epd.set_wire_order(WireOrder {
+            rows: RowOrder::BottomUp,
+            bytes: ByteOrderInRow::Reverse,
+        });
+        spi.take();
+
+        // 16 × 2 window at (8, 10), mirrored to (176 - 24, 264 - 12)
+        block_on(epd.update_partial_frame(&mut spi, &[1, 2, 3, 4], 8, 10, 16, 2)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [0x14, 0, 152, 0, 252, 0, 16, 0, 2, !4, !3, !2, !1, 0x11]
+        );
+        block_on(epd.display_partial_frame(&mut spi, 8, 10, 16, 2)).unwrap();
+        assert_eq!(spi.take(), [0x16, 0, 152, 0, 252, 0, 16, 0, 2]);
+
+        // the default order keeps the window and the bytes
+        epd.set_wire_order(WireOrder::default());
+        block_on(epd.update_partial_frame(&mut spi, &[1, 2, 3, 4], 8, 10, 16, 2)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [0x14, 0, 8, 0, 10, 0, 16, 0, 2, !1, !2, !3, !4, 0x11]
+        );
+    }
+
+    #[test]
+    fn strided_partial_frame_matches_a_copy() {
+        use crate::wire::{ByteOrderInRow, RowOrder};
+
+        const ROW_LEN: usize = WIDTH as usize / 8;
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| (i * 13) as u8);
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        for bytes in [ByteOrderInRow::Forward, ByteOrderInRow::Reverse] {
+            epd.set_wire_order(WireOrder {
+                rows: RowOrder::BottomUp,
+                bytes,
+            });
+            for (x, y, width, height) in [(0, 0, 8, 1), (16, 5, 24, 10), (64, 20, 64, 200)] {
+                let window =
+                    StridedSlice::window(&frame, ROW_LEN, x / 8, y, width / 8, height).unwrap();
+                let mut copy = [0; buffer_len(WIDTH as usize, HEIGHT as usize)];
+                for (row, dest) in window.rows().zip(copy.chunks_mut(width / 8)) {
+                    dest.copy_from_slice(row);
+                }
+                let copy = &copy[..window.len()];
+                let (x, y, width, height) = (x as u32, y as u32, width as u32, height as u32);
+
+                spi.take();
+                block_on(epd.update_partial_frame(&mut spi, copy, x, y, width, height)).unwrap();
+                let expected = spi.take();
+                block_on(epd.update_partial_frame_strided(&mut spi, window, x, y, width, height))
+                    .unwrap();
+                assert_eq!(spi.take(), expected);
+                // contiguous windows take the same path
+                block_on(epd.update_partial_frame_strided(
+                    &mut spi,
+                    StridedSlice::from(copy),
+                    x,
+                    y,
+                    width,
+                    height,
+                ))
+                .unwrap();
+                assert_eq!(spi.take(), expected);
+            }
+        }
+
+        // the rows of the window have to match its width
+        let window = StridedSlice::window(&frame, ROW_LEN, 0, 0, 2, 4).unwrap();
+        assert!(matches!(
+            block_on(epd.update_partial_frame_strided(&mut spi, window, 0, 0, 8, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn tricolor_display() {
+        use crate::color::TriColor;
+        use embedded_graphics::prelude::*;
+
+        let mut display = Display2in7b::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(9, 1), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(0, 2), TriColor::Black));
+
+        let row_len = WIDTH as usize / 8;
+        let (black, chromatic) = (display.bw_buffer(), display.chromatic_buffer());
+        assert_eq!(black.len(), row_len * HEIGHT as usize);
+        assert_eq!(chromatic[row_len + 1], 0x40);
+        assert_eq!(chromatic.iter().filter(|&&byte| byte != 0).count(), 1);
+        // the chromatic pixel leaves the black/white plane white
+        assert_eq!(black[row_len + 1], 0xFF);
+        assert_eq!(black[2 * row_len], 0x7F);
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+        block_on(epd.update_color_frame(&mut spi, black, chromatic)).unwrap();
+        let written = spi.take();
+        let frame = buffer_len(WIDTH as usize, HEIGHT as usize);
+        // the controller takes a set bit as black and as chromatic
+        let (dtm1, dtm2) = (&written[1..1 + frame], &written[3 + frame..3 + 2 * frame]);
+        assert_eq!(written[0], Command::DataStartTransmission1 as u8);
+        assert_eq!(written[2 + frame], Command::DataStartTransmission2 as u8);
+        assert_eq!(dtm1[2 * row_len], 0x80);
+        assert_eq!(dtm1.iter().filter(|&&byte| byte != 0).count(), 1);
+        assert_eq!(dtm2[row_len + 1], 0x40);
+        assert_eq!(dtm2.iter().filter(|&&byte| byte != 0).count(), 1);
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in7b,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+            epd.set_wire_order(WireOrder {
+                bytes: crate::wire::ByteOrderInRow::Reverse,
+                ..WireOrder::default()
+            });
+        },
+        [
+            [&[Command::LutForVcom as u8][..], &CustomLut::QUICK.vcom[..]].concat(),
+            [Command::DataStartTransmission1 as u8, 0x55, 0x55].to_vec(),
+            [Command::DataStartTransmission2 as u8, 0x00, 0x00].to_vec(),
+        ]
+    );
 }
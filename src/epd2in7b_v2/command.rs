@@ -0,0 +1,49 @@
+//! SPI Commands for the Waveshare 2.7" (B) V2 E-Ink Display
+use crate::traits;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    DriverOutputControl = 0x01,
+    GateDrivingVoltageControl = 0x03,
+    SourceDrivingVoltageControl = 0x04,
+    DeepSleepMode = 0x10,
+    DataEntryModeSetting = 0x11,
+    SwReset = 0x12,
+    TemperatureSensorControl = 0x18,
+    MasterActivation = 0x20,
+    DisplayUpdateControl1 = 0x21,
+    DisplayUpdateControl2 = 0x22,
+    WriteRam = 0x24,
+    WriteRamRed = 0x26,
+    WriteVcomRegister = 0x2C,
+    WriteLutRegister = 0x32,
+    BorderWaveformControl = 0x3C,
+    SetRamXAddressStartEndPosition = 0x44,
+    SetRamYAddressStartEndPosition = 0x45,
+    SetRamXAddressCounter = 0x4E,
+    SetRamYAddressCounter = 0x4F,
+    Nop = 0x7F,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::SwReset.address(), 0x12);
+
+        assert_eq!(Command::WriteRamRed.address(), 0x26);
+
+        assert_eq!(Command::MasterActivation.address(), 0x20);
+    }
+}
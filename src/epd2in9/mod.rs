@@ -47,15 +47,19 @@ pub const WIDTH: u32 = 128;
 pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy::DEFAULT;
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::{
-    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay},
+    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay},
     type_a::{
         command::Command,
         constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
@@ -64,8 +68,9 @@ use crate::{
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::strided::StridedSlice;
 
 /// Display with Fullsize buffer for use with the 2in9 EPD
 #[cfg(feature = "graphics")]
@@ -82,21 +87,38 @@ pub type Display2in9 = crate::graphics::Display<
 pub struct Epd2in9<SPI, BUSY, DC, RST> {
     /// SPI
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Color
-    background_color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -106,11 +128,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -165,11 +187,11 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
@@ -181,24 +203,32 @@ where
         HEIGHT
     }
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
 
-        let mut epd = Epd2in9 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
 
-        epd.init(spi).await?;
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
 
-        Ok(epd)
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -237,9 +267,7 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        self.set_ram_area(spi, x, y, x + width, y + height).await?;
-        self.set_ram_counter(spi, x, y).await?;
+        self.use_partial_frame(spi, x, y, width, height).await?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)
@@ -247,6 +275,48 @@ where
         Ok(())
     }
 
+    async fn update_partial_frame_strided(
+        &mut self,
+        spi: &mut SPI,
+        window: StridedSlice<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if window.len() != buffer_len(width as usize, height as usize) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.use_partial_frame(spi, x, y, width, height).await?;
+
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        for row in window.rows() {
+            self.interface.data(spi, row).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.use_partial_frame(spi, x, y, width, height).await?;
+        self.interface.cmd(spi, Command::WriteRam).await
+    }
+
+    async fn write_partial_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Self::Error> {
+        self.interface.data(spi, data).await
+    }
+
+    async fn end_partial_update(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        // the RAM write simply ends with the next command
+        Ok(())
+    }
+
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
@@ -272,12 +342,22 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.use_full_frame(spi).await?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
@@ -287,11 +367,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.background_color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.background_color
+        &self.config.background_color
     }
 
     async fn set_lut(
@@ -300,9 +380,9 @@ where
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
         if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+            self.config.refresh = refresh_lut;
         }
-        match self.refresh {
+        match self.config.refresh {
             RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE).await,
             RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE).await,
         }
@@ -311,19 +391,61 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd2in9 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in9<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -335,6 +457,26 @@ where
         self.set_ram_counter(spi, 0, 0).await
     }
 
+    async fn use_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if width == 0
+            || height == 0
+            || x.saturating_add(width) > WIDTH
+            || y.saturating_add(height) > HEIGHT
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width, y + height).await?;
+        self.set_ram_counter(spi, x, y).await
+    }
+
     async fn set_ram_area(
         &mut self,
         spi: &mut SPI,
@@ -343,8 +485,9 @@ where
         end_x: u32,
         end_y: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        if start_x >= end_x || start_y >= end_y {
+            return Err(ErrorKind::InvalidArgument);
+        }
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -377,7 +520,7 @@ where
         x: u32,
         y: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.wait_until_idle(spi, delay).await?;
+        self.wait_until_idle(spi).await?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
@@ -401,8 +544,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.wait_until_idle(spi, delay).await?;
-        assert!(buffer.len() == 30);
+        self.wait_until_idle(spi).await?;
+        debug_assert_eq!(buffer.len(), 30);
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)
             .await?;
@@ -410,6 +553,9 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in9, Display2in9, "epd2in9");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -420,4 +566,83 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn strided_partial_frame_matches_a_copy() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        const ROW_LEN: usize = WIDTH as usize / 8;
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| (i * 7) as u8);
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in9::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        for (x, y, width, height) in [(0, 0, 8, 1), (16, 5, 24, 10), (64, 0, 64, 296)] {
+            let window =
+                StridedSlice::window(&frame, ROW_LEN, x / 8, y, width / 8, height).unwrap();
+            let mut copy = [0; buffer_len(WIDTH as usize, HEIGHT as usize)];
+            for (row, dest) in window.rows().zip(copy.chunks_mut(width / 8)) {
+                dest.copy_from_slice(row);
+            }
+            let copy = &copy[..window.len()];
+            let (x, y, width, height) = (x as u32, y as u32, width as u32, height as u32);
+
+            spi.take();
+            block_on(epd.update_partial_frame(&mut spi, copy, x, y, width, height)).unwrap();
+            let expected = spi.take();
+            block_on(epd.update_partial_frame_strided(&mut spi, window, x, y, width, height))
+                .unwrap();
+            assert_eq!(spi.take(), expected);
+        }
+
+        let window = StridedSlice::window(&frame, ROW_LEN, 0, 0, 1, 4).unwrap();
+        assert!(matches!(
+            block_on(epd.update_partial_frame_strided(&mut spi, window, 0, 0, 8, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn streamed_partial_frame_matches_a_single_write() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let buffer: [u8; buffer_len(24, 10)] = core::array::from_fn(|i| (i * 3) as u8);
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in9::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        spi.take();
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 16, 5, 24, 10)).unwrap();
+        let expected = spi.take();
+
+        block_on(epd.set_partial_window(&mut spi, 16, 5, 24, 10)).unwrap();
+        for part in buffer.chunks(7) {
+            block_on(epd.write_partial_data(&mut spi, part)).unwrap();
+        }
+        block_on(epd.end_partial_update(&mut spi)).unwrap();
+        assert_eq!(spi.take(), expected);
+
+        assert!(matches!(
+            block_on(epd.set_partial_window(&mut spi, 0, 0, 8, HEIGHT + 1)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in9,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            [
+                &[Command::WriteLutRegister as u8][..],
+                &LUT_PARTIAL_UPDATE[..]
+            ]
+            .concat(),
+            [Command::WriteRam as u8, 0x00, 0x00].to_vec(),
+        ]
+    );
 }
@@ -61,6 +61,13 @@ pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+
+/// Diagnosis of a panel whose busy line idles high, like the one of a UC controller
+const UC_PANEL: MismatchDiagnosis = MismatchDiagnosis {
+    suspected: "UC8151 (2.9\" flexible or V1 b/c)",
+    driver: Some("epd2in9d"),
+};
+const _: () = assert!(Command::SwReset as u8 == confirm::FAMILY_PROBE.command);
 const SINGLE_BYTE_WRITE: bool = true;
 
 const LUT_PARTIAL_2IN9: [u8; 159] = [
@@ -88,12 +95,16 @@ const WS_20_30: [u8; 159] = [
 ];
 
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::confirm;
+use crate::error::{ErrorKind, InitError, MismatchDiagnosis};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay,
+};
 
 use crate::type_a::command::Command;
 
@@ -115,21 +126,37 @@ pub type Display2in9 = crate::graphics::Display<
 pub struct Epd2in9<SPI, BUSY, DC, RST> {
     /// SPI
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Color
-    background_color: Color,
+    config: UserConfig,
     /// Refresh LUT
     refresh: RefreshLut,
 }
 
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+}
+
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -139,15 +166,18 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.reset(spi, 10_000, 2_000).await?;
+        if self.interface.check_panel() {
+            self.probe_panel(spi).await?;
+        }
 
         self.wait_until_idle(spi).await?;
         self.interface.cmd(spi, Command::SwReset).await?;
@@ -201,11 +231,11 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
@@ -217,24 +247,32 @@ where
         HEIGHT
     }
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
 
-        let mut epd = Epd2in9 {
-            interface,
-            background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
-        };
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
 
-        epd.init(spi).await?;
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
 
-        Ok(epd)
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -300,11 +338,26 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.wait_until_idle(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])
+            .await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
@@ -317,11 +370,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.background_color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.background_color
+        &self.config.background_color
     }
 
     async fn set_lut(
@@ -338,19 +391,114 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    async fn sanity_check(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.probe_panel(spi).await?;
+        self.init(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        Self::try_init(spi, DisplayInterface::new(busy, dc, rst, delay_us)).await
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in9<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    /// Creates the driver like [`try_new`](WaveshareConstructor::try_new), with the
+    /// [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation
+    ///
+    /// The check runs before the driver first waits for the busy line, so a panel of the wrong
+    /// generation fails with [`ErrorKind::PanelMismatch`] instead of hanging.
+    pub async fn try_new_checked(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_check_panel(true);
+        Self::try_init(spi, interface).await
+    }
+
+    /// Runs the [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation, off
+    /// by default
+    pub fn set_check_panel(&mut self, check: bool) {
+        self.interface.set_check_panel(check);
+    }
+
+    async fn try_init(
+        spi: &mut SPI,
+        interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut epd = Epd2in9 {
+            interface,
+            config: UserConfig::default(),
+            refresh: RefreshLut::Full,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+
+    /// Fails if the busy line idles at the level of the other controller family, call right
+    /// after the reset
+    async fn probe_panel(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let idle_high = self
+            .interface
+            .busy_reaction(spi, Command::SwReset, confirm::FAMILY_PROBE.within_us)
+            .await?;
+        if idle_high == Some(!IS_BUSY_LOW) {
+            return Err(ErrorKind::PanelMismatch(UC_PANEL));
+        }
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
     async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -428,7 +576,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.wait_until_idle(spi, delay).await?;
+        self.wait_until_idle(spi).await?;
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)
             .await?;
@@ -441,11 +589,11 @@ impl<SPI, BUSY, DC, RST> QuickRefresh<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// To be followed immediately by `update_new_frame`.
@@ -496,7 +644,7 @@ where
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(
+    async fn display_new_frame(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
@@ -565,9 +713,13 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in9, Display2in9, "epd2in9_v2");
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{block_on, NoopPin, ProbedSpi};
 
     #[test]
     fn epd_size() {
@@ -575,4 +727,40 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn sanity_check_detects_uc_panels() {
+        // busy idling high and pulsed low, like a UC8151
+        let mut spi = ProbedSpi::new(true);
+        let busy = spi.busy();
+        let result = block_on(Epd2in9::try_new_checked(
+            &mut spi, busy, NoopPin, NoopPin, None,
+        ));
+        assert!(matches!(
+            result,
+            Err(InitError {
+                error: ErrorKind::PanelMismatch(UC_PANEL),
+                ..
+            })
+        ));
+
+        let mut spi = ProbedSpi::new(false);
+        let busy = spi.busy();
+        let Ok(mut epd) = block_on(Epd2in9::try_new(&mut spi, busy, NoopPin, NoopPin, None)) else {
+            panic!("the panel matches");
+        };
+        // back to idle for the next probe
+        spi.level.set(false);
+        spi.reacted = false;
+        block_on(epd.sanity_check(&mut spi)).unwrap();
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in9,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::WriteRam as u8, 0x00, 0x00][..]]
+    );
 }
@@ -55,13 +55,15 @@
 //!# }
 //!```
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::confirm;
+use crate::error::{ErrorKind, InitError, MismatchDiagnosis};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, SafeCommands, Timing};
 use crate::traits::{
-    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in9bc in pixels
@@ -74,15 +76,98 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 
 const IS_BUSY_LOW: bool = true;
-const VCOM_DATA_INTERVAL: u8 = 0x07;
-const WHITE_BORDER: u8 = 0x70;
-const BLACK_BORDER: u8 = 0x30;
-const CHROMATIC_BORDER: u8 = 0xb0;
-const FLOATING_BORDER: u8 = 0xF0;
+
+/// Diagnosis of a panel whose busy line idles low, like the one of an SSD controller
+const SSD_PANEL: MismatchDiagnosis = MismatchDiagnosis {
+    suspected: "SSD1680 (2.9\" b V4)",
+    driver: Some("epd2in9_v2"),
+};
+const _: () = assert!(Command::DisplayRefresh as u8 == confirm::FAMILY_PROBE.command);
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Values of the `VcomAndDataIntervalSetting` register
+pub mod consts {
+    use crate::color::TriColor;
+
+    /// Panel setting sent by the reference driver: 128x296, look-up tables from the OTP,
+    /// scanning up and shifting right
+    pub const PANEL_SETTING: u8 = 0x8F;
+
+    /// VCOM and data interval, lower nibble of the register
+    pub const VCOM_DATA_INTERVAL: u8 = 0x07;
+    /// Border bits for a white border
+    pub const WHITE_BORDER: u8 = 0x70;
+    /// Border bits for a black border
+    pub const BLACK_BORDER: u8 = 0x30;
+    /// Border bits for a chromatic border
+    pub const CHROMATIC_BORDER: u8 = 0xb0;
+    /// Border bits for a floating border, used before going to sleep
+    pub const FLOATING_BORDER: u8 = 0xF0;
+
+    /// Largest VCOM and data interval accepted by the controller
+    pub const MAX_VCOM_DATA_INTERVAL: u8 = 0x0F;
+
+    /// Value of the `VcmDcSetting` register sent by the reference driver, -0.6 V
+    pub const VCOM_DC: u8 = 0x0A;
+    /// Highest VCOM voltage in millivolts, register value `0x00`
+    pub const MAX_VCOM_MV: i16 = -100;
+    /// Lowest VCOM voltage in millivolts, register value `0x3A`
+    pub const MIN_VCOM_MV: i16 = -3000;
+    /// Voltage difference between two register values, in millivolts
+    pub const VCOM_STEP_MV: i16 = 50;
+
+    /// `VcmDcSetting` register value for a VCOM voltage in millivolts
+    ///
+    /// Voltages between two steps are rounded towards zero, voltages outside of
+    /// `MIN_VCOM_MV..=MAX_VCOM_MV` return `None`.
+    pub const fn vcom_dc_register(vcom_mv: i16) -> Option<u8> {
+        if vcom_mv > MAX_VCOM_MV || vcom_mv < MIN_VCOM_MV {
+            return None;
+        }
+        Some(((MAX_VCOM_MV - vcom_mv) / VCOM_STEP_MV) as u8)
+    }
+
+    /// Border bits, upper nibble of the register, for a border of the given color
+    ///
+    /// The match covers every [`BorderColor`](super::BorderColor), a new variant of [`TriColor`]
+    /// fails to compile here instead of sending an invalid border.
+    pub const fn border_bits(color: TriColor) -> u8 {
+        match color {
+            TriColor::Black => BLACK_BORDER,
+            TriColor::White => WHITE_BORDER,
+            TriColor::Chromatic => CHROMATIC_BORDER,
+        }
+    }
+
+    /// Complete register value for a border of the given color and the default interval
+    pub const fn border_register(color: TriColor) -> u8 {
+        border_bits(color) | VCOM_DATA_INTERVAL
+    }
+
+    // Values sent by the Waveshare reference driver on init and sleep
+    const _: () = assert!(border_register(TriColor::White) == 0x77);
+    const _: () = assert!(border_register(TriColor::Black) == 0x37);
+    const _: () = assert!(border_register(TriColor::Chromatic) == 0xB7);
+    const _: () = assert!(FLOATING_BORDER | VCOM_DATA_INTERVAL == 0xF7);
+    const _: () = assert!(matches!(vcom_dc_register(-600), Some(VCOM_DC)));
+    const _: () = assert!(matches!(vcom_dc_register(MIN_VCOM_MV), Some(0x3A)));
+}
+use self::consts::*;
+
 use crate::color::{Color, TriColor};
 
+/// Color of the outer border, see [`Epd2in9bc::set_border_color`]
+///
+/// Every [`TriColor`] is a valid border, [`border_bits`] maps it to the register:
+///
+/// - [`TriColor::White`] drives the border white, [`WHITE_BORDER`]
+/// - [`TriColor::Black`] drives it black, [`BLACK_BORDER`]
+/// - [`TriColor::Chromatic`] drives it red or yellow, [`CHROMATIC_BORDER`]
+///
+/// The floating border ([`FLOATING_BORDER`]) isn't a color, the driver only uses it before going
+/// to sleep.
+pub type BorderColor = TriColor;
+
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
@@ -101,18 +186,62 @@ pub type Display2in9bc = crate::graphics::Display<
 /// Epd2in9bc driver
 pub struct Epd2in9bc<SPI, BUSY, DC, RST> {
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    color: Color,
+    config: UserConfig,
+    chromatic_passes: ChromaticPasses,
+}
+
+#[cfg(feature = "compat-0-5")]
+impl<SPI, BUSY, DC, RST> Epd2in9bc<SPI, BUSY, DC, RST> {
+    /// The same driver for another type of SPI device, see [`crate::compat`]
+    pub(crate) fn rebind_spi<S>(self) -> Epd2in9bc<S, BUSY, DC, RST> {
+        Epd2in9bc {
+            interface: self.interface.rebind_spi(),
+            config: self.config,
+            chromatic_passes: self.chromatic_passes,
+        }
+    }
+}
+
+/// Settings chosen by the user
+///
+/// The display forgets its registers in deep sleep, `init` sends these values again on every
+/// wake up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used to clear the chromatic layer in `update_frame`
+    pub background_color: Color,
+    /// Color of the outer border
+    pub border_color: BorderColor,
+    /// Lower nibble of the `VcomAndDataIntervalSetting` register, see
+    /// [`Epd2in9bc::set_vcom_data_interval`]
+    pub vcom_data_interval: u8,
+    /// Value of the `VcmDcSetting` register, see [`Epd2in9bc::set_vcom_voltage`]
+    pub vcom_dc: u8,
+    /// Value of the `PanelSetting` register, see [`Epd2in9bc::reinit_with_config`]
+    pub panel_setting: u8,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            border_color: BorderColor::White,
+            vcom_data_interval: VCOM_DATA_INTERVAL,
+            vcom_dc: VCOM_DC,
+            panel_setting: PANEL_SETTING,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -122,17 +251,20 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9bc<S
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Values taken from datasheet and sample code
 
         self.interface.reset(spi, 10_000, 10_000).await?;
+        if self.interface.check_panel() {
+            self.probe_panel(spi).await?;
+        }
 
         // start the booster
         self.interface
@@ -145,20 +277,16 @@ where
         self.wait_until_idle(spi).await?;
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x8F])
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.config.panel_setting])
             .await?;
 
-        self.cmd_with_data(
-            spi,
-            Command::VcomAndDataIntervalSetting,
-            &[WHITE_BORDER | VCOM_DATA_INTERVAL],
-        )
-        .await?;
+        self.send_vcom_and_data_interval(spi, border_bits(self.config.border_color))
+            .await?;
 
         // set resolution
         self.send_resolution(spi).await?;
 
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[self.config.vcom_dc])
             .await?;
 
         self.wait_until_idle(spi).await?;
@@ -172,11 +300,11 @@ impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn update_color_frame(
@@ -220,45 +348,31 @@ where
         self.wait_until_idle(spi).await?;
         Ok(())
     }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
 }
 
 impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in9bc { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Section 8.2 from datasheet
-        self.interface
-            .cmd_with_data(
-                spi,
-                Command::VcomAndDataIntervalSetting,
-                &[FLOATING_BORDER | VCOM_DATA_INTERVAL],
-            )
+        self.send_vcom_and_data_interval(spi, FLOATING_BORDER)
             .await?;
 
         self.command(spi, Command::PowerOff).await?;
@@ -273,11 +387,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -288,6 +402,34 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -296,7 +438,7 @@ where
         self.interface.data(spi, buffer).await?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.config.background_color.get_byte_value();
 
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
@@ -308,7 +450,8 @@ where
         self.wait_until_idle(spi).await
     }
 
-    #[allow(unused)]
+    /// Writes the black/white data of a window, see
+    /// [`update_partial_achromatic_frame`](Epd2in9bc::update_partial_achromatic_frame)
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -318,7 +461,8 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        Ok(())
+        self.update_partial_achromatic_frame(spi, buffer, x, y, width, height)
+            .await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -336,6 +480,17 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.send_resolution(spi).await?;
 
@@ -372,17 +527,49 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
+    async fn sanity_check(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.probe_panel(spi).await?;
+        self.init(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        Self::try_init(spi, DisplayInterface::new(busy, dc, rst, delay_us)).await
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in9bc<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn command(
@@ -390,7 +577,9 @@ where
         spi: &mut SPI,
         command: Command,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.cmd(spi, command).await
+        self.interface
+            .cmd_confirmed(spi, command, IS_BUSY_LOW, confirm::UC81XX)
+            .await
     }
 
     async fn send_data(
@@ -424,22 +613,769 @@ where
         self.send_data(spi, &[h as u8]).await
     }
 
+    /// Creates the driver like [`try_new`](WaveshareConstructor::try_new), with the
+    /// [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation
+    ///
+    /// The check runs before the driver first waits for the busy line, so a panel of the wrong
+    /// generation fails with [`ErrorKind::PanelMismatch`] instead of hanging.
+    pub async fn try_new_checked(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        interface.set_check_panel(true);
+        Self::try_init(spi, interface).await
+    }
+
+    /// Runs the [`sanity_check`](WaveshareDisplay::sanity_check) on every initialisation, off
+    /// by default
+    pub fn set_check_panel(&mut self, check: bool) {
+        self.interface.set_check_panel(check);
+    }
+
+    async fn try_init(
+        spi: &mut SPI,
+        interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    ) -> Result<Self, InitError<<Self as ErrorType<SPI, BUSY, DC, RST>>::Error, BUSY, DC, RST>>
+    {
+        let mut epd = Epd2in9bc {
+            interface,
+            config: UserConfig::default(),
+            chromatic_passes: ChromaticPasses::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+
+    /// Fails if the busy line idles at the level of the other controller family, call right
+    /// after the reset
+    async fn probe_panel(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let idle_high = self
+            .interface
+            .busy_reaction(
+                spi,
+                Command::DisplayRefresh,
+                confirm::FAMILY_PROBE.within_us,
+            )
+            .await?;
+        if idle_high == Some(!IS_BUSY_LOW) {
+            return Err(ErrorKind::PanelMismatch(SSD_PANEL));
+        }
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
+    /// Checks that power on, power off and the refresh reach the controller
+    ///
+    /// See [`SafeCommands`], `None` turns the checks off, which is the default.
+    pub fn set_safe_commands(&mut self, safe_commands: Option<SafeCommands>) {
+        self.interface.set_safe_commands(safe_commands);
+    }
+
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     /// Set the outer border of the display to the chosen color.
+    ///
+    /// The color is kept across `sleep` and `wake_up`.
     pub async fn set_border_color(
         &mut self,
         spi: &mut SPI,
-        color: TriColor,
+        color: BorderColor,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let border = match color {
-            TriColor::Black => BLACK_BORDER,
-            TriColor::White => WHITE_BORDER,
-            TriColor::Chromatic => CHROMATIC_BORDER,
+        self.config.border_color = color;
+        self.send_vcom_and_data_interval(spi, border_bits(color))
+            .await
+    }
+
+    /// Resets the controller and initialises it again with another panel setting, e.g. with the
+    /// scan direction bits flipped
+    ///
+    /// The default is [`PANEL_SETTING`], the value is kept across `sleep` and `wake_up` like the
+    /// rest of the [`UserConfig`]. The content of the controller RAM is lost.
+    pub async fn reinit_with_config(
+        &mut self,
+        spi: &mut SPI,
+        panel_setting: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.config.panel_setting = panel_setting;
+        self.init(spi).await
+    }
+
+    /// Set the VCOM and data interval, `0x00..=0x0F`, the default is `0x07`.
+    ///
+    /// Tuning the interval can improve the image at temperatures outside of the usual range.
+    /// The value is kept across `sleep` and `wake_up`.
+    pub async fn set_vcom_data_interval(
+        &mut self,
+        spi: &mut SPI,
+        interval: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if interval > MAX_VCOM_DATA_INTERVAL {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.config.vcom_data_interval = interval;
+        self.send_vcom_and_data_interval(spi, border_bits(self.config.border_color))
+            .await
+    }
+
+    /// Set the VCOM voltage in millivolts, `-3000..=-100` in steps of 50 mV, the default is -600 mV.
+    ///
+    /// Use the value printed on the FPC of the panel, or find it with
+    /// [`calibrate_vcom_step`](Self::calibrate_vcom_step).
+    /// The value is kept across `sleep` and `wake_up`.
+    pub async fn set_vcom_voltage(
+        &mut self,
+        spi: &mut SPI,
+        vcom_mv: i16,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let Some(vcom_dc) = vcom_dc_register(vcom_mv) else {
+            return Err(ErrorKind::InvalidArgument);
         };
+        self.config.vcom_dc = vcom_dc;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom_dc])
+            .await
+    }
+
+    /// Try a VCOM voltage: sets it and refreshes the whole display black, white and black again.
+    ///
+    /// The effect of a wrong VCOM only shows after a full refresh cycle, ghosting and a grey
+    /// instead of a deep black are typical. Call this in a loop, adjusting `vcom_mv` until the
+    /// image looks right, and then keep that value with [`set_vcom_voltage`](Self::set_vcom_voltage)
+    /// after every reset. The displayed frame is lost.
+    pub async fn calibrate_vcom_step(
+        &mut self,
+        spi: &mut SPI,
+        vcom_mv: i16,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_vcom_voltage(spi, vcom_mv).await?;
+        for color in [Color::Black, Color::White, Color::Black] {
+            self.fill_frame(spi, color).await?;
+            self.display_frame(spi).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the black/white data of a window, without sending the rest of the frame
+    ///
+    /// x is rounded down to a multiple of 8 and the window is clipped to the panel, `buffer`
+    /// holds `height` rows of `width` pixels. The panel can't refresh a part of the screen,
+    /// `display_frame` still refreshes all of it.
+    pub async fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.write_partial(
+            spi,
+            Command::DataStartTransmission1,
+            black,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await
+    }
+
+    /// Writes the chromatic data of a window, like
+    /// [`update_partial_achromatic_frame`](Epd2in9bc::update_partial_achromatic_frame)
+    pub async fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.write_partial(
+            spi,
+            Command::DataStartTransmission2,
+            chromatic,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn write_partial(
+        &mut self,
+        spi: &mut SPI,
+        layer: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let x = x & 0xf8;
+        if width == 0
+            || height == 0
+            || x >= WIDTH
+            || y >= HEIGHT
+            || buffer.len() != buffer_len(width as usize, height as usize)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        let row = buffer_len(width as usize, 1);
+        let clipped_width = width.min(WIDTH - x);
+        let clipped_height = height.min(HEIGHT - y);
+        let x_end = (x + clipped_width - 1) | 0x07;
+        let y_end = y + clipped_height - 1;
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialIn).await?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                x as u8,
+                x_end as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // gates scan both inside and outside of the window
+                0x01,
+            ],
+        )
+        .await?;
+
+        self.command(spi, layer).await?;
+        let rows = &buffer[..row * clipped_height as usize];
+        if clipped_width == width {
+            self.send_data(spi, rows).await?;
+        } else {
+            let clipped_row = buffer_len(clipped_width as usize, 1);
+            for line in rows.chunks(row) {
+                self.send_data(spi, &line[..clipped_row]).await?;
+            }
+        }
+
+        self.command(spi, Command::PartialOut).await
+    }
+
+    /// Fills the black/white layer with `color`, without chromatic pixels
+    async fn fill_frame(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd(spi, Command::DataStartTransmission1)
+            .await?;
+        self.interface
+            .data_x_times(spi, color.get_byte_value(), NUM_DISPLAY_BITS)
+            .await?;
+
+        self.interface
+            .cmd(spi, Command::DataStartTransmission2)
+            .await?;
+        self.interface
+            .data_x_times(spi, Color::White.get_byte_value(), NUM_DISPLAY_BITS)
+            .await?;
+
+        self.wait_until_idle(spi).await
+    }
+
+    async fn send_vcom_and_data_interval(
+        &mut self,
+        spi: &mut SPI,
+        border: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.cmd_with_data(
             spi,
             Command::VcomAndDataIntervalSetting,
-            &[border | VCOM_DATA_INTERVAL],
+            &[border | self.config.vcom_data_interval],
         )
         .await
     }
 }
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in9bc, Display2in9bc, "epd2in9bc", planes);
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::interface::BusyWait;
+    use crate::test_utils::*;
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    crate::test_utils::user_config_survives_sleep!(
+        border_and_background_survive_sleep,
+        Epd2in9bc,
+        |epd, spi| {
+            block_on(epd.set_border_color(&mut spi, TriColor::Chromatic)).unwrap();
+            epd.set_background_color(Color::Black);
+        },
+        [
+            &[
+                Command::VcomAndDataIntervalSetting as u8,
+                border_register(TriColor::Chromatic),
+            ][..],
+            &[Command::DataStartTransmission2 as u8, 0x00, 0x00][..],
+        ]
+    );
+
+    crate::test_utils::user_config_survives_sleep!(
+        panel_setting_survives_sleep,
+        Epd2in9bc,
+        |epd, spi| {
+            block_on(epd.reinit_with_config(&mut spi, 0x83)).unwrap();
+        },
+        [&[Command::PanelSetting as u8, 0x83][..]]
+    );
+
+    crate::test_utils::user_config_survives_sleep!(
+        vcom_data_interval_survives_sleep,
+        Epd2in9bc,
+        |epd, spi| {
+            block_on(epd.set_vcom_data_interval(&mut spi, 0x0A)).unwrap();
+        },
+        [&[
+            Command::VcomAndDataIntervalSetting as u8,
+            WHITE_BORDER | 0x0A
+        ][..]]
+    );
+
+    #[test]
+    fn vcom_data_interval_is_validated() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        assert!(matches!(
+            block_on(epd.set_vcom_data_interval(&mut spi, 0x10)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+        assert_eq!(epd.config().vcom_data_interval, VCOM_DATA_INTERVAL);
+
+        block_on(epd.set_vcom_data_interval(&mut spi, MAX_VCOM_DATA_INTERVAL)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [
+                Command::VcomAndDataIntervalSetting as u8,
+                WHITE_BORDER | 0x0F
+            ]
+        );
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        vcom_voltage_survives_sleep,
+        Epd2in9bc,
+        |epd, spi| {
+            block_on(epd.set_vcom_voltage(&mut spi, -1_250)).unwrap();
+        },
+        [&[Command::VcmDcSetting as u8, 0x17][..]]
+    );
+
+    #[test]
+    fn calibrate_vcom_step_cycles_black_white_black() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        assert!(matches!(
+            block_on(epd.calibrate_vcom_step(&mut spi, -50)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+
+        block_on(epd.calibrate_vcom_step(&mut spi, -800)).unwrap();
+        assert_eq!(epd.config().vcom_dc, 0x0E);
+        let written = spi.take();
+        let refresh = Command::DisplayRefresh as u8;
+        let black_white = Command::DataStartTransmission1 as u8;
+        assert_eq!(
+            written[..4],
+            [Command::VcmDcSetting as u8, 0x0E, black_white, 0x00]
+        );
+        assert_eq!(written.iter().filter(|&&b| b == refresh).count(), 3);
+        // every refresh follows an empty chromatic layer, first white then black again
+        assert!(contains(&written, &[0xFF, refresh, black_white, 0xFF]));
+        assert!(contains(&written, &[0xFF, refresh, black_white, 0x00]));
+        assert_eq!(written[written.len() - 2..], [0xFF, refresh]);
+    }
+
+    #[test]
+    fn swapped_color_frame_arguments() {
+        let black = [0x0F; NUM_DISPLAY_BITS as usize];
+        let chromatic = [0xF0; NUM_DISPLAY_BITS as usize];
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        block_on(epd.update_color_frame(&mut spi, &black, &chromatic)).unwrap();
+        let expected = spi.take();
+        block_on(epd.update_color_frame_swapped(&mut spi, &chromatic, &black)).unwrap();
+        assert_eq!(spi.take(), expected);
+    }
+
+    #[test]
+    fn failed_init_returns_the_pins() {
+        let busy = FailingBusy::default();
+        busy.0.set(true);
+        let mut spi = RecordingSpi::default();
+
+        let Err(error) = block_on(Epd2in9bc::try_new(
+            &mut spi,
+            busy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        )) else {
+            panic!("init should fail while the busy line fails");
+        };
+        assert!(matches!(error.error, ErrorKind::BusyError(_)));
+
+        let (_, busy, dc, rst) = error.into_parts();
+        busy.0.set(false);
+        spi.take();
+        let epd = block_on(Epd2in9bc::try_new(&mut spi, busy, dc, rst, Some(0)));
+        assert!(epd.is_ok());
+        assert!(contains(&spi.take(), &[Command::PowerOn as u8]));
+    }
+
+    #[test]
+    fn timing_changes_after_construction() {
+        let busy = CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let waits = busy.0.get();
+        assert!(waits > 0);
+        assert!(spi.delays.contains(&5_000_000));
+
+        // a delay with a 3ms tick, polling the busy line
+        epd.set_delay_granularity(3_000);
+        epd.set_wait_strategy(BusyWait::Poll);
+        spi.delays.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(busy.0.get(), waits);
+        assert!(spi.delays.iter().all(|ns| ns % 3_000_000 == 0));
+        assert!(spi.delays.contains(&6_000_000));
+
+        epd.set_timing(Timing::default());
+        spi.delays.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(busy.0.get() > waits);
+        assert!(spi.delays.contains(&5_000_000));
+    }
+
+    /// Pin reading busy until its edge is awaited, like a GPIO with interrupts
+    #[derive(Clone, Default)]
+    struct InterruptPin {
+        wait_for_high: Rc<Cell<u32>>,
+    }
+
+    impl embedded_hal::digital::ErrorType for InterruptPin {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_hal::digital::InputPin for InterruptPin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(false)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    impl embedded_hal_async::digital::Wait for InterruptPin {
+        async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+            self.wait_for_high.set(self.wait_for_high.get() + 1);
+            Ok(())
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+            unreachable!("busy is low active")
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+            unreachable!()
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+            unreachable!()
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn busy_pin_is_awaited() {
+        let busy = InterruptPin::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let waits = busy.wait_for_high.get();
+        assert!(waits > 0);
+
+        // the pin reads busy all along, polling it would never end
+        spi.delays.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(busy.wait_for_high.get(), waits + 1);
+        assert!(spi.delays.is_empty());
+    }
+
+    #[test]
+    fn flush_waits_only_for_pending_updates() {
+        let busy = CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let buffer = [0xFF; NUM_DISPLAY_BITS as usize];
+        let waits = busy.0.get();
+
+        block_on(epd.flush(&mut spi)).unwrap();
+        assert_eq!(busy.0.get(), waits);
+
+        block_on(epd.update_and_display_frame_no_wait(&mut spi, &buffer)).unwrap();
+        assert!(epd.update_pending());
+        let waits = busy.0.get();
+        block_on(epd.flush(&mut spi)).unwrap();
+        assert_eq!(busy.0.get(), waits + 1);
+        assert!(!epd.update_pending());
+        block_on(epd.flush(&mut spi)).unwrap();
+        assert_eq!(busy.0.get(), waits + 1);
+
+        // a refresh waiting for the display leaves nothing to flush
+        block_on(epd.update_and_display_frame_no_wait(&mut spi, &buffer)).unwrap();
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert!(!epd.update_pending());
+    }
+
+    /// Busy line staying idle for a number of checks, like a controller that missed a command
+    #[derive(Clone, Default)]
+    struct LateBusy(Rc<Cell<u32>>);
+
+    impl crate::interface::BusySignal for LateBusy {
+        type Error = core::convert::Infallible;
+
+        async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+            let idle_checks = self.0.get();
+            self.0.set(idle_checks.saturating_sub(1));
+            idle_checks == 0
+        }
+    }
+
+    #[test]
+    fn register_verification_is_rejected() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        // the UC8151 can't read its registers back
+        assert!(matches!(
+            epd.verify_register_writes(true),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        epd.verify_register_writes(false).unwrap();
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn safe_commands() {
+        let busy = LateBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let unchecked = spi.take();
+        assert_eq!(unchecked, [Command::DisplayRefresh as u8]);
+
+        // the busy line follows the command right away, nothing but the check
+        epd.set_safe_commands(Some(SafeCommands::default()));
+        spi.delays.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(spi.take(), unchecked);
+        assert!(spi.delays.is_empty());
+
+        // the first refresh command is swallowed, 1ms checked every 100us
+        busy.0.set(11);
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(spi.take(), [Command::DisplayRefresh as u8; 2]);
+        assert_eq!(spi.delays, [100_000; 10]);
+
+        // the controller doesn't react at all
+        busy.0.set(u32::MAX);
+        assert!(matches!(
+            block_on(epd.display_frame(&mut spi)),
+            Err(ErrorKind::CommandUnconfirmed(0x12))
+        ));
+        assert_eq!(spi.take(), [Command::DisplayRefresh as u8; 3]);
+
+        // commands without a reaction of the busy line aren't checked
+        busy.0.set(u32::MAX);
+        block_on(epd.set_border_color(&mut spi, TriColor::Black)).unwrap();
+        epd.set_safe_commands(None);
+        block_on(epd.display_frame(&mut spi)).unwrap();
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd2in9bc, "epd2in9bc");
+
+    #[test]
+    fn partial_frame_writes_the_window_of_both_layers() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        // a 16x2 clock region at x 35 (rounded down to 32), y 260
+        block_on(epd.update_partial_frame(&mut spi, &[1, 2, 3, 4], 35, 260, 16, 2)).unwrap();
+        block_on(epd.update_partial_chromatic_frame(&mut spi, &[5, 6, 7, 8], 35, 260, 16, 2))
+            .unwrap();
+        let window = [0x90, 32, 47, 0x01, 0x04, 0x01, 0x05, 0x01];
+        let mut expected = std::vec![0x91];
+        expected.extend_from_slice(&window);
+        expected.extend_from_slice(&[0x10, 1, 2, 3, 4, 0x92, 0x91]);
+        expected.extend_from_slice(&window);
+        expected.extend_from_slice(&[0x13, 5, 6, 7, 8, 0x92]);
+        assert_eq!(spi.take(), expected);
+
+        // clipped to the right and bottom edge of the panel
+        block_on(epd.update_partial_frame(&mut spi, &[1, 2, 3, 4], 120, 295, 16, 2)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [0x91, 0x90, 120, 127, 0x01, 0x27, 0x01, 0x27, 0x01, 0x10, 1, 0x92]
+        );
+
+        for (buffer, x, y) in [
+            (&[0; 3][..], 0, 0),
+            (&[0; 4][..], 128, 0),
+            (&[0; 4][..], 0, 296),
+        ] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, buffer, x, y, 16, 2)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn sanity_check_detects_ssd_panels() {
+        // busy idling low and pulsed high, like an SSD1680
+        let mut spi = ProbedSpi::new(false);
+        let busy = spi.busy();
+        let result = block_on(Epd2in9bc::try_new_checked(
+            &mut spi, busy, NoopPin, NoopPin, None,
+        ));
+        assert!(matches!(
+            result,
+            Err(InitError {
+                error: ErrorKind::PanelMismatch(SSD_PANEL),
+                ..
+            })
+        ));
+
+        // the UC8151 idles high
+        let mut spi = ProbedSpi::new(true);
+        let busy = spi.busy();
+        let Ok(mut epd) = block_on(Epd2in9bc::try_new_checked(
+            &mut spi, busy, NoopPin, NoopPin, None,
+        )) else {
+            panic!("the panel matches");
+        };
+        // back to idle for the next probe
+        spi.level.set(true);
+        spi.reacted = false;
+        block_on(epd.sanity_check(&mut spi)).unwrap();
+
+        // a line that doesn't move tells nothing
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(&mut spi, IdleBusy, NoopPin, NoopPin, None)).unwrap();
+        block_on(epd.sanity_check(&mut spi)).unwrap();
+    }
+}
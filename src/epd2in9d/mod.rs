@@ -8,11 +8,15 @@
 use core::fmt::{Debug, Display};
 use core::slice::from_raw_parts;
 
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
-use crate::{interface::DisplayInterface, prelude::ErrorKind, traits::ErrorType};
+use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay};
+use crate::{
+    interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing},
+    prelude::{ErrorKind, InitError},
+    traits::ErrorType,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -51,9 +55,7 @@ pub type Display2in9d = crate::graphics::Display<
 pub struct Epd2in9d<'a, SPI, BUSY, DC, RST> {
     /// SPI
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Color
-    // background_color: Color,
-    color: Color,
+    config: UserConfig,
     /// Refresh LUT
     refresh: RefreshLut,
     // Storing old data for partial refreshes
@@ -62,15 +64,32 @@ pub struct Epd2in9d<'a, SPI, BUSY, DC, RST> {
     is_partial_refresh: bool,
 }
 
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+}
+
 impl<'a, SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9d<'a, SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -81,11 +100,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -118,39 +137,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9d<'_, S
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-        let old_data: &[u8] = &[];
-        let is_partial_refresh = false;
-
-        let mut epd = Epd2in9d {
-            interface,
-            color,
-            refresh: RefreshLut::Full,
-            old_data,
-            is_partial_refresh,
-        };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.is_partial_refresh = false;
         self.interface
@@ -172,11 +166,11 @@ where
     }
 
     fn set_background_color(&mut self, background_color: Color) {
-        self.color = background_color;
+        self.config.background_color = background_color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -187,6 +181,35 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.is_partial_refresh = false;
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     // Corresponds to the Display function.
     // Used to write the data to be displayed to the screen SRAM.
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
@@ -271,6 +294,18 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.interface.cmd(spi, Command::DisplayRefresh).await?;
+        self.interface.delay(spi, 1_000).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -280,7 +315,13 @@ where
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
-        self.interface.data_x_times(spi, 0xFF, EPD_ARRAY).await?;
+        self.interface
+            .data_x_times(
+                spi,
+                self.config.background_color.get_byte_value(),
+                EPD_ARRAY,
+            )
+            .await?;
 
         self.display_frame(spi).await?;
 
@@ -304,17 +345,60 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST>
+    for Epd2in9d<'_, SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let old_data: &[u8] = &[];
+        let is_partial_refresh = false;
+
+        let mut epd = Epd2in9d {
+            interface,
+            config: UserConfig::default(),
+            refresh: RefreshLut::Full,
+            old_data,
+            is_partial_refresh,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd2in9d<'_, SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// Wake Up Screen
@@ -440,3 +524,27 @@ where
         Ok(())
     }
 }
+
+impl<'a, SPI, BUSY, DC, RST> Epd2in9d<'a, SPI, BUSY, DC, RST> {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd2in9d<'a>, Display2in9d, "epd2in9d");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd2in9d,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::DataStartTransmission2 as u8, 0x00, 0x00][..]]
+    );
+}
@@ -24,6 +24,8 @@ pub(crate) enum Command {
     GateVoltageSource = 0x04,
     ///
     BoosterSoftStartControl = 0x0C,
+    /// Set the gate scanned first, i.e. the RAM row shown at the top of the panel
+    GateScanStartPosition = 0x0F,
     /// After this command initiated, the chip will enter Deep Sleep Mode,
     /// BUSY pad will keep output high.
     ///
@@ -53,6 +55,12 @@ pub(crate) enum Command {
     DisplayUpdateSequenceSetting = 0x22,
     /// This command will transfer its data to B/W RAM, until another command is written
     WriteRam = 0x24,
+    /// This command will transfer its data to RED RAM, until another command is written
+    ///
+    /// With four gray levels it holds the upper bit of every pixel.
+    WriteRam2 = 0x26,
+    /// Reads the RAM selected by [`Command::ReadRamOption`], the first byte read is a dummy
+    ReadRam = 0x27,
     /// This command writes VCOM register from MCU interface
     WriteVcomRegister = 0x2C,
     /// This command writes LUT register from MCU interface (105 bytes),
@@ -62,6 +70,8 @@ pub(crate) enum Command {
     DisplayOption = 0x37,
     ///
     BorderWaveformControl = 0x3C,
+    /// Selects the RAM read by [`Command::ReadRam`], 0x00 for B/W and 0x01 for RED
+    ReadRamOption = 0x41,
     /// This command specifies the start/end positions of the window address in the X direction,
     /// by an address unit of RAM.
     SetRamXAddressStartEndPosition = 0x44,
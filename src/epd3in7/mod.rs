@@ -3,8 +3,8 @@
 //!
 //! Build with the help of documentation/code from [Waveshare](https://www.waveshare.com/wiki/3.7inch_e-Paper_HAT),
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 pub(crate) mod command;
 mod constants;
@@ -14,9 +14,13 @@ use self::constants::*;
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::gray;
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, GrayscaleLevels, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareGrayscaleDisplay,
+};
 
 /// Width of the display.
 pub const WIDTH: u32 = 280;
@@ -27,6 +31,15 @@ pub const HEIGHT: u32 = 480;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+///
+/// The quick table is a direct update waveform, which copes with dense line art.
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy {
+    max_edge_density: 40,
+    ..crate::graphics::RefreshPolicy::DEFAULT
+};
+
 const IS_BUSY_LOW: bool = false;
 
 const SINGLE_BYTE_WRITE: bool = true;
@@ -41,23 +54,73 @@ pub type Display3in7 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with Fullsize buffer of four gray levels for use with the 3in7 EPD, see
+/// [`WaveshareGrayscaleDisplay`]
+#[cfg(feature = "graphics")]
+pub type Display3in7Grayscale = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { gray::packed_len(WIDTH, HEIGHT) },
+    embedded_graphics_core::pixelcolor::Gray2,
+>;
+
+/// How [`Epd3in7::scroll_up`] moves the content up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScrollStrategy {
+    /// The controller starts scanning at a later RAM row, only the rows appearing at the
+    /// bottom are sent
+    #[default]
+    GateScanStart,
+    /// The content is read back from the RAM a row at a time and written back shifted, for
+    /// panels whose glass doesn't follow the gate scan start. Needs a readable data line
+    /// (e.g. MISO) and sends the whole frame twice.
+    Rewrite,
+}
+
 /// Epd3in7 driver
 pub struct Epd3in7<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    background_color: Color,
+    config: UserConfig,
+    /// RAM row currently shown at the top of the panel
+    scroll_offset: u32,
+    /// Used by [`Epd3in7::scroll_up`]
+    scroll_strategy: ScrollStrategy,
+    /// A partial frame waits for [`display_frame`](WaveshareDisplay::display_frame) with the
+    /// quick LUT loaded
+    partial: bool,
+    /// The four gray level LUT is loaded, see
+    /// [`update_grayscale_frame`](WaveshareGrayscaleDisplay::update_grayscale_frame)
+    gray: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -67,11 +130,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd3in7<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -80,6 +143,8 @@ where
 
         self.interface.cmd(spi, Command::SwReset).await?;
         self.interface.delay(spi, 300000u32).await?;
+        self.scroll_offset = 0;
+        self.partial = false;
 
         self.interface
             .cmd_with_data(spi, Command::AutoWriteRedRamRegularPattern, &[0xF7])
@@ -159,31 +224,15 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd3in7<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let mut epd = Epd3in7 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
-            background_color: DEFAULT_BACKGROUND_COLOR,
-        };
-
-        epd.init(spi).await?;
-        Ok(epd)
-    }
-
     async fn wake_up(
         &mut self,
         spi: &mut SPI,
@@ -205,11 +254,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
-        self.background_color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Self::DisplayColor {
-        &self.background_color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -220,25 +269,61 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.scroll_offset = 0;
+        self.partial = false;
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])
-            .await?;
+        if buffer.len() != buffer_len(WIDTH as usize, HEIGHT as usize) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        if self.gray {
+            self.set_lut(spi, Some(RefreshLut::Full)).await?;
+        }
+        self.end_partial(spi).await?;
+        self.reset_ram_counters(spi).await?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await
+            .await?;
+        self.reset_scroll(spi).await
     }
 
-    #[allow(unused)]
+    /// Writes the window into RAM and loads the quick LUT, the next
+    /// [`display_frame`](WaveshareDisplay::display_frame) refreshes with it and switches back
+    /// to the full LUT.
+    ///
+    /// X and WIDTH need to be multiples of 8. Not available after a frame of four gray levels.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -248,19 +333,45 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        todo!()
+        if self.gray
+            || !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || width == 0
+            || height == 0
+            || x.saturating_add(width) > WIDTH
+            || y.saturating_add(height) > HEIGHT
+            || buffer.len() != buffer_len(width as usize, height as usize)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        if !self.partial {
+            self.set_lut(spi, Some(RefreshLut::Quick)).await?;
+            self.partial = true;
+        }
+
+        // After a scroll the window starts at a later RAM row and may wrap around
+        let row_len = width as usize / 8;
+        let start = (y + self.scroll_offset) % HEIGHT;
+        let first = height.min(HEIGHT - start);
+        let (top, bottom) = buffer.split_at(row_len * first as usize);
+        self.write_window(spi, top, x, start, width, first).await?;
+        if !bottom.is_empty() {
+            self.write_window(spi, bottom, x, 0, width, height - first)
+                .await?;
+        }
+        self.set_ram_window(spi, 0, 0, WIDTH, HEIGHT).await
     }
 
     async fn display_frame(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        //self.interface
-        //    .cmd_with_data(spi, Command::WRITE_LUT_REGISTER, &LUT_1GRAY_GC)?;
         self.interface
             .cmd(spi, Command::DisplayUpdateSequence)
             .await?;
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await?;
+        self.end_partial(spi).await
     }
 
     async fn update_and_display_frame(
@@ -272,19 +383,37 @@ where
         self.display_frame(spi).await
     }
 
-    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
-            .await?;
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_frame(spi, buffer).await?;
         self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])
+            .cmd(spi, Command::DisplayUpdateSequence)
             .await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
 
-        let color = self.background_color.get_byte_value();
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.end_partial(spi).await?;
+        self.reset_ram_counters(spi).await?;
+
+        let color = self.config.background_color.get_byte_value();
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
             .data_x_times(spi, color, WIDTH * HEIGHT)
-            .await
+            .await?;
+        if self.gray {
+            // both bits of the level
+            self.reset_ram_counters(spi).await?;
+            self.interface.cmd(spi, Command::WriteRam2).await?;
+            self.interface
+                .data_x_times(spi, color, WIDTH * HEIGHT)
+                .await?;
+        }
+        self.reset_scroll(spi).await
     }
 
     async fn set_lut(
@@ -292,20 +421,820 @@ where
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_1GRAY_GC,
-            Some(RefreshLut::Quick) => &LUT_1GRAY_DU,
+        let buffer = match refresh_rate.unwrap_or_default() {
+            RefreshLut::Full => &LUT_1GRAY_GC,
+            RefreshLut::Quick => &LUT_1GRAY_DU,
         };
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)
-            .await
+            .await?;
+        self.gray = false;
+        self.partial = false;
+        Ok(())
     }
 
     async fn wait_until_idle(
         &mut self,
-        _spi: &mut SPI,
+        spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let mut epd = Epd3in7 {
+            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            config: UserConfig::default(),
+            scroll_offset: 0,
+            scroll_strategy: ScrollStrategy::default(),
+            partial: false,
+            gray: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareGrayscaleDisplay<SPI, BUSY, DC, RST>
+    for Epd3in7<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    fn supports_levels(&self, levels: GrayscaleLevels) -> bool {
+        levels != GrayscaleLevels::Sixteen
+    }
+
+    /// Two levels are written like [`update_frame`](WaveshareDisplay::update_frame), four
+    /// load the vendor's 4 gray LUT. It stays loaded until the next black/white frame or
+    /// [`set_lut`](WaveshareDisplay::set_lut).
+    async fn update_grayscale_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        levels: GrayscaleLevels,
+    ) -> Result<(), Self::Error> {
+        match levels {
+            GrayscaleLevels::Two => self.update_frame(spi, buffer).await,
+            GrayscaleLevels::Four => self.update_gray_frame(spi, buffer).await,
+            GrayscaleLevels::Sixteen => Err(ErrorKind::InvalidArgument),
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST> Epd3in7<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    /// Scroll the displayed content up by `rows` and refresh the display with the quick LUT
+    ///
+    /// The rows appearing at the bottom are filled with `fill`. By default this is a hardware
+    /// scroll: the controller is told to start scanning at a later RAM row, so only the new
+    /// rows are sent. Writing a full frame afterwards resets the scroll position. See
+    /// [`ScrollStrategy`] for the alternative. Not available after a frame of four gray levels.
+    pub async fn scroll_up(
+        &mut self,
+        spi: &mut SPI,
+        rows: u32,
+        fill: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.gray {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        let rows = rows.min(HEIGHT);
+        if rows == 0 {
+            return Ok(());
+        }
+
+        match self.scroll_strategy {
+            ScrollStrategy::GateScanStart => {
+                // The RAM rows leaving the top of the panel wrap around to the bottom
+                self.fill_wrapping(spi, self.scroll_offset, rows, fill)
+                    .await?;
+                self.scroll_offset = (self.scroll_offset + rows) % HEIGHT;
+                self.set_gate_scan_start(spi, self.scroll_offset).await?;
+            }
+            ScrollStrategy::Rewrite => {
+                self.move_ram_rows_up(spi, rows).await?;
+                let bottom = (self.scroll_offset + HEIGHT - rows) % HEIGHT;
+                self.fill_wrapping(spi, bottom, rows, fill).await?;
+            }
+        }
+
+        self.set_lut(spi, Some(RefreshLut::Quick)).await?;
+        self.display_frame(spi).await?;
+        self.set_lut(spi, Some(RefreshLut::Full)).await
+    }
+
+    /// RAM row currently shown at the top of the panel, non-zero after [`Self::scroll_up`]
+    pub fn scroll_offset(&self) -> u32 {
+        self.scroll_offset
+    }
+
+    /// How [`Self::scroll_up`] moves the content
+    pub fn scroll_strategy(&self) -> ScrollStrategy {
+        self.scroll_strategy
+    }
+
+    /// Change how [`Self::scroll_up`] moves the content, a scroll position reached before is
+    /// kept until the next full frame
+    pub fn set_scroll_strategy(&mut self, strategy: ScrollStrategy) {
+        self.scroll_strategy = strategy;
+    }
+
+    /// Fills `count` RAM rows starting at `row`, wrapping around at the end of the RAM
+    async fn fill_wrapping(
+        &mut self,
+        spi: &mut SPI,
+        mut row: u32,
+        count: u32,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let mut remaining = count;
+        while remaining > 0 {
+            let count = remaining.min(HEIGHT - row);
+            self.fill_ram_rows(spi, row, count, color).await?;
+            remaining -= count;
+            row = (row + count) % HEIGHT;
+        }
+        Ok(())
+    }
+
+    /// Copies every shown row `rows` rows up, one row at a time so only a row is buffered
+    async fn move_ram_rows_up(
+        &mut self,
+        spi: &mut SPI,
+        rows: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::ReadRamOption, &[0x00])
+            .await?;
+        // the dummy byte and a row
+        let mut line = [0; WIDTH as usize / 8 + 1];
+        for row in 0..HEIGHT - rows {
+            let source = (self.scroll_offset + row + rows) % HEIGHT;
+            self.set_ram_counters(spi, source).await?;
+            self.interface
+                .cmd_read(spi, Command::ReadRam, &mut line)
+                .await?;
+
+            let target = (self.scroll_offset + row) % HEIGHT;
+            self.set_ram_counters(spi, target).await?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam, &line[1..])
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn set_ram_counters(
+        &mut self,
+        spi: &mut SPI,
+        row: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressCounter,
+                &[row as u8, (row >> 8) as u8],
+            )
+            .await
+    }
+
+    async fn fill_ram_rows(
+        &mut self,
+        spi: &mut SPI,
+        row: u32,
+        count: u32,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_ram_counters(spi, row).await?;
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color.get_byte_value(), WIDTH / 8 * count)
+            .await
+    }
+
+    async fn set_gate_scan_start(
+        &mut self,
+        spi: &mut SPI,
+        row: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::GateScanStartPosition,
+                &[row as u8, (row >> 8) as u8],
+            )
+            .await
+    }
+
+    /// Writes a frame of four gray levels, packed as described in [`crate::gray`]
+    async fn update_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if buffer.len() != gray::packed_len(WIDTH, HEIGHT) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        if !self.gray {
+            self.interface
+                .cmd_with_data(spi, Command::WriteLutRegister, &LUT_4GRAY_GC)
+                .await?;
+            self.gray = true;
+            self.partial = false;
+        }
+        for (command, bit) in [(Command::WriteRam, 0), (Command::WriteRam2, 1)] {
+            self.reset_ram_counters(spi).await?;
+            self.interface.cmd(spi, command).await?;
+            let mut row = [0; WIDTH as usize / 8];
+            for packed in buffer.chunks(WIDTH as usize / 4) {
+                for (byte, pixels) in row.iter_mut().zip(packed.chunks(2)) {
+                    *byte = gray_plane(pixels, bit);
+                }
+                self.interface.data(spi, &row).await?;
+            }
+        }
+        self.reset_scroll(spi).await
+    }
+
+    async fn reset_ram_counters(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])
+            .await
+    }
+
+    /// Writes `rows` rows of `width` pixels to the RAM, starting at pixel `x` of RAM row `row`
+    async fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+        x: u32,
+        row: u32,
+        width: u32,
+        rows: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_ram_window(spi, x, row, width, rows).await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressCounter,
+                &[x as u8, (x >> 8) as u8],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressCounter,
+                &[row as u8, (row >> 8) as u8],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, data)
+            .await
+    }
+
+    async fn set_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let x_end = x + width - 1;
+        let y_end = y + height - 1;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressStartEndPosition,
+                &[x as u8, (x >> 8) as u8, x_end as u8, (x_end >> 8) as u8],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressStartEndPosition,
+                &[y as u8, (y >> 8) as u8, y_end as u8, (y_end >> 8) as u8],
+            )
+            .await
+    }
+
+    /// Switches back to the full LUT after a partial frame
+    async fn end_partial(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.partial {
+            self.partial = false;
+            self.set_lut(spi, Some(RefreshLut::Full)).await?;
+        }
+        Ok(())
+    }
+
+    async fn reset_scroll(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.scroll_offset != 0 {
+            self.scroll_offset = 0;
+            self.set_gate_scan_start(spi, 0).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Bit `bit` of the levels of eight pixels packed with two bits per pixel
+fn gray_plane(pixels: &[u8], bit: u8) -> u8 {
+    (0..8).fold(0, |plane, pixel| {
+        let level = pixels[pixel / 4] >> (6 - 2 * (pixel % 4));
+        plane | (level >> bit & 1) << (7 - pixel)
+    })
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd3in7, Display3in7, "epd3in7");
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+    use core::cell::{Cell, RefCell};
+    use core::convert::Infallible;
+    use embedded_hal_async::spi::Operation;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    const LINE_BYTES: usize = WIDTH as usize / 8;
+
+    /// Minimal model of the controller RAM, enough to follow reads, writes and the gate scan
+    /// start
+    struct Ram {
+        data: Vec<u8>,
+        command: u8,
+        params: Vec<u8>,
+        x: usize,
+        y: usize,
+        gate_start: usize,
+        dummy_read: bool,
+    }
+
+    impl Ram {
+        fn new() -> Self {
+            Ram {
+                data: vec![0; LINE_BYTES * HEIGHT as usize],
+                command: 0,
+                params: Vec::new(),
+                x: 0,
+                y: 0,
+                gate_start: 0,
+                dummy_read: false,
+            }
+        }
+
+        fn receive(&mut self, is_data: bool, byte: u8) {
+            if !is_data {
+                self.command = byte;
+                self.params.clear();
+                self.dummy_read = false;
+                return;
+            }
+            self.params.push(byte);
+            let value = self
+                .params
+                .iter()
+                .rev()
+                .fold(0, |acc, &b| acc << 8 | b as usize);
+            match self.command {
+                0x4E => self.x = value / 8,
+                0x4F => self.y = value,
+                0x0F => self.gate_start = value,
+                0x24 => {
+                    self.data[self.y * LINE_BYTES + self.x] = byte;
+                    self.advance();
+                }
+                _ => {}
+            }
+        }
+
+        fn read(&mut self) -> u8 {
+            assert_eq!(self.command, 0x27);
+            if !self.dummy_read {
+                self.dummy_read = true;
+                return 0;
+            }
+            let byte = self.data[self.y * LINE_BYTES + self.x];
+            self.advance();
+            byte
+        }
+
+        fn advance(&mut self) {
+            self.x += 1;
+            if self.x == LINE_BYTES {
+                self.x = 0;
+                self.y = (self.y + 1) % HEIGHT as usize;
+            }
+        }
+
+        /// Rows as seen on the panel
+        fn visible(&self) -> Vec<u8> {
+            let split = self.gate_start * LINE_BYTES;
+            [&self.data[split..], &self.data[..split]].concat()
+        }
+    }
+
+    struct RamSpi {
+        ram: Rc<RefCell<Ram>>,
+        dc: Rc<Cell<bool>>,
+    }
+
+    impl embedded_hal::spi::ErrorType for RamSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for RamSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations.iter_mut() {
+                match operation {
+                    Operation::Write(data) => {
+                        for &byte in data.iter() {
+                            self.ram.borrow_mut().receive(self.dc.get(), byte);
+                        }
+                    }
+                    Operation::Read(buffer) => {
+                        for byte in buffer.iter_mut() {
+                            *byte = self.ram.borrow_mut().read();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+    }
+
+    struct DcPin(Rc<Cell<bool>>);
+
+    impl embedded_hal::digital::ErrorType for DcPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for DcPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.0.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.0.set(true);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 280);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn scroll_up_matches_shifted_frame() {
+        for strategy in [ScrollStrategy::GateScanStart, ScrollStrategy::Rewrite] {
+            check_scroll_up(strategy);
+        }
+    }
+
+    fn check_scroll_up(strategy: ScrollStrategy) {
+        let ram = Rc::new(RefCell::new(Ram::new()));
+        let dc = Rc::new(Cell::new(false));
+        let mut spi = RamSpi {
+            ram: ram.clone(),
+            dc: dc.clone(),
+        };
+        let rst = DcPin(Rc::new(Cell::new(false)));
+        let mut epd = block_on(Epd3in7::new(&mut spi, IdleBusy, DcPin(dc), rst, Some(0))).unwrap();
+        epd.set_scroll_strategy(strategy);
+
+        // every row gets a distinct byte
+        let frame: Vec<u8> = (0..HEIGHT as usize)
+            .flat_map(|row| [(row % 251) as u8 + 1; LINE_BYTES])
+            .collect();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+
+        let mut expected = frame.clone();
+        for rows in [1, 8, 13, 16, 470] {
+            block_on(epd.scroll_up(&mut spi, rows, Color::Black)).unwrap();
+
+            expected.drain(..rows as usize * LINE_BYTES);
+            expected.resize(frame.len(), Color::Black.get_byte_value());
+            assert_eq!(
+                ram.borrow().visible(),
+                expected,
+                "{:?} by {}",
+                strategy,
+                rows
+            );
+        }
+        if strategy == ScrollStrategy::Rewrite {
+            assert_eq!(epd.scroll_offset(), 0);
+        }
+
+        // a full frame write starts over at the top
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(epd.scroll_offset(), 0);
+        assert_eq!(ram.borrow().visible(), frame);
+    }
+
+    #[test]
+    fn update_frame_rejects_wrong_size() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in7::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        let buffer = vec![0; buffer_len(WIDTH as usize, HEIGHT as usize) - 1];
+        assert!(matches!(
+            block_on(epd.update_frame(&mut spi, &buffer)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn partial_frame_uses_the_quick_lut() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in7::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        let window = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC];
+        block_on(epd.update_partial_frame(&mut spi, &window, 16, 300, 16, 3)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+
+        let mut expected = vec![Command::WriteLutRegister as u8];
+        expected.extend_from_slice(&LUT_1GRAY_DU);
+        expected.extend_from_slice(&[
+            Command::SetRamXAddressStartEndPosition as u8,
+            16,
+            0,
+            31,
+            0,
+            Command::SetRamYAddressStartEndPosition as u8,
+            0x2C,
+            0x01,
+            0x2E,
+            0x01,
+            Command::SetRamXAddressCounter as u8,
+            16,
+            0,
+            Command::SetRamYAddressCounter as u8,
+            0x2C,
+            0x01,
+            Command::WriteRam as u8,
+        ]);
+        expected.extend_from_slice(&window);
+        expected.extend_from_slice(&[
+            Command::SetRamXAddressStartEndPosition as u8,
+            0x00,
+            0x00,
+            0x17,
+            0x01,
+            Command::SetRamYAddressStartEndPosition as u8,
+            0x00,
+            0x00,
+            0xDF,
+            0x01,
+            Command::DisplayUpdateSequence as u8,
+            Command::WriteLutRegister as u8,
+        ]);
+        expected.extend_from_slice(&LUT_1GRAY_GC);
+        assert_eq!(spi.take(), expected);
+
+        // the window has to be whole bytes and on the panel
+        for (x, y, width, height) in [
+            (4, 0, 16, 3),
+            (0, 0, 12, 4),
+            (272, 0, 16, 3),
+            (0, 478, 16, 3),
+        ] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &window, x, y, width, height)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn partial_frame_follows_the_scroll() {
+        let ram = Rc::new(RefCell::new(Ram::new()));
+        let dc = Rc::new(Cell::new(false));
+        let mut spi = RamSpi {
+            ram: ram.clone(),
+            dc: dc.clone(),
+        };
+        let rst = DcPin(Rc::new(Cell::new(false)));
+        let mut epd = block_on(Epd3in7::new(&mut spi, IdleBusy, DcPin(dc), rst, Some(0))).unwrap();
+
+        block_on(epd.scroll_up(&mut spi, 470, Color::White)).unwrap();
+        // full rows, so the RAM model doesn't need the window
+        let window = vec![0xA5; LINE_BYTES * 20];
+        block_on(epd.update_partial_frame(&mut spi, &window, 0, 5, WIDTH, 20)).unwrap();
+
+        let visible = ram.borrow().visible();
+        assert_eq!(visible[5 * LINE_BYTES..25 * LINE_BYTES], window[..]);
+    }
+
+    #[test]
+    fn gray_frame_splits_the_levels() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in7::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // black, dark gray, light gray, white over and over
+        let frame = vec![0b00_01_10_11; gray::packed_len(WIDTH, HEIGHT)];
+        block_on(epd.update_grayscale_frame(&mut spi, &frame, GrayscaleLevels::Four)).unwrap();
+        let written = spi.take();
+        assert_eq!(written[0], Command::WriteLutRegister as u8);
+        assert_eq!(written[1..106], LUT_4GRAY_GC);
+        let written = &written[106..];
+        let plane_len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        let counters = [
+            Command::SetRamXAddressCounter as u8,
+            0x00,
+            0x00,
+            Command::SetRamYAddressCounter as u8,
+            0x00,
+            0x00,
+        ];
+        let (low, high) = written.split_at(counters.len() + 1 + plane_len);
+        assert_eq!(low[..6], counters);
+        assert_eq!(low[6], Command::WriteRam as u8);
+        assert!(low[7..].iter().all(|&byte| byte == 0b0101_0101));
+        assert_eq!(high[..6], counters);
+        assert_eq!(high[6], Command::WriteRam2 as u8);
+        assert!(high[7..].iter().all(|&byte| byte == 0b0011_0011));
+
+        // windows don't fit, a black/white frame switches back to the full LUT
+        let mono = vec![0; plane_len];
+        assert!(matches!(
+            block_on(epd.update_partial_frame(&mut spi, &mono[..8], 0, 0, 16, 4)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(matches!(
+            block_on(epd.scroll_up(&mut spi, 8, Color::White)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+
+        block_on(epd.update_frame(&mut spi, &mono)).unwrap();
+        let written = spi.take();
+        assert_eq!(written[0], Command::WriteLutRegister as u8);
+        assert_eq!(written[1..106], LUT_1GRAY_GC);
+    }
+
+    /// Transcription of the loops of `EPD_3IN7_4Gray_Display` in Waveshare's `EPD_3in7.c`,
+    /// which sends a plane of the image to command 0x24 and one to 0x26. The image has two
+    /// bits per pixel, 0xC0 is white, 0x80 gray 1, 0x40 gray 2 and 0x00 black.
+    fn vendor_plane(image: [u8; 2], command: u8) -> u8 {
+        let mut temp3 = 0u8;
+        for mut temp1 in image {
+            for _ in 0..4 {
+                let white = match temp1 & 0xC0 {
+                    0xC0 => true,
+                    0x00 => false,
+                    0x80 => command == 0x26,
+                    _ => command == 0x24,
+                };
+                temp3 = temp3 << 1 | white as u8;
+                temp1 <<= 2;
+            }
+        }
+        temp3
+    }
+
+    #[test]
+    fn gray_planes_match_the_vendor_driver() {
+        assert_eq!(Command::WriteRam as u8, 0x24);
+        assert_eq!(Command::WriteRam2 as u8, 0x26);
+        for image in 0..=u16::MAX {
+            let image = image.to_be_bytes();
+            assert_eq!(gray_plane(&image, 0), vendor_plane(image, 0x24));
+            assert_eq!(gray_plane(&image, 1), vendor_plane(image, 0x26));
+        }
+    }
+
+    #[test]
+    fn grayscale_frames_switch_the_lut() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in7::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let gray = vec![0xFF; GrayscaleLevels::Four.buffer_len(WIDTH, HEIGHT)];
+        let mono = vec![0xFF; GrayscaleLevels::Two.buffer_len(WIDTH, HEIGHT)];
+        spi.take();
+
+        block_on(epd.update_grayscale_frame(&mut spi, &gray, GrayscaleLevels::Four)).unwrap();
+        let written = spi.take();
+        assert_eq!(written[0], Command::WriteLutRegister as u8);
+        assert_eq!(written[1..106], LUT_4GRAY_GC);
+        // already selected
+        block_on(epd.update_grayscale_frame(&mut spi, &gray, GrayscaleLevels::Four)).unwrap();
+        assert_ne!(spi.take()[0], Command::WriteLutRegister as u8);
+
+        block_on(epd.update_grayscale_frame(&mut spi, &mono, GrayscaleLevels::Two)).unwrap();
+        assert_eq!(spi.take()[1..106], LUT_1GRAY_GC);
+
+        assert!(!epd.supports_levels(GrayscaleLevels::Sixteen));
+        let sixteen = vec![0xFF; GrayscaleLevels::Sixteen.buffer_len(WIDTH, HEIGHT)];
+        assert!(matches!(
+            block_on(epd.update_grayscale_frame(&mut spi, &sixteen, GrayscaleLevels::Sixteen)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn gray_display_packs_two_bits() {
+        use embedded_graphics::{pixelcolor::Gray2, prelude::*};
+
+        let mut display = std::boxed::Box::<Display3in7Grayscale>::default();
+        display.clear(Gray2::WHITE).unwrap();
+        display.set_pixel(Pixel(Point::new(1, 0), Gray2::new(0b10)));
+        display.set_pixel(Pixel(Point::new(279, 1), Gray2::BLACK));
+        assert_eq!(display.buffer()[0], 0b11_10_11_11);
+        assert_eq!(display.buffer()[2 * 70 - 1], 0b11_11_11_00);
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd3in7, "epd3in7");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd3in7,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::WriteRam as u8, 0x00, 0x00][..]]
+    );
 }
@@ -0,0 +1,84 @@
+//! SPI Commands for the Waveshare 3.71" E-Ink Display
+use crate::traits;
+
+/// EPD3IN71 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// The description of the single commands is mostly taken from the UC8253 datasheet
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Resolution, LUT selection, black/white mode, scan directions, booster switch and soft reset
+    ///
+    /// Two bytes, the second one is kept at its default.
+    PanelSetting = 0x00,
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+    /// Turns off charge pump, T-con, source driver, gate driver, VCOM and temperature sensor,
+    /// the registers are kept
+    PowerOff = 0x02,
+    /// Turning On the Power
+    PowerOn = 0x04,
+    /// Booster soft start timing
+    BoosterSoftStart = 0x06,
+    /// Enters deep sleep, needs the check code 0xA5. Only a hardware reset wakes the chip up.
+    DeepSleep = 0x07,
+    /// Writes the "OLD" data in black/white mode
+    DataStartTransmission1 = 0x10,
+    /// Stopping data transmission
+    DataStop = 0x11,
+    /// Refreshes the display according to the RAM and the LUT, BUSY_N goes low during the refresh
+    DisplayRefresh = 0x12,
+    /// Writes the "NEW" data in black/white mode
+    DataStartTransmission2 = 0x13,
+    /// Frame rate
+    PllControl = 0x30,
+    /// Temperature sensor selection
+    TemperatureSensorSelection = 0x41,
+    /// Border output and interval between VCOM and data output
+    VcomAndDataIntervalSetting = 0x50,
+    /// Non-overlap period of gate and source
+    TconSetting = 0x60,
+    /// Horizontal resolution in one byte, vertical resolution in two bytes
+    ResolutionSetting = 0x61,
+    /// Status flags, e.g. whether the chip is busy
+    GetStatus = 0x71,
+    /// VCOM DC voltage
+    VcmDcSetting = 0x82,
+    /// Partial window: horizontal start and end in one byte each, vertical start and end in two
+    /// bytes each and the gate scan mode
+    PartialWindow = 0x90,
+    /// Enters partial mode, only the partial window is written and refreshed
+    PartialIn = 0x91,
+    /// Leaves partial mode
+    PartialOut = 0x92,
+    /// Cascade setting, bit 1 uses the temperature of `ForceTemperature` for choosing the LUT
+    CascadeSetting = 0xE0,
+    /// Power saving
+    PowerSaving = 0xE3,
+    /// Temperature used instead of the sensor reading if enabled by `CascadeSetting`
+    ForceTemperature = 0xE5,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::ForceTemperature.address(), 0xE5);
+
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+    }
+}
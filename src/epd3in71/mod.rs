@@ -0,0 +1,524 @@
+//! A simple Driver for the Waveshare 3.71" E-Ink Display via SPI
+//!
+//! The panel is driven by an UC8253 controller. It supports full refreshes with the look-up
+//! tables from the OTP, a fast mode using the short 4-frame waveform and partial refreshes.
+//!
+//! # Example
+//!
+//!```rust, ignore
+//!use epd_waveshare_async::{epd3in71::*, prelude::*};
+//!
+//!let mut epd = Epd3in71::new(&mut spi, busy, dc, rst, None).await?;
+//!let mut display = Display3in71::default();
+//!// draw with embedded-graphics ...
+//!epd.update_and_display_frame(&mut spi, display.buffer()).await?;
+//!
+//!// Refresh only a changed region with the fast waveform
+//!epd.set_lut(&mut spi, Some(RefreshLut::Quick)).await?;
+//!epd.update_partial_frame(&mut spi, &clock, 80, 200, 80, 32).await?;
+//!epd.display_frame(&mut spi).await?;
+//!
+//!epd.sleep(&mut spi).await?;
+//!```
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::color::Color;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::buffer_len;
+
+/// Width of the display
+pub const WIDTH: u32 = 240;
+/// Height of the display
+pub const HEIGHT: u32 = 416;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+const SINGLE_BYTE_WRITE: bool = true;
+const NUM_DISPLAY_BYTES: u32 = WIDTH / 8 * HEIGHT;
+
+/// Black/white mode with the look-up tables from the OTP
+const PANEL_SETTING: [u8; 2] = [0x1F, 0x0D];
+/// Border white, VCOM and data interval of 10 frames
+const VCOM_AND_DATA_INTERVAL: u8 = 0x97;
+/// Floating border, used before going to sleep
+const FLOATING_BORDER: u8 = 0xF7;
+/// Forcing this temperature makes the controller use its 4-frame waveform
+const FAST_LUT_TEMPERATURE: u8 = 0x5F;
+
+/// Full size buffer for use with the 3in71 EPD
+#[cfg(feature = "graphics")]
+pub type Display3in71 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd3in71 driver
+pub struct Epd3in71<SPI, BUSY, DC, RST> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and sent as the old data by `update_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd3in71<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd3in71<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+
+        self.cmd_with_data(spi, Command::PanelSetting, &PANEL_SETTING)
+            .await?;
+        self.send_resolution(spi).await?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[VCOM_AND_DATA_INTERVAL],
+        )
+        .await?;
+
+        self.set_lut(spi, None).await?;
+
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd3in71<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type DisplayColor = Color;
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[FLOATING_BORDER])
+            .await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.config.background_color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.config.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        if buffer.len() != NUM_DISPLAY_BYTES as usize {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+
+        let color_value = self.config.background_color.get_byte_value();
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, color_value, NUM_DISPLAY_BYTES)
+            .await?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    /// Writes `buffer` to the window at `x`, `y`
+    ///
+    /// `x` and `width` need to be multiples of 8. Returns `ErrorKind::InvalidArgument` if the
+    /// window isn't aligned, doesn't fit on the display or doesn't match the buffer.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        if width == 0
+            || height == 0
+            || !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || x.saturating_add(width) > WIDTH
+            || y.saturating_add(height) > HEIGHT
+            || buffer.len() != buffer_len(width as usize, height as usize)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::PartialIn).await?;
+        let (x_end, y_end) = (x + width - 1, y + height - 1);
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                x as u8,
+                x_end as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // Gates scan both inside and outside of the partial window
+                0x01,
+            ],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.command(spi, Command::PartialOut).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        let color_value = self.config.background_color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, color_value, NUM_DISPLAY_BYTES)
+            .await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, color_value, NUM_DISPLAY_BYTES)
+            .await
+    }
+
+    /// Selects the waveform of the following refreshes
+    ///
+    /// Both waveforms come from the OTP. The controller picks them by temperature, the fast
+    /// mode forces a temperature that selects the short 4-frame waveform.
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.config.refresh = refresh_lut;
+        }
+        match self.config.refresh {
+            RefreshLut::Full => {
+                // Choose the LUT by the reading of the temperature sensor again
+                self.cmd_with_data(spi, Command::CascadeSetting, &[0x00])
+                    .await
+            }
+            RefreshLut::Quick => {
+                self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+                    .await?;
+                self.cmd_with_data(spi, Command::ForceTemperature, &[FAST_LUT_TEMPERATURE])
+                    .await
+            }
+        }
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd3in71<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+
+        let mut epd = Epd3in71 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> Epd3in71<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd3in71, Display3in71, "epd3in71");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 240);
+        assert_eq!(HEIGHT, 416);
+        assert_eq!(buffer_len(WIDTH as usize, HEIGHT as usize), 12480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn fast_lut_forces_temperature() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in71::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        assert!(contains(
+            &spi.take(),
+            &[Command::ResolutionSetting as u8, 0xF0, 0x01, 0xA0]
+        ));
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(
+            spi.take(),
+            [
+                Command::CascadeSetting as u8,
+                0x02,
+                Command::ForceTemperature as u8,
+                FAST_LUT_TEMPERATURE
+            ]
+        );
+
+        // the mode survives a wake up
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(contains(
+            &spi.take(),
+            &[Command::ForceTemperature as u8, FAST_LUT_TEMPERATURE]
+        ));
+    }
+
+    #[test]
+    fn partial_frame_window() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd3in71::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        let buffer = [0xAA; 2 * 300];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 224, 100, 16, 300)).unwrap();
+        let written = spi.take();
+        assert_eq!(
+            written[..9],
+            [
+                Command::PartialIn as u8,
+                Command::PartialWindow as u8,
+                224,
+                239,
+                0x00,
+                100,
+                0x01,
+                0x8F,
+                0x01
+            ]
+        );
+        assert_eq!(written.last(), Some(&(Command::PartialOut as u8)));
+
+        for (x, y, width, height) in [(4, 0, 16, 300), (232, 0, 16, 300), (0, 200, 16, 300)] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &buffer, x, y, width, height)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd3in71, "epd3in71");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd3in71,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            &[Command::CascadeSetting as u8, 0x02][..],
+            &[Command::ForceTemperature as u8, FAST_LUT_TEMPERATURE][..],
+            &[Command::DataStartTransmission1 as u8, 0x00, 0x00][..],
+        ]
+    );
+}
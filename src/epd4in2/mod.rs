@@ -49,12 +49,15 @@
 //!
 //! BE CAREFUL! The screen can get ghosting/burn-ins through the Partial Fast Update Drawing.
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -66,6 +69,17 @@ pub const WIDTH: u32 = 400;
 pub const HEIGHT: u32 = 300;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+
+/// Frames that look fine with the quick look-up table, see [`RefreshPolicy`](crate::graphics::RefreshPolicy)
+///
+/// The quick tables of this panel ghost more than others, so larger black areas get a full
+/// refresh.
+#[cfg(feature = "graphics")]
+pub const REFRESH_POLICY: crate::graphics::RefreshPolicy = crate::graphics::RefreshPolicy {
+    max_ink_coverage_pct: 20,
+    max_filled_run: 32,
+    ..crate::graphics::RefreshPolicy::DEFAULT
+};
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = true;
 
@@ -90,21 +104,49 @@ pub type Display4in2 = crate::graphics::Display<
 pub struct Epd4in2<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and sent as the old data by `update_frame`
+    pub background_color: Color,
+    /// Look-up table chosen with [`set_lut`](WaveshareDisplay::set_lut)
+    pub refresh: RefreshLut,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        }
+    }
+}
+
+#[cfg(feature = "compat-0-5")]
+impl<SPI, BUSY, DC, RST> Epd4in2<SPI, BUSY, DC, RST> {
+    /// The same driver for another type of SPI device, see [`crate::compat`]
+    pub(crate) fn rebind_spi<S>(self) -> Epd4in2<S, BUSY, DC, RST> {
+        Epd4in2 {
+            interface: self.interface.rebind_spi(),
+            config: self.config,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -114,11 +156,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd4in2<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -173,35 +215,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd4in2<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd4in2 {
-            interface,
-            color,
-            refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.interface
@@ -228,11 +249,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -243,9 +264,37 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        let color_value = self.color.get_byte_value();
+        let color_value = self.config.background_color.get_byte_value();
 
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -269,12 +318,26 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
         if buffer.len() as u32 != width / 8 * height {
             //TODO: panic!! or sth like that
             //return Err("Wrong buffersize");
         }
 
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.write_partial_data(spi, buffer).await?;
+        self.end_partial_update(spi).await
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
         self.command(spi, Command::PartialIn).await?;
         self.command(spi, Command::PartialWindow).await?;
         self.send_data(spi, &[(x >> 8) as u8]).await?;
@@ -300,11 +363,15 @@ where
         } else {
             self.command(spi, Command::DataStartTransmission2).await?;
         }
+        Ok(())
+    }
 
-        self.send_data(spi, buffer).await?;
+    async fn write_partial_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Self::Error> {
+        self.send_data(spi, data).await
+    }
 
-        self.command(spi, Command::PartialOut).await?;
-        Ok(())
+    async fn end_partial_update(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::PartialOut).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -323,11 +390,21 @@ where
         Ok(())
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.config.background_color.get_byte_value();
 
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -351,9 +428,9 @@ where
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
         if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+            self.config.refresh = refresh_lut;
         }
-        match self.refresh {
+        match self.config.refresh {
             RefreshLut::Full => {
                 self.set_lut_helper(spi, &LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
                     .await
@@ -375,19 +452,60 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd4in2 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd4in2<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -413,7 +531,10 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn send_resolution(&mut self, spi: &mut SPI    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let w = self.width();
         let h = self.height();
 
@@ -491,11 +612,11 @@ impl<SPI, BUSY, DC, RST> QuickRefresh<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// To be followed immediately after by `update_old_frame`.
@@ -527,7 +648,7 @@ where
 
     /// This is a wrapper around `display_frame` for using this device as a true
     /// `QuickRefresh` device.
-    async fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), Self::Error> {
+    async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.display_frame(spi).await
     }
 
@@ -614,7 +735,7 @@ where
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.config.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::PartialIn).await?;
         self.interface.cmd(spi, Command::PartialWindow).await?;
@@ -640,6 +761,9 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd4in2, Display4in2, "epd4in2");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -650,4 +774,70 @@ mod tests {
         assert_eq!(HEIGHT, 300);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn refresh_mode_follows_the_content() {
+        use crate::test_utils::*;
+        use crate::traits::WaveshareDisplayExt;
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd4in2::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let mut frame = blank_buffer(WIDTH, HEIGHT);
+        frame.fill(Color::White.get_byte_value());
+        // a line of text, 10 pixel wide strokes on a white background
+        for byte in frame[40 * 50..40 * 60].iter_mut().step_by(3) {
+            *byte = 0x00;
+        }
+        spi.take();
+
+        let lut =
+            block_on(epd.update_and_display_frame_with_policy(&mut spi, &frame, &REFRESH_POLICY));
+        assert_eq!(lut.unwrap(), RefreshLut::Quick);
+        assert!(contains(&spi.take(), &LUT_VCOM0_QUICK));
+
+        // 60 percent black
+        frame[..(buffer_len(WIDTH as usize, HEIGHT as usize) * 6 / 10)].fill(0x00);
+        let lut =
+            block_on(epd.update_and_display_frame_with_policy(&mut spi, &frame, &REFRESH_POLICY));
+        assert_eq!(lut.unwrap(), RefreshLut::Full);
+        assert!(contains(&spi.take(), &LUT_VCOM0));
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd4in2, "epd4in2");
+
+    #[test]
+    fn streamed_partial_frame_matches_a_single_write() {
+        use crate::test_utils::*;
+
+        let buffer: [u8; buffer_len(32, 4)] = core::array::from_fn(|i| i as u8);
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd4in2::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        spi.take();
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 8, 2, 32, 4)).unwrap();
+        let expected = spi.take();
+
+        block_on(epd.set_partial_window(&mut spi, 8, 2, 32, 4)).unwrap();
+        for part in buffer.chunks(5) {
+            block_on(epd.write_partial_data(&mut spi, part)).unwrap();
+        }
+        block_on(epd.end_partial_update(&mut spi)).unwrap();
+        assert_eq!(spi.take(), expected);
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd4in2,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        },
+        [
+            [&[Command::LutForVcom as u8][..], &LUT_VCOM0_QUICK[..]].concat(),
+            [Command::DataStartTransmission1 as u8, 0x00, 0x00].to_vec(),
+        ]
+    );
 }
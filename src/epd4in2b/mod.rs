@@ -0,0 +1,600 @@
+//! A simple Driver for the Waveshare 4.2" (B) E-Ink Display via SPI
+//!
+//! The black/white/red panel uses the same IL0398 controller as the
+//! [black/white one](crate::epd4in2), in red mode: DataStartTransmission1 takes the
+//! black/white data and DataStartTransmission2 the red data, with `0` for a red pixel.
+//!
+//! Build with the help of documentation/code from [Waveshare](https://www.waveshare.com/wiki/4.2inch_e-Paper_Module_(B)).
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::eh1::*;
+//!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd4in2b::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!
+//!// Setup EPD
+//!let mut epd = Epd4in2b::new(&mut spi, busy_in, dc, rst, None)?;
+//!
+//!// One display for the black/white pixels
+//!let mut mono_display = Display4in2b::default();
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut mono_display);
+//!
+//!// And a second one for the red pixels, `Black` is shown as red
+//!let mut chromatic_display = Display4in2b::default();
+//!let _ = Line::new(Point::new(15, 120), Point::new(15, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut chromatic_display);
+//!
+//!// Display updated frame
+//!epd.update_color_frame(&mut spi, &mono_display.buffer(), &chromatic_display.buffer())?;
+//!epd.display_frame(&mut spi)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi)?;
+//!# Ok(())
+//!# }
+//!```
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ChromaticPasses, ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+
+/// Width of epd4in2b in pixels
+pub const WIDTH: u32 = 400;
+/// Height of epd4in2b in pixels
+pub const HEIGHT: u32 = 300;
+/// Default Background Color (white)
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Red mode, LUT from OTP
+const PANEL_SETTING: u8 = 0x0F;
+/// White border, default VCOM and data interval
+const VCOM_DATA_INTERVAL: u8 = 0x77;
+/// Floating border, sent before going to sleep
+const VCOM_DATA_INTERVAL_SLEEP: u8 = 0xF7;
+
+use crate::color::Color;
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::buffer_len;
+
+/// Full size buffer for use with the 4in2b EPD
+///
+/// One buffer holds the black/white layer, a second one the red layer.
+#[cfg(feature = "graphics")]
+pub type Display4in2b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Epd4in2b driver
+pub struct Epd4in2b<SPI, BUSY, DC, RST> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    config: UserConfig,
+    chromatic_passes: ChromaticPasses,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and to clear the chromatic layer in
+    /// `update_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+}
+
+impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // Based on the reference code from:
+        // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_4in2b_V1.c
+        self.interface.reset(spi, 10_000, 2_000).await?;
+
+        // start the booster
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])
+            .await?;
+
+        // power on
+        self.command(spi, Command::PowerOn).await?;
+        self.interface.delay(spi, 5000).await?;
+        self.wait_until_idle(spi).await?;
+
+        self.cmd_with_data(spi, Command::PanelSetting, &[PANEL_SETTING])
+            .await?;
+
+        self.send_resolution(spi).await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[VCOM_DATA_INTERVAL],
+        )
+        .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
+    for Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, black).await?;
+        self.update_chromatic_frame(spi, chromatic).await
+    }
+
+    /// Update only the black/white data of the display.
+    ///
+    /// Finish by calling `update_chromatic_frame`.
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, black)
+            .await
+    }
+
+    /// Update only the red data of the display.
+    ///
+    /// This data takes precedence over the black/white data.
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)
+            .await
+    }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    type DisplayColor = Color;
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[VCOM_DATA_INTERVAL_SLEEP],
+        )
+        .await?;
+
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.config.background_color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.config.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, buffer).await?;
+
+        // Clear the red layer
+        let color = self.config.background_color.get_byte_value();
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    /// Writes the black/white data of the window and clears its red data, `x` is aligned
+    /// down to a multiple of 8.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        let x = x & !0x07;
+        let row_len = buffer_len(width as usize, 1) as u32;
+        if width == 0
+            || height == 0
+            || buffer.len() != buffer_len(width as usize, height as usize)
+            || x.checked_add(row_len * 8).is_none_or(|end| end > WIDTH)
+            || y.checked_add(height).is_none_or(|end| end > HEIGHT)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        let (x_end, y_end) = (x + row_len * 8 - 1, y + height - 1);
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialIn).await?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x >> 8) as u8,
+                x as u8,
+                (x_end >> 8) as u8,
+                x_end as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // gates scan both inside and outside of the partial window
+                0x01,
+            ],
+        )
+        .await?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(
+                spi,
+                self.config.background_color.get_byte_value(),
+                row_len * height,
+            )
+            .await?;
+
+        self.command(spi, Command::PartialOut).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        let color = self.config.background_color.get_byte_value();
+
+        // Clear the black
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await?;
+
+        // Clear the red
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd4in2b {
+            interface,
+            config: UserConfig::default(),
+            chromatic_passes: ChromaticPasses::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> Epd4in2b<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.data(spi, data).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.command(spi, Command::ResolutionSetting).await?;
+        self.send_data(spi, &[(w >> 8) as u8]).await?;
+        self.send_data(spi, &[w as u8]).await?;
+        self.send_data(spi, &[(h >> 8) as u8]).await?;
+        self.send_data(spi, &[h as u8]).await
+    }
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd4in2b, Display4in2b, "epd4in2b", planes);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 400);
+        assert_eq!(HEIGHT, 300);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn partial_frame_writes_the_window() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd4in2b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // 16x2 at x 13, aligned down to 8
+        let buffer = [0x0F, 0xF0, 0x00, 0xFF];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 13, 290, 16, 2)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [
+                Command::PartialIn as u8,
+                Command::PartialWindow as u8,
+                0x00,
+                8,
+                0x00,
+                23,
+                0x01,
+                34,
+                0x01,
+                35,
+                0x01,
+                Command::DataStartTransmission1 as u8,
+                0x0F,
+                0xF0,
+                0x00,
+                0xFF,
+                Command::DataStartTransmission2 as u8,
+                0xFF,
+                0xFF,
+                0xFF,
+                0xFF,
+                Command::PartialOut as u8,
+            ]
+        );
+
+        for (len, x, y, width) in [
+            (3, 0, 0, 16),
+            (4, 392, 0, 16),
+            (4, 0, 299, 16),
+            (0, 0, 0, 0),
+        ] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &buffer[..len], x, y, width, 2)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd4in2b, "epd4in2b");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd4in2b,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::DataStartTransmission1 as u8, 0x00, 0x00][..]]
+    );
+}
@@ -6,13 +6,15 @@
 //! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_5in65f.c)
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd5in65f.py)
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::color::OctColor;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -42,19 +44,35 @@ const SINGLE_BYTE_WRITE: bool = true;
 pub struct Epd5in65f<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: OctColor,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame` and for the border
+    pub background_color: OctColor,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd5in65f<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -64,11 +82,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd5in65f<S
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -105,31 +123,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd5in65f<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = OctColor;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd5in65f { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.init(spi).await
     }
@@ -148,6 +149,7 @@ where
         Ok(())
     }
 
+    /// Partial updates aren't supported, returns [`ErrorKind::InvalidArgument`]
     async fn update_partial_frame(
         &mut self,
         _spi: &mut SPI,
@@ -157,7 +159,7 @@ where
         _width: u32,
         _height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        Err(ErrorKind::InvalidArgument)
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -181,7 +183,7 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let bg = OctColor::colors_byte(self.color, self.color);
+        let bg = OctColor::colors_byte(self.config.background_color, self.config.background_color);
         self.wait_until_idle(spi).await?;
         self.update_vcom(spi).await?;
         self.send_resolution(spi).await?;
@@ -193,11 +195,11 @@ where
     }
 
     fn set_background_color(&mut self, color: OctColor) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &OctColor {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -208,6 +210,30 @@ where
         HEIGHT
     }
 
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -219,19 +245,60 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, true).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(true)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd5in65f<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd5in65f {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd5in65f<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -257,7 +324,10 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn wait_busy_low(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+    async fn wait_busy_low(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface.wait_until_idle(spi, false).await
     }
 
@@ -279,13 +349,16 @@ where
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let bg_color = (self.color.get_nibble() & 0b111) << 5;
+        let bg_color = (self.config.background_color.get_nibble() & 0b111) << 5;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])
             .await?;
         Ok(())
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd5in65f, Display5in65f, "epd5in65f");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,4 +369,46 @@ mod tests {
         assert_eq!(HEIGHT, 448);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
     }
+
+    #[test]
+    fn stripes_need_partial_updates() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd5in65f::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+
+        let stripe = [0x11; WIDTH as usize / 2 * 8];
+        assert!(matches!(
+            block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd5in65f,
+        |epd, spi| {
+            epd.set_background_color(OctColor::Red);
+        },
+        [
+            &[
+                Command::VcomAndDataIntervalSetting as u8,
+                0x17 | (OctColor::Red.get_nibble() & 0b111) << 5,
+            ][..],
+            &[
+                Command::DataStartTransmission1 as u8,
+                OctColor::colors_byte(OctColor::Red, OctColor::Red),
+                OctColor::colors_byte(OctColor::Red, OctColor::Red),
+            ][..],
+        ]
+    );
 }
@@ -8,15 +8,15 @@
 
 use core::fmt::{Debug, Display};
 
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::{
     buffer_len,
     color::OctColor,
-    interface::DisplayInterface,
-    prelude::ErrorKind,
-    traits::{ErrorType, InternalWiAdditions, WaveshareDisplay},
+    interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing},
+    prelude::{ErrorKind, InitError},
+    traits::{ErrorType, InternalWiAdditions, WaveshareConstructor, WaveshareDisplay},
 };
 
 use self::command::Command;
@@ -46,19 +46,35 @@ const SINGLE_BYTE_WRITE: bool = true;
 pub struct Epd7in3f<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: OctColor,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: OctColor,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in3f<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -68,11 +84,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in3f<SP
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -115,35 +131,15 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in3f<SPI,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = OctColor;
 
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd7in3f { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
     }
@@ -153,11 +149,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Self::DisplayColor {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -168,22 +164,47 @@ where
         HEIGHT
     }
 
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
             .await
     }
 
+    /// Partial updates aren't supported, returns [`ErrorKind::InvalidArgument`]
     async fn update_partial_frame(
         &mut self,
         _spi: &mut SPI,
-        __buffer: &[u8],
+        _buffer: &[u8],
         _x: u32,
         _y: u32,
         _width: u32,
         _height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!()
+        Err(ErrorKind::InvalidArgument)
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -207,7 +228,7 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let bg = OctColor::colors_byte(self.color, self.color);
+        let bg = OctColor::colors_byte(self.config.background_color, self.config.background_color);
 
         self.wait_busy_low(spi).await?;
         self.command(spi, Command::DataStartTransmission).await?;
@@ -229,19 +250,63 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_busy_low(spi).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(true)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd7in3f<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>>
+    where
+        Self: Sized,
+    {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd7in3f {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd7in3f<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -259,7 +324,10 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn wait_busy_low(&mut self) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+    async fn wait_busy_low(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface.wait_until_idle(spi, true).await
     }
 
@@ -303,3 +371,24 @@ where
         self.display_frame(spi).await
     }
 }
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd7in3f, Display7in3f, "epd7in3f");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd7in3f,
+        |epd, spi| {
+            epd.set_background_color(OctColor::Red);
+        },
+        [&[
+            Command::DataStartTransmission as u8,
+            OctColor::colors_byte(OctColor::Red, OctColor::Red),
+            OctColor::colors_byte(OctColor::Red, OctColor::Red),
+        ][..]]
+    );
+}
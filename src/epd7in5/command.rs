@@ -128,6 +128,13 @@ pub(crate) enum Command {
     /// This command sets `VCOM_DC` value.
     VcmDcSetting = 0x82,
 
+    /// This command sets the partial window, the horizontal start and end are multiples of 8.
+    PartialWindow = 0x90,
+    /// This command makes the display enter partial mode.
+    PartialIn = 0x91,
+    /// This command makes the display exit partial mode and enter normal mode.
+    PartialOut = 0x92,
+
     /// This is in all the Waveshare controllers for Epd7in5, but it's not documented
     /// anywhere in the datasheet `¯\_(ツ)_/¯`
     FlashMode = 0xE5,
@@ -5,14 +5,23 @@
 //! - [Datasheet](https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT)
 //! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/702def06bcb75983c98b0f9d25d43c552c248eb0/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_7in5.c)
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/702def06bcb75983c98b0f9d25d43c552c248eb0/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5.py)
+//!
+//! # 16 bit SPI words
+//!
+//! The controller has no command that could be used as padding, so this driver can't be used with
+//! a [`WordSpi`](crate::WordSpi), [`WordSpiConstructor::try_new_word`] refuses to create it.
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing, WordSpi};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+    WordSpiConstructor,
+};
+use crate::wire::{RowOrder, WireBytes, WireFrame, WireOrder, WireTransform};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -34,27 +43,94 @@ pub const WIDTH: u32 = 640;
 pub const HEIGHT: u32 = 384;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Length of a frame encoded with 4 bits per pixel, see [`Epd7in5::update_frame_encoded`]
+pub const ENCODED_LEN: usize = WIDTH as usize * HEIGHT as usize / 2;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = false;
 
+#[cfg(feature = "graphics")]
+impl Display7in5 {
+    /// Encodes the frame with the 4 bits per pixel expected by the controller
+    ///
+    /// The result can be sent with [`Epd7in5::update_frame_encoded`].
+    pub fn to_encoded_buffer(&self) -> [u8; ENCODED_LEN] {
+        let mut encoded = [0; ENCODED_LEN];
+        crate::frame_encoder::encode_1bpp_to_4bpp(self.buffer(), &mut encoded);
+        encoded
+    }
+}
+
+/// Pixel encoding on the wire, the controller expects 4 bits per pixel
+pub mod consts {
+    use crate::color::Color;
+
+    /// 4 bit value of a black pixel
+    pub const BLACK_NIBBLE: u8 = 0x0;
+    /// 4 bit value of a white pixel
+    pub const WHITE_NIBBLE: u8 = 0x3;
+
+    /// 4 bit value of a pixel of the given color
+    pub const fn nibble(color: Color) -> u8 {
+        match color {
+            Color::Black => BLACK_NIBBLE,
+            Color::White => WHITE_NIBBLE,
+        }
+    }
+
+    /// Byte holding two pixels of the given color
+    pub const fn fill_byte(color: Color) -> u8 {
+        nibble(color) << 4 | nibble(color)
+    }
+
+    /// Byte used to clear the display, same as the Waveshare reference driver
+    pub const CLEAR_BYTE: u8 = fill_byte(Color::White);
+
+    const _: () = assert!(CLEAR_BYTE == 0x33);
+    const _: () = assert!(fill_byte(Color::Black) == 0x00);
+}
+use self::consts::*;
+
 /// Epd7in5 driver
 ///
 pub struct Epd7in5<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: Color,
+    config: UserConfig,
+    /// Next row expected by an open stripe transmission
+    stripe_row: Option<u32>,
+    /// A partial window is open, the next refresh only changes it
+    partial: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+    /// Order the frames are sent in, see [`Epd7in5::set_wire_order`]
+    pub wire_order: WireOrder,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            wire_order: WireOrder::default(),
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -64,16 +140,18 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
         self.interface.reset(spi, 10_000, 10_000).await?;
+        self.partial = false;
+        self.stripe_row = None;
 
         // Set the power settings
         self.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00])
@@ -129,33 +207,18 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd7in5 { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await?;
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
@@ -167,11 +230,11 @@ where
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -182,38 +245,115 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.stripe_row = None;
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await?;
         self.command(spi, Command::DataStartTransmission1).await?;
-        for byte in buffer {
-            let mut temp = *byte;
-            for _ in 0..4 {
-                let mut data = if temp & 0x80 == 0 { 0x00 } else { 0x03 };
-                data <<= 4;
-                temp <<= 1;
-                data |= if temp & 0x80 == 0 { 0x00 } else { 0x03 };
-                temp <<= 1;
-                self.send_data(spi, &[data]).await?;
-            }
-        }
-        Ok(())
+        self.send_expanded(spi, buffer, WIDTH as usize / 8).await
     }
 
+    /// Sends `buffer` to a window, the next [`display_frame`](WaveshareDisplay::display_frame)
+    /// only refreshes that window
+    ///
+    /// `x` is rounded down to a multiple of 8, `buffer` holds `height` rows of `width` pixels
+    /// each starting on a new byte. The window is remapped with the
+    /// [wire order](Self::set_wire_order). Returns `ErrorKind::InvalidArgument` if the buffer
+    /// doesn't match the window or the window doesn't fit the panel.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        let row_len = buffer_len(width as usize, 1);
+        if buffer.len() != buffer_len(width as usize, height as usize) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.open_partial_window(spi, x & !0x07, y, row_len as u32 * 8, height)
+            .await?;
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.send_expanded(spi, buffer, row_len).await
+    }
+
+    /// Keeps the frame transmission open between stripes, so no other command
+    /// may be sent until [`end_stripe`](WaveshareDisplay::end_stripe) is called.
+    async fn update_frame_stripe(
+        &mut self,
+        spi: &mut SPI,
+        stripe: &[u8],
+        y_start: u32,
+        stripe_height: u32,
+    ) -> Result<(), Self::Error> {
+        if stripe.len() != buffer_len(WIDTH as usize, stripe_height as usize)
+            || y_start.saturating_add(stripe_height) > HEIGHT
+            // the last rows would have to come first
+            || self.config.wire_order.rows == RowOrder::BottomUp
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        if y_start == 0 {
+            self.wait_until_idle(spi).await?;
+            self.end_partial(spi).await?;
+            self.command(spi, Command::DataStartTransmission1).await?;
+        } else if self.stripe_row != Some(y_start) {
+            // stripes have to be contiguous, the controller can't skip rows
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.send_expanded(spi, stripe, WIDTH as usize / 8).await?;
+        self.stripe_row = Some(y_start + stripe_height);
+        Ok(())
+    }
+
+    async fn end_stripe(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.stripe_row.take().is_some() {
+            self.command(spi, Command::DataStop).await?;
+        }
+        Ok(())
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.command(spi, Command::DisplayRefresh).await
+        self.command(spi, Command::DisplayRefresh).await?;
+        if self.partial {
+            self.wait_until_idle(spi).await?;
+            self.end_partial(spi).await?;
+        }
+        Ok(())
     }
 
     async fn update_and_display_frame(
@@ -225,14 +365,30 @@ where
         self.command(spi, Command::DisplayRefresh).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await?;
         self.send_resolution(spi).await?;
 
-        // The Waveshare controllers all implement clear using 0x33
+        // The Waveshare controllers all implement clear using white, the default background
         self.command(spi, Command::DataStartTransmission1).await?;
         self.interface
-            .data_x_times(spi, 0x33, WIDTH / 8 * HEIGHT * 4)
+            .data_x_times(
+                spi,
+                fill_byte(self.config.background_color),
+                WIDTH / 8 * HEIGHT * 4,
+            )
             .await
     }
 
@@ -247,19 +403,227 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd7in5 {
+            interface,
+            config: UserConfig::default(),
+            stripe_row: None,
+            partial: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WordSpiConstructor<SPI, BUSY, DC, RST>
+    for Epd7in5<WordSpi<SPI>, BUSY, DC, RST>
+where
+    SPI: SpiDevice<u16>,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    const WORD_PADDING: Option<u8> = None;
 }
 
 impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
+    /// Writes a frame prepared on the host, e.g. with the `epd-wire` tool, to the panel
+    ///
+    /// The data already is in the 4 bits per pixel format of the controller. Returns
+    /// `ErrorKind::InvalidArgument` if the frame wasn't made for this panel.
+    pub async fn update_frame_wire(
+        &mut self,
+        spi: &mut SPI,
+        frame: WireFrame<'_>,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let frame = frame
+            .check(&crate::wire::EPD7IN5)
+            .map_err(|_| ErrorKind::InvalidArgument)?;
+        self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await?;
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.send_data(spi, frame.data()).await
+    }
+
+    /// Sends the rows and bytes of every frame in `order`, for panels mounted the other way round
+    ///
+    /// The order is applied before expanding the pixels, see the [`wire`](crate::wire) module.
+    /// Stripes can only be sent top down, [`update_frame_stripe`](WaveshareDisplay::update_frame_stripe)
+    /// returns `ErrorKind::InvalidArgument` for [`RowOrder::BottomUp`]. Prepared frames, wire or
+    /// encoded, are sent as they are.
+    pub fn set_wire_order(&mut self, order: WireOrder) {
+        self.config.wire_order = order;
+    }
+
+    /// Transmits a frame that is already encoded with 4 bits per pixel
+    ///
+    /// See [`frame_encoder`](crate::frame_encoder), ENCODED needs to be of size: [`ENCODED_LEN`] !
+    pub async fn update_frame_encoded(
+        &mut self,
+        spi: &mut SPI,
+        encoded: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if encoded.len() != ENCODED_LEN {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await?;
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.send_data(spi, encoded).await
+    }
+
+    /// Fills a window with `color`, without sending the rest of the frame
+    ///
+    /// `x` and `width` are rounded down to multiples of 8 as the controller can only address
+    /// whole bytes of the 1 bit per pixel buffer. The window is remapped with the
+    /// [wire order](Self::set_wire_order). Returns `ErrorKind::InvalidArgument` if the window is
+    /// empty or doesn't fit the panel. Call [`display_frame`](WaveshareDisplay::display_frame)
+    /// afterwards to show it.
+    pub async fn clear_frame_partial(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (x, width) = (x & !0x07, width & !0x07);
+        self.open_partial_window(spi, x, y, width, height).await?;
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, fill_byte(color), width / 8 * height * 4)
+            .await?;
+        self.end_partial(spi).await
+    }
+
+    /// Refreshes a window without sending new data, e.g. after
+    /// [`clear_frame_partial`](Self::clear_frame_partial)
+    ///
+    /// The window is widened to whole bytes and remapped with the
+    /// [wire order](Self::set_wire_order). Returns `ErrorKind::InvalidArgument` if the window is
+    /// empty or doesn't fit the panel.
+    pub async fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let x_end = x.saturating_add(width).saturating_add(0x07) & !0x07;
+        let x = x & !0x07;
+        self.open_partial_window(spi, x, y, x_end.saturating_sub(x), height)
+            .await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await?;
+        self.end_partial(spi).await
+    }
+
+    /// Enters the partial mode with a byte aligned window, remapped with the wire order
+    async fn open_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if width == 0
+            || height == 0
+            || x.checked_add(width).is_none_or(|end| end > WIDTH)
+            || y.checked_add(height).is_none_or(|end| end > HEIGHT)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        let (x, y) = self
+            .config
+            .wire_order
+            .remap_window(WIDTH, HEIGHT, x, y, width, height);
+        let (x_end, y_end) = (x + width - 1, y + height - 1);
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialIn).await?;
+        self.partial = true;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x >> 8) as u8,
+                x as u8,
+                (x_end >> 8) as u8,
+                x_end as u8 | 0x07,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // gates scan both inside and outside of the partial window
+                0x01,
+            ],
+        )
+        .await
+    }
+
+    /// Leaves the partial mode if a window is open
+    async fn end_partial(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.partial {
+            self.command(spi, Command::PartialOut).await?;
+            self.partial = false;
+        }
+        Ok(())
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -276,6 +640,24 @@ where
         self.interface.data(spi, data).await
     }
 
+    /// Sends a 1 bit per pixel buffer as the 4 bits per pixel expected by the controller
+    async fn send_expanded(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        row_len: usize,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        for data in WireBytes::ordered(
+            WireTransform::Nibbles,
+            buffer,
+            row_len,
+            self.config.wire_order,
+        ) {
+            self.send_data(spi, &[data]).await?;
+        }
+        Ok(())
+    }
+
     async fn cmd_with_data(
         &mut self,
         spi: &mut SPI,
@@ -285,7 +667,10 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let w = self.width();
         let h = self.height();
 
@@ -297,6 +682,9 @@ where
     }
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd7in5, Display7in5, "epd7in5");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -307,4 +695,266 @@ mod tests {
         assert_eq!(HEIGHT, 384);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn encoded_frame_matches_update_frame() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| i as u8);
+        let mut encoded = [0; ENCODED_LEN];
+        crate::frame_encoder::encode_1bpp_to_4bpp(&frame, &mut encoded);
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let expected = spi.take();
+        block_on(epd.update_frame_encoded(&mut spi, &encoded)).unwrap();
+        assert_eq!(spi.take(), expected);
+
+        assert!(matches!(
+            block_on(epd.update_frame_encoded(&mut spi, &frame)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_to_encoded_buffer() {
+        use embedded_graphics::{
+            prelude::*,
+            primitives::{PrimitiveStyle, Rectangle},
+        };
+
+        let mut display = Display7in5::default();
+        display.clear(Color::White).unwrap();
+        Rectangle::new(Point::new(0, 0), Size::new(2, 1))
+            .into_styled(PrimitiveStyle::with_fill(Color::Black))
+            .draw(&mut display)
+            .unwrap();
+
+        let encoded = display.to_encoded_buffer();
+        assert_eq!(encoded[0], fill_byte(Color::Black));
+        assert!(encoded[1..].iter().all(|&byte| byte == CLEAR_BYTE));
+    }
+
+    #[test]
+    fn wire_order_applies_before_the_expansion() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+        use crate::wire::{ByteOrderInRow, EPD7IN5};
+
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| (i * 7) as u8);
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        for rows in [RowOrder::TopDown, RowOrder::BottomUp] {
+            for bytes in [ByteOrderInRow::Forward, ByteOrderInRow::Reverse] {
+                let order = WireOrder { rows, bytes };
+                epd.set_wire_order(order);
+                spi.take();
+                block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+                let written = spi.take();
+                assert_eq!(written[0], Command::DataStartTransmission1 as u8);
+                assert!(EPD7IN5
+                    .wire_bytes_ordered(&frame, order)
+                    .eq(written[1..].iter().copied()));
+            }
+        }
+
+        let stripe = [0; buffer_len(WIDTH as usize, 8)];
+        assert!(matches!(
+            block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn partial_frame_refreshes_the_window_only() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // 64x64 at x 13, aligned down to 8
+        let buffer = [0xF0; 64 / 8 * 64];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 13, 100, 64, 64)).unwrap();
+        let written = spi.take();
+        assert_eq!(
+            written[..12],
+            [
+                Command::PartialIn as u8,
+                Command::PartialWindow as u8,
+                0x00,
+                8,
+                0x00,
+                71,
+                0x00,
+                100,
+                0x00,
+                163,
+                0x01,
+                Command::DataStartTransmission1 as u8,
+            ]
+        );
+        // 4 bits per pixel, 4 white pixels and 4 black ones per buffer byte
+        assert_eq!(written.len(), 12 + 64 * 64 / 2);
+        assert!(written[12..]
+            .chunks(4)
+            .all(|pixels| pixels == [0x33, 0x33, 0x00, 0x00]));
+
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [Command::DisplayRefresh as u8, Command::PartialOut as u8]
+        );
+
+        for (len, x, width) in [(511, 0, 64), (512, 600, 64), (8, 0, 0)] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &buffer[..len], x, 0, width, 64)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+    }
+
+    #[test]
+    fn display_partial_frame_widens_the_window() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // a 200x100 status area from x 4 to 203
+        block_on(epd.display_partial_frame(&mut spi, 4, 284, 200, 100)).unwrap();
+        assert_eq!(
+            spi.take(),
+            [
+                Command::PartialIn as u8,
+                Command::PartialWindow as u8,
+                0x00,
+                0,
+                0x00,
+                207,
+                0x01,
+                28,
+                0x01,
+                127,
+                0x01,
+                Command::DisplayRefresh as u8,
+                Command::PartialOut as u8,
+            ]
+        );
+
+        for (x, y, width, height) in [(0, 285, 200, 100), (440, 0, 201, 1), (0, 0, 0, 1)] {
+            assert!(matches!(
+                block_on(epd.display_partial_frame(&mut spi, x, y, width, height)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn clear_frame_partial_sends_the_window_only() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+        use crate::wire::ByteOrderInRow;
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.clear_frame_partial(&mut spi, 13, 10, 20, 3, Color::Black)).unwrap();
+        let written = spi.take();
+        assert_eq!(
+            written[..12],
+            [
+                Command::PartialIn as u8,
+                Command::PartialWindow as u8,
+                0x00,
+                8,
+                0x00,
+                23,
+                0x00,
+                10,
+                0x00,
+                12,
+                0x01,
+                Command::DataStartTransmission1 as u8,
+            ]
+        );
+        // 16 pixels in 3 rows with 2 pixels per byte
+        assert_eq!(written.len(), 12 + 24 + 1);
+        assert!(written[12..36].iter().all(|&byte| byte == 0x00));
+        assert_eq!(written[36], Command::PartialOut as u8);
+
+        epd.set_wire_order(WireOrder {
+            rows: RowOrder::BottomUp,
+            bytes: ByteOrderInRow::Reverse,
+        });
+        block_on(epd.clear_frame_partial(&mut spi, 0, 0, 8, 1, Color::White)).unwrap();
+        let written = spi.take();
+        assert_eq!(
+            written[2..10],
+            [0x02, 0x78, 0x02, 0x7F, 0x01, 0x7F, 0x01, 0x7F]
+        );
+        assert!(written[12..16].iter().all(|&byte| byte == CLEAR_BYTE));
+
+        for (x, y, width, height) in [(0, 0, 7, 1), (0, 0, 8, 0), (640, 0, 8, 1), (0, 380, 8, 5)] {
+            assert!(matches!(
+                block_on(epd.clear_frame_partial(&mut spi, x, y, width, height, Color::White)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+    }
+
+    #[test]
+    fn word_spi_is_rejected() {
+        use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingWordSpi};
+
+        let mut spi = WordSpi::new(RecordingWordSpi::default(), 0x00);
+        let result = block_on(Epd7in5::try_new_word(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ));
+        assert!(matches!(
+            result,
+            Err(InitError {
+                error: ErrorKind::InvalidArgument,
+                ..
+            })
+        ));
+        assert!(spi.release().take_bytes().is_empty());
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd7in5,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+            epd.set_wire_order(WireOrder {
+                bytes: crate::wire::ByteOrderInRow::Reverse,
+                ..WireOrder::default()
+            });
+        },
+        [
+            [
+                &[Command::DataStartTransmission1 as u8][..],
+                &[nibble(Color::White) << 4 | nibble(Color::Black); 4][..],
+            ]
+            .concat(),
+            [Command::DataStartTransmission1 as u8, 0x00, 0x00].to_vec(),
+        ]
+    );
 }
@@ -9,14 +9,22 @@
 //! - [Datasheet](https://www.waveshare.com/w/upload/2/27/7inch_HD_e-Paper_Specification.pdf)
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in5_HD.py)
 //!
+//! # 16 bit SPI words
+//!
+//! The driver can be used with a 16 bit SPI device by wrapping it in a [`WordSpi`](crate::WordSpi)
+//! padding with [`consts::WORD_PADDING`]. [`WordSpiConstructor::try_new_word`] checks the padding.
+//!
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing, WordSpi};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareConstructor, WaveshareDisplay,
+    WordSpiConstructor,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -41,24 +49,73 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White; // Inverted for HD as
 const IS_BUSY_LOW: bool = false;
 const SINGLE_BYTE_WRITE: bool = false;
 
+/// Data of the auto write commands
+pub mod consts {
+    use crate::color::Color;
+
+    /// Regular pattern with a single step covering the whole RAM, bit 7 holds the pixel value
+    pub const fn auto_write_pattern(color: Color) -> u8 {
+        0x77 | (color.get_byte_value() & 0x80)
+    }
+
+    // The Waveshare reference driver fills the RAM with 0xF7 on init
+    const _: () = assert!(auto_write_pattern(Color::White) == 0xF7);
+    const _: () = assert!(auto_write_pattern(Color::Black) == 0x77);
+
+    /// `DisplayUpdateControl2` sequence of a full refresh: load the temperature and the look-up
+    /// table, display mode 1
+    pub const FULL_UPDATE: u8 = 0xF7;
+    /// `DisplayUpdateControl2` sequence of a partial refresh: like [`FULL_UPDATE`] with display
+    /// mode 2, only the pixels differing from the red RAM are driven
+    pub const PARTIAL_UPDATE: u8 = 0xFF;
+    /// `DisplayUpdateControl2` sequence loading the temperature and the look-up table without a
+    /// refresh
+    pub const LOAD_TEMPERATURE: u8 = 0xB1;
+
+    /// Padding for a [`WordSpi`](crate::WordSpi), the `Nop` command of the controller
+    ///
+    /// Sent as a command it does nothing, commands with an odd number of parameters receive it as
+    /// an excess parameter. Full frames have an even length and are never padded.
+    pub const WORD_PADDING: u8 = 0x7F;
+
+    const _: () = assert!(WORD_PADDING == super::command::Command::Nop as u8);
+}
+use self::consts::*;
+
 /// EPD7in5 (HD) driver
 ///
 pub struct Epd7in5<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: Color,
+    config: UserConfig,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -68,11 +125,11 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -88,11 +145,19 @@ where
         self.command(spi, Command::SwReset).await?;
         self.wait_until_idle(spi).await?;
 
-        self.cmd_with_data(spi, Command::AutoWriteRed, &[0xF7])
-            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::AutoWriteRed,
+            &[auto_write_pattern(Color::White)],
+        )
+        .await?;
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::AutoWriteBw, &[0xF7])
-            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::AutoWriteBw,
+            &[auto_write_pattern(Color::White)],
+        )
+        .await?;
         self.wait_until_idle(spi).await?;
 
         self.cmd_with_data(spi, Command::SoftStart, &[0xAE, 0xC7, 0xC3, 0xC0, 0x40])
@@ -111,14 +176,7 @@ where
         self.cmd_with_data(spi, Command::VbdControl, &[0x05])
             .await?;
 
-        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])
-            .await?;
-
-        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])
-            .await?;
-
-        self.command(spi, Command::MasterActivation).await?;
-        self.wait_until_idle(spi).await?;
+        self.load_temperature(spi).await?;
 
         self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])
             .await?;
@@ -131,31 +189,14 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd7in5 { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.init(spi).await
     }
@@ -170,20 +211,54 @@ where
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
             .await?;
         self.cmd_with_data(spi, Command::WriteRamBw, buffer).await?;
-        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[FULL_UPDATE])
             .await
     }
 
+    /// Writes a window of the black/white RAM and selects a partial refresh
+    ///
+    /// `x` is rounded down to a multiple of 8, `buffer` holds `height` rows of `width` pixels
+    /// each starting on a new byte. The next `display_frame` only drives the pixels that differ
+    /// from the red RAM, so write the current image into both RAMs first, e.g. with
+    /// [`update_frame_dual_plane`](Epd7in5::update_frame_dual_plane). Returns
+    /// `ErrorKind::InvalidArgument` if the buffer doesn't match the window or the window doesn't
+    /// fit the panel.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        let x = x & !0x07;
+        let row_len = buffer_len(width as usize, 1);
+        let width = row_len as u32 * 8;
+        if row_len == 0
+            || height == 0
+            || buffer.len() != row_len * height as usize
+            || x.checked_add(width).is_none_or(|end| end > WIDTH)
+            || y.checked_add(height).is_none_or(|end| end > HEIGHT)
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.wait_until_idle(spi).await?;
+        let (mut y, mut rows) = (y, buffer);
+        if y == 0 {
+            // the first row is at RAM address 0, the following ones count down from the last one
+            self.write_window(spi, &rows[..row_len], x, width, 0, 1)
+                .await?;
+            (y, rows) = (1, &rows[row_len..]);
+        }
+        if !rows.is_empty() {
+            let height = (rows.len() / row_len) as u32;
+            self.write_window(spi, rows, x, width, y, height).await?;
+        }
+        self.restore_full_window(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[PARTIAL_UPDATE])
+            .await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -200,9 +275,20 @@ where
         self.display_frame(spi).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         let pixel_count = WIDTH / 8 * HEIGHT;
-        let background_color_byte = self.color.get_byte_value();
+        let background_color_byte = self.config.background_color.get_byte_value();
 
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
@@ -215,18 +301,18 @@ where
                 .await?;
         }
 
-        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[FULL_UPDATE])
             .await?;
         self.command(spi, Command::MasterActivation).await?;
         self.wait_until_idle(spi).await
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -237,6 +323,34 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -248,19 +362,75 @@ where
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd7in5 {
+            interface,
+            config: UserConfig::default(),
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WordSpiConstructor<SPI, BUSY, DC, RST>
+    for Epd7in5<WordSpi<SPI>, BUSY, DC, RST>
+where
+    SPI: SpiDevice<u16>,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    const WORD_PADDING: Option<u8> = Some(WORD_PADDING);
 }
 
 impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -277,11 +447,202 @@ where
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface.cmd_with_data(spi, command, data).await
     }
+
+    /// Writes `rows` of the black/white RAM, the window starts at row `y`
+    ///
+    /// Row `y` of the frame is at RAM address `(HEIGHT - y) % HEIGHT`, as the Y address counts
+    /// down from the last row after the first one, so `y` 0 is only valid for a single row.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        rows: &[u8],
+        x: u32,
+        width: u32,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let x_end = x + width - 1;
+        let y_start = (HEIGHT - y) % HEIGHT;
+        let y_end = (HEIGHT - (y + height - 1)) % HEIGHT;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[x as u8, (x >> 8) as u8, x_end as u8, (x_end >> 8) as u8],
+        )
+        .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYStartEnd,
+            &[
+                y_start as u8,
+                (y_start >> 8) as u8,
+                y_end as u8,
+                (y_end >> 8) as u8,
+            ],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[x as u8, (x >> 8) as u8])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAc,
+            &[y_start as u8, (y_start >> 8) as u8],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::WriteRamBw, rows).await
+    }
+
+    /// Sets the RAM window and address counters back to the whole frame, like `init`
+    async fn restore_full_window(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::SetRamXStartEnd, &[0x00, 0x00, 0x6F, 0x03])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYStartEnd, &[0xAF, 0x02, 0x00, 0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
+            .await
+    }
+
+    /// Clear the frame to `color` and display it, like [`WaveshareDisplay::clear_frame`]
+    ///
+    /// Uses the auto write commands of the controller to fill both RAMs, instead of
+    /// sending every single byte over SPI.
+    pub async fn clear_frame_fast(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let pattern = auto_write_pattern(color);
+
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::AutoWriteRed, &[pattern])
+            .await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::AutoWriteBw, &[pattern])
+            .await?;
+        self.wait_until_idle(spi).await?;
+
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[FULL_UPDATE])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    /// Measures the temperature with the internal sensor of the controller, in °C
+    ///
+    /// The reading is also used to choose the look-up table of the next refresh. Needs a
+    /// readable data line, e.g. through a MISO line, the fraction of a degree is rounded down.
+    pub async fn read_temperature_register(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.load_temperature(spi).await?;
+        let mut register = [0; 2];
+        self.interface
+            .cmd_read(spi, Command::TemperatureSensorRead, &mut register)
+            .await?;
+        Ok(degrees_from_register(register))
+    }
+
+    async fn load_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])
+            .await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[LOAD_TEMPERATURE])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    /// Writes both RAM banks and displays them
+    ///
+    /// `update_frame` only writes the black/white bank. On panels with a red layer this
+    /// shows white, black, red and the black and red overlay. Both buffers need to be of
+    /// size: width / 8 * height !
+    pub async fn update_frame_dual_plane(
+        &mut self,
+        spi: &mut SPI,
+        bw_buffer: &[u8],
+        red_buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        if bw_buffer.len() != len || red_buffer.len() != len {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.wait_until_idle(spi).await?;
+        for (cmd, buffer) in [
+            (Command::WriteRamBw, bw_buffer),
+            (Command::WriteRamRed, red_buffer),
+        ] {
+            self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
+                .await?;
+            self.cmd_with_data(spi, cmd, buffer).await?;
+        }
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[FULL_UPDATE])
+            .await?;
+        self.display_frame(spi).await
+    }
+}
+
+// The temperature register holds 1/16 °C as 12 bit two's complement, left aligned in two bytes,
+// so the first byte is the whole degrees
+fn degrees_from_register(register: [u8; 2]) -> i8 {
+    (i16::from_be_bytes(register) >> 8) as i8
 }
 
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd7in5, Display7in5, "epd7in5_hd");
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{
+        blank_buffer, block_on, contains, CountingBusy, IdleBusy, NoopPin, RecordingSpi,
+        RecordingWordSpi, ScriptedSpi,
+    };
+    use crate::WordSpi;
+    use core::convert::Infallible;
+    use embedded_hal_async::spi::Operation;
+    use std::vec::Vec;
+
+    extern crate std;
+
+    /// Spi device recording the byte stream a `WordSpi` with `WORD_PADDING` is expected to send
+    #[derive(Default)]
+    struct PaddedSpi {
+        expected: Vec<u8>,
+    }
+
+    impl embedded_hal::spi::ErrorType for PaddedSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for PaddedSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations.iter() {
+                if let Operation::Write(data) = operation {
+                    self.expected.extend_from_slice(data);
+                    if data.len() % 2 == 1 {
+                        self.expected.push(WORD_PADDING);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
 
     #[test]
     fn epd_size() {
@@ -289,4 +650,249 @@ mod tests {
         assert_eq!(HEIGHT, 528);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn clear_frame_fast_uses_auto_write() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.clear_frame_fast(&mut spi, Color::Black)).unwrap();
+        let written = spi.take();
+        let pattern = auto_write_pattern(Color::Black);
+        assert!(contains(&written, &[0x46, pattern, 0x47, pattern]));
+        assert!(written.len() < 16);
+
+        block_on(epd.clear_frame_fast(&mut spi, Color::White)).unwrap();
+        let pattern = auto_write_pattern(Color::White);
+        assert!(contains(&spi.take(), &[0x46, pattern, 0x47, pattern]));
+    }
+
+    #[test]
+    fn temperature_register() {
+        let mut spi = ScriptedSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // 25.5 °C
+        spi.answer(&[0x19, 0x80]);
+        assert_eq!(
+            block_on(epd.read_temperature_register(&mut spi)).unwrap(),
+            25
+        );
+        assert_eq!(
+            spi.take(),
+            [
+                Command::TemperatureSensorControl as u8,
+                0x80,
+                Command::DisplayUpdateControl2 as u8,
+                LOAD_TEMPERATURE,
+                Command::MasterActivation as u8,
+                Command::TemperatureSensorRead as u8,
+            ]
+        );
+
+        // -0.5 °C and -25 °C
+        spi.answer(&[0xFF, 0x80]);
+        assert_eq!(
+            block_on(epd.read_temperature_register(&mut spi)).unwrap(),
+            -1
+        );
+        spi.answer(&[0xE7, 0x00]);
+        assert_eq!(
+            block_on(epd.read_temperature_register(&mut spi)).unwrap(),
+            -25
+        );
+        assert_eq!(spi.reads, 3);
+    }
+
+    #[test]
+    fn dual_plane_writes_both_banks() {
+        let bw = blank_buffer(WIDTH, HEIGHT);
+        let red = std::vec![0xA5; bw.len()];
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        assert!(matches!(
+            block_on(epd.update_frame_dual_plane(&mut spi, &bw, &red[1..])),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+
+        block_on(epd.update_frame_dual_plane(&mut spi, &bw, &red)).unwrap();
+        let written = spi.take();
+        let bank = |cmd: Command| {
+            let start = written.iter().position(|&byte| byte == cmd as u8).unwrap() + 1;
+            &written[start..start + bw.len()]
+        };
+        assert_eq!(bank(Command::WriteRamBw), &bw[..]);
+        assert_eq!(bank(Command::WriteRamRed), &red[..]);
+        assert!(written.ends_with(&[
+            Command::DisplayUpdateControl2 as u8,
+            0xF7,
+            Command::MasterActivation as u8
+        ]));
+    }
+
+    #[test]
+    fn partial_frame_writes_the_window() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // 64x64 at x 70, aligned down to 64, rows 100 to 163
+        let buffer = [0xAA; 64 / 8 * 64];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 70, 100, 64, 64)).unwrap();
+        let written = spi.take();
+        let (y_start, y_end) = (528 - 100, 528 - 163);
+        let mut expected = std::vec![
+            Command::SetRamXStartEnd as u8,
+            64,
+            0,
+            127,
+            0,
+            Command::SetRamYStartEnd as u8,
+            y_start as u8,
+            (y_start >> 8) as u8,
+            y_end as u8,
+            (y_end >> 8) as u8,
+            Command::SetRamXAc as u8,
+            64,
+            0,
+            Command::SetRamYAc as u8,
+            y_start as u8,
+            (y_start >> 8) as u8,
+            Command::WriteRamBw as u8,
+        ];
+        expected.extend_from_slice(&buffer);
+        assert_eq!(written[..expected.len()], expected);
+        // the full window is restored for the next frame
+        assert_eq!(
+            written[expected.len()..],
+            [
+                0x44,
+                0x00,
+                0x00,
+                0x6F,
+                0x03,
+                0x45,
+                0xAF,
+                0x02,
+                0x00,
+                0x00,
+                0x4E,
+                0x00,
+                0x00,
+                0x4F,
+                0x00,
+                0x00,
+                Command::DisplayUpdateControl2 as u8,
+                PARTIAL_UPDATE,
+            ]
+        );
+
+        // the first row is written on its own
+        block_on(epd.update_partial_frame(&mut spi, &[0x0F; 3], 0, 0, 8, 3)).unwrap();
+        let written = spi.take();
+        assert!(contains(
+            &written,
+            &[0x45, 0, 0, 0, 0, 0x4E, 0, 0, 0x4F, 0, 0, 0x24, 0x0F, 0x44]
+        ));
+        assert!(contains(&written, &[0x45, 0x0F, 0x02, 0x0E, 0x02]));
+        assert!(contains(&written, &[0x24, 0x0F, 0x0F, 0x44]));
+
+        for (len, x, y) in [(511, 0, 0), (512, 824, 0), (512, 0, 465)] {
+            assert!(matches!(
+                block_on(epd.update_partial_frame(&mut spi, &buffer[..len], x, y, 64, 64)),
+                Err(ErrorKind::InvalidArgument)
+            ));
+        }
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn word_spi_sends_padded_byte_stream() {
+        let buffer = blank_buffer(WIDTH, HEIGHT);
+
+        let mut spi = PaddedSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        block_on(epd.sleep(&mut spi)).unwrap();
+
+        let mut word_spi = WordSpi::new(RecordingWordSpi::default(), WORD_PADDING);
+        let Ok(mut epd) = block_on(Epd7in5::try_new_word(
+            &mut word_spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        )) else {
+            panic!("the controller ignores the padding");
+        };
+        block_on(epd.update_and_display_frame(&mut word_spi, &buffer)).unwrap();
+        block_on(epd.sleep(&mut word_spi)).unwrap();
+
+        assert_eq!(word_spi.release().take_bytes(), spi.expected);
+    }
+
+    #[test]
+    fn word_spi_with_other_padding_is_rejected() {
+        let mut spi = WordSpi::new(RecordingWordSpi::default(), 0x00);
+        let result = block_on(Epd7in5::try_new_word(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ));
+        assert!(matches!(
+            result,
+            Err(InitError {
+                error: ErrorKind::InvalidArgument,
+                ..
+            })
+        ));
+        assert!(spi.release().take_bytes().is_empty());
+    }
+
+    #[test]
+    fn refresh_without_waiting() {
+        let busy = CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd7in5::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let buffer = blank_buffer(WIDTH, HEIGHT);
+        spi.take();
+
+        busy.0.set(0);
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        let waiting = busy.0.replace(0);
+        let written = spi.take();
+
+        block_on(epd.update_and_display_frame_no_wait(&mut spi, &buffer)).unwrap();
+        assert_eq!(busy.0.get(), waiting - 1);
+        assert_eq!(spi.take(), written);
+        assert_eq!(written.last(), Some(&(Command::MasterActivation as u8)));
+    }
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd7in5,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::WriteRamBw as u8, 0x00, 0x00][..]]
+    );
 }
@@ -128,9 +128,13 @@ pub(crate) enum Command {
     ReadVcomValue = 0x81,
     /// This command sets `VCOM_DC` value.
     VcmDcSetting = 0x82,
-    // /// This is in all the Waveshare controllers for Epd7in5, but it's not documented
-    // /// anywhere in the datasheet `¯\_(ツ)_/¯`
-    // FlashMode = 0xE5,
+
+    /// Cascade setting, bit 1 uses the temperature of `ForceTemperature` for choosing the LUT
+    CascadeSetting = 0xE0,
+    /// Temperature used instead of the sensor reading if enabled by `CascadeSetting`
+    ///
+    /// The OTP holds the four-level gray waveform at the otherwise unused temperature 0x5F.
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
@@ -149,5 +153,6 @@ mod tests {
     fn command_addr() {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::ForceTemperature.address(), 0xE5);
     }
 }
@@ -11,13 +11,17 @@
 //! The hardware and interface of V2 are compatible with V1, however, the related software should be updated.
 
 use core::fmt::{Debug, Display};
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
 use crate::color::Color;
-use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::error::{ErrorKind, InitError};
+use crate::gray;
+use crate::interface::{BusySignal, DisplayInterface, IoStats, ResetControl, Timing};
+use crate::traits::{
+    ErrorType, GrayscaleLevels, InternalWiAdditions, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareGrayscaleDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -33,6 +37,11 @@ pub type Display7in5 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer for the four gray levels, see [`Epd7in5::update_gray_frame`]
+///
+/// Pixels are packed with two bits per pixel as described in [`crate::gray`].
+pub type Display7in5Gray = [u8; gray::packed_len(WIDTH, HEIGHT)];
+
 /// Width of the display
 pub const WIDTH: u32 = 800;
 /// Height of the display
@@ -41,25 +50,60 @@ pub const HEIGHT: u32 = 480;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
 const SINGLE_BYTE_WRITE: bool = false;
+/// Forced temperature selecting the four-level waveform of the OTP
+const GRAY_TEMPERATURE: u8 = 0x5F;
 
 /// Epd7in5 (V2) driver
 ///
 pub struct Epd7in5<SPI, BUSY, DC, RST> {
     /// Connection Interface
     interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    /// Background Color
-    color: Color,
+    config: UserConfig,
+    /// Next row expected by an open stripe transmission
+    stripe_row: Option<u32>,
+    /// The four-level waveform is selected
+    gray: bool,
+}
+
+/// Settings chosen by the user
+///
+/// Kept across `sleep` and `wake_up`, unlike the registers of the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserConfig {
+    /// Background color, used by `clear_frame`
+    pub background_color: Color,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        }
+    }
+}
+
+#[cfg(feature = "compat-0-5")]
+impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST> {
+    /// The same driver for another type of SPI device, see [`crate::compat`]
+    pub(crate) fn rebind_spi<S>(self) -> Epd7in5<S, BUSY, DC, RST> {
+        Epd7in5 {
+            interface: self.interface.rebind_spi(),
+            config: self.config,
+            stripe_row: self.stripe_row,
+            gray: self.gray,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
@@ -69,16 +113,18 @@ impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
         self.interface.reset(spi, 10_000, 2_000).await?;
+        self.gray = false;
+        self.stripe_row = None;
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
@@ -110,36 +156,20 @@ impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, B
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type DisplayColor = Color;
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd7in5 { interface, color };
-
-        epd.init(spi).await?;
-
-        Ok(epd)
-    }
-
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.init(spi).await
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
@@ -147,11 +177,14 @@ where
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
+        self.leave_gray(spi).await?;
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
             .await
     }
 
+    /// Partial updates aren't supported, returns [`ErrorKind::InvalidArgument`]
     async fn update_partial_frame(
         &mut self,
         _spi: &mut SPI,
@@ -161,7 +194,43 @@ where
         _width: u32,
         _height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        Err(ErrorKind::InvalidArgument)
+    }
+
+    /// Keeps the frame transmission open between stripes, so no other command
+    /// may be sent until [`end_stripe`](WaveshareDisplay::end_stripe) is called.
+    async fn update_frame_stripe(
+        &mut self,
+        spi: &mut SPI,
+        stripe: &[u8],
+        y_start: u32,
+        stripe_height: u32,
+    ) -> Result<(), Self::Error> {
+        if stripe.len() != buffer_len(WIDTH as usize, stripe_height as usize)
+            || y_start.saturating_add(stripe_height) > HEIGHT
+        {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        if y_start == 0 {
+            self.wait_until_idle(spi).await?;
+            self.leave_gray(spi).await?;
+            self.command(spi, Command::DataStartTransmission2).await?;
+        } else if self.stripe_row != Some(y_start) {
+            // stripes have to be contiguous, the controller can't skip rows
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        self.send_data(spi, stripe).await?;
+        self.stripe_row = Some(y_start + stripe_height);
+        Ok(())
+    }
+
+    async fn end_stripe(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.stripe_row.take().is_some() {
+            self.command(spi, Command::DataStop).await?;
+        }
+        Ok(())
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -178,8 +247,20 @@ where
         self.command(spi, Command::DisplayRefresh).await
     }
 
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await?;
+        self.interface.set_update_pending();
+        Ok(())
+    }
+
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.stripe_row = None;
         self.wait_until_idle(spi).await?;
+        self.leave_gray(spi).await?;
         self.send_resolution(spi).await?;
 
         self.command(spi, Command::DataStartTransmission1).await?;
@@ -187,20 +268,22 @@ where
             .data_x_times(spi, 0x00, WIDTH / 8 * HEIGHT)
             .await?;
 
+        // the controller takes a set bit as black
+        let color = !self.config.background_color.get_byte_value();
         self.command(spi, Command::DataStartTransmission2).await?;
         self.interface
-            .data_x_times(spi, 0x00, WIDTH / 8 * HEIGHT)
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
             .await?;
 
         self.command(spi, Command::DisplayRefresh).await
     }
 
     fn set_background_color(&mut self, color: Color) {
-        self.color = color;
+        self.config.background_color = color;
     }
 
     fn background_color(&self) -> &Color {
-        &self.color
+        &self.config.background_color
     }
 
     fn width(&self) -> u32 {
@@ -211,6 +294,35 @@ where
         HEIGHT
     }
 
+    fn update_pending(&self) -> bool {
+        self.interface.update_pending()
+    }
+
+    fn needs_init(&self) -> bool {
+        self.interface.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.stripe_row = None;
+        self.interface.on_external_reset();
+    }
+
+    fn timing(&self) -> Timing {
+        self.interface.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.interface.set_timing(timing);
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.interface.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.interface.reset_io_stats();
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -224,19 +336,92 @@ where
             .wait_until_idle_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
             .await
     }
+
+    fn is_busy(&mut self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareConstructor<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let mut epd = Epd7in5 {
+            interface,
+            config: UserConfig::default(),
+            stripe_row: None,
+            gray: false,
+        };
+
+        if let Err(error) = epd.init(spi).await {
+            return Err(epd.interface.into_init_error(error));
+        }
+
+        Ok(epd)
+    }
+}
+
+impl<SPI, BUSY, DC, RST> WaveshareGrayscaleDisplay<SPI, BUSY, DC, RST>
+    for Epd7in5<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    fn supports_levels(&self, levels: GrayscaleLevels) -> bool {
+        levels != GrayscaleLevels::Sixteen
+    }
+
+    async fn update_grayscale_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        levels: GrayscaleLevels,
+    ) -> Result<(), Self::Error> {
+        match levels {
+            GrayscaleLevels::Two => self.update_frame(spi, buffer).await,
+            GrayscaleLevels::Four => self.update_gray_frame(spi, buffer).await,
+            GrayscaleLevels::Sixteen => Err(ErrorKind::InvalidArgument),
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
+    /// Settings that are restored whenever the display wakes up
+    pub fn config(&self) -> &UserConfig {
+        &self.config
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -262,7 +447,72 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+    /// Transmits a frame of four gray levels
+    ///
+    /// `buffer` needs to be a [`Display7in5Gray`], packed as described in [`crate::gray`]. The
+    /// frame is shown by the next [`display_frame`](WaveshareDisplay::display_frame) with the
+    /// four-level waveform, which stays selected until the next black/white frame.
+    pub async fn update_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if buffer.len() != gray::packed_len(WIDTH, HEIGHT) {
+            return Err(ErrorKind::InvalidArgument);
+        }
+        self.wait_until_idle(spi).await?;
+        if !self.gray {
+            self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x27, 0x27, 0x18, 0x17])
+                .await?;
+            self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+                .await?;
+            self.cmd_with_data(spi, Command::ForceTemperature, &[GRAY_TEMPERATURE])
+                .await?;
+            self.gray = true;
+        }
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.send_gray_plane(spi, buffer, |(old, _)| old).await?;
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.send_gray_plane(spi, buffer, |(_, new)| new).await
+    }
+
+    /// Sends one of the two planes of a gray frame, a row at a time
+    async fn send_gray_plane(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        plane: fn((u8, u8)) -> u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let mut row = [0; WIDTH as usize / 8];
+        for packed in buffer.chunks(WIDTH as usize / 4) {
+            for (byte, pixels) in row.iter_mut().zip(packed.chunks_exact(2)) {
+                *byte = plane(gray_planes([pixels[0], pixels[1]]));
+            }
+            self.send_data(spi, &row).await?;
+        }
+        Ok(())
+    }
+
+    /// Switches back to the black/white waveform after a gray frame
+    async fn leave_gray(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.gray {
+            self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17])
+                .await?;
+            self.cmd_with_data(spi, Command::CascadeSetting, &[0x00])
+                .await?;
+            self.gray = false;
+        }
+        Ok(())
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let w = self.width();
         let h = self.height();
 
@@ -274,9 +524,34 @@ where
     }
 }
 
+/// Bits of eight pixels packed with two bits per pixel in the old (`DataStartTransmission1`) and
+/// the new (`DataStartTransmission2`) data of the four-level waveform
+///
+/// Black sets the bit in both planes, light gray only in the old and dark gray only in the new
+/// one, white in neither, as in the Waveshare reference driver.
+fn gray_planes(pixels: [u8; 2]) -> (u8, u8) {
+    let mut old = 0;
+    let mut new = 0;
+    for pixel in 0..8 {
+        let level = pixels[pixel / 4] >> (6 - 2 * (pixel % 4)) & 0b11;
+        let bit = 0x80 >> pixel;
+        if level == gray::BLACK || level == gray::LIGHT_GRAY {
+            old |= bit;
+        }
+        if level == gray::BLACK || level == gray::DARK_GRAY {
+            new |= bit;
+        }
+    }
+    (old, new)
+}
+
+#[cfg(feature = "demo")]
+crate::demo::impl_demo!(Epd7in5, Display7in5, "epd7in5_v2");
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
 
     #[test]
     fn epd_size() {
@@ -284,4 +559,254 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn frame_in_stripes() {
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| i as u8);
+        let stripe_len = buffer_len(WIDTH as usize, 32);
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let full = spi.take();
+
+        for (i, stripe) in frame.chunks(stripe_len).enumerate() {
+            block_on(epd.update_frame_stripe(&mut spi, stripe, i as u32 * 32, 32)).unwrap();
+        }
+        block_on(epd.end_stripe(&mut spi)).unwrap();
+        let striped = spi.take();
+
+        assert_eq!(&striped[..full.len()], &full[..]);
+        assert_eq!(&striped[full.len()..], &[Command::DataStop as u8]);
+    }
+
+    #[test]
+    fn stripes_must_be_contiguous() {
+        let stripe = [0u8; buffer_len(WIDTH as usize, 8)];
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)).unwrap();
+        assert!(matches!(
+            block_on(epd.update_frame_stripe(&mut spi, &stripe, 16, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(matches!(
+            block_on(epd.update_frame_stripe(&mut spi, &stripe, 8, 4)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn frame_commands_abandon_the_stripes() {
+        let stripe = [0u8; buffer_len(WIDTH as usize, 8)];
+        let frame = [0u8; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+
+        block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)).unwrap();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)).unwrap();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        block_on(epd.update_frame_stripe(&mut spi, &stripe, 0, 8)).unwrap();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        spi.take();
+
+        assert!(matches!(
+            block_on(epd.update_frame_stripe(&mut spi, &stripe, 8, 8)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        // no DataStop in the middle of the next frame
+        block_on(epd.end_stripe(&mut spi)).unwrap();
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn run_length_encoded_frame() {
+        extern crate std;
+        use crate::traits::WaveshareDisplayExt;
+
+        let frame: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)] =
+            core::array::from_fn(|i| (i / 700) as u8);
+        let mut rle = std::vec::Vec::new();
+        for run in frame.chunk_by(|a, b| a == b) {
+            for part in run.chunks(255) {
+                rle.extend_from_slice(&[part.len() as u8, part[0]]);
+            }
+        }
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let full = spi.take();
+
+        block_on(epd.update_frame_rle(&mut spi, &rle)).unwrap();
+        let decoded = spi.take();
+        assert_eq!(&decoded[..full.len()], &full[..]);
+        assert_eq!(&decoded[full.len()..], &[Command::DataStop as u8]);
+
+        // one byte short of a frame
+        rle[0] -= 1;
+        assert!(matches!(
+            block_on(epd.update_frame_rle(&mut spi, &rle)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+        assert!(spi.take().is_empty());
+    }
+
+    #[test]
+    fn frame_from_fn() {
+        use crate::traits::WaveshareDisplayExt;
+
+        let pixel = |x: u32, y: u32| {
+            if (x / 8 + y).is_multiple_of(3) || x == y {
+                Color::Black
+            } else {
+                Color::White
+            }
+        };
+        let mut frame = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                if pixel(x, y) == Color::Black {
+                    frame[(y * WIDTH + x) as usize / 8] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let full = spi.take();
+
+        block_on(epd.update_frame_from_fn(&mut spi, pixel)).unwrap();
+        let generated = spi.take();
+        assert_eq!(&generated[..full.len()], &full[..]);
+        assert_eq!(&generated[full.len()..], &[Command::DataStop as u8]);
+    }
+
+    #[test]
+    fn transfer_benchmark() {
+        use crate::traits::WaveshareDisplayExt;
+        use core::cell::Cell;
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        // 2 ms between the two readings of the clock
+        let now = Cell::new(0);
+        let clock = || now.replace(now.get() + 2_000);
+        // 10 whole rows of 100 bytes
+        let rate = block_on(epd.benchmark_spi_transfer(&mut spi, clock, 950)).unwrap();
+        assert_eq!(rate, 500_000);
+        let written = spi.take();
+        assert_eq!(written[0], Command::DataStartTransmission2 as u8);
+        assert_eq!(written[1..1001], [0xFF; 1000]);
+        assert_eq!(written[1001..], [Command::DataStop as u8]);
+
+        // more than a frame starts over at the top
+        let frame_len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        block_on(epd.benchmark_spi_transfer(&mut spi, || 0, frame_len as u32 + 1)).unwrap();
+        let starts = spi
+            .take()
+            .iter()
+            .filter(|&&byte| byte == Command::DataStartTransmission2 as u8)
+            .count();
+        assert_eq!(starts, 2);
+    }
+
+    #[test]
+    fn gray_levels_in_both_planes() {
+        // black, dark gray, light gray, white, then the same levels in reverse
+        assert_eq!(
+            gray_planes([0b00_01_10_11, 0b11_10_01_00]),
+            (0b1010_0101, 0b1100_0011)
+        );
+        assert_eq!(gray_planes([0xFF, 0xFF]), (0x00, 0x00));
+        assert_eq!(gray_planes([0x00, 0x00]), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn gray_frame_selects_the_gray_waveform() {
+        extern crate std;
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        assert!(matches!(
+            block_on(epd.update_gray_frame(&mut spi, &[0xFF; 100])),
+            Err(ErrorKind::InvalidArgument)
+        ));
+
+        // dark gray everywhere
+        let frame: std::boxed::Box<Display7in5Gray> =
+            std::boxed::Box::new([0x55; gray::packed_len(WIDTH, HEIGHT)]);
+        block_on(epd.update_gray_frame(&mut spi, &frame[..])).unwrap();
+        let written = spi.take();
+        let plane_len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        let header = written.len() - 2 * plane_len - 1;
+        assert_eq!(
+            &written[..header],
+            &[
+                Command::BoosterSoftStart as u8,
+                0x27,
+                0x27,
+                0x18,
+                0x17,
+                Command::CascadeSetting as u8,
+                0x02,
+                Command::ForceTemperature as u8,
+                GRAY_TEMPERATURE,
+                Command::DataStartTransmission1 as u8,
+            ]
+        );
+        assert!(written[header..][..plane_len].iter().all(|&b| b == 0x00));
+        assert_eq!(
+            written[header + plane_len],
+            Command::DataStartTransmission2 as u8
+        );
+        assert!(written[header + plane_len + 1..].iter().all(|&b| b == 0xFF));
+
+        // a black/white frame switches back
+        block_on(epd.update_frame(&mut spi, &[0xFF; 10])).unwrap();
+        assert_eq!(
+            spi.take()[..7],
+            [
+                Command::BoosterSoftStart as u8,
+                0x17,
+                0x17,
+                0x27,
+                0x17,
+                Command::CascadeSetting as u8,
+                0x00,
+            ]
+        );
+    }
+
+    crate::golden::golden_trace!(golden_command_stream, Epd7in5, "epd7in5_v2");
+
+    crate::test_utils::user_config_survives_sleep!(
+        user_config_survives_sleep,
+        Epd7in5,
+        |epd, spi| {
+            epd.set_background_color(Color::Black);
+        },
+        [&[Command::DataStartTransmission2 as u8, 0xFF, 0xFF][..]]
+    );
 }
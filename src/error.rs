@@ -1,8 +1,9 @@
 use core::fmt::{Debug, Display, Formatter};
 
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal::spi::SpiDevice;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
+use crate::interface::{BusySignal, ResetControl};
 use crate::traits::Error;
 
 /// Epd error type
@@ -11,11 +12,10 @@ pub enum ErrorKind<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy,
-    BUSY: InputPin,
-    BUSY::Error: Copy,
+    BUSY: BusySignal,
     DC: OutputPin,
     DC::Error: Copy,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy,
 {
     /// Encountered an SPI error
@@ -30,6 +30,29 @@ where
     /// Encountered an error on RST GPIO
     RstError(RST::Error),
 
+    /// An argument was out of the range accepted by the display, e.g. a buffer of the wrong size
+    InvalidArgument,
+
+    /// A register still read back wrong after writing it again, holds the address of the command
+    RegisterVerifyFailed(u8),
+
+    /// The busy line didn't react to a command, even after sending it again. Holds the address
+    /// of the command, see [`SafeCommands`](crate::SafeCommands)
+    CommandUnconfirmed(u8),
+
+    /// The panel reacted like a different controller generation than the one of the driver,
+    /// see [`sanity_check`](crate::traits::WaveshareDisplay::sanity_check)
+    PanelMismatch(MismatchDiagnosis),
+
+    /// Only the chromatic RAM was to be sent, but the black/white RAM of the controller doesn't
+    /// hold a known frame, see
+    /// [`Tracked::refresh_with_existing_achromatic`](crate::validity::Tracked::refresh_with_existing_achromatic)
+    AchromaticStale,
+
+    /// The display was still busy after the busy timeout, see
+    /// [`Timing::busy_timeout_us`](crate::Timing::busy_timeout_us)
+    BusyTimeout,
+
     /// Anything else
     Other,
 }
@@ -38,11 +61,10 @@ impl<SPI, BUSY, DC, RST> Clone for ErrorKind<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy,
-    BUSY: InputPin,
-    BUSY::Error: Copy,
+    BUSY: BusySignal,
     DC: OutputPin,
     DC::Error: Copy,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy,
 {
     fn clone(&self) -> Self {
@@ -54,11 +76,10 @@ impl<SPI, BUSY, DC, RST> Copy for ErrorKind<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy,
-    BUSY: InputPin,
-    BUSY::Error: Copy,
+    BUSY: BusySignal,
     DC: OutputPin,
     DC::Error: Copy,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy,
 {
 }
@@ -67,11 +88,11 @@ impl<SPI, BUSY, DC, RST> Display for ErrorKind<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
@@ -80,6 +101,19 @@ where
             Self::BusyError(err) => Display::fmt(&err, f),
             Self::DcError(err) => Display::fmt(&err, f),
             Self::RstError(err) => Display::fmt(&err, f),
+            Self::InvalidArgument => write!(f, "An argument was out of the accepted range"),
+            Self::RegisterVerifyFailed(command) => {
+                write!(f, "Register {:#04x} read back a different value", command)
+            }
+            Self::CommandUnconfirmed(command) => {
+                write!(f, "The display didn't react to command {:#04x}", command)
+            }
+            Self::PanelMismatch(diagnosis) => Display::fmt(diagnosis, f),
+            Self::AchromaticStale => write!(
+                f,
+                "The black/white RAM of the display is unknown, send the full frame first"
+            ),
+            Self::BusyTimeout => write!(f, "The display didn't become idle in time"),
             Self::Other => write!(
                 f,
                 "A different error occurred. The original error may contain more information"
@@ -92,11 +126,11 @@ impl<SPI, BUSY, DC, RST> Debug for ErrorKind<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
@@ -105,6 +139,16 @@ where
             Self::BusyError(err) => Debug::fmt(&err, f),
             Self::DcError(err) => Debug::fmt(&err, f),
             Self::RstError(err) => Debug::fmt(&err, f),
+            Self::InvalidArgument => write!(f, "InvalidArgument"),
+            Self::RegisterVerifyFailed(command) => {
+                write!(f, "RegisterVerifyFailed({:#04x})", command)
+            }
+            Self::CommandUnconfirmed(command) => {
+                write!(f, "CommandUnconfirmed({:#04x})", command)
+            }
+            Self::PanelMismatch(diagnosis) => write!(f, "PanelMismatch({:?})", diagnosis),
+            Self::AchromaticStale => write!(f, "AchromaticStale"),
+            Self::BusyTimeout => write!(f, "BusyTimeout"),
             Self::Other => write!(
                 f,
                 "A different error occurred. The original error may contain more information"
@@ -117,14 +161,64 @@ impl<SPI, BUSY, DC, RST> Error<SPI, BUSY, DC, RST> for ErrorKind<SPI, BUSY, DC,
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     fn kind(&self) -> &ErrorKind<SPI, BUSY, DC, RST> {
         self
     }
 }
+
+/// Controller generation a panel seems to have instead of the one of the driver
+///
+/// Returned by [`sanity_check`](crate::traits::WaveshareDisplay::sanity_check) when V1 driver
+/// code meets a V2 panel or the other way round, which otherwise shows up as noise on the
+/// display or a driver waiting forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MismatchDiagnosis {
+    /// Panel generation the controller reacted like, e.g. `"SSD1680, like the 2.9\" b V4"`
+    pub suspected: &'static str,
+    /// Module of the driver for such a panel, if this crate has one
+    pub driver: Option<&'static str>,
+}
+
+impl Display for MismatchDiagnosis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "The panel reacts like a {} controller", self.suspected)?;
+        match self.driver {
+            Some(driver) => write!(f, ", try the {} driver", driver),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Error of [`try_new`](crate::traits::WaveshareConstructor::try_new), handing back the pins
+///
+/// Allows retrying the initialisation on HALs where the pins can only be taken once.
+#[derive(Debug)]
+pub struct InitError<E, BUSY, DC, RST> {
+    /// Error that occurred during the initialisation
+    pub error: E,
+    /// Busy signal the driver was created with
+    pub busy: BUSY,
+    /// Data/Command pin the driver was created with
+    pub dc: DC,
+    /// Reset pin the driver was created with
+    pub rst: RST,
+}
+
+impl<E, BUSY, DC, RST> InitError<E, BUSY, DC, RST> {
+    /// Drops the pins and returns the error
+    pub fn into_error(self) -> E {
+        self.error
+    }
+
+    /// Returns the error and the pins as `(error, busy, dc, rst)`
+    pub fn into_parts(self) -> (E, BUSY, DC, RST) {
+        (self.error, self.busy, self.dc, self.rst)
+    }
+}
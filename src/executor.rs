@@ -0,0 +1,176 @@
+//! Minimal executor for calling the async drivers from blocking code
+//!
+//! [`block_on`] polls a future until it completes, without any runtime, heap or interrupts.
+//! Together with [`Blocking`], which turns blocking SPI devices and delays into their async
+//! counterparts, it allows using the drivers with a blocking HAL.
+//!
+//! # Constraints
+//!
+//! **The executor never sleeps and nothing ever wakes it up.** A future that is pending is simply
+//! polled again, after calling the `on_pending` hook of [`block_on_with`]. This is fine as long
+//! as every future completes while being polled, which is the case if
+//!
+//! - the SPI device and the delay are blocking ones wrapped in [`Blocking`], and
+//! - the busy pin is a [`PollingBusy`](crate::PollingBusy) using a [`Blocking`] delay, instead of
+//!   a pin implementing `Wait`.
+//!
+//! With real async peripherals the futures wait for interrupts, `block_on` then busy loops until
+//! they are done, which works but wastes the CPU. Use a proper executor like embassy instead.
+//!
+//! # Example
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::{epd2in9bc::*, executor::{block_on, Blocking}, prelude::*, PollingBusy};
+//!
+//! // Blocking peripherals of the HAL, e.g. stm32f4xx-hal
+//! let spi = dp.SPI1.spi((sck, miso, mosi), SPI_MODE, 4.MHz(), &clocks);
+//! let mut spi = Blocking(ExclusiveDevice::new(spi, cs, NoDelay).unwrap());
+//! let busy = PollingBusy::new(busy_pin, Blocking(cp.SYST.delay(&clocks)), 1_000);
+//!
+//! let mut epd = block_on(Epd2in9bc::new(&mut spi, busy, dc, rst, None))?;
+//! block_on(epd.update_and_display_frame(&mut spi, display.buffer()))?;
+//! block_on(epd.sleep(&mut spi))?;
+//! ```
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+/// Polls `future` to completion, see the [module documentation](self) for the constraints
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    block_on_with(future, core::hint::spin_loop)
+}
+
+/// Same as [`block_on`], calling `on_pending` whenever the future isn't ready yet
+///
+/// `on_pending` could e.g. feed a watchdog or wait for an interrupt.
+pub fn block_on_with<F: Future>(future: F, mut on_pending: impl FnMut()) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => on_pending(),
+        }
+    }
+}
+
+/// Adapter implementing the async traits for blocking SPI devices and delays
+///
+/// The async functions block and are always ready when polled for the first time.
+#[repr(transparent)]
+pub struct Blocking<T>(pub T);
+
+impl<T> Blocking<T> {
+    /// Borrows `inner` as the adapter, e.g. to pass a blocking SPI device to an async driver
+    pub fn from_mut(inner: &mut T) -> &mut Self {
+        // SAFETY: `Blocking` is a transparent wrapper, it has the layout of `T`
+        unsafe { &mut *(inner as *mut T as *mut Self) }
+    }
+}
+
+impl<T> embedded_hal::spi::ErrorType for Blocking<T>
+where
+    T: embedded_hal::spi::ErrorType,
+{
+    type Error = T::Error;
+}
+
+impl<T> embedded_hal_async::spi::SpiDevice for Blocking<T>
+where
+    T: embedded_hal::spi::SpiDevice,
+{
+    async fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        self.0.transaction(operations)
+    }
+}
+
+impl<T> embedded_hal_async::delay::DelayNs for Blocking<T>
+where
+    T: embedded_hal::delay::DelayNs,
+{
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.delay_ns(ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9bc::{self, Epd2in9bc};
+    use crate::test_utils::{blank_buffer, contains, NoopPin, RecordingSpi};
+    use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+    use crate::PollingBusy;
+    use core::convert::Infallible;
+
+    /// Future that is pending a given number of times before it completes
+    struct PendingFor(u32);
+
+    impl Future for PendingFor {
+        type Output = u32;
+
+        fn poll(mut self: core::pin::Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<u32> {
+            if self.0 == 0 {
+                Poll::Ready(42)
+            } else {
+                self.0 -= 1;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn hook_called_while_pending() {
+        let mut pending = 0;
+        assert_eq!(block_on_with(PendingFor(3), || pending += 1), 42);
+        assert_eq!(pending, 3);
+        assert_eq!(block_on(PendingFor(0)), 42);
+    }
+
+    /// Blocking busy pin that reports busy for a number of reads
+    struct BusyPin(u32);
+
+    impl embedded_hal::digital::ErrorType for BusyPin {
+        type Error = Infallible;
+    }
+
+    impl embedded_hal::digital::InputPin for BusyPin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            self.is_low().map(|low| !low)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            // the 2in9bc signals busy with a low level
+            let busy = self.0 > 0;
+            self.0 = self.0.saturating_sub(1);
+            Ok(busy)
+        }
+    }
+
+    struct NoDelay;
+
+    impl embedded_hal::delay::DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn update_cycle_through_shim() {
+        let mut spi = RecordingSpi::default();
+        let busy = PollingBusy::new(BusyPin(5), Blocking(NoDelay), 1_000);
+
+        let mut epd = block_on_with(
+            Epd2in9bc::new(&mut spi, busy, NoopPin, NoopPin, Some(0)),
+            || panic!("a blocking setup never pends"),
+        )
+        .unwrap();
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        block_on(epd.sleep(&mut spi)).unwrap();
+
+        let written = spi.take();
+        assert!(contains(&written, &buffer));
+        assert_eq!(written.last(), Some(&0xA5));
+    }
+}
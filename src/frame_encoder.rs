@@ -0,0 +1,52 @@
+//! Pre-encoding of frames for controllers that don't take 1 bit per pixel
+//!
+//! The [`epd7in5`](crate::epd7in5) expands every pixel of a frame to 4 bits while sending it.
+//! Frames that are shown more than once, or prepared while the display is busy, can be encoded
+//! ahead of time and sent as is with
+//! [`Epd7in5::update_frame_encoded`](crate::epd7in5::Epd7in5::update_frame_encoded).
+use crate::wire::{WireBytes, WireTransform};
+
+/// Length of the 4 bits per pixel encoding of a 1 bit per pixel buffer of `buffer_len` bytes
+pub const fn encoded_len_4bpp(buffer_len: usize) -> usize {
+    buffer_len * 4
+}
+
+/// Expands a 1 bit per pixel buffer to the 4 bits per pixel sent to the 7in5
+///
+/// OUTPUT needs to be of size: input.len() * 4 !
+pub fn encode_1bpp_to_4bpp(input: &[u8], output: &mut [u8]) {
+    assert_eq!(output.len(), encoded_len_4bpp(input.len()));
+    for (encoded, byte) in output
+        .iter_mut()
+        .zip(WireBytes::new(WireTransform::Nibbles, input))
+    {
+        *encoded = byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd7in5::consts::{BLACK_NIBBLE, WHITE_NIBBLE};
+
+    #[test]
+    fn two_pixels_per_byte() {
+        let mut output = [0; 8];
+        encode_1bpp_to_4bpp(&[0b1000_0001, 0xFF], &mut output);
+
+        let black_white = BLACK_NIBBLE << 4 | WHITE_NIBBLE;
+        let white = WHITE_NIBBLE << 4 | WHITE_NIBBLE;
+        let black = BLACK_NIBBLE << 4 | BLACK_NIBBLE;
+        assert_eq!(
+            output[..4],
+            [WHITE_NIBBLE << 4 | BLACK_NIBBLE, black, black, black_white]
+        );
+        assert_eq!(output[4..], [white; 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn output_of_the_wrong_size() {
+        encode_1bpp_to_4bpp(&[0; 2], &mut [0; 7]);
+    }
+}
@@ -0,0 +1,489 @@
+//! Golden command streams of the drivers
+//!
+//! The canonical operations of a driver (init, full update and display, clear, sleep and wake up)
+//! are run against a [`Trace`] of the pins and the spi device. The recorded command stream is
+//! compared with a checked-in, human-readable file in `tests/golden`, which turns every change of
+//! the wire traffic into a reviewable text diff:
+//!
+//! ```text
+//! # init
+//! RESET
+//! DELAY 10000
+//! CMD 04
+//! WAIT_BUSY
+//! DATA 17 17 17
+//! DATA 00*4736
+//! ```
+//!
+//! `DATA` lines hold up to 16 bytes, runs of identical bytes are written as `byte*count`.
+//! Consecutive `DATA` lines belong to the same transfer, as on the wire.
+//!
+//! After an intentional change the files are regenerated with
+//!
+//! ```sh
+//! UPDATE_GOLDENS=1 cargo test golden
+//! ```
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use core::fmt::Write;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::{Operation, SpiDevice};
+use std::format;
+use std::rc::Rc;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::interface::BusySignal;
+
+/// Environment variable that makes the golden tests write the files instead of checking them
+const UPDATE_ENV: &str = "UPDATE_GOLDENS";
+/// Bytes per `DATA` line
+const BYTES_PER_LINE: usize = 16;
+/// Shortest run of identical bytes that is written as `byte*count`
+const MIN_RUN: usize = 4;
+
+/// Something the driver did on the wire
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// The reset pin was pulled low
+    Reset,
+    /// A command byte, sent with DC low
+    Cmd(u8),
+    /// Data bytes, sent with DC high
+    Data(Vec<u8>),
+    /// A delay in µs
+    Delay(u32),
+    /// The driver waited for the busy pin
+    WaitBusy,
+}
+
+/// Command stream shared by the spi device and the pins of a driver under test
+#[derive(Clone, Default)]
+pub(crate) struct Trace {
+    events: Rc<RefCell<Vec<Event>>>,
+    data_mode: Rc<Cell<bool>>,
+}
+
+impl Trace {
+    /// Returns and forgets all events recorded so far
+    pub(crate) fn take(&self) -> Vec<Event> {
+        self.events.take()
+    }
+
+    pub(crate) fn spi(&self) -> TraceSpi {
+        TraceSpi(self.clone())
+    }
+
+    pub(crate) fn busy(&self) -> TraceBusy {
+        TraceBusy(self.clone())
+    }
+
+    pub(crate) fn dc(&self) -> TraceDc {
+        TraceDc(self.clone())
+    }
+
+    pub(crate) fn rst(&self) -> TraceRst {
+        TraceRst(self.clone())
+    }
+
+    fn push(&self, event: Event) {
+        self.events.borrow_mut().push(event);
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut events = self.events.borrow_mut();
+        if !self.data_mode.get() {
+            events.extend(bytes.iter().copied().map(Event::Cmd));
+        } else if let Some(Event::Data(data)) = events.last_mut() {
+            data.extend_from_slice(bytes);
+        } else {
+            events.push(Event::Data(bytes.to_vec()));
+        }
+    }
+}
+
+/// Spi device of a [`Trace`], commands and data are told apart by the level of [`TraceDc`]
+pub(crate) struct TraceSpi(Trace);
+
+impl embedded_hal::spi::ErrorType for TraceSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for TraceSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter() {
+            match operation {
+                Operation::Write(data) => self.0.write(data),
+                Operation::DelayNs(ns) => self.0.push(Event::Delay(ns / 1000)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Data/command pin of a [`Trace`]
+pub(crate) struct TraceDc(Trace);
+
+impl embedded_hal::digital::ErrorType for TraceDc {
+    type Error = Infallible;
+}
+
+impl OutputPin for TraceDc {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.data_mode.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.data_mode.set(true);
+        Ok(())
+    }
+}
+
+/// Reset pin of a [`Trace`]
+pub(crate) struct TraceRst(Trace);
+
+impl embedded_hal::digital::ErrorType for TraceRst {
+    type Error = Infallible;
+}
+
+impl OutputPin for TraceRst {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.push(Event::Reset);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Busy signal of a [`Trace`], the display is never busy
+pub(crate) struct TraceBusy(Trace);
+
+impl BusySignal for TraceBusy {
+    type Error = Infallible;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        self.0.push(Event::WaitBusy);
+        Ok(())
+    }
+
+    fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+        false
+    }
+}
+
+/// Recorded events of one operation, e.g. `init`
+pub(crate) type Section = (String, Vec<Event>);
+
+/// Writes the sections in the golden file format
+pub(crate) fn serialize(sections: &[Section]) -> String {
+    let mut text = String::new();
+    for (index, (name, events)) in sections.iter().enumerate() {
+        if index > 0 {
+            text.push('\n');
+        }
+        let _ = writeln!(text, "# {}", name);
+        for event in events {
+            match event {
+                Event::Reset => text.push_str("RESET\n"),
+                Event::Cmd(command) => {
+                    let _ = writeln!(text, "CMD {:02x}", command);
+                }
+                Event::Data(data) => serialize_data(&mut text, data),
+                Event::Delay(us) => {
+                    let _ = writeln!(text, "DELAY {}", us);
+                }
+                Event::WaitBusy => text.push_str("WAIT_BUSY\n"),
+            }
+        }
+    }
+    text
+}
+
+fn serialize_data(text: &mut String, data: &[u8]) {
+    let mut tokens = Vec::new();
+    let mut rest = data;
+    while let Some(&byte) = rest.first() {
+        let run = rest.iter().take_while(|&&other| other == byte).count();
+        if run >= MIN_RUN {
+            tokens.push(format!("{:02x}*{}", byte, run));
+            rest = &rest[run..];
+        } else {
+            tokens.push(format!("{:02x}", byte));
+            rest = &rest[1..];
+        }
+    }
+    for line in tokens.chunks(BYTES_PER_LINE) {
+        let _ = writeln!(text, "DATA {}", line.join(" "));
+    }
+}
+
+/// Reads sections from the golden file format
+pub(crate) fn parse(text: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {}: {:?}", number + 1, message, line);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('#') {
+            sections.push((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let Some((_, events)) = sections.last_mut() else {
+            return Err(error("event outside of a section"));
+        };
+
+        let mut words = line.split_whitespace();
+        let event = match (words.next(), words.next()) {
+            (Some("RESET"), None) => Event::Reset,
+            (Some("WAIT_BUSY"), None) => Event::WaitBusy,
+            (Some("CMD"), Some(command)) => {
+                Event::Cmd(u8::from_str_radix(command, 16).map_err(|_| error("invalid command"))?)
+            }
+            (Some("DELAY"), Some(us)) => {
+                Event::Delay(us.parse().map_err(|_| error("invalid delay"))?)
+            }
+            (Some("DATA"), Some(first)) => {
+                let mut data = Vec::new();
+                for token in core::iter::once(first).chain(words.by_ref()) {
+                    let (byte, count) = token.split_once('*').unwrap_or((token, "1"));
+                    let byte = u8::from_str_radix(byte, 16).map_err(|_| error("invalid byte"))?;
+                    let count: usize = count.parse().map_err(|_| error("invalid count"))?;
+                    data.extend(core::iter::repeat_n(byte, count));
+                }
+                if let Some(Event::Data(previous)) = events.last_mut() {
+                    previous.extend(data);
+                    continue;
+                }
+                Event::Data(data)
+            }
+            _ => return Err(error("unknown event")),
+        };
+        if words.next().is_some() {
+            return Err(error("trailing words"));
+        }
+        events.push(event);
+    }
+    Ok(sections)
+}
+
+/// Compares the recorded sections with `tests/golden/<name>.trace`, or rewrites the file if
+/// `UPDATE_GOLDENS` is set
+pub(crate) fn check(name: &str, sections: &[Section]) {
+    let path = format!("{}/tests/golden/{}.trace", env!("CARGO_MANIFEST_DIR"), name);
+    let actual = serialize(sections);
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing, create it with {}=1 cargo test golden",
+            path, UPDATE_ENV
+        )
+    });
+    let expected = parse(&golden).unwrap_or_else(|error| panic!("{}: {}", path, error));
+    if expected.as_slice() == sections {
+        return;
+    }
+
+    // Compare the normalized text to point at the first difference
+    let expected = serialize(&expected);
+    let mismatch = expected
+        .lines()
+        .map(Some)
+        .chain(core::iter::repeat(None))
+        .zip(actual.lines().map(Some).chain(core::iter::repeat(None)))
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual);
+    if let Some((line, (expected, actual))) = mismatch {
+        panic!(
+            "command stream differs from {} in line {}\n  golden: {}\n  actual: {}\n\
+             run {}=1 cargo test golden if the change is intended",
+            path,
+            line + 1,
+            expected.unwrap_or("<end>"),
+            actual.unwrap_or("<end>"),
+            UPDATE_ENV
+        );
+    }
+}
+
+/// Compares a generated text file with its checked-in copy at `path`, relative to the crate,
+/// or rewrites it if `UPDATE_GOLDENS` is set
+pub(crate) fn check_text(path: &str, actual: &str) {
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        checked_in == actual,
+        "{} is stale, run {}=1 cargo test if the change is intended\n{}",
+        path,
+        UPDATE_ENV,
+        actual
+    );
+}
+
+/// Generates a test running the canonical operations of a driver against a [`Trace`] and
+/// comparing them with the golden file `$golden`
+macro_rules! golden_trace {
+    ($name:ident, $epd:ident, $golden:literal) => {
+        #[test]
+        fn $name() {
+            extern crate std;
+            use crate::golden::{check, Trace};
+            use crate::test_utils::{blank_buffer, block_on};
+            use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+            use std::string::ToString;
+
+            let trace = Trace::default();
+            let mut spi = trace.spi();
+            let mut sections = std::vec::Vec::new();
+
+            let mut epd = block_on($epd::new(
+                &mut spi,
+                trace.busy(),
+                trace.dc(),
+                trace.rst(),
+                Some(0),
+            ))
+            .unwrap();
+            sections.push(("init".to_string(), trace.take()));
+
+            let buffer = blank_buffer(epd.width(), epd.height());
+            block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+            sections.push(("update_and_display_frame".to_string(), trace.take()));
+
+            block_on(epd.clear_frame(&mut spi)).unwrap();
+            sections.push(("clear_frame".to_string(), trace.take()));
+
+            block_on(epd.sleep(&mut spi)).unwrap();
+            sections.push(("sleep".to_string(), trace.take()));
+
+            block_on(epd.wake_up(&mut spi)).unwrap();
+            sections.push(("wake_up".to_string(), trace.take()));
+
+            check($golden, &sections);
+        }
+    };
+}
+pub(crate) use golden_trace;
+
+/// Generates a test running the [`demo`](crate::demo) of a driver against a [`Trace`] and
+/// comparing it with the golden file `$golden.demo`
+#[cfg(feature = "demo")]
+macro_rules! golden_demo {
+    ($name:ident, $epd:path, $golden:literal) => {
+        #[test]
+        fn $name() {
+            extern crate std;
+            use crate::golden::{check, Trace};
+            use crate::test_utils::block_on;
+            use crate::traits::WaveshareConstructor;
+            use std::string::ToString;
+            use $epd as Epd;
+
+            let trace = Trace::default();
+            let mut spi = trace.spi();
+
+            let mut epd = block_on(Epd::new(
+                &mut spi,
+                trace.busy(),
+                trace.dc(),
+                trace.rst(),
+                Some(0),
+            ))
+            .unwrap();
+            trace.take();
+
+            block_on(epd.demo(&mut spi, || 0)).unwrap();
+            let sections = [("demo".to_string(), trace.take())];
+
+            check(concat!($golden, ".demo"), &sections);
+        }
+    };
+}
+#[cfg(feature = "demo")]
+pub(crate) use golden_demo;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    #[test]
+    fn format_roundtrip() {
+        let mut data = vec![0x17, 0x17, 0x17];
+        data.extend([0x00; 100]);
+        data.extend(1..21);
+        let sections = vec![
+            (
+                "init".to_string(),
+                vec![
+                    Event::Reset,
+                    Event::Delay(10_000),
+                    Event::Cmd(0x04),
+                    Event::WaitBusy,
+                    Event::Cmd(0x10),
+                    Event::Data(data),
+                ],
+            ),
+            (
+                "sleep".to_string(),
+                vec![Event::Cmd(0x07), Event::Data(vec![0xA5])],
+            ),
+        ];
+
+        let text = serialize(&sections);
+        assert!(text.starts_with("# init\nRESET\nDELAY 10000\nCMD 04\nWAIT_BUSY\nCMD 10\n"));
+        assert!(text.contains("DATA 17 17 17 00*100 01 02 03"));
+        assert!(text.ends_with("\n\n# sleep\nCMD 07\nDATA a5\n"));
+        assert_eq!(parse(&text).unwrap(), sections);
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(parse("CMD 04").is_err());
+        assert!(parse("# init\nCMD 4g").is_err());
+        assert!(parse("# init\nDATA 00*x").is_err());
+        assert!(parse("# init\nWAIT_BUSY now").is_err());
+        assert!(parse("# init\nBEEP").is_err());
+    }
+
+    #[test]
+    fn trace_tells_commands_and_data_apart() {
+        let trace = Trace::default();
+        let (mut spi, mut dc) = (trace.spi(), trace.dc());
+        crate::test_utils::block_on(async {
+            dc.set_low().unwrap();
+            spi.write(&[0x12, 0x13]).await.unwrap();
+            dc.set_high().unwrap();
+            spi.write(&[1]).await.unwrap();
+            spi.write(&[2, 3]).await.unwrap();
+        });
+        assert_eq!(
+            trace.take(),
+            [
+                Event::Cmd(0x12),
+                Event::Cmd(0x13),
+                Event::Data(vec![1, 2, 3])
+            ]
+        );
+    }
+}
@@ -1,11 +1,23 @@
 //! Graphics Support for EPDs
 
-use crate::color::{ColorType, TriColor};
+use crate::color::{Color, ColorType, TriColor};
+use crate::strided::StridedSlice;
+use crate::traits::RefreshLut;
 use core::marker::PhantomData;
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
 
 /// Display rotation, only 90° increments supported
-#[derive(Clone, Copy, Default)]
+///
+/// The rotation turns the drawing clockwise on the panel, looking at the panel with its
+/// buffer origin in the top left corner: with `Rotate90` the top left corner of the drawing
+/// ends up in the top right corner of the panel, the drawing's x axis points down the panel.
+///
+/// Drawing coordinates are the ones given to the [`DrawTarget`], panel coordinates the ones of
+/// the buffer. [`transform_point`](DisplayRotation::transform_point) maps the former to the
+/// latter exactly like drawing does, [`as_transform`](DisplayRotation::as_transform) gives the
+/// same map as an integer matrix for layout code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum DisplayRotation {
     /// No rotation
     #[default]
@@ -18,8 +30,179 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
+impl DisplayRotation {
+    /// The rotation by `degrees` clockwise, negative values turn counterclockwise
+    ///
+    /// Returns `None` unless `degrees` is a multiple of 90.
+    pub const fn from_degrees(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            0 => Some(DisplayRotation::Rotate0),
+            90 => Some(DisplayRotation::Rotate90),
+            180 => Some(DisplayRotation::Rotate180),
+            270 => Some(DisplayRotation::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// The clockwise angle in degrees
+    pub const fn degrees(self) -> u16 {
+        match self {
+            DisplayRotation::Rotate0 => 0,
+            DisplayRotation::Rotate90 => 90,
+            DisplayRotation::Rotate180 => 180,
+            DisplayRotation::Rotate270 => 270,
+        }
+    }
+
+    /// The rotation undoing this one
+    pub const fn inverse(self) -> Self {
+        match self {
+            DisplayRotation::Rotate90 => DisplayRotation::Rotate270,
+            DisplayRotation::Rotate270 => DisplayRotation::Rotate90,
+            rotation => rotation,
+        }
+    }
+
+    /// Size of the drawing area on a panel of `panel_size`
+    pub const fn rotated_size(self, panel_size: Size) -> Size {
+        match self {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => panel_size,
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(panel_size.height, panel_size.width)
+            }
+        }
+    }
+
+    /// The map from drawing to panel coordinates on a panel of `panel_size`
+    pub const fn as_transform(self, panel_size: Size) -> RotationTransform {
+        // as i32 = never use more than 2 billion pixel per line or per column
+        let (right, bottom) = (panel_size.width as i32 - 1, panel_size.height as i32 - 1);
+        let (matrix, offset) = match self {
+            DisplayRotation::Rotate0 => ([[1, 0], [0, 1]], Point::new(0, 0)),
+            DisplayRotation::Rotate90 => ([[0, -1], [1, 0]], Point::new(right, 0)),
+            DisplayRotation::Rotate180 => ([[-1, 0], [0, -1]], Point::new(right, bottom)),
+            DisplayRotation::Rotate270 => ([[0, 1], [-1, 0]], Point::new(0, bottom)),
+        };
+        RotationTransform { matrix, offset }
+    }
+
+    /// Panel coordinates of the drawing coordinates `point`
+    pub const fn transform_point(self, point: Point, panel_size: Size) -> Point {
+        self.as_transform(panel_size).apply(point)
+    }
+
+    /// Drawing coordinates of the panel coordinates `point`
+    pub const fn inverse_point(self, point: Point, panel_size: Size) -> Point {
+        self.as_transform(panel_size).inverse().apply(point)
+    }
+
+    /// Panel area covered by the drawing area `rect`
+    pub fn transform_rect(self, rect: Rectangle, panel_size: Size) -> Rectangle {
+        let top_left = self.transform_point(rect.top_left, panel_size);
+        let size = self.rotated_size(rect.size);
+        match rect.bottom_right() {
+            Some(bottom_right) => {
+                let bottom_right = self.transform_point(bottom_right, panel_size);
+                Rectangle::new(
+                    Point::new(
+                        top_left.x.min(bottom_right.x),
+                        top_left.y.min(bottom_right.y),
+                    ),
+                    size,
+                )
+            }
+            None => Rectangle::new(top_left, size),
+        }
+    }
+
+    /// Drawing area covering the panel area `rect`
+    pub fn inverse_rect(self, rect: Rectangle, panel_size: Size) -> Rectangle {
+        self.inverse()
+            .transform_rect(rect, self.rotated_size(panel_size))
+    }
+}
+
+/// Integer affine map of a [`DisplayRotation`]: `panel = matrix * drawing + offset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationTransform {
+    /// Rows of the 2x2 matrix, every entry is -1, 0 or 1
+    pub matrix: [[i32; 2]; 2],
+    /// Panel coordinates of the drawing origin
+    pub offset: Point,
+}
+
+impl RotationTransform {
+    /// Maps `point`
+    pub const fn apply(&self, point: Point) -> Point {
+        let [[a, b], [c, d]] = self.matrix;
+        Point::new(
+            a * point.x + b * point.y + self.offset.x,
+            c * point.x + d * point.y + self.offset.y,
+        )
+    }
+
+    /// The map in the other direction
+    pub const fn inverse(&self) -> Self {
+        // a rotation matrix is orthogonal, its inverse is the transpose
+        let [[a, b], [c, d]] = self.matrix;
+        let offset = self.offset;
+        RotationTransform {
+            matrix: [[a, c], [b, d]],
+            offset: Point::new(
+                -(a * offset.x + c * offset.y),
+                -(b * offset.x + d * offset.y),
+            ),
+        }
+    }
+}
+
+/// A rectangular area of a display buffer, in unrotated buffer coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayRegion {
+    /// Left edge in pixel
+    pub x: u32,
+    /// Top edge in pixel
+    pub y: u32,
+    /// Width in pixel
+    pub width: u32,
+    /// Height in pixel
+    pub height: u32,
+}
+
+impl DisplayRegion {
+    /// Create a new region from its top left corner and its size
+    pub const fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Direction of the stripes drawn by [`Display::pattern_diagonal_stripe`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StripeAngle {
+    /// Stripes rising from the bottom left to the top right, `/`
+    Deg45,
+    /// Stripes falling from the top left to the bottom right, `\`
+    Deg135,
+}
+
+/// Bits of a byte full of `color` pixels, for every plane
+fn byte_pattern<COLOR: ColorType>(color: COLOR, bwrbit: bool) -> [u8; 2] {
+    let mut pattern = [0u8; 2];
+    for pixel in 0..(8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32 {
+        let (_, bits) = color.bitmask(bwrbit, pixel);
+        pattern[0] |= bits as u8;
+        pattern[1] |= (bits >> 8) as u8;
+    }
+    pattern
+}
+
 /// count the number of bytes per line knowing that it may contains padding bits
-const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
+pub(crate) const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
     // round to upper 8 bit count
     (width as usize * bits_per_pixel + 7) / 8
 }
@@ -118,10 +301,7 @@ impl<
     > OriginDimensions for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
 {
     fn size(&self) -> Size {
-        match self.rotation {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(WIDTH, HEIGHT),
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(HEIGHT, WIDTH),
-        }
+        self.rotation.rotated_size(Size::new(WIDTH, HEIGHT))
     }
 }
 
@@ -138,6 +318,60 @@ impl<
         &self.buffer
     }
 
+    /// Borrows the rows of `region` from the buffer, e.g. for
+    /// [`update_partial_frame_strided`](crate::traits::WaveshareDisplay::update_partial_frame_strided)
+    ///
+    /// The region is in unrotated buffer coordinates and widened to whole bytes. Tricolor
+    /// displays give the window of their black/white plane. Returns `None` if the region is
+    /// empty or doesn't fit the display.
+    pub fn window(&self, region: DisplayRegion) -> Option<StridedSlice<'_>> {
+        let bits = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        if region.width == 0
+            || region.height == 0
+            || region.x.checked_add(region.width)? > WIDTH
+            || region.y.checked_add(region.height)? > HEIGHT
+        {
+            return None;
+        }
+        let start = region.x as usize * bits / 8;
+        let end = line_bytes(region.x + region.width, bits);
+        StridedSlice::window(
+            &self.buffer,
+            line_bytes(WIDTH, bits),
+            start,
+            region.y as usize,
+            end - start,
+            region.height as usize,
+        )
+        .ok()
+    }
+
+    /// Iterates over the pixels as `(x, y, color)`, row by row
+    ///
+    /// The coordinates are those of the unrotated buffer, the pixels are decoded on demand.
+    pub fn iter_pixels(&self) -> PixelIter<'_, COLOR> {
+        PixelIter::new(&self.buffer, WIDTH, HEIGHT)
+    }
+
+    /// Same as [`buffer`](Display::buffer), the bytes as expected by the display
+    pub fn to_raw_buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Create a display from bytes in the format expected by the display,
+    /// e.g. a buffer previously returned by [`to_raw_buffer`](Display::to_raw_buffer)
+    pub fn from_raw_buffer(data: &[u8]) -> Result<Self, LengthError> {
+        if data.len() != BYTECOUNT {
+            return Err(LengthError {
+                expected: BYTECOUNT,
+                actual: data.len(),
+            });
+        }
+        let mut display = Self::default();
+        display.buffer.copy_from_slice(data);
+        Ok(display)
+    }
+
     /// Set the display rotation.
     ///
     /// This only concerns future drawing made to it. Anything aready drawn
@@ -152,6 +386,10 @@ impl<
     }
 
     /// Set a specific pixel color on this display
+    ///
+    /// Pixels outside of the display are ignored. So are pixels beyond the end of the buffer if
+    /// `BYTECOUNT` is too small for the display, the [`DrawTarget`] implementation can't report
+    /// it either, its error is [`Infallible`](core::convert::Infallible).
     pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
         set_pixel(
             &mut self.buffer,
@@ -162,6 +400,448 @@ impl<
             pixel,
         );
     }
+
+    /// Invert every pixel inside `region`
+    ///
+    /// Works on whole bytes where possible, so this is much faster than redrawing the
+    /// region pixel by pixel. The region is clipped to the display and is not affected
+    /// by the current rotation. Tricolor displays only get their black/white plane inverted.
+    pub fn flip_bits_in_region(&mut self, region: DisplayRegion) {
+        flip_bits_in_region(
+            &mut self.buffer,
+            WIDTH,
+            HEIGHT,
+            COLOR::BITS_PER_PIXEL_PER_BUFFER,
+            region,
+        );
+    }
+
+    /// Fill the display with diagonal stripes of `stripe_width` pixels, alternating between
+    /// `color_a` and `color_b`
+    ///
+    /// Useful as test pattern for checking the uniformity of a display. The pattern follows the
+    /// current rotation and is much faster than drawing the stripes with embedded-graphics
+    /// primitives. A `stripe_width` of 0 leaves the display unchanged.
+    pub fn pattern_diagonal_stripe(
+        &mut self,
+        stripe_width: u32,
+        angle: StripeAngle,
+        color_a: COLOR,
+        color_b: COLOR,
+    ) {
+        if stripe_width == 0 {
+            return;
+        }
+        let period = stripe_width.saturating_mul(2);
+        let size = self.size();
+        for y in 0..size.height {
+            // Position of the first pixel of this scanline inside the period
+            let offset = match angle {
+                StripeAngle::Deg45 => y,
+                StripeAngle::Deg135 => size.height - 1 - y,
+            } % period;
+            for x in 0..size.width {
+                let color = if (x % period + offset) % period < stripe_width {
+                    color_a
+                } else {
+                    color_b
+                };
+                self.set_pixel(Pixel(Point::new(x as i32, y as i32), color));
+            }
+        }
+    }
+
+    /// Fill the display with a horizontal gradient from `left` to `right`
+    ///
+    /// The share of `left` pixels falls from all of them in the first column to none in the last
+    /// one, spread with a 4×4 ordered dither. Like the stripes, the gradient follows the current
+    /// rotation.
+    pub fn fill_gradient_horizontal(&mut self, left: COLOR, right: COLOR) {
+        let size = self.size();
+        let last = u64::from(size.width.saturating_sub(1));
+        for y in 0..size.height {
+            let row = &BAYER_4X4[y as usize % 4];
+            for x in 0..size.width {
+                // compare the threshold, centered in its 1/16 step, with the share of `left`
+                let threshold = u64::from(row[x as usize % 4]) * 2 + 1;
+                let color = if last == 0 || threshold * last < 32 * (last - u64::from(x)) {
+                    left
+                } else {
+                    right
+                };
+                self.set_pixel(Pixel(Point::new(x as i32, y as i32), color));
+            }
+        }
+    }
+
+    /// Copy `src_region` of `src` into this display, with its top left corner at `dst_x`, `dst_y`
+    ///
+    /// Allows keeping sprites in separate, smaller displays. Coordinates are unrotated buffer
+    /// coordinates, like for [`DisplayRegion`], and pixels don't need to be byte aligned. The
+    /// region is clipped to both displays. Every buffer plane is copied, so tricolor pixels keep
+    /// their chromatic bit.
+    pub fn paste_at<const SRC_WIDTH: u32, const SRC_HEIGHT: u32, const SRC_BYTECOUNT: usize>(
+        &mut self,
+        src: &Display<SRC_WIDTH, SRC_HEIGHT, BWRBIT, SRC_BYTECOUNT, COLOR>,
+        dst_x: u32,
+        dst_y: u32,
+        src_region: DisplayRegion,
+    ) {
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let width = src_region
+            .width
+            .min(SRC_WIDTH.saturating_sub(src_region.x))
+            .min(WIDTH.saturating_sub(dst_x));
+        let height = src_region
+            .height
+            .min(SRC_HEIGHT.saturating_sub(src_region.y))
+            .min(HEIGHT.saturating_sub(dst_y));
+
+        let src_line = line_bytes(SRC_WIDTH, bits_per_pixel);
+        let dst_line = line_bytes(WIDTH, bits_per_pixel);
+        let src_plane = src.buffer.len() / COLOR::BUFFER_COUNT;
+        let dst_plane = self.buffer.len() / COLOR::BUFFER_COUNT;
+        let src_first_bit = src_region.x as usize * bits_per_pixel;
+        let dst_first_bit = dst_x as usize * bits_per_pixel;
+
+        for plane in 0..COLOR::BUFFER_COUNT {
+            for row in 0..height as usize {
+                let src_start = plane * src_plane + (src_region.y as usize + row) * src_line;
+                let dst_start = plane * dst_plane + (dst_y as usize + row) * dst_line;
+                let (Some(src_row), Some(dst_row)) = (
+                    src.buffer.get(src_start..src_start + src_line),
+                    self.buffer.get_mut(dst_start..dst_start + dst_line),
+                ) else {
+                    // One of the buffers is too small for its display
+                    return;
+                };
+                for bit in 0..width as usize * bits_per_pixel {
+                    let (src_bit, dst_bit) = (src_first_bit + bit, dst_first_bit + bit);
+                    let mask = 0x80 >> (dst_bit % 8);
+                    if src_row[src_bit / 8] & (0x80 >> (src_bit % 8)) != 0 {
+                        dst_row[dst_bit / 8] |= mask;
+                    } else {
+                        dst_row[dst_bit / 8] &= !mask;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copy this display rotated by 90° clockwise into `dst`, without allocating
+    ///
+    /// The pixel at `x`, `y` of this buffer ends up at `HEIGHT - 1 - y`, `x` of `dst`, the same
+    /// mapping as [`DisplayRotation::Rotate90`]: a frame drawn unrotated here looks like the
+    /// same frame drawn with `Rotate90` on `dst`. Every buffer plane is copied, the rotation
+    /// setting of `dst` is kept.
+    pub fn transpose_into<const DST_BYTECOUNT: usize>(
+        &self,
+        dst: &mut Display<HEIGHT, WIDTH, BWRBIT, DST_BYTECOUNT, COLOR>,
+    ) {
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let src_line = line_bytes(WIDTH, bits_per_pixel);
+        let dst_line = line_bytes(HEIGHT, bits_per_pixel);
+        let src_plane = self.buffer.len() / COLOR::BUFFER_COUNT;
+        let dst_plane = dst.buffer.len() / COLOR::BUFFER_COUNT;
+        if src_line * HEIGHT as usize > src_plane || dst_line * WIDTH as usize > dst_plane {
+            // One of the buffers is too small for its display
+            return;
+        }
+
+        for plane in 0..COLOR::BUFFER_COUNT {
+            let src = &self.buffer[plane * src_plane..];
+            let dst = &mut dst.buffer[plane * dst_plane..];
+            for y in 0..HEIGHT as usize {
+                let dst_x = HEIGHT as usize - 1 - y;
+                for x in 0..WIDTH as usize {
+                    for bit in 0..bits_per_pixel {
+                        let src_bit = x * bits_per_pixel + bit;
+                        let dst_bit = dst_x * bits_per_pixel + bit;
+                        let index = x * dst_line + dst_bit / 8;
+                        let mask = 0x80 >> (dst_bit % 8);
+                        if src[y * src_line + src_bit / 8] & (0x80 >> (src_bit % 8)) != 0 {
+                            dst[index] |= mask;
+                        } else {
+                            dst[index] &= !mask;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw `other` onto this display with its top left corner at `x`, `y`, skipping every pixel
+    /// of `other` that has the `transparent` color
+    ///
+    /// Useful for compositing sprites that aren't rectangular. Like [`paste_at`](Display::paste_at)
+    /// this works on unrotated buffer coordinates and clips `other` to the display, but it
+    /// compares and copies whole bytes with bit masks instead of single pixels.
+    pub fn overlay_transparent<
+        const SRC_WIDTH: u32,
+        const SRC_HEIGHT: u32,
+        const SRC_BYTECOUNT: usize,
+    >(
+        &mut self,
+        other: &Display<SRC_WIDTH, SRC_HEIGHT, BWRBIT, SRC_BYTECOUNT, COLOR>,
+        x: u32,
+        y: u32,
+        transparent: COLOR,
+    ) {
+        let bits_per_pixel = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+        let width = SRC_WIDTH.min(WIDTH.saturating_sub(x));
+        let height = SRC_HEIGHT.min(HEIGHT.saturating_sub(y));
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let pattern = byte_pattern(transparent, BWRBIT);
+
+        let src_line = line_bytes(SRC_WIDTH, bits_per_pixel);
+        let dst_line = line_bytes(WIDTH, bits_per_pixel);
+        let src_plane = other.buffer.len() / COLOR::BUFFER_COUNT;
+        let dst_plane = self.buffer.len() / COLOR::BUFFER_COUNT;
+        let first_bit = x as usize * bits_per_pixel;
+        let end_bit = first_bit + width as usize * bits_per_pixel;
+        let (first_byte, last_byte) = (first_bit / 8, (end_bit - 1) / 8);
+
+        for row in 0..height as usize {
+            for byte in first_byte..=last_byte {
+                // Bits of this byte covered by `other`
+                let mut covered = 0xFF;
+                if byte == first_byte {
+                    covered &= 0xFFu8 >> (first_bit % 8);
+                }
+                if byte == last_byte {
+                    covered &= 0xFFu8 << ((8 - end_bit % 8) % 8);
+                }
+
+                // The source bits ending up in this byte, as the source rows aren't aligned
+                let src_bit = (byte * 8) as isize - first_bit as isize;
+                let mut src = [0u8; 2];
+                let mut differs = 0;
+                for (plane, src) in src.iter_mut().enumerate().take(COLOR::BUFFER_COUNT) {
+                    let start = plane * src_plane + row * src_line;
+                    let Some(src_row) = other.buffer.get(start..start + src_line) else {
+                        // The buffer is too small for the display
+                        return;
+                    };
+                    *src = read_bits(src_row, src_bit);
+                    differs |= *src ^ pattern[plane];
+                }
+
+                // A pixel is opaque if any of its bits differs from the transparent color
+                let pixel_mask = 0xFFu8 >> (8 - bits_per_pixel);
+                let mut opaque = 0;
+                for shift in (0..8).step_by(bits_per_pixel) {
+                    if differs & (pixel_mask << shift) != 0 {
+                        opaque |= pixel_mask << shift;
+                    }
+                }
+                let opaque = opaque & covered;
+
+                for (plane, src) in src.iter().enumerate().take(COLOR::BUFFER_COUNT) {
+                    let index = plane * dst_plane + (y as usize + row) * dst_line + byte;
+                    let Some(dst) = self.buffer.get_mut(index) else {
+                        return;
+                    };
+                    *dst = *dst & !opaque | src & opaque;
+                }
+            }
+        }
+    }
+
+    /// Creates a bigger display filled with `fill`, with this one copied to `left`, `top`
+    ///
+    /// Useful for centering a QR code or an icon on a panel with another aspect ratio. The
+    /// padding is in unrotated buffer coordinates like for [`paste_at`](Display::paste_at), the
+    /// new display gets the rotation of this one.
+    ///
+    /// # Panics
+    ///
+    /// If `NEW_WIDTH` and `NEW_HEIGHT` aren't the size of this display plus the padding.
+    pub fn with_padding<const NEW_WIDTH: u32, const NEW_HEIGHT: u32, const NEW_BYTECOUNT: usize>(
+        &self,
+        left: u32,
+        right: u32,
+        top: u32,
+        bottom: u32,
+        fill: COLOR,
+    ) -> Display<NEW_WIDTH, NEW_HEIGHT, BWRBIT, NEW_BYTECOUNT, COLOR> {
+        assert_eq!(left + WIDTH + right, NEW_WIDTH, "padded width");
+        assert_eq!(top + HEIGHT + bottom, NEW_HEIGHT, "padded height");
+
+        let mut padded = Display::<NEW_WIDTH, NEW_HEIGHT, BWRBIT, NEW_BYTECOUNT, COLOR>::default();
+        let pattern = byte_pattern(fill, BWRBIT);
+        let plane_len = NEW_BYTECOUNT / COLOR::BUFFER_COUNT;
+        for (plane, bytes) in padded
+            .buffer
+            .chunks_mut(plane_len.max(1))
+            .enumerate()
+            .take(COLOR::BUFFER_COUNT)
+        {
+            bytes.fill(pattern[plane]);
+        }
+        padded.paste_at(self, left, top, DisplayRegion::new(0, 0, WIDTH, HEIGHT));
+        padded.rotation = self.rotation;
+        padded
+    }
+
+    /// Draw an analog clock showing `hour`:`minute`:`second`, centered at `center_x`, `center_y`
+    ///
+    /// The face is a circle of `radius` with a tick mark at every hour, the hour, minute and
+    /// second hands reach to half, four fifths and nine tenths of the radius. Everything is
+    /// drawn in `hand_color` straight into the buffer, without embedded-graphics primitives,
+    /// and follows the current rotation. Hands move in steps of a sixtieth of a turn, the hour
+    /// hand advances every 12 minutes. Parts outside of the display are clipped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_clock_face(
+        &mut self,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        center_x: u32,
+        center_y: u32,
+        radius: u32,
+        hand_color: COLOR,
+    ) {
+        let center = Point::new(center_x as i32, center_y as i32);
+        let radius = radius.min(i32::MAX as u32 / 1024) as i32;
+        let mut plot = |point| self.set_pixel(Pixel(point, hand_color));
+
+        circle(&mut plot, center, radius);
+        for tick in (0..60).step_by(5) {
+            let from = clock_point(center, tick, radius * 17 / 20);
+            line(&mut plot, from, clock_point(center, tick, radius));
+        }
+
+        let hour = u32::from(hour % 12) * 5 + u32::from(minute % 60) / 12;
+        line(&mut plot, center, clock_point(center, hour, radius / 2));
+        let minute = clock_point(center, u32::from(minute % 60), radius * 4 / 5);
+        line(&mut plot, center, minute);
+        let second = clock_point(center, u32::from(second % 60), radius * 9 / 10);
+        line(&mut plot, center, second);
+    }
+
+    /// Draw a rectangle with corners rounded to `radius`, its top left corner at `x`, `y`
+    ///
+    /// The inside is filled with `fill` and the outline, one pixel wide, drawn with `stroke`,
+    /// either may be left out. The corners are midpoint circle arcs, the radius is limited to
+    /// half of the shorter side. Drawn straight into the buffer like
+    /// [`render_clock_face`](Display::render_clock_face), rows that stay rows of the buffer in
+    /// the current rotation are filled a byte at a time. Parts outside of the display are clipped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rounded_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        radius: u32,
+        fill: Option<COLOR>,
+        stroke: Option<COLOR>,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let clamp = |value: u32| value.min(i32::MAX as u32 / 2) as i32;
+        let (left, top) = (clamp(x), clamp(y));
+        let (right, bottom) = (left + clamp(width) - 1, top + clamp(height) - 1);
+        let radius = clamp(radius.min((width - 1) / 2).min((height - 1) / 2));
+        // centers of the corner arcs
+        let (inner_left, inner_right) = (left + radius, right - radius);
+        let (inner_top, inner_bottom) = (top + radius, bottom - radius);
+        // rows between the corners, clipped to the display
+        let last_row = self.size().height as i32 - 1;
+        let sides = inner_top.max(0)..=inner_bottom.min(last_row);
+
+        if let Some(color) = fill {
+            midpoint_octant(radius, |dx, dy| {
+                for (row, half) in [(dy, dx), (dx, dy)] {
+                    let (from, to) = (inner_left - half, inner_right + half);
+                    self.fill_span(inner_top - row, from, to, color);
+                    self.fill_span(inner_bottom + row, from, to, color);
+                }
+            });
+            for row in sides.clone() {
+                self.fill_span(row, left, right, color);
+            }
+        }
+
+        if let Some(color) = stroke {
+            self.fill_span(top, inner_left, inner_right, color);
+            self.fill_span(bottom, inner_left, inner_right, color);
+            for row in sides {
+                self.set_pixel(Pixel(Point::new(left, row), color));
+                self.set_pixel(Pixel(Point::new(right, row), color));
+            }
+            midpoint_octant(radius, |dx, dy| {
+                for (a, b) in [(dx, dy), (dy, dx)] {
+                    for point in [
+                        Point::new(inner_left - a, inner_top - b),
+                        Point::new(inner_right + a, inner_top - b),
+                        Point::new(inner_left - a, inner_bottom + b),
+                        Point::new(inner_right + a, inner_bottom + b),
+                    ] {
+                        self.set_pixel(Pixel(point, color));
+                    }
+                }
+            });
+        }
+    }
+
+    // Sets the pixels `from..=to` of row `y` in drawing coordinates, clipped to the display.
+    // Whole bytes are written at once if the row is a row of the buffer.
+    fn fill_span(&mut self, y: i32, from: i32, to: i32, color: COLOR) {
+        let size = self.size();
+        let (from, to) = (from.max(0), to.min(size.width as i32 - 1));
+        if y < 0 || y >= size.height as i32 || from > to {
+            return;
+        }
+
+        let panel = Size::new(WIDTH, HEIGHT);
+        let a = self.rotation.transform_point(Point::new(from, y), panel);
+        let b = self.rotation.transform_point(Point::new(to, y), panel);
+        let per_byte = (8 / COLOR::BITS_PER_PIXEL_PER_BUFFER) as u32;
+        let (start, end) = (a.x.min(b.x) as u32, a.x.max(b.x) as u32 + 1);
+        let (whole_start, whole_end) =
+            (start.next_multiple_of(per_byte), end / per_byte * per_byte);
+        if a.y != b.y || whole_start >= whole_end {
+            // a column of the buffer, or no whole byte
+            for x in from..=to {
+                self.set_pixel(Pixel(Point::new(x, y), color));
+            }
+            return;
+        }
+
+        for x in (start..whole_start).chain(whole_end..end) {
+            set_pixel(
+                &mut self.buffer,
+                WIDTH,
+                HEIGHT,
+                DisplayRotation::Rotate0,
+                BWRBIT,
+                Pixel(Point::new(x as i32, a.y), color),
+            );
+        }
+        let bits = (0..per_byte).fold(0, |bits, pos| bits | color.bitmask(BWRBIT, pos).1);
+        let row = a.y as usize * line_bytes(WIDTH, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+        let bytes = row + (whole_start / per_byte) as usize..row + (whole_end / per_byte) as usize;
+        let plane_len = self.buffer.len() / COLOR::BUFFER_COUNT;
+        for (plane, value) in [bits as u8, (bits >> 8) as u8]
+            .into_iter()
+            .take(COLOR::BUFFER_COUNT)
+            .enumerate()
+        {
+            let offset = plane * plane_len;
+            // a buffer that is too small for the display isn't written
+            if let Some(bytes) = self
+                .buffer
+                .get_mut(bytes.start + offset..bytes.end + offset)
+            {
+                bytes.fill(value);
+            }
+        }
+    }
 }
 
 /// Some Tricolor specifics
@@ -177,6 +857,227 @@ impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: u
     pub fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.buffer.len() / 2..]
     }
+
+    /// [`Display::erode`] for the black/white plane, the chromatic one stays as it is
+    pub fn erode(&mut self, size: u32) {
+        let plane = self.buffer.len() / 2;
+        morph(&mut self.buffer[..plane], WIDTH, HEIGHT, size, true);
+    }
+
+    /// [`Display::dilate`] for the black/white plane, the chromatic one stays as it is
+    pub fn dilate(&mut self, size: u32) {
+        let plane = self.buffer.len() / 2;
+        morph(&mut self.buffer[..plane], WIDTH, HEIGHT, size, false);
+    }
+}
+
+/// Some Black/White specifics
+impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
+    Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+{
+    /// Number of white and black pixels, as `[white, black]`
+    ///
+    /// Works on whole bytes, the padding bits at the end of the lines aren't counted.
+    pub fn histogram(&self) -> [u32; 2] {
+        let line = line_bytes(WIDTH, 1);
+        if line == 0 {
+            return [0, 0];
+        }
+        let last_mask = 0xFFu8 << (line as u32 * 8 - WIDTH);
+        let (mut white, mut black) = (0, 0);
+        for row in self.buffer.chunks_exact(line).take(HEIGHT as usize) {
+            let (last, bytes) = row.split_last().unwrap_or((&0, &[]));
+            for byte in bytes {
+                white += byte.count_ones();
+                black += byte.count_zeros();
+            }
+            white += (last & last_mask).count_ones();
+            black += (!last & last_mask).count_ones();
+        }
+        [white, black]
+    }
+
+    /// Shrink the black areas with a square of `size` × `size` pixels, removing black specks
+    /// and thin lines
+    ///
+    /// A pixel stays black only if every pixel of the square around it is black, pixels outside
+    /// of the display don't count. Uneven sizes center the square on the pixel, even ones reach
+    /// a pixel further to the right and down in buffer coordinates. Sizes below 2 change nothing.
+    pub fn erode(&mut self, size: u32) {
+        morph(&mut self.buffer, WIDTH, HEIGHT, size, true);
+    }
+
+    /// Grow the black areas with a square of `size` × `size` pixels, closing white specks and
+    /// gaps, the opposite of [`erode`](Display::erode)
+    pub fn dilate(&mut self, size: u32) {
+        morph(&mut self.buffer, WIDTH, HEIGHT, size, false);
+    }
+
+    /// Fill a `width` × `height` area at `x_offset`, `y_offset` from an 8 bit grayscale image
+    ///
+    /// `src` holds one byte per pixel, row by row, 0 being black. Pixels darker than `threshold`
+    /// become black, all others white, like [`Quantize::Threshold`](crate::convert::Quantize)
+    /// on the host. Offsets are unrotated buffer coordinates, like for
+    /// [`paste_at`](Display::paste_at). Returns an error without drawing anything if `src` has
+    /// the wrong length or the area doesn't fit the display.
+    pub fn threshold_from_grayscale(
+        &mut self,
+        src: &[u8],
+        width: u32,
+        height: u32,
+        threshold: u8,
+        x_offset: u32,
+        y_offset: u32,
+    ) -> Result<(), &'static str> {
+        if (width as usize).checked_mul(height as usize) != Some(src.len()) {
+            return Err("source length doesn't match width × height");
+        }
+        if x_offset
+            .checked_add(width)
+            .is_none_or(|right| right > WIDTH)
+            || y_offset
+                .checked_add(height)
+                .is_none_or(|bottom| bottom > HEIGHT)
+        {
+            return Err("source doesn't fit the display");
+        }
+
+        let line = line_bytes(WIDTH, 1);
+        for (row, pixels) in src.chunks_exact(width.max(1) as usize).enumerate() {
+            let start = (y_offset as usize + row) * line;
+            for (column, &gray) in pixels.iter().enumerate() {
+                let x = x_offset as usize + column;
+                // the first pixel of a byte is its most significant bit, white is 1
+                let mask = 0x80 >> (x % 8);
+                if gray < threshold {
+                    self.buffer[start + x / 8] &= !mask;
+                } else {
+                    self.buffer[start + x / 8] |= mask;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Interop with the framebuffer of embedded-graphics
+///
+/// The framebuffer holds a copy of the unrotated buffer. [`BinaryColor::On`] is black, like in
+/// the conversion from [`BinaryColor`] to [`Color`].
+///
+/// [`BinaryColor`]: embedded_graphics::pixelcolor::BinaryColor
+/// [`BinaryColor::On`]: embedded_graphics::pixelcolor::BinaryColor::On
+#[cfg(feature = "eg-framebuffer")]
+impl<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize>
+    Display<WIDTH, HEIGHT, false, BYTECOUNT, Color>
+{
+    /// Copy the content into an embedded-graphics [`Framebuffer`](embedded_graphics::framebuffer::Framebuffer)
+    ///
+    /// `W` and `H` have to be the width and height of the display.
+    pub fn to_framebuffer<const W: usize, const H: usize>(
+        &self,
+    ) -> embedded_graphics::framebuffer::Framebuffer<
+        embedded_graphics::pixelcolor::BinaryColor,
+        embedded_graphics::pixelcolor::raw::RawU1,
+        embedded_graphics::pixelcolor::raw::BigEndian,
+        W,
+        H,
+        BYTECOUNT,
+    > {
+        const { assert!(W == WIDTH as usize && H == HEIGHT as usize) };
+        let mut framebuffer = embedded_graphics::framebuffer::Framebuffer::new();
+        for (target, byte) in framebuffer.data_mut().iter_mut().zip(self.buffer) {
+            *target = !byte;
+        }
+        framebuffer
+    }
+
+    /// Replace the content with the one of an embedded-graphics framebuffer, e.g. one created
+    /// by [`to_framebuffer`](Display::to_framebuffer)
+    pub fn copy_from_framebuffer<const W: usize, const H: usize>(
+        &mut self,
+        framebuffer: &embedded_graphics::framebuffer::Framebuffer<
+            embedded_graphics::pixelcolor::BinaryColor,
+            embedded_graphics::pixelcolor::raw::RawU1,
+            embedded_graphics::pixelcolor::raw::BigEndian,
+            W,
+            H,
+            BYTECOUNT,
+        >,
+    ) {
+        const { assert!(W == WIDTH as usize && H == HEIGHT as usize) };
+        for (target, byte) in self.buffer.iter_mut().zip(framebuffer.data()) {
+            *target = !byte;
+        }
+    }
+}
+
+/// The buffer passed to [`Display::from_raw_buffer`] doesn't have the size of the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthError {
+    /// Length needed by the display
+    pub expected: usize,
+    /// Length of the given buffer
+    pub actual: usize,
+}
+
+/// Iterator over the pixels of a display, see [`Display::iter_pixels`]
+#[derive(Debug, Clone)]
+pub struct PixelIter<'a, COLOR> {
+    buffer: &'a [u8],
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    color: PhantomData<COLOR>,
+}
+
+impl<'a, COLOR: ColorType> PixelIter<'a, COLOR> {
+    fn new(buffer: &'a [u8], width: u32, height: u32) -> Self {
+        PixelIter {
+            buffer,
+            width,
+            height,
+            x: 0,
+            y: 0,
+            color: PhantomData,
+        }
+    }
+}
+
+impl<COLOR: ColorType> Iterator for PixelIter<'_, COLOR> {
+    type Item = (u32, u32, COLOR);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.height || self.width == 0 {
+            return None;
+        }
+        let (x, y) = (self.x, self.y);
+        let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
+            + y as usize * line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+        let mut bits = u16::from(*self.buffer.get(index)?);
+        if COLOR::BUFFER_COUNT == 2 {
+            // the chromatic plane follows the black/white one
+            bits |= u16::from(*self.buffer.get(index + self.buffer.len() / 2)?) << 8;
+        }
+
+        self.x += 1;
+        if self.x == self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some((x, y, COLOR::from_bits(bits, x)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = if self.width == 0 {
+            0
+        } else {
+            (self.height.saturating_sub(self.y) as usize * self.width as usize)
+                .saturating_sub(self.x as usize)
+        };
+        (0, Some(remaining))
+    }
 }
 
 /// Same as `Display`, except that its characteristics are defined at runtime.
@@ -210,14 +1111,8 @@ impl<'a, COLOR: ColorType + PixelColor> DrawTarget for VarDisplay<'a, COLOR> {
 /// For use with embedded_grahics
 impl<'a, COLOR: ColorType + PixelColor> OriginDimensions for VarDisplay<'a, COLOR> {
     fn size(&self) -> Size {
-        match self.rotation {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-                Size::new(self.width, self.height)
-            }
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-                Size::new(self.height, self.width)
-            }
-        }
+        self.rotation
+            .rotated_size(Size::new(self.width, self.height))
     }
 }
 
@@ -281,7 +1176,7 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
         self.rotation
     }
 
-    /// Set a specific pixel color on this display
+    /// Set a specific pixel color on this display, pixels outside of the display are ignored
     pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
         let size = self.buffer_size();
         set_pixel(
@@ -293,6 +1188,24 @@ impl<'a, COLOR: ColorType + PixelColor> VarDisplay<'a, COLOR> {
             pixel,
         );
     }
+
+    /// Iterates over the pixels as `(x, y, color)`, see [`Display::iter_pixels`]
+    pub fn iter_pixels(&self) -> PixelIter<'_, COLOR> {
+        let size = self.buffer_size();
+        PixelIter::new(&self.buffer[..size], self.width, self.height)
+    }
+
+    /// Invert every pixel inside `region`, see [`Display::flip_bits_in_region`]
+    pub fn flip_bits_in_region(&mut self, region: DisplayRegion) {
+        let size = self.buffer_size();
+        flip_bits_in_region(
+            &mut self.buffer[..size],
+            self.width,
+            self.height,
+            COLOR::BITS_PER_PIXEL_PER_BUFFER,
+            region,
+        );
+    }
 }
 
 /// Some Tricolor specifics
@@ -309,45 +1222,386 @@ impl<'a> VarDisplay<'a, TriColor> {
 }
 
 // This is a function to share code between `Display` and `VarDisplay`
-// It sets a specific pixel in a buffer to a given color.
-// The big number of parameters is due to the fact that it is an internal function to both
-// strctures.
-fn set_pixel<COLOR: ColorType + PixelColor>(
-    buffer: &mut [u8],
-    width: u32,
-    height: u32,
-    rotation: DisplayRotation,
-    bwrbit: bool,
-    pixel: Pixel<COLOR>,
-) {
-    let Pixel(point, color) = pixel;
+/// Properties of a black/white frame that decide how well it tolerates a quick refresh
+///
+/// Text and line art look fine with the quick look-up tables, large filled areas and dithered
+/// images end up with uneven grey patches. See [`analyze_frame`] and [`RefreshPolicy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameCharacter {
+    /// Share of black pixels, in percent
+    pub ink_coverage_pct: u8,
+    /// Longest horizontal run of black pixels
+    pub largest_filled_run: u32,
+    /// Share of horizontally adjacent pixel pairs with different colors, in percent
+    pub edge_density: u8,
+}
 
-    // final coordinates
-    let (x, y) = match rotation {
-        // as i32 = never use more than 2 billion pixel per line or per column
-        DisplayRotation::Rotate0 => (point.x, point.y),
-        DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
-        DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
-        DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
-    };
+impl FrameCharacter {
+    /// Analyzes a packed 1 bit per pixel `buffer` of a `width` x `height` display
+    ///
+    /// Works on 32 pixels at a time, padding bits at the end of the lines are ignored.
+    pub fn of_buffer(buffer: &[u8], width: u32, height: u32) -> Self {
+        let line = line_bytes(width, 1);
+        let (mut ink, mut edges, mut largest_filled_run) = (0u64, 0u64, 0);
+        let mut rows = 0u64;
+        if line == 0 {
+            return FrameCharacter::default();
+        }
+        for row in buffer.chunks_exact(line).take(height as usize) {
+            let mut scan = RowScan::default();
+            for (i, chunk) in row.chunks(4).enumerate() {
+                let mut bytes = [Color::White.get_byte_value(); 4];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                let bits = (width - i as u32 * 32).min(32);
+                scan.word(u32::from_be_bytes(bytes), bits);
+            }
+            ink += u64::from(scan.ink);
+            edges += u64::from(scan.edges);
+            largest_filled_run = largest_filled_run.max(scan.longest);
+            rows += 1;
+        }
 
-    // Out of range check
-    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
-        // don't do anything in case of out of range
-        return;
+        let pixels = rows * u64::from(width);
+        let pairs = rows * u64::from(width.saturating_sub(1));
+        FrameCharacter {
+            ink_coverage_pct: (ink * 100).checked_div(pixels).unwrap_or(0) as u8,
+            largest_filled_run,
+            edge_density: (edges * 100).checked_div(pairs).unwrap_or(0) as u8,
+        }
     }
+}
 
-    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
+/// Analyzes the content of a black/white display, see [`FrameCharacter`]
+pub fn analyze_frame<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+>(
+    display: &Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>,
+) -> FrameCharacter {
+    FrameCharacter::of_buffer(display.buffer(), WIDTH, HEIGHT)
+}
+
+// Running counts of one line of a frame, fed with up to 32 pixels at a time
+#[derive(Default)]
+struct RowScan {
+    ink: u32,
+    edges: u32,
+    run: u32,
+    longest: u32,
+    last_pixel: Option<u32>,
+}
+
+impl RowScan {
+    // `word` holds `bits` pixels starting from the most significant bit, a set bit is white
+    fn word(&mut self, word: u32, bits: u32) {
+        let valid = !(u32::MAX.checked_shr(bits).unwrap_or(0));
+        let ink = !word & valid;
+        self.ink += ink.count_ones();
+
+        if ink == valid {
+            self.run += bits;
+        } else {
+            self.run += ink.leading_ones();
+            self.longest = self.longest.max(self.run).max(longest_ones(ink));
+            self.run = (ink >> (32 - bits)).trailing_ones();
+        }
+        self.longest = self.longest.max(self.run);
+
+        // bit n of `changes` compares the pixels in bit n and n - 1
+        let changes = word ^ (word << 1);
+        self.edges += (changes & valid & (valid << 1)).count_ones();
+        if let Some(last_pixel) = self.last_pixel {
+            self.edges += last_pixel ^ (word >> 31);
+        }
+        self.last_pixel = Some((word >> (32 - bits)) & 1);
+    }
+}
+
+// Length of the longest run of set bits
+fn longest_ones(mut bits: u32) -> u32 {
+    let mut length = 0;
+    while bits != 0 {
+        bits &= bits << 1;
+        length += 1;
+    }
+    length
+}
+
+/// Thresholds mapping a [`FrameCharacter`] to the recommended refresh mode
+///
+/// Frames within all limits get [`RefreshLut::Quick`], everything else [`RefreshLut::Full`].
+/// Drivers with a quick mode offer tuned values as `REFRESH_POLICY`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RefreshPolicy {
+    /// Highest share of black pixels, in percent
+    pub max_ink_coverage_pct: u8,
+    /// Longest horizontal run of black pixels, longer runs are filled areas
+    pub max_filled_run: u32,
+    /// Highest edge density in percent, dithered images have a lot of edges
+    pub max_edge_density: u8,
+}
+
+impl RefreshPolicy {
+    /// Suits text and line art: black text covers around 5 to 20 percent of a frame, with
+    /// strokes a few pixels wide
+    pub const DEFAULT: RefreshPolicy = RefreshPolicy {
+        max_ink_coverage_pct: 30,
+        max_filled_run: 48,
+        max_edge_density: 25,
+    };
+
+    /// Refresh mode recommended for a frame with the given character
+    pub fn recommend(&self, frame: &FrameCharacter) -> RefreshLut {
+        if frame.ink_coverage_pct <= self.max_ink_coverage_pct
+            && frame.largest_filled_run <= self.max_filled_run
+            && frame.edge_density <= self.max_edge_density
+        {
+            RefreshLut::Quick
+        } else {
+            RefreshLut::Full
+        }
+    }
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// It sets a specific pixel in a buffer to a given color.
+// The big number of parameters is due to the fact that it is an internal function to both
+// strctures.
+fn set_pixel<COLOR: ColorType + PixelColor>(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    pixel: Pixel<COLOR>,
+) {
+    let Pixel(point, color) = pixel;
+
+    // final coordinates
+    let Point { x, y } = rotation.transform_point(point, Size::new(width, height));
+
+    // Out of range check
+    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
+        // don't do anything in case of out of range
+        return;
+    }
+
+    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
         + y as usize * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
     let (mask, bits) = color.bitmask(bwrbit, x as u32);
 
+    // A buffer that is too small for the display, e.g. because of a wrong BYTECOUNT, can't be
+    // reported through DrawTarget, pixels outside of it are dropped
     if COLOR::BUFFER_COUNT == 2 {
         // split buffer is for tricolor displays that use 2 buffer for 2 bits per pixel
+        let chromatic_index = index + buffer.len() / 2;
+        if chromatic_index >= buffer.len() {
+            return;
+        }
         buffer[index] = buffer[index] & mask | (bits & 0xFF) as u8;
-        let index = index + buffer.len() / 2;
-        buffer[index] = buffer[index] & mask | (bits >> 8) as u8;
-    } else {
-        buffer[index] = buffer[index] & mask | bits as u8;
+        buffer[chromatic_index] = buffer[chromatic_index] & mask | (bits >> 8) as u8;
+    } else if let Some(byte) = buffer.get_mut(index) {
+        *byte = *byte & mask | bits as u8;
+    }
+}
+
+// The 8 bits of `row` starting at bit `first`, which may lie outside of the row. Bits outside
+// of the row are 0.
+fn read_bits(row: &[u8], first: isize) -> u8 {
+    let byte = |index: isize| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| row.get(index))
+            .copied()
+            .unwrap_or(0)
+    };
+    let (index, shift) = (first.div_euclid(8), first.rem_euclid(8) as u32);
+    let high = byte(index).checked_shl(shift).unwrap_or(0);
+    let low = byte(index + 1).checked_shr(8 - shift).unwrap_or(0);
+    high | low
+}
+
+// Thresholds of the ordered dither in `fill_gradient_horizontal`, in sixteenths
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// sin of 0° to 90° in steps of 6°, one sixtieth of a turn, scaled by 1024
+const SIN_6DEG: [i32; 16] = [
+    0, 107, 213, 316, 416, 512, 602, 685, 761, 828, 887, 935, 974, 1002, 1018, 1024,
+];
+
+// Point `length` pixels from `center` towards `position` of 60 on a clock face, 0 at the top
+fn clock_point(center: Point, position: u32, length: i32) -> Point {
+    let sin = |position: u32| {
+        let position = position % 60;
+        match position / 15 {
+            0 => SIN_6DEG[position as usize],
+            1 => SIN_6DEG[30 - position as usize],
+            2 => -SIN_6DEG[position as usize - 30],
+            _ => -SIN_6DEG[60 - position as usize],
+        }
+    };
+    let (dx, dy) = (sin(position), -sin(position + 15));
+    // round to the nearest pixel
+    let scale = |d: i32| (d * length + d.signum() * 512) / 1024;
+    center + Point::new(scale(dx), scale(dy))
+}
+
+// Bresenham's line algorithm, plotting both ends
+fn line(plot: &mut impl FnMut(Point), from: Point, to: Point) {
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let mut point = from;
+    loop {
+        plot(point);
+        if point == to {
+            return;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            point.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            point.y += step_y;
+        }
+    }
+}
+
+// Midpoint circle algorithm, plotting the eight octants at once
+fn circle(plot: &mut impl FnMut(Point), center: Point, radius: i32) {
+    midpoint_octant(radius, |x, y| {
+        for (dx, dy) in [
+            (x, y),
+            (y, x),
+            (-y, x),
+            (-x, y),
+            (-x, -y),
+            (-y, -x),
+            (y, -x),
+            (x, -y),
+        ] {
+            plot(center + Point::new(dx, dy));
+        }
+    });
+}
+
+// The points `x`, `y` of the octant of a circle around the origin with `x >= y >= 0`, as
+// chosen by the midpoint circle algorithm, starting at `radius`, 0
+fn midpoint_octant(radius: i32, mut point: impl FnMut(i32, i32)) {
+    let (mut x, mut y) = (radius, 0);
+    let mut error = 1 - radius;
+    while x >= y {
+        point(x, y);
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+// Shared by `erode` and `dilate`: grows the white (set) or the black (cleared) pixels of a
+// plane with one bit per pixel by a square of `size`. Separable, a horizontal and a vertical
+// pass, each made of steps growing by one pixel in place, whole bytes at a time.
+fn morph(plane: &mut [u8], width: u32, height: u32, size: u32, white: bool) {
+    let line = line_bytes(width, 1);
+    let rows = (plane.len() / line.max(1)).min(height as usize);
+    if size < 2 || line == 0 || rows == 0 {
+        return;
+    }
+    let grow = |a: u8, b: u8| if white { a | b } else { a & b };
+    let (before, after) = ((size - 1) / 2, size / 2);
+    // the last pixel of a row has no right neighbour, the first no left one, they count as
+    // their own neighbour
+    let last_bit = 0x80u8 >> ((width - 1) % 8);
+
+    for row in plane.chunks_exact_mut(line).take(rows) {
+        for _ in 0..after {
+            for i in 0..line {
+                let next = row.get(i + 1).map_or(0, |next| next >> 7);
+                let mut neighbours = row[i] << 1 | next;
+                if i == line - 1 {
+                    neighbours = neighbours & !last_bit | row[i] & last_bit;
+                }
+                row[i] = grow(row[i], neighbours);
+            }
+        }
+        for _ in 0..before {
+            for i in (0..line).rev() {
+                let previous = if i == 0 {
+                    row[0] & 0x80
+                } else {
+                    row[i - 1] << 7
+                };
+                row[i] = grow(row[i], row[i] >> 1 | previous);
+            }
+        }
+    }
+
+    for _ in 0..after {
+        for y in 0..rows - 1 {
+            for i in y * line..(y + 1) * line {
+                plane[i] = grow(plane[i], plane[i + line]);
+            }
+        }
+    }
+    for _ in 0..before {
+        for y in (1..rows).rev() {
+            for i in y * line..(y + 1) * line {
+                plane[i] = grow(plane[i], plane[i - line]);
+            }
+        }
+    }
+}
+
+// Shared by `Display` and `VarDisplay`: XOR all bits covered by `region` in the first
+// buffer plane, masking the partial bytes on the left and right edge of each line.
+fn flip_bits_in_region(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    bits_per_pixel: usize,
+    region: DisplayRegion,
+) {
+    let x_end = region.x.saturating_add(region.width).min(width);
+    let y_end = region.y.saturating_add(region.height).min(height);
+    if region.x >= x_end || region.y >= y_end {
+        return;
+    }
+
+    let line = line_bytes(width, bits_per_pixel);
+    let first_bit = region.x as usize * bits_per_pixel;
+    let end_bit = x_end as usize * bits_per_pixel;
+    let first_byte = first_bit / 8;
+    let last_byte = (end_bit - 1) / 8;
+    let left_mask = 0xFFu8 >> (first_bit % 8);
+    let right_mask = 0xFFu8 << ((8 - end_bit % 8) % 8);
+
+    for y in region.y..y_end {
+        let Some(row) = buffer.get_mut(y as usize * line..(y as usize + 1) * line) else {
+            // The buffer is too small for the display
+            return;
+        };
+        if first_byte == last_byte {
+            row[first_byte] ^= left_mask & right_mask;
+        } else {
+            row[first_byte] ^= left_mask;
+            for byte in &mut row[first_byte + 1..last_byte] {
+                *byte ^= 0xFF;
+            }
+            row[last_byte] ^= right_mask;
+        }
     }
 }
 
@@ -357,7 +1611,7 @@ mod tests {
     use crate::color::*;
     use embedded_graphics::{
         prelude::*,
-        primitives::{Line, PrimitiveStyle},
+        primitives::{Line, PrimitiveStyle, Rectangle},
     };
 
     // test buffer length
@@ -449,4 +1703,762 @@ mod tests {
             assert_eq!(byte, 0);
         }
     }
+
+    const ROTATIONS: [DisplayRotation; 4] = [
+        DisplayRotation::Rotate0,
+        DisplayRotation::Rotate90,
+        DisplayRotation::Rotate180,
+        DisplayRotation::Rotate270,
+    ];
+
+    // Draws the corners and a few inner pixels in every rotation and checks the bits set in the
+    // buffer, which is laid out in the panel orientation with rows padded to whole bytes
+    fn check_rotated_corners<const WIDTH: u32, const HEIGHT: u32, const BYTECOUNT: usize>() {
+        let row_len = WIDTH.div_ceil(8) as usize;
+        for rotation in ROTATIONS {
+            let mut display = Display::<WIDTH, HEIGHT, false, BYTECOUNT, Color>::default();
+            display.set_rotation(rotation);
+            let size = display.size();
+            assert_eq!(size, rotation.rotated_size(Size::new(WIDTH, HEIGHT)));
+            let (w, h) = (size.width as i32, size.height as i32);
+
+            // outside of the rotated bounds, dropped. The buffer starts out with every bit 0
+            for point in [(w, 0), (0, h), (-1, 0), (0, -1), (w, h)] {
+                let _ = Pixel(Point::new(point.0, point.1), Color::White).draw(&mut display);
+            }
+            assert!(display.buffer().iter().all(|&byte| byte == 0));
+
+            for (x, y) in [
+                (0, 0),
+                (w - 1, 0),
+                (0, h - 1),
+                (w - 1, h - 1),
+                (3, 9),
+                (w - 2, 5),
+            ] {
+                let mut display = Display::<WIDTH, HEIGHT, false, BYTECOUNT, Color>::default();
+                display.set_rotation(rotation);
+                let _ = Pixel(Point::new(x, y), Color::White).draw(&mut display);
+
+                let (right, bottom) = (WIDTH as i32 - 1, HEIGHT as i32 - 1);
+                let (px, py) = match rotation {
+                    DisplayRotation::Rotate0 => (x, y),
+                    DisplayRotation::Rotate90 => (right - y, x),
+                    DisplayRotation::Rotate180 => (right - x, bottom - y),
+                    DisplayRotation::Rotate270 => (y, bottom - x),
+                };
+                let index = py as usize * row_len + px as usize / 8;
+                for (i, &byte) in display.buffer().iter().enumerate() {
+                    let expected = if i == index { 0x80 >> (px % 8) } else { 0 };
+                    assert_eq!(
+                        byte, expected,
+                        "{:?} at ({}, {}) on {}x{}",
+                        rotation, x, y, WIDTH, HEIGHT
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_asymmetric() {
+        // 2in9
+        check_rotated_corners::<128, 296, { 16 * 296 }>();
+        // 2in13, the last byte of every row has 6 padding bits
+        check_rotated_corners::<122, 250, { 16 * 250 }>();
+    }
+
+    #[test]
+    fn rotation_transform_matches_drawing() {
+        let panel = Size::new(16, 8);
+        for rotation in ROTATIONS {
+            let mut display = Display::<16, 8, false, { 16 * 8 / 8 }, Color>::default();
+            display.set_rotation(rotation);
+            assert_eq!(display.size(), rotation.rotated_size(panel));
+            for point in display.bounding_box().points() {
+                display.clear(Color::White).unwrap();
+                Pixel(point, Color::Black).draw(&mut display).unwrap();
+
+                let index = display.buffer().iter().position(|&b| b != 0xFF).unwrap();
+                let bit = display.buffer()[index].leading_ones() as i32;
+                let panel_point = Point::new(index as i32 % 2 * 8 + bit, index as i32 / 2);
+                assert_eq!(rotation.transform_point(point, panel), panel_point);
+                assert_eq!(rotation.inverse_point(panel_point, panel), point);
+                assert_eq!(
+                    rotation.as_transform(panel).inverse().apply(panel_point),
+                    point
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_direction() {
+        let panel = Size::new(16, 8);
+        // the top left corner of the drawing moves clockwise around the panel
+        let corners = ROTATIONS.map(|rotation| rotation.transform_point(Point::zero(), panel));
+        assert_eq!(
+            corners,
+            [
+                Point::new(0, 0),
+                Point::new(15, 0),
+                Point::new(15, 7),
+                Point::new(0, 7)
+            ]
+        );
+        for rotation in ROTATIONS {
+            let degrees = rotation.degrees() as i32;
+            assert_eq!(DisplayRotation::from_degrees(degrees), Some(rotation));
+            assert_eq!(DisplayRotation::from_degrees(degrees - 360), Some(rotation));
+            assert_eq!(
+                DisplayRotation::from_degrees(-degrees),
+                Some(rotation.inverse())
+            );
+        }
+        assert_eq!(DisplayRotation::from_degrees(45), None);
+    }
+
+    #[test]
+    fn rotation_transform_rect() {
+        let panel = Size::new(16, 8);
+        let rect = Rectangle::new(Point::new(1, 2), Size::new(3, 4));
+        for rotation in ROTATIONS {
+            let mut display = Display::<16, 8, false, { 16 * 8 / 8 }, Color>::default();
+            display.set_rotation(rotation);
+            display.clear(Color::White).unwrap();
+            rect.into_styled(PrimitiveStyle::with_fill(Color::Black))
+                .draw(&mut display)
+                .unwrap();
+
+            let transformed = rotation.transform_rect(rect, panel);
+            for point in Rectangle::new(Point::zero(), panel).points() {
+                let index = point.y as usize * 2 + point.x as usize / 8;
+                let black = display.buffer()[index] & (0x80 >> (point.x % 8)) == 0;
+                assert_eq!(black, transformed.contains(point));
+            }
+            assert_eq!(rotation.inverse_rect(transformed, panel), rect);
+        }
+        let empty = Rectangle::new(Point::new(1, 2), Size::zero());
+        assert_eq!(
+            DisplayRotation::Rotate90.transform_rect(empty, panel),
+            Rectangle::new(Point::new(13, 1), Size::zero())
+        );
+    }
+
+    #[test]
+    fn graphics_flip_bits_in_region() {
+        let mut display = Display::<24, 3, false, { 24 * 3 / 8 }, Color>::default();
+        display.flip_bits_in_region(DisplayRegion::new(3, 1, 15, 1));
+
+        assert_eq!(display.buffer(), &[0, 0, 0, 0x1F, 0xFF, 0xC0, 0, 0, 0]);
+
+        // flipping twice restores the buffer
+        display.flip_bits_in_region(DisplayRegion::new(3, 1, 15, 1));
+        assert!(display.buffer().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn graphics_flip_bits_in_region_single_byte_and_clipped() {
+        let mut display = Display::<16, 2, false, { 16 * 2 / 8 }, Color>::default();
+        display.flip_bits_in_region(DisplayRegion::new(2, 0, 3, 1));
+        assert_eq!(display.buffer(), &[0x38, 0, 0, 0]);
+
+        display.flip_bits_in_region(DisplayRegion::new(12, 1, 100, 100));
+        assert_eq!(display.buffer(), &[0x38, 0, 0, 0x0F]);
+
+        display.flip_bits_in_region(DisplayRegion::new(16, 0, 4, 4));
+        assert_eq!(display.buffer(), &[0x38, 0, 0, 0x0F]);
+    }
+
+    #[test]
+    fn graphics_raw_buffer_roundtrip() {
+        let mut display = Display::<16, 2, false, { 16 * 2 / 8 }, Color>::default();
+        display.flip_bits_in_region(DisplayRegion::new(0, 0, 12, 1));
+
+        let copy = Display::<16, 2, false, { 16 * 2 / 8 }, Color>::from_raw_buffer(
+            display.to_raw_buffer(),
+        )
+        .unwrap();
+        assert_eq!(copy.buffer(), &[0xFF, 0xF0, 0x00, 0x00]);
+
+        assert_eq!(
+            Display::<16, 2, false, { 16 * 2 / 8 }, Color>::from_raw_buffer(&[0; 3]).err(),
+            Some(LengthError {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+
+    #[cfg(feature = "eg-framebuffer")]
+    #[test]
+    fn graphics_framebuffer_roundtrip() {
+        use embedded_graphics::{image::GetPixel, pixelcolor::BinaryColor};
+
+        let mut display = Display::<12, 4, false, { crate::buffer_len(12, 4) }, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.set_pixel(Pixel(Point::new(9, 2), Color::Black));
+
+        let mut framebuffer = display.to_framebuffer::<12, 4>();
+        assert_eq!(framebuffer.pixel(Point::new(9, 2)), Some(BinaryColor::On));
+        assert_eq!(framebuffer.pixel(Point::new(8, 2)), Some(BinaryColor::Off));
+
+        framebuffer.set_pixel(Point::new(0, 0), BinaryColor::On);
+        display.copy_from_framebuffer(&framebuffer);
+        assert_eq!(display.buffer()[0], 0x7F);
+        // only the upper 4 bits of the last byte in a row are pixels
+        assert_eq!(display.buffer()[2 * 2 + 1] & 0xF0, 0b1011_0000);
+    }
+
+    #[test]
+    fn graphics_diagonal_stripes() {
+        let mut display = Display::<16, 4, false, { crate::buffer_len(16, 4) }, Color>::default();
+
+        display.pattern_diagonal_stripe(2, StripeAngle::Deg45, Color::White, Color::Black);
+        assert_eq!(
+            display.buffer(),
+            [
+                0b1100_1100,
+                0b1100_1100,
+                0b1001_1001,
+                0b1001_1001,
+                0b0011_0011,
+                0b0011_0011,
+                0b0110_0110,
+                0b0110_0110,
+            ]
+        );
+
+        display.pattern_diagonal_stripe(2, StripeAngle::Deg135, Color::White, Color::Black);
+        assert_eq!(
+            display.buffer(),
+            [
+                0b0110_0110,
+                0b0110_0110,
+                0b0011_0011,
+                0b0011_0011,
+                0b1001_1001,
+                0b1001_1001,
+                0b1100_1100,
+                0b1100_1100,
+            ]
+        );
+    }
+
+    #[test]
+    fn graphics_gradient_horizontal() {
+        let mut display = Display::<32, 4, false, { crate::buffer_len(32, 4) }, Color>::default();
+        display.fill_gradient_horizontal(Color::Black, Color::White);
+
+        let mut black_in_column = [0; 32];
+        for (x, _, color) in display.iter_pixels() {
+            if color == Color::Black {
+                black_in_column[x as usize] += 1;
+            }
+        }
+        assert_eq!(black_in_column[0], 4);
+        assert_eq!(black_in_column[31], 0);
+        // the density falls from left to right
+        let mut blocks = [0; 8];
+        for (x, count) in black_in_column.iter().enumerate() {
+            blocks[x / 4] += count;
+        }
+        assert!(
+            blocks.windows(2).all(|pair| pair[0] >= pair[1]),
+            "{blocks:?}"
+        );
+        // the first row, black pixels are 0
+        assert_eq!(display.buffer()[0..4], [0x00, 0x11, 0x55, 0x57]);
+
+        let mut display = Display::<8, 1, false, 1, Color>::default();
+        display.fill_gradient_horizontal(Color::White, Color::Black);
+        assert_eq!(display.buffer(), [0b1110_1000]);
+    }
+
+    #[test]
+    fn graphics_too_small_buffer_is_not_written() {
+        // BYTECOUNT only covers the first 2 of 16 lines
+        let mut display = Display::<16, 16, false, 4, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.flip_bits_in_region(DisplayRegion::new(0, 0, 16, 16));
+        display.pattern_diagonal_stripe(3, StripeAngle::Deg45, Color::White, Color::Black);
+        assert_eq!(display.buffer().len(), 4);
+
+        let mut display = Display::<16, 16, false, 8, TriColor>::default();
+        display.clear(TriColor::Chromatic).unwrap();
+        assert_eq!(display.bw_buffer(), [0xFF; 4]);
+        assert_eq!(display.chromatic_buffer(), [0xFF; 4]);
+    }
+
+    #[test]
+    fn graphics_histogram() {
+        let mut display = Display::<12, 2, false, 4, Color>::default();
+        assert_eq!(display.histogram(), [0, 24]);
+        display.clear(Color::White).unwrap();
+        assert_eq!(display.histogram(), [24, 0]);
+        display.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        display.set_pixel(Pixel(Point::new(11, 1), Color::Black));
+        assert_eq!(display.histogram(), [22, 2]);
+    }
+
+    #[test]
+    fn graphics_paste_at() {
+        let src = Display::<16, 2, false, { 16 * 2 / 8 }, Color>::from_raw_buffer(&[
+            0b1010_1100,
+            0b0000_1111,
+            0xFF,
+            0x00,
+        ])
+        .unwrap();
+        let mut display = Display::<24, 3, false, { 24 * 3 / 8 }, Color>::default();
+
+        display.paste_at(&src, 5, 1, DisplayRegion::new(2, 0, 8, 2));
+        assert_eq!(display.buffer(), [0, 0, 0, 0x05, 0x80, 0, 0x07, 0xE0, 0]);
+
+        // clipped to the right and bottom edge of the destination
+        display.paste_at(&src, 20, 2, DisplayRegion::new(0, 0, 16, 2));
+        assert_eq!(display.buffer(), [0, 0, 0, 0x05, 0x80, 0, 0x07, 0xE0, 0x0A]);
+
+        // regions outside of the source copy nothing
+        display.paste_at(&src, 0, 0, DisplayRegion::new(16, 0, 4, 2));
+        assert_eq!(display.buffer()[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn graphics_transpose_into() {
+        // 12 and 20 pixels wide rows end in padded bytes
+        let mut src = Display::<12, 20, false, { 2 * 20 }, Color>::default();
+        let mut rotated = Display::<20, 12, false, { 3 * 12 }, Color>::default();
+        rotated.set_rotation(DisplayRotation::Rotate90);
+        fn draw<D: DrawTarget<Color = Color>>(display: &mut D) {
+            let _ = Line::new(Point::new(0, 0), Point::new(11, 19))
+                .into_styled(PrimitiveStyle::with_stroke(Color::White, 1))
+                .draw(display);
+            let _ = Rectangle::new(Point::new(7, 2), Size::new(5, 3))
+                .into_styled(PrimitiveStyle::with_fill(Color::White))
+                .draw(display);
+        }
+        draw(&mut src);
+        draw(&mut rotated);
+
+        // every bit of the destination is written, garbage included
+        let mut dst = Display::<20, 12, false, { 3 * 12 }, Color>::default();
+        dst.clear(Color::White).unwrap();
+        src.transpose_into(&mut dst);
+        assert_eq!(dst.buffer(), rotated.buffer());
+
+        let mut tricolor = Display::<12, 20, true, { 2 * 2 * 20 }, TriColor>::default();
+        tricolor.set_pixel(Pixel(Point::new(11, 0), TriColor::Chromatic));
+        let mut dst = Display::<20, 12, true, { 2 * 3 * 12 }, TriColor>::default();
+        tricolor.transpose_into(&mut dst);
+        let mut expected = Display::<20, 12, true, { 2 * 3 * 12 }, TriColor>::default();
+        expected.set_pixel(Pixel(Point::new(19, 11), TriColor::Chromatic));
+        assert_eq!(dst.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn graphics_paste_at_tricolor() {
+        let mut src = Display::<8, 1, false, 2, TriColor>::default();
+        src.clear(TriColor::Chromatic).unwrap();
+        let mut display = Display::<8, 1, false, 2, TriColor>::default();
+
+        display.paste_at(&src, 2, 0, DisplayRegion::new(0, 0, 4, 1));
+        assert_eq!(display.bw_buffer(), [0b0011_1100]);
+        assert_eq!(display.chromatic_buffer(), [0b0011_1100]);
+    }
+
+    #[test]
+    fn graphics_with_padding() {
+        let mut icon = Display::<4, 2, false, 2, Color>::default();
+        icon.set_pixel(Pixel(Point::new(3, 1), Color::White));
+
+        let padded: Display<16, 4, false, { 16 * 4 / 8 }, Color> =
+            icon.with_padding(6, 6, 1, 1, Color::White);
+        assert_eq!(
+            padded.buffer(),
+            [0xFF, 0xFF, 0xFC, 0x3F, 0xFC, 0x7F, 0xFF, 0xFF]
+        );
+
+        let mut icon = Display::<2, 1, false, 2, TriColor>::default();
+        icon.clear(TriColor::Chromatic).unwrap();
+        let padded: Display<8, 1, false, 2, TriColor> =
+            icon.with_padding(3, 3, 0, 0, TriColor::White);
+        assert_eq!(padded.bw_buffer(), [0xFF]);
+        assert_eq!(padded.chromatic_buffer(), [0b0001_1000]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn graphics_with_wrong_padding() {
+        let icon = Display::<4, 2, false, 2, Color>::default();
+        let _: Display<16, 4, false, 8, Color> = icon.with_padding(6, 5, 1, 1, Color::White);
+    }
+
+    #[test]
+    fn graphics_overlay_transparent() {
+        let mut sprite = Display::<8, 1, false, 1, Color>::default();
+        sprite.clear(Color::White).unwrap();
+        sprite.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        sprite.set_pixel(Pixel(Point::new(7, 0), Color::Black));
+
+        let mut display = Display::<16, 2, false, { 16 * 2 / 8 }, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.set_pixel(Pixel(Point::new(5, 0), Color::Black));
+
+        // the white pixels of the sprite leave the black pixel at x = 5 alone
+        display.overlay_transparent(&sprite, 3, 0, Color::White);
+        assert_eq!(display.buffer(), [0b1110_1011, 0b1101_1111, 0xFF, 0xFF]);
+
+        // now only the white pixels are drawn
+        display.overlay_transparent(&sprite, 3, 0, Color::Black);
+        assert_eq!(display.buffer(), [0b1110_1111, 0b1101_1111, 0xFF, 0xFF]);
+
+        // clipped to the right and bottom edge
+        display.overlay_transparent(&sprite, 12, 1, Color::White);
+        assert_eq!(
+            display.buffer(),
+            [0b1110_1111, 0b1101_1111, 0xFF, 0b1111_0111]
+        );
+        display.overlay_transparent(&sprite, 16, 0, Color::White);
+        display.overlay_transparent(&sprite, 0, 2, Color::White);
+        assert_eq!(
+            display.buffer(),
+            [0b1110_1111, 0b1101_1111, 0xFF, 0b1111_0111]
+        );
+    }
+
+    #[test]
+    fn graphics_overlay_transparent_tricolor() {
+        let colors = [TriColor::Chromatic, TriColor::White, TriColor::Black];
+        let mut sprite = Display::<3, 1, false, 2, TriColor>::default();
+        for (x, color) in colors.iter().enumerate() {
+            sprite.set_pixel(Pixel(Point::new(x as i32, 0), *color));
+        }
+
+        let mut display = Display::<16, 1, false, 4, TriColor>::default();
+        let mut expected = Display::<16, 1, false, 4, TriColor>::default();
+        for display in [&mut display, &mut expected] {
+            display.clear(TriColor::Black).unwrap();
+            display.set_pixel(Pixel(Point::new(7, 0), TriColor::Chromatic));
+        }
+        expected.set_pixel(Pixel(Point::new(6, 0), TriColor::Chromatic));
+        expected.set_pixel(Pixel(Point::new(8, 0), TriColor::Black));
+
+        display.overlay_transparent(&sprite, 6, 0, TriColor::White);
+        assert_eq!(display.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn graphics_overlay_transparent_octcolor() {
+        let mut sprite = Display::<3, 1, false, 2, OctColor>::default();
+        sprite.clear(OctColor::White).unwrap();
+        sprite.set_pixel(Pixel(Point::new(1, 0), OctColor::Red));
+
+        let mut display = Display::<4, 1, false, 2, OctColor>::default();
+        display.clear(OctColor::Blue).unwrap();
+        display.overlay_transparent(&sprite, 1, 0, OctColor::White);
+
+        let mut expected = Display::<4, 1, false, 2, OctColor>::default();
+        expected.clear(OctColor::Blue).unwrap();
+        expected.set_pixel(Pixel(Point::new(2, 0), OctColor::Red));
+        assert_eq!(display.buffer(), expected.buffer());
+    }
+
+    #[test]
+    fn graphics_analyze_buffer() {
+        assert_eq!(
+            FrameCharacter::of_buffer(&[0x0F, 0xFF, 0x00, 0x00], 16, 2),
+            FrameCharacter {
+                ink_coverage_pct: 62,
+                largest_filled_run: 16,
+                edge_density: 3,
+            }
+        );
+
+        // padding bits of the last byte don't count
+        assert_eq!(
+            FrameCharacter::of_buffer(&[0xAA, 0xA0], 12, 1),
+            FrameCharacter {
+                ink_coverage_pct: 50,
+                largest_filled_run: 1,
+                edge_density: 100,
+            }
+        );
+
+        // runs continue across words
+        assert_eq!(
+            FrameCharacter::of_buffer(&[0xFF, 0xFF, 0xFF, 0xF0, 0x00], 40, 1),
+            FrameCharacter {
+                ink_coverage_pct: 30,
+                largest_filled_run: 12,
+                edge_density: 2,
+            }
+        );
+        assert_eq!(
+            FrameCharacter::of_buffer(&[0b1100_0111, 0x7F, 0xFF, 0xFF], 32, 1).largest_filled_run,
+            3
+        );
+    }
+
+    #[test]
+    fn graphics_clock_face() {
+        let mut display = Display::<32, 32, false, 128, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.render_clock_face(15, 0, 0, 16, 16, 12, Color::Black);
+        let black = |display: &Display<32, 32, false, 128, Color>, x: usize, y: usize| {
+            display.buffer()[y * 4 + x / 8] & (0x80 >> (x % 8)) == 0
+        };
+
+        // hour hand to the right, minute and second hand up
+        assert!((16..=22).all(|x| black(&display, x, 16)));
+        assert!((6..=16).all(|y| black(&display, 16, y)));
+        // face and tick marks
+        for (x, y) in [(28, 16), (16, 28), (4, 16), (16, 4), (6, 16), (16, 26)] {
+            assert!(black(&display, x, y), "{x}, {y}");
+        }
+        for (x, y) in [(10, 16), (16, 20), (23, 16), (20, 20)] {
+            assert!(!black(&display, x, y), "{x}, {y}");
+        }
+        assert_eq!(clock_point(Point::zero(), 45, 12), Point::new(-12, 0));
+        assert_eq!(clock_point(Point::zero(), 30, 12), Point::new(0, 12));
+        assert_eq!(clock_point(Point::zero(), 5, 12), Point::new(6, -10));
+
+        // rotated, the hour hand points down the panel and is clipped at its edge
+        let mut rotated = Display::<32, 32, false, 128, Color>::default();
+        rotated.clear(Color::White).unwrap();
+        rotated.set_rotation(DisplayRotation::Rotate90);
+        rotated.render_clock_face(15, 0, 0, 16, 16, 40, Color::Black);
+        assert!((16..32).all(|y| black(&rotated, 15, y)));
+    }
+
+    #[test]
+    fn graphics_rounded_rect() {
+        type Display32x12 = Display<32, 12, false, { crate::buffer_len(32, 12) }, Color>;
+        let black = |display: &Display32x12, x: i32, y: i32| {
+            display.buffer()[y as usize * 4 + x as usize / 8] & (0x80 >> (x % 8)) == 0
+        };
+
+        // without a radius it is a plain rectangle
+        let mut display = Display32x12::default();
+        display.clear(Color::White).unwrap();
+        display.draw_rounded_rect(3, 1, 27, 9, 0, Some(Color::Black), None);
+        let mut expected = Display32x12::default();
+        expected.clear(Color::White).unwrap();
+        Rectangle::new(Point::new(3, 1), Size::new(27, 9))
+            .into_styled(PrimitiveStyle::with_fill(Color::Black))
+            .draw(&mut expected)
+            .unwrap();
+        assert_eq!(display.buffer(), expected.buffer());
+
+        // the corners are cut off, the straight edges are not
+        let mut display = Display32x12::default();
+        display.clear(Color::White).unwrap();
+        display.draw_rounded_rect(2, 1, 28, 10, 3, Some(Color::Black), None);
+        for (x, y) in [(2, 1), (29, 1), (2, 10), (29, 10), (3, 1)] {
+            assert!(!black(&display, x, y), "{x}, {y}");
+        }
+        for (x, y) in [(5, 1), (26, 1), (2, 4), (29, 7), (16, 5), (4, 2)] {
+            assert!(black(&display, x, y), "{x}, {y}");
+        }
+
+        // the outline, over a fill
+        let mut outlined = Display32x12::default();
+        outlined.clear(Color::White).unwrap();
+        outlined.draw_rounded_rect(2, 1, 28, 10, 3, Some(Color::White), Some(Color::Black));
+        for (x, y) in [(5, 1), (26, 10), (2, 5), (29, 6), (3, 2), (28, 9)] {
+            assert!(black(&outlined, x, y), "{x}, {y}");
+        }
+        for (x, y) in [(16, 5), (4, 3), (2, 1)] {
+            assert!(!black(&outlined, x, y), "{x}, {y}");
+        }
+        // the outline is the border of the filled shape
+        for (x, y, _) in outlined.iter_pixels() {
+            let (x, y) = (x as i32, y as i32);
+            if black(&outlined, x, y) {
+                assert!(black(&display, x, y), "{x}, {y}");
+            }
+        }
+
+        // upside down the rows run backwards through the buffer
+        let mut rotated = Display32x12::default();
+        rotated.clear(Color::White).unwrap();
+        rotated.set_rotation(DisplayRotation::Rotate180);
+        rotated.draw_rounded_rect(2, 1, 28, 10, 3, Some(Color::Black), None);
+        assert_eq!(rotated.buffer(), display.buffer());
+    }
+
+    #[test]
+    fn graphics_iter_pixels() {
+        let mut display = Display::<10, 2, false, 4, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.set_pixel(Pixel(Point::new(9, 0), Color::Black));
+        display.set_pixel(Pixel(Point::new(1, 1), Color::Black));
+        let pixels = display.iter_pixels();
+        assert_eq!(pixels.size_hint(), (0, Some(20)));
+        assert!(pixels
+            .filter(|&(_, _, color)| color == Color::Black)
+            .map(|(x, y, _)| (x, y))
+            .eq([(9, 0), (1, 1)]));
+        assert_eq!(display.iter_pixels().last(), Some((9, 1, Color::White)));
+
+        let mut display = Display::<8, 2, true, 4, TriColor>::default();
+        display.clear(TriColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(3, 1), TriColor::Chromatic));
+        display.set_pixel(Pixel(Point::new(4, 1), TriColor::Black));
+        let row: [TriColor; 3] =
+            core::array::from_fn(|i| display.iter_pixels().nth(8 + 3 + i).unwrap().2);
+        assert_eq!(row, [TriColor::Chromatic, TriColor::Black, TriColor::White]);
+
+        let mut display = Display::<3, 1, false, 2, OctColor>::default();
+        display.clear(OctColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(2, 0), OctColor::Green));
+        assert!(display.iter_pixels().map(|(_, _, color)| color).eq([
+            OctColor::White,
+            OctColor::White,
+            OctColor::Green
+        ]));
+    }
+
+    #[test]
+    fn graphics_window() {
+        let mut display = Display::<16, 4, false, 8, Color>::default();
+        display.buffer.copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let window = display.window(DisplayRegion::new(9, 1, 7, 3)).unwrap();
+        assert!(window.rows().eq([&[3][..], &[5], &[7]]));
+        let window = display.window(DisplayRegion::new(4, 2, 8, 1)).unwrap();
+        assert_eq!(window.as_contiguous(), Some(&[4, 5][..]));
+
+        assert_eq!(display.window(DisplayRegion::new(0, 0, 17, 1)), None);
+        assert_eq!(display.window(DisplayRegion::new(0, 4, 8, 1)), None);
+        assert_eq!(display.window(DisplayRegion::new(0, 0, 0, 1)), None);
+
+        // the black/white plane of tricolor displays
+        let display = Display::<8, 2, false, 4, TriColor>::default();
+        let window = display.window(DisplayRegion::new(0, 0, 8, 2)).unwrap();
+        assert_eq!(window.len(), 2);
+    }
+
+    #[test]
+    fn graphics_threshold_from_grayscale() {
+        let mut display = Display::<12, 3, false, 6, Color>::default();
+        display.clear(Color::White).unwrap();
+
+        // 3 × 2 gradient at (7, 1), across the byte boundary
+        let src = [0, 127, 128, 255, 10, 200];
+        display
+            .threshold_from_grayscale(&src, 3, 2, 128, 7, 1)
+            .unwrap();
+        assert_eq!(display.buffer(), [0xFF, 0xF0, 0xFE, 0x70, 0xFF, 0x70]);
+
+        display
+            .threshold_from_grayscale(&src, 3, 2, 0, 7, 1)
+            .unwrap();
+        assert_eq!(display.buffer(), [0xFF, 0xF0, 0xFF, 0xF0, 0xFF, 0xF0]);
+
+        assert!(display
+            .threshold_from_grayscale(&src, 2, 2, 128, 0, 0)
+            .is_err());
+        assert!(display
+            .threshold_from_grayscale(&src, 3, 2, 128, 10, 0)
+            .is_err());
+        assert!(display
+            .threshold_from_grayscale(&src, 3, 2, 128, 0, 2)
+            .is_err());
+        assert!(display
+            .threshold_from_grayscale(&src, 3, 2, 128, u32::MAX, 0)
+            .is_err());
+        assert!(display
+            .threshold_from_grayscale(&[], 0, 0, 128, 12, 3)
+            .is_ok());
+    }
+
+    #[test]
+    fn graphics_erode_dilate() {
+        const W: u32 = 13;
+        const H: u32 = 7;
+        let is_black = |display: &Display<W, H, false, 14, Color>, x: i32, y: i32| {
+            display.buffer()[y as usize * 2 + x as usize / 8] & (0x80 >> (x % 8)) == 0
+        };
+        let mut noise = Display::<W, H, false, 14, Color>::default();
+        let mut seed = 0x2545_f491u32;
+        for byte in noise.buffer.iter_mut() {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            *byte = seed as u8;
+        }
+
+        for size in 0..6 {
+            let (before, after) = (((size as i32) - 1).max(0) / 2, size as i32 / 2);
+            for erode in [true, false] {
+                let mut display = Display::<W, H, false, 14, Color> {
+                    buffer: noise.buffer,
+                    ..Default::default()
+                };
+                if erode {
+                    display.erode(size);
+                } else {
+                    display.dilate(size);
+                }
+                for y in 0..H as i32 {
+                    for x in 0..W as i32 {
+                        let mut window = (y - before..=y + after)
+                            .flat_map(|wy| (x - before..=x + after).map(move |wx| (wx, wy)))
+                            .filter(|&(wx, wy)| {
+                                wx >= 0 && wx < W as i32 && wy >= 0 && wy < H as i32
+                            })
+                            .map(|(wx, wy)| is_black(&noise, wx, wy));
+                        let expected = if size < 2 {
+                            is_black(&noise, x, y)
+                        } else if erode {
+                            window.all(|black| black)
+                        } else {
+                            window.any(|black| black)
+                        };
+                        assert_eq!(is_black(&display, x, y), expected, "{size} {erode} {x} {y}");
+                    }
+                }
+            }
+        }
+
+        // the chromatic plane of tricolor displays stays as it is
+        let mut tricolor = Display::<8, 2, false, 4, TriColor> {
+            buffer: [0xFF, 0xEF, 0x12, 0x34],
+            ..Default::default()
+        };
+        tricolor.dilate(3);
+        assert_eq!(tricolor.buffer, [0xC7, 0xC7, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn graphics_refresh_policy() {
+        let mut text = Display::<64, 32, false, { crate::buffer_len(64, 32) }, Color>::default();
+        text.clear(Color::White).unwrap();
+        for y in (4..28).step_by(4) {
+            for x in (2..60).step_by(8) {
+                for dx in 0..4 {
+                    text.set_pixel(Pixel(Point::new(x + dx, y), Color::Black));
+                }
+            }
+        }
+        let character = analyze_frame(&text);
+        assert_eq!(character.ink_coverage_pct, 9);
+        assert_eq!(character.largest_filled_run, 4);
+        assert_eq!(
+            RefreshPolicy::DEFAULT.recommend(&character),
+            RefreshLut::Quick
+        );
+
+        let mut filled = Display::<64, 32, false, { crate::buffer_len(64, 32) }, Color>::default();
+        filled.clear(Color::White).unwrap();
+        let _ = Rectangle::new(Point::zero(), Size::new(39, 32))
+            .into_styled(PrimitiveStyle::with_fill(Color::Black))
+            .draw(&mut filled);
+        let character = analyze_frame(&filled);
+        assert_eq!(character.ink_coverage_pct, 60);
+        assert_eq!(
+            RefreshPolicy::DEFAULT.recommend(&character),
+            RefreshLut::Full
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! Quantization of 8 bit grayscale to the four gray levels of 4-gray panels
+//!
+//! A [`GrayMap`] splits the 0..=255 range of a source pixel into four levels with three
+//! thresholds. The gray levels of e-paper aren't evenly spaced to the eye, and different content
+//! wants different breakpoints: [`GrayMap::LINEAR`] keeps an even split, which suits UI elements
+//! drawn in the four levels, [`GrayMap::HIGH_CONTRAST`] narrows the two gray bands so text and
+//! line art stay crisp, and [`GrayMap::PHOTO_OPTIMIZED`] moves the breakpoints into the shadows,
+//! where photos otherwise sink into the dark gray level.
+//!
+//! The packed format uses two bits per pixel, the first pixel in the upper bits, from
+//! `0b00` for black to `0b11` for white. Every row starts at a new byte.
+//!
+//! ```rust, ignore
+//! let mut packed = [0; packed_len(WIDTH, HEIGHT)];
+//! GrayMap::PHOTO_OPTIMIZED.pack_2bpp(&photo, WIDTH, &mut packed);
+//! ```
+
+/// Level of a black pixel
+pub const BLACK: u8 = 0b00;
+/// Level of a dark gray pixel
+pub const DARK_GRAY: u8 = 0b01;
+/// Level of a light gray pixel
+pub const LIGHT_GRAY: u8 = 0b10;
+/// Level of a white pixel
+pub const WHITE: u8 = 0b11;
+
+/// Length of a buffer with two bits per pixel, rows are padded to whole bytes
+pub const fn packed_len(width: u32, height: u32) -> usize {
+    (width as usize).div_ceil(4) * height as usize
+}
+
+/// Number of pixels of every level in a buffer packed with two bits per pixel, indexed by the
+/// level from [`BLACK`] to [`WHITE`]
+///
+/// `width` is the number of pixels in a row, the padding at the end of the rows isn't counted.
+pub fn histogram_4gray(packed: &[u8], width: u32) -> [u32; 4] {
+    let mut histogram = [0; 4];
+    let row_len = (width as usize).div_ceil(4);
+    if row_len == 0 {
+        return histogram;
+    }
+    for row in packed.chunks_exact(row_len) {
+        for pixel in 0..width as usize {
+            let level = row[pixel / 4] >> (6 - 2 * (pixel % 4)) & 0b11;
+            histogram[level as usize] += 1;
+        }
+    }
+    histogram
+}
+
+/// Maps 8 bit grayscale values to the four gray levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrayMap {
+    thresholds: [u8; 3],
+}
+
+impl Default for GrayMap {
+    fn default() -> Self {
+        GrayMap::LINEAR
+    }
+}
+
+impl GrayMap {
+    /// Four bands of the same width
+    pub const LINEAR: GrayMap = GrayMap {
+        thresholds: [64, 128, 192],
+    };
+    /// Narrow gray bands, most pixels end up black or white
+    pub const HIGH_CONTRAST: GrayMap = GrayMap {
+        thresholds: [96, 128, 160],
+    };
+    /// Breakpoints shifted to the shadows, for photos
+    pub const PHOTO_OPTIMIZED: GrayMap = GrayMap {
+        thresholds: [40, 100, 170],
+    };
+
+    /// Creates a map from three thresholds, a value reaching a threshold gets the next level
+    ///
+    /// Returns `None` unless the thresholds are strictly ascending and the first isn't 0.
+    pub const fn new(thresholds: [u8; 3]) -> Option<Self> {
+        let [dark, light, white] = thresholds;
+        if dark == 0 || dark >= light || light >= white {
+            return None;
+        }
+        Some(GrayMap { thresholds })
+    }
+
+    /// The thresholds of the dark gray, light gray and white levels
+    pub const fn thresholds(&self) -> [u8; 3] {
+        self.thresholds
+    }
+
+    /// Level of an 8 bit grayscale value, from [`BLACK`] to [`WHITE`]
+    pub const fn level(&self, value: u8) -> u8 {
+        let [dark, light, white] = self.thresholds;
+        if value >= white {
+            WHITE
+        } else if value >= light {
+            LIGHT_GRAY
+        } else if value >= dark {
+            DARK_GRAY
+        } else {
+            BLACK
+        }
+    }
+
+    /// Packs rows of `width` 8 bit grayscale pixels with two bits per pixel
+    ///
+    /// INPUT needs to be a whole number of rows, OUTPUT of size: [`packed_len`] of them !
+    /// Padding pixels at the end of a row are white.
+    pub fn pack_2bpp(&self, input: &[u8], width: u32, output: &mut [u8]) {
+        let width = width as usize;
+        assert!(width > 0 && input.len().is_multiple_of(width));
+        let height = input.len() / width;
+        assert_eq!(output.len(), packed_len(width as u32, height as u32));
+
+        let row_len = width.div_ceil(4);
+        for (row, packed) in input.chunks(width).zip(output.chunks_mut(row_len)) {
+            for (i, byte) in packed.iter_mut().enumerate() {
+                *byte = 0;
+                for pixel in 0..4 {
+                    let level = row.get(i * 4 + pixel).map_or(WHITE, |&v| self.level(v));
+                    *byte |= level << (6 - 2 * pixel);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_at_the_thresholds() {
+        let map = GrayMap::new([10, 20, 30]).unwrap();
+        assert_eq!(map.level(0), BLACK);
+        assert_eq!(map.level(9), BLACK);
+        assert_eq!(map.level(10), DARK_GRAY);
+        assert_eq!(map.level(19), DARK_GRAY);
+        assert_eq!(map.level(20), LIGHT_GRAY);
+        assert_eq!(map.level(29), LIGHT_GRAY);
+        assert_eq!(map.level(30), WHITE);
+        assert_eq!(map.level(255), WHITE);
+    }
+
+    #[test]
+    fn thresholds_need_to_ascend() {
+        assert_eq!(GrayMap::new([0, 20, 30]), None);
+        assert_eq!(GrayMap::new([20, 20, 30]), None);
+        assert_eq!(GrayMap::new([30, 20, 10]), None);
+        for map in [
+            GrayMap::LINEAR,
+            GrayMap::HIGH_CONTRAST,
+            GrayMap::PHOTO_OPTIMIZED,
+        ] {
+            assert_eq!(GrayMap::new(map.thresholds()), Some(map));
+        }
+    }
+
+    #[test]
+    fn pack_first_pixel_in_the_upper_bits() {
+        let mut output = [0; 2];
+        GrayMap::LINEAR.pack_2bpp(&[0, 64, 128, 192, 255, 191, 127, 63], 8, &mut output);
+        assert_eq!(output, [0b00_01_10_11, 0b11_10_01_00]);
+    }
+
+    #[test]
+    fn pack_pads_rows_with_white() {
+        let mut output = [0; 4];
+        GrayMap::LINEAR.pack_2bpp(&[0; 10], 5, &mut output);
+        assert_eq!(output, [0x00, 0b00_11_11_11, 0x00, 0b00_11_11_11]);
+    }
+
+    #[test]
+    fn maps_differ_on_the_same_input() {
+        let ramp = [30, 50, 90, 110, 140, 165, 180, 200];
+        let mut packed = [[0; 2]; 3];
+        for (map, output) in [
+            GrayMap::LINEAR,
+            GrayMap::HIGH_CONTRAST,
+            GrayMap::PHOTO_OPTIMIZED,
+        ]
+        .iter()
+        .zip(packed.iter_mut())
+        {
+            map.pack_2bpp(&ramp, 8, output);
+        }
+        assert_eq!(packed[0], [0b00_00_01_01, 0b10_10_10_11]);
+        assert_eq!(packed[1], [0b00_00_00_01, 0b10_11_11_11]);
+        assert_eq!(packed[2], [0b00_01_01_10, 0b10_10_11_11]);
+    }
+
+    #[test]
+    fn histogram_of_packed_pixels() {
+        let mut packed = [0; 4];
+        GrayMap::LINEAR.pack_2bpp(&[0, 64, 128, 255, 255, 0, 0, 100, 200, 255], 5, &mut packed);
+        assert_eq!(histogram_4gray(&packed, 5), [3, 2, 1, 4]);
+        assert_eq!(histogram_4gray(&packed, 0), [0; 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pack_into_the_wrong_size() {
+        GrayMap::LINEAR.pack_2bpp(&[0; 8], 8, &mut [0; 3]);
+    }
+}
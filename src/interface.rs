@@ -1,11 +1,701 @@
-use crate::{error::ErrorKind, traits::Command};
+use crate::{
+    confirm::{self, BusyExpectation},
+    error::{ErrorKind, InitError},
+    traits::Command,
+};
+use core::cell::{Cell, RefCell};
 use core::fmt::{Debug, Display};
 use core::marker::PhantomData;
 use embedded_hal::{
     digital::{InputPin, OutputPin},
     spi::Operation,
 };
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+/// The BUSY signal of a display
+///
+/// This is implemented for every pin implementing [`InputPin`] and [`Wait`], which covers
+/// the common case of the busy line being connected to a GPIO of the controlling device.
+///
+/// Exotic setups (e.g. the busy line being routed through an I2C GPIO expander) can implement
+/// this trait themselves and pass their implementation wherever a busy pin is expected.
+/// [`PollingBusy`] can be used for pins that don't implement [`Wait`].
+pub trait BusySignal {
+    /// Error returned when the state of the signal can't be read
+    type Error: Copy + Debug + Display;
+
+    /// Waits until the display isn't busy anymore
+    ///
+    /// `is_busy_low` is true if the display signals busy with a low level
+    async fn wait_idle(&mut self, is_busy_low: bool) -> Result<(), Self::Error>;
+
+    /// Checks if the display is busy right now
+    ///
+    /// Errors while reading the signal are treated as not busy.
+    fn is_busy(&mut self, is_busy_low: bool) -> bool;
+}
+
+impl<P> BusySignal for P
+where
+    P: InputPin + Wait,
+    P::Error: Copy + Debug + Display,
+{
+    type Error = P::Error;
+
+    async fn wait_idle(&mut self, is_busy_low: bool) -> Result<(), Self::Error> {
+        if is_busy_low {
+            self.wait_for_high().await
+        } else {
+            self.wait_for_low().await
+        }
+    }
+
+    fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        (is_busy_low && self.is_low().unwrap_or(false))
+            || (!is_busy_low && self.is_high().unwrap_or(false))
+    }
+}
+
+/// [`BusySignal`] for pins which can only be polled
+///
+/// The pin is checked every `interval_us` microseconds until the display is idle.
+pub struct PollingBusy<P, D> {
+    pin: P,
+    delay: D,
+    interval_us: u32,
+}
+
+impl<P, D> PollingBusy<P, D>
+where
+    P: InputPin,
+    D: DelayNs,
+{
+    /// Creates a new polling busy signal
+    pub fn new(pin: P, delay: D, interval_us: u32) -> Self {
+        PollingBusy {
+            pin,
+            delay,
+            interval_us,
+        }
+    }
+
+    /// Changes the polling interval, taking effect from the next check
+    pub fn set_interval(&mut self, interval_us: u32) {
+        self.interval_us = interval_us;
+    }
+
+    /// Returns the wrapped pin and delay
+    pub fn release(self) -> (P, D) {
+        (self.pin, self.delay)
+    }
+}
+
+impl<P, D> BusySignal for PollingBusy<P, D>
+where
+    P: InputPin,
+    P::Error: Copy + Debug + Display,
+    D: DelayNs,
+{
+    type Error = P::Error;
+
+    async fn wait_idle(&mut self, is_busy_low: bool) -> Result<(), Self::Error> {
+        while self.pin.is_low()? == is_busy_low {
+            self.delay.delay_us(self.interval_us).await;
+        }
+        Ok(())
+    }
+
+    fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        (is_busy_low && self.pin.is_low().unwrap_or(false))
+            || (!is_busy_low && self.pin.is_high().unwrap_or(false))
+    }
+}
+
+/// The RST line of a display
+///
+/// This is implemented for every pin implementing [`OutputPin`], the common case of one reset
+/// line per display. Displays sharing a reset line use a [`SharedReset`] each.
+pub trait ResetControl {
+    /// Error returned when the line can't be driven
+    type Error: Copy + Debug + Display;
+
+    /// Pulls the line low while `active`, resetting the controller
+    fn set_reset(&mut self, active: bool) -> Result<(), Self::Error>;
+
+    /// Whether another device pulsed the line since this one did or [acknowledged](Self::acknowledge) it
+    fn reset_by_others(&self) -> bool {
+        false
+    }
+
+    /// Marks the pulses of other devices as seen
+    fn acknowledge(&mut self) {}
+}
+
+impl<P> ResetControl for P
+where
+    P: OutputPin,
+    P::Error: Copy + Debug + Display,
+{
+    type Error = P::Error;
+
+    fn set_reset(&mut self, active: bool) -> Result<(), Self::Error> {
+        if active {
+            self.set_low()
+        } else {
+            self.set_high()
+        }
+    }
+}
+
+/// A reset line shared by several displays, e.g. to save a GPIO
+///
+/// Resetting one display resets the controllers of all of them. Every display gets its own
+/// [`SharedReset`] from [`handle`](Self::handle), which notices the pulses of the others:
+/// the drivers report [`needs_init`](crate::traits::WaveshareDisplay::needs_init) and the next
+/// initialisation skips the pulse, as the controller was reset already.
+pub struct ResetLine<P> {
+    pin: RefCell<P>,
+    pulses: Cell<u32>,
+}
+
+impl<P> ResetLine<P> {
+    /// Creates a shared line driven by `pin`
+    pub const fn new(pin: P) -> Self {
+        ResetLine {
+            pin: RefCell::new(pin),
+            pulses: Cell::new(0),
+        }
+    }
+
+    /// Reset control for one display on the line
+    ///
+    /// If the line was pulsed before, the first initialisation of the display skips the pulse,
+    /// so creating the drivers one after another doesn't reset the ones created before.
+    pub fn handle(&self) -> SharedReset<'_, P> {
+        SharedReset {
+            line: self,
+            seen: 0,
+        }
+    }
+
+    /// Returns the pin
+    pub fn release(self) -> P {
+        self.pin.into_inner()
+    }
+}
+
+/// [`ResetControl`] of one display on a [`ResetLine`]
+pub struct SharedReset<'a, P> {
+    line: &'a ResetLine<P>,
+    seen: u32,
+}
+
+impl<P> ResetControl for SharedReset<'_, P>
+where
+    P: OutputPin,
+    P::Error: Copy + Debug + Display,
+{
+    type Error = P::Error;
+
+    fn set_reset(&mut self, active: bool) -> Result<(), Self::Error> {
+        let mut pin = self.line.pin.borrow_mut();
+        if active {
+            pin.set_low()?;
+            self.line.pulses.set(self.line.pulses.get().wrapping_add(1));
+            self.seen = self.line.pulses.get();
+            Ok(())
+        } else {
+            pin.set_high()
+        }
+    }
+
+    fn reset_by_others(&self) -> bool {
+        self.line.pulses.get() != self.seen
+    }
+
+    fn acknowledge(&mut self) {
+        self.seen = self.line.pulses.get();
+    }
+}
+
+/// One delay lent to several SPI devices
+///
+/// The drivers take no delay of their own, their delays are operations of the [`SpiDevice`].
+/// Bus sharing devices like the ones of `embedded-hal-bus` take a delay by value though, and
+/// delays usually aren't `Clone`. `SharedDelay` is a `Copy` handle to a delay in a [`RefCell`],
+/// hand one to every device:
+///
+/// ```rust, ignore
+/// let delay = RefCell::new(Delay);
+/// let spi_a = RefCellDevice::new(&bus, cs_a, SharedDelay::new(&delay))?;
+/// let spi_b = RefCellDevice::new(&bus, cs_b, SharedDelay::new(&delay))?;
+/// ```
+///
+/// If two drivers wait at the same time, e.g. both joined in one task, the second waits for the
+/// delay of the first to end before its own starts. A delay may last longer than asked for,
+/// never shorter.
+pub struct SharedDelay<'a, D> {
+    delay: &'a RefCell<D>,
+}
+
+impl<'a, D> SharedDelay<'a, D> {
+    /// Creates a handle to `delay`
+    pub const fn new(delay: &'a RefCell<D>) -> Self {
+        SharedDelay { delay }
+    }
+
+    /// Borrows the delay, waiting for other handles to finish their delays
+    async fn borrow(&self) -> core::cell::RefMut<'a, D> {
+        loop {
+            if let Ok(delay) = self.delay.try_borrow_mut() {
+                return delay;
+            }
+            YieldNow(false).await;
+        }
+    }
+}
+
+impl<D> Clone for SharedDelay<'_, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for SharedDelay<'_, D> {}
+
+impl<D: embedded_hal::delay::DelayNs> embedded_hal::delay::DelayNs for SharedDelay<'_, D> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay.borrow_mut().delay_ns(ns)
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay.borrow_mut().delay_us(us)
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay.borrow_mut().delay_ms(ms)
+    }
+}
+
+// the borrow is held during the delay on purpose, other handles wait for it in `borrow`
+#[allow(clippy::await_holding_refcell_ref)]
+impl<D: DelayNs> DelayNs for SharedDelay<'_, D> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.borrow().await.delay_ns(ns).await
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        self.borrow().await.delay_us(us).await
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        self.borrow().await.delay_ms(ms).await
+    }
+}
+
+/// Returns pending once, letting other futures of the task run
+pub(crate) struct YieldNow(pub(crate) bool);
+
+impl core::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<()> {
+        if self.0 {
+            return core::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        core::task::Poll::Pending
+    }
+}
+
+/// Number of bytes [`WordSpi`] packs into one SPI operation
+const WORD_CHUNK_LEN: usize = 64;
+
+/// Adapter for SPI devices transferring 16 bit words
+///
+/// Some MCUs (e.g. STM32 with a data size of 16 bit) achieve a better throughput with 16 bit
+/// frames. The drivers stay byte oriented: every operation is packed into words with the first
+/// byte in the upper half, so the bits on the wire are the same as with 8 bit frames.
+///
+/// Operations with an odd number of bytes are padded with `padding`, which is sent after the
+/// last byte. Every command is a single byte, so the controller has to ignore the padding both
+/// as command and as trailing data byte. Only use this adapter with drivers that document
+/// a suitable padding byte, e.g. [`epd7in5_hd::consts::WORD_PADDING`](crate::epd7in5_hd::consts::WORD_PADDING).
+/// [`WordSpiConstructor::try_new_word`](crate::traits::WordSpiConstructor::try_new_word) checks
+/// the padding when the driver is created.
+///
+/// Operations are split into chunks of 64 bytes, which may toggle the chip select in between.
+pub struct WordSpi<SPI> {
+    spi: SPI,
+    padding: u8,
+}
+
+impl<SPI> WordSpi<SPI>
+where
+    SPI: SpiDevice<u16>,
+{
+    /// Creates a new adapter padding odd operations with `padding`
+    pub fn new(spi: SPI, padding: u8) -> Self {
+        WordSpi { spi, padding }
+    }
+
+    /// Byte sent after operations with an odd number of bytes
+    pub fn padding(&self) -> u8 {
+        self.padding
+    }
+
+    /// Returns the wrapped SPI device
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+
+    /// Writes `write` and reads into `read` in chunks, missing write bytes are padded
+    async fn transfer_bytes(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), SPI::Error> {
+        let len = read.len().max(write.len());
+        let mut start = 0;
+        while start < len {
+            let end = (start + WORD_CHUNK_LEN).min(len);
+            let mut words = [0u16; WORD_CHUNK_LEN / 2];
+            let words = &mut words[..(end - start).div_ceil(2)];
+            for (i, word) in words.iter_mut().enumerate() {
+                let byte = |n: usize| write.get(start + n).copied().unwrap_or(self.padding);
+                *word = u16::from_be_bytes([byte(2 * i), byte(2 * i + 1)]);
+            }
+
+            if read.len() <= start {
+                self.spi.write(words).await?;
+            } else {
+                self.spi.transfer_in_place(words).await?;
+                for (i, byte) in words.iter().flat_map(|w| w.to_be_bytes()).enumerate() {
+                    if let Some(target) = read.get_mut(start + i) {
+                        *target = byte;
+                    }
+                }
+            }
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI> embedded_hal::spi::ErrorType for WordSpi<SPI>
+where
+    SPI: SpiDevice<u16>,
+{
+    type Error = SPI::Error;
+}
+
+impl<SPI> SpiDevice for WordSpi<SPI>
+where
+    SPI: SpiDevice<u16>,
+{
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(data) => self.transfer_bytes(&mut [], data).await?,
+                Operation::Read(buffer) => self.transfer_bytes(buffer, &[]).await?,
+                Operation::Transfer(read, write) => self.transfer_bytes(read, write).await?,
+                Operation::TransferInPlace(buffer) => {
+                    for chunk in buffer.chunks_mut(WORD_CHUNK_LEN) {
+                        let mut write = [0u8; WORD_CHUNK_LEN];
+                        let write = &mut write[..chunk.len()];
+                        write.copy_from_slice(chunk);
+                        self.transfer_bytes(chunk, write).await?;
+                    }
+                }
+                Operation::DelayNs(ns) => {
+                    self.spi.transaction(&mut [Operation::DelayNs(*ns)]).await?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// SPI device telling how many bytes its last transaction wrote
+///
+/// For devices that may report success before everything went out, e.g. non-blocking
+/// implementations, see [`VerifiedSpiDevice`].
+pub trait WriteCount {
+    /// Number of bytes the last transaction wrote
+    fn bytes_written(&self) -> usize;
+}
+
+/// Error of a [`VerifiedSpiDevice`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiError<E> {
+    /// Error of the wrapped device
+    Spi(E),
+    /// The transaction only wrote this many bytes
+    PartialWrite(usize),
+}
+
+impl<E: Display> Display for SpiError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SpiError::Spi(err) => Display::fmt(err, f),
+            SpiError::PartialWrite(n) => write!(f, "Only {} bytes were written", n),
+        }
+    }
+}
+
+impl<E: embedded_hal::spi::Error> embedded_hal::spi::Error for SpiError<E> {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        match self {
+            SpiError::Spi(err) => err.kind(),
+            SpiError::PartialWrite(_) => embedded_hal::spi::ErrorKind::Other,
+        }
+    }
+}
+
+/// Adapter checking that every transaction wrote all of its bytes
+///
+/// A driver writing through this adapter fails with `ErrorKind::SpiError(SpiError::PartialWrite(n))`
+/// instead of leaving a frame half sent. The wrapped device reports its count with [`WriteCount`].
+pub struct VerifiedSpiDevice<SPI> {
+    spi: SPI,
+}
+
+impl<SPI> VerifiedSpiDevice<SPI>
+where
+    SPI: SpiDevice + WriteCount,
+{
+    /// Creates a new adapter
+    pub fn new(spi: SPI) -> Self {
+        VerifiedSpiDevice { spi }
+    }
+
+    /// Returns the wrapped SPI device
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> embedded_hal::spi::ErrorType for VerifiedSpiDevice<SPI>
+where
+    SPI: SpiDevice + WriteCount,
+{
+    type Error = SpiError<SPI::Error>;
+}
+
+impl<SPI> SpiDevice for VerifiedSpiDevice<SPI>
+where
+    SPI: SpiDevice + WriteCount,
+{
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        let expected: usize = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Write(data) => data.len(),
+                Operation::Transfer(_, write) => write.len(),
+                Operation::TransferInPlace(buffer) => buffer.len(),
+                Operation::Read(_) | Operation::DelayNs(_) => 0,
+            })
+            .sum();
+        self.spi
+            .transaction(operations)
+            .await
+            .map_err(SpiError::Spi)?;
+        match self.spi.bytes_written() {
+            written if written < expected => Err(SpiError::PartialWrite(written)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How a driver waits for the display to become idle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BusyWait {
+    /// Wait with [`BusySignal::wait_idle`], e.g. for an edge interrupt of the busy pin
+    #[default]
+    Signal,
+    /// Check [`BusySignal::is_busy`] every busy poll interval, sleeping with the delay of the SPI
+    /// device in between
+    Poll,
+}
+
+/// Timing parameters of a driver, they can be changed at any time with
+/// [`set_timing`](crate::traits::WaveshareDisplay::set_timing)
+///
+/// The fixed delays of the command sequences, like the reset pulse or the power on time, are
+/// scaled by `delay_scale_percent` and rounded up to a multiple of `delay_granularity_us`.
+/// A delay implementation with a 10ms tick would otherwise turn a 5ms delay into no delay at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// Sleep between two checks of the busy signal while polling, in microseconds
+    pub busy_poll_interval_us: u32,
+    /// How to wait for the busy signal
+    pub busy_wait: BusyWait,
+    /// Longest wait for the busy signal in microseconds, after which the wait fails with
+    /// [`ErrorKind::BusyTimeout`]. `None` waits forever
+    ///
+    /// The time slept between two checks of the signal is counted, so with a timeout the busy
+    /// signal is always polled as with [`BusyWait::Poll`]. Every check counts at least 1 µs, when
+    /// busy waiting the timeout is the number of checks.
+    pub busy_timeout_us: Option<u32>,
+    /// Scale of the fixed delays, in percent
+    pub delay_scale_percent: u32,
+    /// Fixed delays are rounded up to a multiple of this, in microseconds. 0 disables rounding
+    pub delay_granularity_us: u32,
+    /// Hook called regularly during long operations, off if `None`
+    pub watchdog: Option<Watchdog>,
+}
+
+impl Timing {
+    /// Default busy poll interval, 10ms
+    pub const DEFAULT_BUSY_POLL_INTERVAL_US: u32 = 10_000;
+
+    /// Duration of a fixed delay of `duration_us` microseconds after scaling and rounding up
+    pub const fn scale(&self, duration_us: u32) -> u32 {
+        let scaled = (duration_us as u64 * self.delay_scale_percent as u64).div_ceil(100);
+        let granularity = self.delay_granularity_us as u64;
+        let rounded = if granularity > 1 {
+            scaled.div_ceil(granularity) * granularity
+        } else {
+            scaled
+        };
+        if rounded > u32::MAX as u64 {
+            u32::MAX
+        } else {
+            rounded as u32
+        }
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            busy_poll_interval_us: Self::DEFAULT_BUSY_POLL_INTERVAL_US,
+            busy_wait: BusyWait::Signal,
+            busy_timeout_us: None,
+            delay_scale_percent: 100,
+            delay_granularity_us: 0,
+            watchdog: None,
+        }
+    }
+}
+
+/// Hook called regularly during long waits and transmissions, e.g. to feed a hardware watchdog
+///
+/// The drivers count the time they sleep, in the fixed delays and while polling the busy signal,
+/// and the bytes they send. Whenever these add up to `interval_us`, `feed` is called, so it
+/// runs at least that often during any operation of the driver. Waiting for the edge of the busy
+/// signal can't be interrupted, so with a watchdog the busy signal is always polled as with
+/// [`BusyWait::Poll`].
+///
+/// `feed` is called directly by the driver, between two SPI transactions and never while
+/// another call of it is running. It should return quickly.
+#[derive(Debug, Clone, Copy)]
+pub struct Watchdog {
+    /// Called at least every `interval_us`
+    pub feed: fn(),
+    /// Longest time between two calls, in microseconds
+    pub interval_us: u32,
+    /// Bytes sent within `interval_us` at the slowest SPI clock, sending them counts as the
+    /// whole interval
+    pub bytes_per_interval: u32,
+}
+
+// Compares the addresses of the hooks, the same function may have several
+impl PartialEq for Watchdog {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::fn_addr_eq(self.feed, other.feed)
+            && self.interval_us == other.interval_us
+            && self.bytes_per_interval == other.bytes_per_interval
+    }
+}
+
+impl Eq for Watchdog {}
+
+impl Watchdog {
+    /// Default interval between two calls, 500ms
+    pub const DEFAULT_INTERVAL_US: u32 = 500_000;
+
+    /// Calls `feed` every 500ms, for an SPI clock of at least 100kHz
+    pub const fn new(feed: fn()) -> Self {
+        Watchdog {
+            feed,
+            interval_us: Self::DEFAULT_INTERVAL_US,
+            // 100kHz are 12500 bytes per second
+            bytes_per_interval: 6_250,
+        }
+    }
+
+    // The progress towards the next call is counted in units of 1 / (interval × bytes), so a
+    // microsecond counts as `bytes_per_interval` and a byte as `interval_us`
+    fn due(&self) -> u64 {
+        self.interval_us.max(1) as u64 * self.bytes_per_interval.max(1) as u64
+    }
+}
+
+/// Bytes a driver wrote to the controller, see
+/// [`io_stats`](crate::traits::WaveshareDisplay::io_stats)
+///
+/// The counters wrap around, [`since`](Self::since) still gives the right difference.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Command bytes, sent with the data/command line low
+    pub command_bytes: u32,
+    /// Data bytes, sent with the data/command line high
+    pub data_bytes: u32,
+}
+
+impl IoStats {
+    /// All bytes written
+    pub const fn total(&self) -> u32 {
+        self.command_bytes.wrapping_add(self.data_bytes)
+    }
+
+    /// The bytes written since the counters were at `earlier`
+    pub const fn since(&self, earlier: IoStats) -> IoStats {
+        IoStats {
+            command_bytes: self.command_bytes.wrapping_sub(earlier.command_bytes),
+            data_bytes: self.data_bytes.wrapping_sub(earlier.data_bytes),
+        }
+    }
+}
+
+/// Confirmation of state changing commands, for displays at the end of a long cable
+///
+/// After a command that makes the controller busy, like starting the refresh, the busy line is
+/// checked every `poll_interval_us` until it asserts. If it doesn't within the time the
+/// controller takes (see the tables in `confirm.rs`), the command is sent again, up to
+/// `retries` times, before failing with `ErrorKind::CommandUnconfirmed`.
+///
+/// Every confirmed command costs at least one check of the busy line, nothing else is sent
+/// while the command lands. Needs a busy signal whose [`BusySignal::is_busy`] reads the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeCommands {
+    /// How often an unconfirmed command is sent again
+    pub retries: u8,
+    /// Sleep between two checks of the busy line, in microseconds
+    pub poll_interval_us: u32,
+}
+
+impl Default for SafeCommands {
+    fn default() -> Self {
+        SafeCommands {
+            retries: 2,
+            poll_interval_us: 100,
+        }
+    }
+}
+
+/// Time the busy line has to stay at one level before [`DisplayInterface::busy_reaction`]
+/// trusts it as the idle level, long enough for a controller to finish its reset
+pub(crate) const REACTION_STEADY_US: u32 = 10_000;
+/// Interval between two samples of the busy line in [`DisplayInterface::busy_reaction`]
+const REACTION_SAMPLE_US: u32 = 50;
 
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
@@ -20,8 +710,42 @@ pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE:
     dc: DC,
     /// Pin for Resetting
     rst: RST,
-    /// number of ms the idle loop should sleep on
-    delay_us: u32,
+    /// Busy polling and the scaling of the fixed delays
+    timing: Timing,
+    /// The controller was reset behind the back of the driver
+    needs_init: bool,
+    /// A refresh was started without waiting for it
+    update_pending: bool,
+    /// Confirmation of state changing commands, off if `None`
+    safe_commands: Option<SafeCommands>,
+    /// Run the sanity check of the driver on every initialisation
+    check_panel: bool,
+    /// Time and bytes since the last call of the watchdog, see [`Watchdog::due`]
+    watchdog_progress: u64,
+    /// Bytes written since the last reset of the counters
+    io_stats: IoStats,
+}
+
+#[cfg(feature = "compat-0-5")]
+impl<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE: bool>
+    DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>
+{
+    /// The same interface for another type of SPI device, see [`crate::compat`]
+    pub(crate) fn rebind_spi<S>(self) -> DisplayInterface<S, BUSY, DC, RST, SINGLE_BYTE_WRITE> {
+        DisplayInterface {
+            _spi: PhantomData,
+            busy: self.busy,
+            dc: self.dc,
+            rst: self.rst,
+            timing: self.timing,
+            needs_init: self.needs_init,
+            update_pending: self.update_pending,
+            safe_commands: self.safe_commands,
+            check_panel: self.check_panel,
+            watchdog_progress: self.watchdog_progress,
+            io_stats: self.io_stats,
+        }
+    }
 }
 
 impl<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE: bool>
@@ -29,25 +753,98 @@ impl<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE: bool>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// Creates a new `DisplayInterface` struct
     ///
-    /// If no delay is given, a default delay of 10ms is used.
+    /// `delay_us` is the busy poll interval, if no delay is given a default delay of 10ms is
+    /// used.
     pub fn new(busy: BUSY, dc: DC, rst: RST, delay_us: Option<u32>) -> Self {
-        // default delay of 10ms
-        let delay_us = delay_us.unwrap_or(10_000);
         DisplayInterface {
             _spi: PhantomData,
             busy,
             dc,
             rst,
-            delay_us,
+            timing: Timing {
+                busy_poll_interval_us: delay_us.unwrap_or(Timing::DEFAULT_BUSY_POLL_INTERVAL_US),
+                ..Timing::default()
+            },
+            needs_init: false,
+            update_pending: false,
+            safe_commands: None,
+            check_panel: false,
+            watchdog_progress: 0,
+            io_stats: IoStats::default(),
+        }
+    }
+
+    /// The timing parameters
+    pub(crate) fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Replaces the timing parameters, taking effect from the next delay or wait
+    pub(crate) fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Turns the confirmation of state changing commands on or off
+    pub(crate) fn set_safe_commands(&mut self, safe_commands: Option<SafeCommands>) {
+        self.safe_commands = safe_commands;
+    }
+
+    /// Whether the drivers run their sanity check on every initialisation
+    pub(crate) fn check_panel(&self) -> bool {
+        self.check_panel
+    }
+
+    /// Turns the sanity check on initialisation on or off
+    pub(crate) fn set_check_panel(&mut self, check: bool) {
+        self.check_panel = check;
+    }
+
+    /// The bytes written since the last [`reset_io_stats`](Self::reset_io_stats)
+    pub(crate) fn io_stats(&self) -> IoStats {
+        self.io_stats
+    }
+
+    /// Sets the byte counters back to zero
+    pub(crate) fn reset_io_stats(&mut self) {
+        self.io_stats = IoStats::default();
+    }
+
+    /// Whether a refresh was started without waiting for it to finish
+    pub(crate) fn update_pending(&self) -> bool {
+        self.update_pending
+    }
+
+    /// Notes that a refresh was started without waiting, the next wait until idle clears it
+    pub(crate) fn set_update_pending(&mut self) {
+        self.update_pending = true;
+    }
+
+    /// Whether the controller was reset since the last initialisation and needs another one
+    pub(crate) fn needs_init(&self) -> bool {
+        self.needs_init || self.rst.reset_by_others()
+    }
+
+    /// Notes that the controller was reset by other means than [`reset`](Self::reset)
+    pub(crate) fn on_external_reset(&mut self) {
+        self.needs_init = true;
+    }
+
+    /// Gives back the pins together with an error that happened during the initialisation
+    pub(crate) fn into_init_error<E>(self, error: E) -> InitError<E, BUSY, DC, RST> {
+        InitError {
+            error,
+            busy: self.busy,
+            dc: self.dc,
+            rst: self.rst,
         }
     }
 
@@ -62,10 +859,86 @@ where
         // low for commands
         let _ = self.dc.set_low().map_err(ErrorKind::DcError)?;
 
+        self.io_stats.command_bytes = self.io_stats.command_bytes.wrapping_add(1);
         // Transfer the command over spi
         self.write(spi, &[command.address()]).await
     }
 
+    /// Sends a command, checking that the busy line reacts to it if [`SafeCommands`] are on
+    ///
+    /// `expectations` is the table of the controller family, commands not in it are sent as is.
+    pub(crate) async fn cmd_confirmed<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        is_busy_low: bool,
+        expectations: &[BusyExpectation],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let (Some(safe), Some(within_us)) = (
+            self.safe_commands,
+            confirm::busy_within(expectations, command.address()),
+        ) else {
+            return self.cmd(spi, command).await;
+        };
+
+        for _ in 0..=safe.retries {
+            self.cmd(spi, command).await?;
+            let mut waited = 0;
+            loop {
+                if self.is_busy(is_busy_low) {
+                    return Ok(());
+                }
+                if waited >= within_us {
+                    break;
+                }
+                let step = safe.poll_interval_us.clamp(1, within_us - waited);
+                self.sleep(spi, step).await?;
+                waited += step;
+            }
+        }
+        Err(ErrorKind::CommandUnconfirmed(command.address()))
+    }
+
+    /// Sends `command` and reports the level the busy line idles at, judged by its reaction
+    ///
+    /// `Some(true)` if the line idled high and went low, like BUSY_N of the UC81xx controllers,
+    /// `Some(false)` if it idled low and went high, like BUSY of the SSD16xx controllers. The
+    /// line has to stay at one level for [`REACTION_STEADY_US`] before the command and switch
+    /// to the other for two samples in a row within `within_us` after it, everything else is
+    /// `None`: a line that doesn't move, a floating one or one still settling from the reset.
+    pub(crate) async fn busy_reaction<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        within_us: u32,
+    ) -> Result<Option<bool>, ErrorKind<SPI, BUSY, DC, RST>> {
+        let idle_high = self.busy.is_busy(false);
+        let mut waited = 0;
+        while waited < REACTION_STEADY_US {
+            self.sleep(spi, REACTION_SAMPLE_US).await?;
+            waited += REACTION_SAMPLE_US;
+            if self.busy.is_busy(false) != idle_high {
+                return Ok(None);
+            }
+        }
+
+        self.cmd(spi, command).await?;
+        let (mut waited, mut switched) = (0, 0);
+        while waited < within_us {
+            self.sleep(spi, REACTION_SAMPLE_US).await?;
+            waited += REACTION_SAMPLE_US;
+            if self.busy.is_busy(false) == idle_high {
+                switched = 0;
+                continue;
+            }
+            switched += 1;
+            if switched == 2 {
+                return Ok(Some(idle_high));
+            }
+        }
+        Ok(None)
+    }
+
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
@@ -76,6 +949,7 @@ where
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         // high for data
         let _ = self.dc.set_high().map_err(ErrorKind::DcError)?;
+        self.io_stats.data_bytes = self.io_stats.data_bytes.wrapping_add(data.len() as u32);
 
         if SINGLE_BYTE_WRITE {
             for val in data.iter().copied() {
@@ -102,6 +976,20 @@ where
         self.data(spi, data).await
     }
 
+    /// Sends `command` and reads the answer of the controller into `buffer`
+    ///
+    /// Only works if the data line of the controller can be read, e.g. through a MISO line.
+    pub(crate) async fn cmd_read<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        buffer: &mut [u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.cmd(spi, command).await?;
+        self.dc.set_high().map_err(ErrorKind::DcError)?;
+        spi.read(buffer).await.map_err(ErrorKind::SpiError)
+    }
+
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
@@ -113,6 +1001,7 @@ where
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         // high for data
         let _ = self.dc.set_high().map_err(ErrorKind::DcError)?;
+        self.io_stats.data_bytes = self.io_stats.data_bytes.wrapping_add(repetitions);
         // Transfer data (u8) over spi
         for _ in 0..repetitions {
             self.write(spi, &[val]).await?;
@@ -120,11 +1009,31 @@ where
         Ok(())
     }
 
-    // spi write helper/abstraction function
+    // spi write helper/abstraction function, splits the data to call the watchdog in time
     async fn write(
         &mut self,
         spi: &mut SPI,
         data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let Some(watchdog) = self.timing.watchdog else {
+            return self.write_chunks(spi, data).await;
+        };
+        let mut data = data;
+        while !data.is_empty() {
+            let left = watchdog.due().saturating_sub(self.watchdog_progress);
+            let until_due = left.div_ceil(watchdog.interval_us.max(1) as u64);
+            let (now, rest) = data.split_at(data.len().min(until_due as usize));
+            self.write_chunks(spi, now).await?;
+            self.watchdog_advance(0, now.len());
+            data = rest;
+        }
+        Ok(())
+    }
+
+    async fn write_chunks(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         // transfer spi data
         // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
@@ -153,17 +1062,29 @@ where
     /// Most likely there was a mistake with the 2in9 busy connection
     pub(crate) async fn wait_until_idle(
         &mut self,
-        _spi: &mut SPI,
+        spi: &mut SPI,
         is_busy_low: bool,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        if is_busy_low {
-            self.busy
-                .wait_for_high()
-                .await
-                .map_err(ErrorKind::BusyError)
-        } else {
-            self.busy.wait_for_low().await.map_err(ErrorKind::BusyError)
+        match self.timing.busy_wait {
+            BusyWait::Signal
+                if self.timing.watchdog.is_none() && self.timing.busy_timeout_us.is_none() =>
+            {
+                self.busy
+                    .wait_idle(is_busy_low)
+                    .await
+                    .map_err(ErrorKind::BusyError)?
+            }
+            _ => {
+                let mut waited = 0;
+                while self.is_busy(is_busy_low) {
+                    self.check_busy_timeout(waited)?;
+                    self.sleep(spi, self.timing.busy_poll_interval_us).await?;
+                    waited = waited.saturating_add(self.timing.busy_poll_interval_us.max(1));
+                }
+            }
         }
+        self.update_pending = false;
+        Ok(())
     }
 
     /// Same as `wait_until_idle` for device needing a command to probe Busy pin
@@ -174,23 +1095,75 @@ where
         status_command: T,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         // TODO: would be better implemented with racing the busy pin state and the delay
+        let mut waited = 0;
         while self.is_busy(is_busy_low) {
+            self.check_busy_timeout(waited)?;
             self.cmd(spi, status_command).await?;
-            if self.delay_us > 0 {
-                self.delay(spi, self.delay_us).await?;
+            if self.timing.busy_poll_interval_us > 0 {
+                self.sleep(spi, self.timing.busy_poll_interval_us).await?;
             }
+            waited = waited.saturating_add(self.timing.busy_poll_interval_us.max(1));
         }
+        self.update_pending = false;
         Ok(())
     }
 
+    /// Fails with [`ErrorKind::BusyTimeout`] once `waited` microseconds reach the busy timeout
+    fn check_busy_timeout(&self, waited: u32) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        match self.timing.busy_timeout_us {
+            Some(timeout) if waited >= timeout => Err(ErrorKind::BusyTimeout),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fixed delay of a command sequence, scaled and rounded up as configured in the [`Timing`]
     pub(crate) async fn delay(
         &mut self,
         spi: &mut SPI,
         duration: u32,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        spi.transaction(&mut [Operation::DelayNs(duration * 1000)])
-            .await
-            .map_err(ErrorKind::SpiError)
+        self.sleep(spi, self.timing.scale(duration)).await
+    }
+
+    /// Sleeps for exactly `duration` microseconds, split up to call the watchdog in time
+    async fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        duration: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let mut remaining = duration;
+        loop {
+            let step = match self.timing.watchdog {
+                Some(watchdog) => {
+                    let left = watchdog.due().saturating_sub(self.watchdog_progress);
+                    let until_due = left.div_ceil(watchdog.bytes_per_interval.max(1) as u64);
+                    remaining.min(until_due.min(u32::MAX as u64) as u32)
+                }
+                None => remaining,
+            };
+            spi.transaction(&mut [Operation::DelayNs(step.saturating_mul(1000))])
+                .await
+                .map_err(ErrorKind::SpiError)?;
+            self.watchdog_advance(step, 0);
+            remaining -= step;
+            if remaining == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Counts `us` microseconds and `bytes` bytes towards the next call of the watchdog
+    fn watchdog_advance(&mut self, us: u32, bytes: usize) {
+        let Some(watchdog) = self.timing.watchdog else {
+            return;
+        };
+        let progress = us as u64 * watchdog.bytes_per_interval.max(1) as u64
+            + bytes as u64 * watchdog.interval_us.max(1) as u64;
+        self.watchdog_progress = self.watchdog_progress.saturating_add(progress);
+        if self.watchdog_progress >= watchdog.due() {
+            self.watchdog_progress = 0;
+            (watchdog.feed)();
+        }
     }
 
     /// Checks if device is still busy
@@ -207,8 +1180,7 @@ where
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
     pub(crate) fn is_busy(&mut self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+        self.busy.is_busy(is_busy_low)
     }
 
     /// Resets the device.
@@ -224,14 +1196,610 @@ where
         initial_delay: u32,
         duration: u32,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        self.rst.set_high().map_err(ErrorKind::RstError)?;
+        self.needs_init = false;
+        if self.rst.reset_by_others() {
+            // another display on the shared line just reset the controller
+            self.rst.acknowledge();
+            return Ok(());
+        }
+
+        self.rst.set_reset(false).map_err(ErrorKind::RstError)?;
         self.delay(spi, initial_delay).await?;
 
-        self.rst.set_low().map_err(ErrorKind::RstError)?;
+        self.rst.set_reset(true).map_err(ErrorKind::RstError)?;
         self.delay(spi, duration).await?;
-        self.rst.set_high().map_err(ErrorKind::RstError)?;
+        self.rst.set_reset(false).map_err(ErrorKind::RstError)?;
         //TODO: the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
         self.delay(spi, 200_000).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd7in5_v2::command::Command;
+    use crate::test_utils::{block_on, NoopPin, RecordingSpi, RecordingWordSpi};
+    use core::convert::Infallible;
+
+    /// Busy signal reporting busy for a fixed number of checks, like an expander would
+    struct ScriptedBusy {
+        busy_checks: u32,
+        waits: u32,
+    }
+
+    impl BusySignal for ScriptedBusy {
+        type Error = Infallible;
+
+        async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+            self.waits += 1;
+            self.busy_checks = 0;
+            Ok(())
+        }
+
+        fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+            if self.busy_checks > 0 {
+                self.busy_checks -= 1;
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn custom_busy_signal() {
+        let busy = ScriptedBusy {
+            busy_checks: 2,
+            waits: 0,
+        };
+        let mut interface =
+            DisplayInterface::<RecordingSpi, _, _, _, false>::new(busy, NoopPin, NoopPin, Some(0));
+        let mut spi = RecordingSpi::default();
+
+        assert!(interface.is_busy(true));
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert!(!interface.is_busy(true));
+        assert_eq!(interface.busy.waits, 1);
+    }
+
+    #[test]
+    fn polling_the_busy_signal() {
+        let busy = ScriptedBusy {
+            busy_checks: 3,
+            waits: 0,
+        };
+        let mut interface =
+            DisplayInterface::<RecordingSpi, _, _, _, false>::new(busy, NoopPin, NoopPin, Some(0));
+        let mut spi = RecordingSpi::default();
+        interface.set_timing(Timing {
+            busy_poll_interval_us: 250,
+            busy_wait: BusyWait::Poll,
+            ..interface.timing()
+        });
+
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert_eq!(interface.busy.waits, 0);
+        assert_eq!(spi.delays, [250_000; 3]);
+
+        // the poll interval isn't scaled
+        interface.set_timing(Timing {
+            busy_wait: BusyWait::Signal,
+            delay_scale_percent: 200,
+            ..interface.timing()
+        });
+        interface.busy.busy_checks = 1;
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert_eq!(interface.busy.waits, 1);
+        interface.busy.busy_checks = 1;
+        block_on(interface.wait_until_idle_with_cmd(&mut spi, true, Command::GetStatus)).unwrap();
+        assert_eq!(spi.delays, [250_000; 4]);
+    }
+
+    #[test]
+    fn busy_timeout() {
+        let busy = ScriptedBusy {
+            busy_checks: u32::MAX,
+            waits: 0,
+        };
+        let mut interface = DisplayInterface::<RecordingSpi, _, _, _, false>::new(
+            busy,
+            NoopPin,
+            NoopPin,
+            Some(1_000),
+        );
+        interface.set_timing(Timing {
+            busy_timeout_us: Some(2_500),
+            ..interface.timing()
+        });
+        let mut spi = RecordingSpi::default();
+
+        assert!(matches!(
+            block_on(interface.wait_until_idle(&mut spi, true)),
+            Err(ErrorKind::BusyTimeout)
+        ));
+        assert_eq!(interface.busy.waits, 0);
+        assert_eq!(spi.delays, [1_000_000; 3]);
+        assert!(matches!(
+            block_on(interface.wait_until_idle_with_cmd(&mut spi, true, Command::GetStatus)),
+            Err(ErrorKind::BusyTimeout)
+        ));
+        assert_eq!(spi.delays.len(), 6);
+
+        // the display becoming idle in time
+        interface.busy.busy_checks = 2;
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert_eq!(spi.delays.len(), 8);
+    }
+
+    #[test]
+    fn busy_timeout_while_busy_waiting() {
+        let busy = ScriptedBusy {
+            busy_checks: u32::MAX,
+            waits: 0,
+        };
+        let mut interface =
+            DisplayInterface::<RecordingSpi, _, _, _, false>::new(busy, NoopPin, NoopPin, Some(0));
+        interface.set_timing(Timing {
+            busy_timeout_us: Some(5),
+            ..interface.timing()
+        });
+        let mut spi = RecordingSpi::default();
+
+        assert!(matches!(
+            block_on(interface.wait_until_idle(&mut spi, true)),
+            Err(ErrorKind::BusyTimeout)
+        ));
+        assert!(matches!(
+            block_on(interface.wait_until_idle_with_cmd(&mut spi, true, Command::GetStatus)),
+            Err(ErrorKind::BusyTimeout)
+        ));
+        assert_eq!(spi.take(), [Command::GetStatus as u8; 5]);
+    }
+
+    /// Pin which is busy until an edge is awaited, recording the awaited levels
+    #[derive(Default)]
+    struct EdgePin {
+        low: bool,
+        awaited: std::vec::Vec<bool>,
+    }
+
+    impl embedded_hal::digital::ErrorType for EdgePin {
+        type Error = Infallible;
+    }
+
+    impl InputPin for EdgePin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.low)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.low)
+        }
+    }
+
+    impl Wait for EdgePin {
+        async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+            self.awaited.push(true);
+            self.low = false;
+            Ok(())
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+            self.awaited.push(false);
+            self.low = true;
+            Ok(())
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+            self.wait_for_high().await
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+            self.wait_for_low().await
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+            self.low = !self.low;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn waiting_for_the_pin_without_polling() {
+        let busy = EdgePin {
+            low: true,
+            ..EdgePin::default()
+        };
+        let mut interface =
+            DisplayInterface::<RecordingSpi, _, _, _, false>::new(busy, NoopPin, NoopPin, Some(0));
+        let mut spi = RecordingSpi::default();
+
+        assert!(interface.is_busy(true));
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert!(!interface.is_busy(true));
+        assert!(interface.is_busy(false));
+        block_on(interface.wait_until_idle(&mut spi, false)).unwrap();
+        assert_eq!(interface.busy.awaited, [true, false]);
+        assert!(spi.delays.is_empty());
+
+        // polling only sleeps while the pin is busy
+        interface.set_timing(Timing {
+            busy_wait: BusyWait::Poll,
+            ..interface.timing()
+        });
+        block_on(interface.wait_until_idle(&mut spi, false)).unwrap();
+        assert_eq!(interface.busy.awaited.len(), 2);
+        assert!(spi.delays.is_empty());
+    }
+
+    #[test]
+    fn fixed_delays_are_scaled_and_rounded_up() {
+        let timing = Timing {
+            delay_granularity_us: 10_000,
+            ..Timing::default()
+        };
+        assert_eq!(timing.scale(0), 0);
+        assert_eq!(timing.scale(1), 10_000);
+        assert_eq!(timing.scale(5_000), 10_000);
+        assert_eq!(timing.scale(10_000), 10_000);
+        assert_eq!(timing.scale(10_001), 20_000);
+
+        let timing = Timing {
+            delay_scale_percent: 150,
+            ..timing
+        };
+        assert_eq!(timing.scale(5_000), 10_000);
+        assert_eq!(timing.scale(10_000), 20_000);
+        assert_eq!(timing.scale(u32::MAX), u32::MAX);
+
+        let timing = Timing {
+            delay_scale_percent: 50,
+            delay_granularity_us: 0,
+            ..timing
+        };
+        assert_eq!(timing.scale(5_001), 2_501);
+        assert_eq!(Timing::default().scale(5_000), 5_000);
+    }
+
+    #[test]
+    fn word_spi_pads_odd_writes() {
+        let mut spi = WordSpi::new(RecordingWordSpi::default(), 0xAA);
+
+        block_on(spi.write(&[0x01, 0x02, 0x03])).unwrap();
+        block_on(spi.write(&[0x04])).unwrap();
+        assert_eq!(spi.spi.written, [0x0102, 0x03AA, 0x04AA]);
+    }
+
+    #[test]
+    fn word_spi_splits_long_writes() {
+        let mut spi = WordSpi::new(RecordingWordSpi::default(), 0xAA);
+        let data: [u8; 2 * WORD_CHUNK_LEN + 1] = core::array::from_fn(|i| i as u8);
+
+        block_on(spi.write(&data)).unwrap();
+        let written = spi.release().take_bytes();
+        assert_eq!(&written[..data.len()], &data[..]);
+        assert_eq!(written[data.len()..], [0xAA]);
+    }
+
+    #[test]
+    fn word_spi_transfers() {
+        let mut spi = WordSpi::new(RecordingWordSpi::default(), 0x00);
+
+        let mut read = [0u8; 3];
+        block_on(spi.transfer(&mut read, &[0x0F, 0xF0])).unwrap();
+        assert_eq!(read, [0xF0, 0x0F, 0xFF]);
+
+        let mut buffer = [0x12, 0x34, 0x56];
+        block_on(spi.transfer_in_place(&mut buffer)).unwrap();
+        assert_eq!(buffer, [!0x12, !0x34, !0x56]);
+    }
+
+    /// Delay recording every call, it yields once before returning like a timer would
+    #[derive(Default)]
+    struct YieldingDelay {
+        delays: std::vec::Vec<u32>,
+    }
+
+    impl DelayNs for YieldingDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            YieldNow(false).await;
+            self.delays.push(ns);
+        }
+    }
+
+    /// Spi device delaying with `D`
+    struct DelayedSpi<D> {
+        delay: D,
+    }
+
+    impl<D> embedded_hal::spi::ErrorType for DelayedSpi<D> {
+        type Error = Infallible;
+    }
+
+    impl<D: DelayNs> SpiDevice for DelayedSpi<D> {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations.iter() {
+                if let Operation::DelayNs(ns) = operation {
+                    self.delay.delay_ns(*ns).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn drivers_share_one_delay() {
+        use crate::epd2in9bc::Epd2in9bc;
+        use crate::test_utils::IdleBusy;
+        use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+        use core::future::{poll_fn, Future};
+        use core::pin::pin;
+
+        async fn run<SPI: SpiDevice>(spi: &mut SPI)
+        where
+            SPI::Error: Copy + Debug + Display,
+        {
+            let mut epd = Epd2in9bc::new(spi, IdleBusy, NoopPin, NoopPin, Some(0))
+                .await
+                .unwrap();
+            epd.clear_frame(spi).await.unwrap();
+            epd.sleep(spi).await.unwrap();
+        }
+
+        let mut recording = RecordingSpi::default();
+        block_on(run(&mut recording));
+        assert!(!recording.delays.is_empty());
+
+        let delay = RefCell::new(YieldingDelay::default());
+        let mut spi_a = DelayedSpi {
+            delay: SharedDelay::new(&delay),
+        };
+        let mut spi_b = DelayedSpi {
+            delay: SharedDelay::new(&delay),
+        };
+        // both drivers wait at the same time
+        let (mut a, mut b) = (pin!(run(&mut spi_a)), pin!(run(&mut spi_b)));
+        let (mut a_done, mut b_done) = (false, false);
+        block_on(poll_fn(|cx| {
+            a_done = a_done || a.as_mut().poll(cx).is_ready();
+            b_done = b_done || b.as_mut().poll(cx).is_ready();
+            if a_done && b_done {
+                core::task::Poll::Ready(())
+            } else {
+                core::task::Poll::Pending
+            }
+        }));
+
+        let delays = &delay.borrow().delays;
+        assert_eq!(delays.len(), 2 * recording.delays.len());
+        assert_eq!(
+            delays.iter().map(|&ns| ns as u64).sum::<u64>(),
+            2 * recording.delays.iter().map(|&ns| ns as u64).sum::<u64>()
+        );
+
+        // lent to blocking code as well
+        let delay = RefCell::new(embedded_hal_mock::eh1::delay::NoopDelay::new());
+        embedded_hal::delay::DelayNs::delay_ms(&mut SharedDelay::new(&delay), 1);
+    }
+
+    /// Spi device sending at most `max` bytes of every write, like a full DMA queue
+    #[derive(Default)]
+    struct ShortSpi {
+        max: usize,
+        written: usize,
+    }
+
+    impl embedded_hal::spi::ErrorType for ShortSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for ShortSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            self.written = operations
+                .iter()
+                .map(|operation| match operation {
+                    Operation::Write(data) => data.len().min(self.max),
+                    _ => 0,
+                })
+                .sum();
+            Ok(())
+        }
+    }
+
+    impl WriteCount for ShortSpi {
+        fn bytes_written(&self) -> usize {
+            self.written
+        }
+    }
+
+    #[test]
+    fn partial_writes_fail() {
+        use crate::epd7in5_v2::{self, Epd7in5};
+        use crate::test_utils::{blank_buffer, IdleBusy};
+        use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+
+        let mut spi = VerifiedSpiDevice::new(ShortSpi {
+            max: 64,
+            ..ShortSpi::default()
+        });
+        let mut epd =
+            block_on(Epd7in5::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        block_on(spi.write(&[0; 64])).unwrap();
+
+        // the frame is written in one go
+        let buffer = blank_buffer(epd7in5_v2::WIDTH, epd7in5_v2::HEIGHT);
+        assert!(matches!(
+            block_on(epd.update_frame(&mut spi, &buffer)),
+            Err(ErrorKind::SpiError(SpiError::PartialWrite(64)))
+        ));
+        assert!(matches!(
+            block_on(spi.write(&[0; 65])),
+            Err(SpiError::PartialWrite(64))
+        ));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum WatchdogStep {
+        Write(usize),
+        Delay(u32),
+        Feed,
+    }
+
+    static WATCHDOG_LOG: std::sync::Mutex<std::vec::Vec<WatchdogStep>> =
+        std::sync::Mutex::new(std::vec::Vec::new());
+
+    fn log(step: WatchdogStep) {
+        WATCHDOG_LOG.lock().unwrap().push(step);
+    }
+
+    struct WatchdogSpi;
+
+    impl embedded_hal::spi::ErrorType for WatchdogSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for WatchdogSpi {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Self::Error> {
+            for operation in operations.iter() {
+                match operation {
+                    Operation::Write(data) => log(WatchdogStep::Write(data.len())),
+                    Operation::DelayNs(ns) => log(WatchdogStep::Delay(ns / 1000)),
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn watchdog_is_fed_in_time() {
+        const INTERVAL_US: u64 = 500_000;
+        const BYTES: u64 = 1_000;
+
+        let busy = ScriptedBusy {
+            busy_checks: 1_000,
+            waits: 0,
+        };
+        let mut interface =
+            DisplayInterface::<WatchdogSpi, _, _, _, false>::new(busy, NoopPin, NoopPin, None);
+        interface.set_timing(Timing {
+            watchdog: Some(Watchdog {
+                feed: || log(WatchdogStep::Feed),
+                interval_us: INTERVAL_US as u32,
+                bytes_per_interval: BYTES as u32,
+            }),
+            ..interface.timing()
+        });
+        let mut spi = WatchdogSpi;
+
+        // 10s of polling, the edge of the busy signal isn't waited for
+        block_on(interface.wait_until_idle(&mut spi, true)).unwrap();
+        assert_eq!(interface.busy.waits, 0);
+        // a 2s delay and 12.5 intervals worth of data, in one go and byte by byte
+        block_on(interface.delay(&mut spi, 2_000_000)).unwrap();
+        block_on(interface.data(&mut spi, &[0; 10_000])).unwrap();
+        block_on(interface.data_x_times(&mut spi, 0, 2_500)).unwrap();
+
+        let steps = WATCHDOG_LOG.lock().unwrap().clone();
+        let mut progress = 0;
+        for step in &steps {
+            match *step {
+                WatchdogStep::Write(len) => progress += len as u64 * INTERVAL_US,
+                WatchdogStep::Delay(us) => progress += u64::from(us) * BYTES,
+                WatchdogStep::Feed => progress = 0,
+            }
+            assert!(progress <= INTERVAL_US * BYTES);
+        }
+        let feeds = steps.iter().filter(|&&step| step == WatchdogStep::Feed);
+        assert_eq!(feeds.count(), 36);
+    }
+
+    #[test]
+    fn io_stats_2in9bc_clear_frame_writes_9478_bytes() {
+        use crate::epd2in9bc::{self, Epd2in9bc};
+        use crate::test_utils::{blank_buffer, IdleBusy};
+        use crate::traits::{WaveshareConstructor, WaveshareDisplay, WaveshareDisplayExt};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        epd.reset_io_stats();
+        spi.take();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        let stats = epd.io_stats();
+        assert_eq!(stats.total() as usize, spi.take().len());
+        // 2in9bc clear_frame: resolution, then both layers of 4736 bytes
+        assert_eq!(
+            stats,
+            IoStats {
+                command_bytes: 3,
+                data_bytes: 9475
+            }
+        );
+
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        let stats = block_on(epd.update_and_display_frame_counted(&mut spi, &buffer)).unwrap();
+        assert_eq!(stats.total() as usize, spi.take().len());
+    }
+
+    #[test]
+    fn io_stats_2in7b_64x16_partial_writes_138_bytes() {
+        use crate::epd2in7b::Epd2in7b;
+        use crate::test_utils::IdleBusy;
+        use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        epd.reset_io_stats();
+        spi.take();
+        block_on(epd.update_partial_frame(&mut spi, &[0xFF; 8 * 16], 8, 8, 64, 16)).unwrap();
+        let stats = epd.io_stats();
+        assert_eq!(stats.total() as usize, spi.take().len());
+        // 2in7b partial of 64x16: a window of 8 bytes, 128 bytes of data and the data stop
+        assert_eq!(
+            stats,
+            IoStats {
+                command_bytes: 2,
+                data_bytes: 136
+            }
+        );
+    }
+
+    #[test]
+    fn io_stats_wrap_around() {
+        let wrapped = IoStats {
+            command_bytes: 2,
+            data_bytes: 5,
+        };
+        let earlier = IoStats {
+            command_bytes: u32::MAX,
+            data_bytes: u32::MAX - 1,
+        };
+        assert_eq!(
+            wrapped.since(earlier),
+            IoStats {
+                command_bytes: 3,
+                data_bytes: 7
+            }
+        );
+    }
+}
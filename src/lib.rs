@@ -60,37 +60,89 @@
 //!
 //! Maximum speed tested by myself was 8Mhz but more should be possible (Ben Krasnow used 18Mhz with his implemenation)
 //!
+//! ### Shared state
+//!
+//! Everything in this crate builds on targets without compare-and-swap, like `thumbv6m-none-eabi`
+//! (CI checks that). Shared state uses [`Cell`](core::cell::Cell) where only one context
+//! touches it, like [`ResetLine`], and at most atomic loads and stores otherwise, like the flag
+//! of [`cancel::CancelToken`]. Read-modify-write atomics, and with them `portable-atomic` and its
+//! critical-section requirement, are only used where there is no way around them, and are
+//! documented where they are.
+//!
 #![no_std]
 #![deny(missing_docs)]
 #![allow(stable_features, incomplete_features, async_fn_in_trait)]
-#![cfg_attr(feature = "async", feature(async_fn_in_trait, impl_trait_projections))]
 #![cfg_attr(feature = "async", allow(incomplete_features, async_fn_in_trait))]
 
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
+#[cfg(feature = "preview")]
+pub mod preview;
+
+#[cfg(feature = "block-on")]
+pub mod executor;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+#[cfg(feature = "embassy")]
+pub mod shared;
+
+#[cfg(feature = "compat-0-5")]
+pub mod compat;
+
+#[cfg(feature = "calibration")]
+pub mod calibration;
+
+#[cfg(feature = "demo")]
+pub mod demo;
+
+#[cfg(feature = "cli")]
+pub mod convert;
+
+#[cfg(feature = "terminal")]
+pub mod terminal;
+
 mod error;
 mod traits;
 
+pub mod cancel;
 pub mod color;
+pub mod frame_encoder;
+pub mod gray;
+pub mod maintenance;
+pub mod power;
+pub mod strided;
+pub mod validity;
+pub mod wire;
 
 /// Interface for the physical connection between display and the controlling device
 mod interface;
+pub use interface::{
+    BusySignal, BusyWait, IoStats, PollingBusy, ResetControl, ResetLine, SafeCommands, SharedDelay,
+    SharedReset, SpiError, Timing, VerifiedSpiDevice, Watchdog, WordSpi, WriteCount,
+};
 
+pub mod epd1in02;
 pub mod epd1in54;
 pub mod epd1in54_v2;
 pub mod epd1in54b;
 pub mod epd1in54c;
 pub mod epd2in13_v2;
+pub mod epd2in13_v3;
 pub mod epd2in13bc;
 pub mod epd2in66b;
 pub mod epd2in7b;
+pub mod epd2in7b_v2;
 pub mod epd2in9;
 pub mod epd2in9_v2;
 pub mod epd2in9bc;
 pub mod epd2in9d;
 pub mod epd3in7;
+pub mod epd3in71;
 pub mod epd4in2;
+pub mod epd4in2b;
 pub mod epd5in65f;
 pub mod epd5in83_v2;
 pub mod epd5in83b_v2;
@@ -101,21 +153,36 @@ pub mod epd7in5_v2;
 pub mod epd7in5b_v2;
 pub use epd7in5b_v2 as epd7in5b_v3;
 
+pub(crate) mod confirm;
+pub(crate) mod lut;
 pub(crate) mod type_a;
 
+#[cfg(test)]
+mod test_utils;
+
+#[cfg(test)]
+mod golden;
+
 /// Includes everything important besides the chosen Display
 pub mod prelude {
     pub use crate::color::{Color, OctColor, TriColor};
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        ChromaticPasses, FrameHash, GrayscaleLevels, PartialAlignment, QuickRefresh, RefreshLut,
+        RefreshType, WaveshareConstructor, WaveshareDisplay, WaveshareDisplayExt,
+        WaveshareGrayscaleDisplay, WaveshareThreeColorDisplay, WordSpiConstructor,
+        MAX_CHROMATIC_REFRESH_PASSES,
     };
 
     pub use crate::error::*;
+    pub use crate::interface::{BusySignal, ResetControl};
 
+    pub use crate::strided::StridedSlice;
     pub use crate::SPI_MODE;
 
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation};
+    pub use crate::graphics::{
+        Display, DisplayRegion, DisplayRotation, FrameCharacter, RefreshPolicy, StripeAngle,
+    };
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
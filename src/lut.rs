@@ -0,0 +1,121 @@
+//! Look-up tables shared by several drivers
+
+/// Default waveforms of the UC8151/IL91874 style controllers, e.g. the one of the 2.7" B
+///
+/// One table for the VCOM level and one for each transition between black and white, in the
+/// layout of the `LutForVcom`, `LutWhiteToWhite`, `LutBlackToWhite`, `LutWhiteToBlack` and
+/// `LutBlackToBlack` commands. The `_QUICK` tables drive a single short phase for partial
+/// refreshes of black and white content.
+pub(crate) mod lut_defaults {
+    #[rustfmt::skip]
+    pub(crate) const LUT_VCOM_DC: [u8; 44] = [
+        0x00, 0x00,
+        0x00, 0x1A, 0x1A, 0x00, 0x00, 0x01,
+        0x00, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x00, 0x0E, 0x01, 0x0E, 0x01, 0x10,
+        0x00, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x00, 0x04, 0x10, 0x00, 0x00, 0x05,
+        0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
+        0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_WW: [u8; 42] = [
+        0x90, 0x1A, 0x1A, 0x00, 0x00, 0x01,
+        0x40, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x84, 0x0E, 0x01, 0x0E, 0x01, 0x10,
+        0x80, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x00, 0x04, 0x10, 0x00, 0x00, 0x05,
+        0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
+        0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_BW: [u8; 42] = [
+        0xA0, 0x1A, 0x1A, 0x00, 0x00, 0x01,
+        0x00, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x84, 0x0E, 0x01, 0x0E, 0x01, 0x10,
+        0x90, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0xB0, 0x04, 0x10, 0x00, 0x00, 0x05,
+        0xB0, 0x03, 0x0E, 0x00, 0x00, 0x0A,
+        0xC0, 0x23, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_BB: [u8; 42] = [
+        0x90, 0x1A, 0x1A, 0x00, 0x00, 0x01,
+        0x40, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x84, 0x0E, 0x01, 0x0E, 0x01, 0x10,
+        0x80, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x00, 0x04, 0x10, 0x00, 0x00, 0x05,
+        0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
+        0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_WB: [u8; 42] = [
+        0x90, 0x1A, 0x1A, 0x00, 0x00, 0x01,
+        0x20, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x84, 0x0E, 0x01, 0x0E, 0x01, 0x10,
+        0x10, 0x0A, 0x0A, 0x00, 0x00, 0x08,
+        0x00, 0x04, 0x10, 0x00, 0x00, 0x05,
+        0x00, 0x03, 0x0E, 0x00, 0x00, 0x0A,
+        0x00, 0x23, 0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_VCOM_DC_QUICK: [u8; 44] = [
+        0x00, 0x00,
+        0x00, 0x19, 0x01, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_WW_QUICK: [u8; 42] = [
+        0x00, 0x19, 0x01, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_BW_QUICK: [u8; 42] = [
+        0x80, 0x19, 0x01, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_BB_QUICK: [u8; 42] = [
+        0x00, 0x19, 0x01, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    pub(crate) const LUT_WB_QUICK: [u8; 42] = [
+        0x40, 0x19, 0x01, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+}
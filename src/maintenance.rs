@@ -0,0 +1,180 @@
+//! Periodic maintenance refreshes for displays that show the same content for a long time
+//!
+//! E-paper that keeps the same image for weeks can develop image persistence. Waveshare
+//! recommends a full refresh at least once every 24 hours, even if the content is unchanged.
+//!
+//! [`MaintenanceSchedule`] keeps track of when the last refresh happened. The time source is a
+//! closure returning the current time in any monotonic unit, e.g. seconds since boot or an RTC
+//! timestamp, the interval is given in the same unit.
+//!
+//! ```rust, ignore
+//! // Refresh once a day, with a clock counting seconds
+//! let mut maintenance = MaintenanceSchedule::new(|| rtc.now_secs(), 24 * 60 * 60);
+//! loop {
+//!     maintenance.run_if_due(&mut epd, &mut spi, display.buffer()).await?;
+//!     // ...
+//! }
+//! ```
+//!
+//! To keep the schedule through a sleep of the MCU that loses RAM, store [`last_run`] before
+//! sleeping and hand it to [`MaintenanceSchedule::restore`] after waking up.
+//!
+//! [`last_run`]: MaintenanceSchedule::last_run
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::interface::{BusySignal, ResetControl};
+use crate::traits::WaveshareDisplay;
+
+/// Recommended interval between two maintenance refreshes, in seconds
+pub const DEFAULT_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Decides when an unchanged image needs to be refreshed again
+pub struct MaintenanceSchedule<CLOCK> {
+    clock: CLOCK,
+    interval: u64,
+    last_run: u64,
+}
+
+impl<CLOCK> MaintenanceSchedule<CLOCK>
+where
+    CLOCK: Fn() -> u64,
+{
+    /// Creates a schedule whose interval starts now
+    pub fn new(clock: CLOCK, interval: u64) -> Self {
+        let last_run = clock();
+        Self::restore(clock, interval, last_run)
+    }
+
+    /// Creates a schedule that continues from a previously stored [`last_run`](Self::last_run)
+    pub fn restore(clock: CLOCK, interval: u64, last_run: u64) -> Self {
+        MaintenanceSchedule {
+            clock,
+            interval,
+            last_run,
+        }
+    }
+
+    /// Time of the last maintenance refresh, or of the creation of the schedule
+    pub fn last_run(&self) -> u64 {
+        self.last_run
+    }
+
+    /// Interval between two maintenance refreshes
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Whether the interval elapsed since the last refresh
+    ///
+    /// A clock that wraps around is handled as long as less than one full wrap passes between
+    /// two calls.
+    pub fn due(&self) -> bool {
+        (self.clock)().wrapping_sub(self.last_run) >= self.interval
+    }
+
+    /// Starts a new interval, e.g. after the content was refreshed for another reason
+    pub fn reset(&mut self) {
+        self.last_run = (self.clock)();
+    }
+
+    /// Refreshes the display with `buffer`, the currently shown frame, if the schedule is due
+    ///
+    /// Returns whether a refresh happened. The interval only restarts if the refresh succeeded.
+    pub async fn run_if_due<EPD, SPI, BUSY, DC, RST>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<bool, EPD::Error>
+    where
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST>,
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+    {
+        if !self.due() {
+            return Ok(false);
+        }
+        epd.update_and_display_frame(spi, buffer).await?;
+        self.reset();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9bc::{self, Epd2in9bc};
+    use crate::test_utils::*;
+    use crate::traits::WaveshareConstructor;
+    use core::cell::Cell;
+
+    #[test]
+    fn due_after_interval() {
+        let now = Cell::new(100);
+        let schedule = MaintenanceSchedule::new(|| now.get(), 50);
+
+        assert!(!schedule.due());
+        now.set(149);
+        assert!(!schedule.due());
+        now.set(150);
+        assert!(schedule.due());
+    }
+
+    #[test]
+    fn clock_wrapping_around() {
+        let now = Cell::new(u64::MAX - 10);
+        let schedule = MaintenanceSchedule::new(|| now.get(), 50);
+
+        now.set(20);
+        assert!(!schedule.due());
+        now.set(39);
+        assert!(schedule.due());
+    }
+
+    #[test]
+    fn restored_schedule_keeps_last_run() {
+        let now = Cell::new(1_000);
+        let stored = MaintenanceSchedule::new(|| now.get(), 500).last_run();
+
+        now.set(1_400);
+        let schedule = MaintenanceSchedule::restore(|| now.get(), 500, stored);
+        assert_eq!(schedule.last_run(), 1_000);
+        assert!(!schedule.due());
+        now.set(1_500);
+        assert!(schedule.due());
+    }
+
+    #[test]
+    fn refresh_only_when_due() {
+        let now = Cell::new(0);
+        let mut schedule = MaintenanceSchedule::new(|| now.get(), 10);
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        spi.take();
+
+        assert!(!block_on(schedule.run_if_due(&mut epd, &mut spi, &buffer)).unwrap());
+        assert!(spi.take().is_empty());
+
+        now.set(12);
+        assert!(block_on(schedule.run_if_due(&mut epd, &mut spi, &buffer)).unwrap());
+        assert!(contains(&spi.take(), &buffer));
+        assert_eq!(schedule.last_run(), 12);
+        assert!(!schedule.due());
+    }
+}
@@ -0,0 +1,462 @@
+//! Typical power consumption of the panels, for battery budgeting
+//!
+//! Every driver has a [`PowerProfile`] in [`PROFILES`], looked up with [`profile`] or
+//! [`Panel::power_profile`](crate::wire::Panel::power_profile). The figures are typical values
+//! of the panel, not of a specific board, and a figure that isn't known is `None` instead of a
+//! guess. [`estimate_daily_charge`] adds up a day of refreshes and idle time:
+//!
+//! ```rust, ignore
+//! let mix = RefreshMix { full: 24, partial: 96, ..RefreshMix::default() };
+//! let daily = estimate_daily_charge(&power::EPD2IN9_V2, mix).unwrap();
+//! let days = battery_uah / daily.0;
+//! ```
+//!
+//! Unless noted otherwise the figures come from the specification tables of the Waveshare wiki:
+//! the refresh power at 3.3 V times the refresh time, and the standby current there, which is
+//! the deep sleep current of the driver. The current drawn while the controller is powered but
+//! not refreshing isn't listed, so `standby_ua` is `None` until measured.
+
+use core::ops::{Add, Mul};
+
+/// Amount of charge, in µAh
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct MicroampHours(pub f32);
+
+impl MicroampHours {
+    /// The same charge in mAh
+    pub fn milliamp_hours(self) -> f32 {
+        self.0 / 1000.0
+    }
+}
+
+impl Add for MicroampHours {
+    type Output = MicroampHours;
+
+    fn add(self, rhs: MicroampHours) -> MicroampHours {
+        MicroampHours(self.0 + rhs.0)
+    }
+}
+
+impl Mul<f32> for MicroampHours {
+    type Output = MicroampHours;
+
+    fn mul(self, rhs: f32) -> MicroampHours {
+        MicroampHours(self.0 * rhs)
+    }
+}
+
+/// Charge of a partial refresh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartialCharge {
+    /// The same for every window, the waveform runs over the whole panel
+    Fixed(MicroampHours),
+    /// Per cm² of the refreshed window
+    PerCm2(MicroampHours),
+}
+
+/// Typical power consumption of a panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerProfile {
+    /// Name of the driver module, e.g. `epd4in2`
+    pub id: &'static str,
+    /// Charge of a full refresh
+    pub full_refresh: Option<MicroampHours>,
+    /// Charge of a quick refresh, for panels with a quick mode
+    pub quick_refresh: Option<MicroampHours>,
+    /// Charge of a partial refresh, for panels with partial updates
+    pub partial_refresh: Option<PartialCharge>,
+    /// Current in deep sleep, in µA
+    pub sleep_ua: Option<f32>,
+    /// Current while powered but not refreshing, in µA
+    pub standby_ua: Option<f32>,
+}
+
+/// Refreshes and idle time of a day
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RefreshMix {
+    /// Number of full refreshes
+    pub full: u32,
+    /// Number of quick refreshes
+    pub quick: u32,
+    /// Number of partial refreshes
+    pub partial: u32,
+    /// Average window of a partial refresh, in cm²
+    pub partial_area_cm2: f32,
+    /// Hours the controller is powered without refreshing, it sleeps the rest of the day
+    pub standby_hours: f32,
+}
+
+/// Charge of `mix` over a day
+///
+/// A quick refresh without a figure of its own is counted as a full refresh, a partial refresh
+/// as a quick one. Returns `None` if the profile lacks a figure needed for `mix`, figures of
+/// things that don't happen, like partial refreshes with a `partial` of 0, may be missing.
+pub fn estimate_daily_charge(profile: &PowerProfile, mix: RefreshMix) -> Option<MicroampHours> {
+    fn times(count: u32, charge: Option<MicroampHours>) -> Option<MicroampHours> {
+        match count {
+            0 => Some(MicroampHours(0.0)),
+            _ => Some(charge? * count as f32),
+        }
+    }
+    fn hours(hours: f32, current_ua: Option<f32>) -> Option<MicroampHours> {
+        if hours > 0.0 {
+            Some(MicroampHours(current_ua? * hours))
+        } else {
+            Some(MicroampHours(0.0))
+        }
+    }
+
+    let quick = profile.quick_refresh.or(profile.full_refresh);
+    let partial = match profile.partial_refresh {
+        Some(PartialCharge::Fixed(charge)) => Some(charge),
+        Some(PartialCharge::PerCm2(charge)) => Some(charge * mix.partial_area_cm2),
+        None => quick,
+    };
+    let sleep_hours = 24.0 - mix.standby_hours;
+    Some(
+        times(mix.full, profile.full_refresh)?
+            + times(mix.quick, quick)?
+            + times(mix.partial, partial)?
+            + hours(mix.standby_hours, profile.standby_ua)?
+            + hours(sleep_hours, profile.sleep_ua)?,
+    )
+}
+
+/// Looks up the profile of a driver module, e.g. `epd4in2`
+pub fn profile(id: &str) -> Option<&'static PowerProfile> {
+    PROFILES.iter().find(|profile| profile.id == id)
+}
+
+/// Charge of a waveform drawing `milliwatts` at 3.3 V for `seconds`
+const fn at_3v3(milliwatts: f32, seconds: f32) -> MicroampHours {
+    MicroampHours(milliwatts / 3.3 * seconds / 3.6)
+}
+
+/// Refresh power of most panels in the wiki
+const TYPICAL_MW: f32 = 26.4;
+/// Standby current of the wiki, an upper bound
+const WIKI_SLEEP_UA: f32 = 0.01;
+
+macro_rules! profiles {
+    ($($name:ident: $driver:ident {
+        full: $full:expr,
+        quick: $quick:expr,
+        partial: $partial:expr,
+        sleep: $sleep:expr,
+        standby: $standby:expr $(,)?
+    })*) => {
+        $(
+            #[doc = concat!("The profile of [`", stringify!($driver), "`](crate::", stringify!($driver), ")")]
+            pub const $name: PowerProfile = PowerProfile {
+                id: stringify!($driver),
+                full_refresh: $full,
+                quick_refresh: $quick,
+                partial_refresh: $partial,
+                sleep_ua: $sleep,
+                standby_ua: $standby,
+            };
+        )*
+
+        /// Profiles of every driver
+        pub const PROFILES: &[PowerProfile] = &[$($name),*];
+    };
+}
+
+profiles! {
+    // refresh time not listed
+    EPD1IN02: epd1in02 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD1IN54: epd1in54 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD1IN54_V2: epd1in54_v2 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD1IN54B: epd1in54b {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD1IN54C: epd1in54c {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD2IN13_V2: epd2in13_v2 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD2IN13_V3: epd2in13_v3 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 15 s full refresh
+    EPD2IN13BC: epd2in13bc {
+        full: Some(at_3v3(TYPICAL_MW, 15.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD2IN66B: epd2in66b {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD2IN7B: epd2in7b {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 15 s full refresh
+    EPD2IN7B_V2: epd2in7b_v2 {
+        full: Some(at_3v3(TYPICAL_MW, 15.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD2IN9: epd2in9 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD2IN9_V2: epd2in9_v2 {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 15 s full refresh
+    EPD2IN9BC: epd2in9bc {
+        full: Some(at_3v3(TYPICAL_MW, 15.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 2 s full and 0.3 s partial refresh
+    EPD2IN9D: epd2in9d {
+        full: Some(at_3v3(TYPICAL_MW, 2.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 3 s full and 0.3 s partial refresh
+    EPD3IN7: epd3in7 {
+        full: Some(at_3v3(TYPICAL_MW, 3.0)),
+        quick: None,
+        partial: Some(PartialCharge::Fixed(at_3v3(TYPICAL_MW, 0.3))),
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // not in the wiki yet
+    EPD3IN71: epd3in71 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: None,
+        standby: None,
+    }
+    // 4 s full refresh
+    EPD4IN2: epd4in2 {
+        full: Some(at_3v3(TYPICAL_MW, 4.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 16 s full refresh
+    EPD4IN2B: epd4in2b {
+        full: Some(at_3v3(TYPICAL_MW, 16.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh power not listed
+    EPD5IN65F: epd5in65f {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD5IN83_V2: epd5in83_v2 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD5IN83B_V2: epd5in83b_v2 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh power not listed
+    EPD7IN3F: epd7in3f {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD7IN5: epd7in5 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD7IN5_HD: epd7in5_hd {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // 5 s full refresh
+    EPD7IN5_V2: epd7in5_v2 {
+        full: Some(at_3v3(TYPICAL_MW, 5.0)),
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+    // refresh time not listed
+    EPD7IN5B_V2: epd7in5b_v2 {
+        full: None,
+        quick: None,
+        partial: None,
+        sleep: Some(WIKI_SLEEP_UA),
+        standby: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::PANELS;
+
+    fn close(a: MicroampHours, b: f32) -> bool {
+        (a.0 - b).abs() < 1e-3
+    }
+
+    const PROFILE: PowerProfile = PowerProfile {
+        id: "test",
+        full_refresh: Some(MicroampHours(10.0)),
+        quick_refresh: None,
+        partial_refresh: Some(PartialCharge::PerCm2(MicroampHours(0.5))),
+        sleep_ua: Some(1.0),
+        standby_ua: Some(100.0),
+    };
+
+    #[test]
+    fn charge_at_3v3() {
+        // 33 mW at 3.3 V are 10 mA, for 3.6 s that's 10 µAh
+        assert!(close(at_3v3(33.0, 3.6), 10.0));
+    }
+
+    #[test]
+    fn daily_charge_adds_up() {
+        let mix = RefreshMix {
+            full: 2,
+            quick: 3,
+            partial: 4,
+            partial_area_cm2: 10.0,
+            standby_hours: 0.5,
+        };
+        // 2 * 10 + 3 * 10 + 4 * 5 + 0.5 * 100 + 23.5 * 1
+        let daily = estimate_daily_charge(&PROFILE, mix).unwrap();
+        assert!(close(daily, 143.5));
+    }
+
+    #[test]
+    fn unknown_figures() {
+        let profile = PowerProfile {
+            full_refresh: None,
+            partial_refresh: None,
+            ..PROFILE
+        };
+        let mix = RefreshMix {
+            full: 1,
+            ..RefreshMix::default()
+        };
+        assert_eq!(estimate_daily_charge(&profile, mix), None);
+        // nothing but sleep
+        let daily = estimate_daily_charge(&profile, RefreshMix::default()).unwrap();
+        assert!(close(daily, 24.0));
+        // partial refreshes fall back to the full one
+        let profile = PowerProfile {
+            partial_refresh: None,
+            sleep_ua: None,
+            ..PROFILE
+        };
+        let mix = RefreshMix {
+            partial: 2,
+            standby_hours: 24.0,
+            ..RefreshMix::default()
+        };
+        let daily = estimate_daily_charge(&profile, mix).unwrap();
+        assert!(close(daily, 2420.0));
+    }
+
+    #[test]
+    fn every_panel_has_a_profile() {
+        for panel in PANELS {
+            assert_eq!(panel.power_profile().map(|p| p.id), Some(panel.id));
+        }
+        for (index, profile) in PROFILES.iter().enumerate() {
+            assert_eq!(super::profile(profile.id), Some(&PROFILES[index]));
+        }
+        assert_eq!(PROFILES.len(), 27);
+    }
+}
@@ -0,0 +1,675 @@
+//! Preview of the display content on the host
+//!
+//! [`PreviewEpd`] implements the same traits as the hardware drivers, but renders every
+//! displayed frame into an in-memory image that gets pushed to a [`PreviewSink`].
+//! This allows iterating on layouts on a desktop without flashing the device.
+//!
+//! The crate ships with the `preview-png` feature [`PngSink`], writing every frame to a file,
+//! and with the `preview-window` feature [`WindowSink`](window::WindowSink), showing it in a
+//! window.
+//!
+//! ```rust, no_run
+//!# use epd_waveshare_async::prelude::*;
+//!# use epd_waveshare_async::color::Color;
+//!# use epd_waveshare_async::preview::{NoHardware, PngSink, PreviewEpd};
+//!# async fn run() -> Result<(), ErrorKind<NoHardware, NoHardware, NoHardware, NoHardware>> {
+//!let mut epd = PreviewEpd::<200, 200, Color, _>::with_sink(
+//!    PngSink::new("preview.png"),
+//!    std::time::Duration::from_secs(2),
+//!);
+//!let buffer = [Color::White.get_byte_value(); 200 / 8 * 200];
+//!epd.update_and_display_frame(&mut NoHardware, &buffer).await?;
+//!# Ok(())
+//!# }
+//! ```
+extern crate std;
+
+use core::convert::Infallible;
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+#[cfg(feature = "preview-png")]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::vec;
+use std::vec::Vec;
+
+use crate::color::{Color, TriColor};
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, YieldNow};
+use crate::traits::{
+    ChromaticPasses, ErrorType, PartialAlignment, RefreshLut, WaveshareConstructor,
+    WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+
+/// Busy time of a refresh used by [`WaveshareConstructor::new`], close to a real full refresh
+pub const DEFAULT_REFRESH_DURATION: Duration = Duration::from_secs(2);
+
+/// Receives every frame displayed by a [`PreviewEpd`]
+pub trait PreviewSink {
+    /// Show a frame of `width` * `height` RGB pixels, stored row by row
+    fn show(&mut self, width: u32, height: u32, pixels: &[[u8; 3]]);
+}
+
+/// Colors that can be shown by a [`PreviewEpd`]
+pub trait PreviewColor: Copy {
+    /// White, the initial background color
+    const WHITE: Self;
+
+    /// RGB value of the color
+    fn rgb(self) -> [u8; 3];
+}
+
+impl PreviewColor for Color {
+    const WHITE: Self = Color::White;
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Color::Black => [0x00, 0x00, 0x00],
+            Color::White => [0xff, 0xff, 0xff],
+        }
+    }
+}
+
+impl PreviewColor for TriColor {
+    const WHITE: Self = TriColor::White;
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            TriColor::Black => [0x00, 0x00, 0x00],
+            TriColor::White => [0xff, 0xff, 0xff],
+            // assume chromatic is red
+            TriColor::Chromatic => [0xff, 0x00, 0x00],
+        }
+    }
+}
+
+/// Stand-in for the SPI device and the pins when there is no hardware
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoHardware;
+
+impl embedded_hal::spi::ErrorType for NoHardware {
+    type Error = Infallible;
+}
+
+impl embedded_hal::spi::SpiDevice for NoHardware {
+    fn transaction(
+        &mut self,
+        _operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl SpiDevice for NoHardware {
+    async fn transaction(
+        &mut self,
+        _operations: &mut [embedded_hal_async::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_hal::digital::ErrorType for NoHardware {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoHardware {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl BusySignal for NoHardware {
+    type Error = Infallible;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+        false
+    }
+}
+
+/// Display rendering into a [`PreviewSink`] instead of a panel
+///
+/// The driver traits are implemented with [`NoHardware`] as busy, DC and RST pin, any
+/// SPI device can be passed.
+///
+/// Buffers use the same layout as the hardware drivers: one bit per pixel, a set bit is white.
+/// For [`TriColor`] a set bit in the chromatic buffer shows the chromatic color.
+pub struct PreviewEpd<const WIDTH: u32, const HEIGHT: u32, C, S> {
+    sink: S,
+    /// Black/white content of the RAM, `true` for white
+    achromatic: Vec<bool>,
+    /// Chromatic content of the RAM
+    chromatic: Vec<bool>,
+    /// Last displayed image
+    image: Vec<[u8; 3]>,
+    background_color: C,
+    refresh_duration: Duration,
+    busy_until: Option<Instant>,
+    chromatic_passes: ChromaticPasses,
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, C, S> PreviewEpd<WIDTH, HEIGHT, C, S>
+where
+    C: PreviewColor,
+    S: PreviewSink,
+{
+    /// Create a preview pushing every displayed frame to `sink`
+    ///
+    /// Every refresh keeps the display busy for `refresh_duration`.
+    pub fn with_sink(sink: S, refresh_duration: Duration) -> Self {
+        let size = (WIDTH * HEIGHT) as usize;
+        Self {
+            sink,
+            achromatic: vec![true; size],
+            chromatic: vec![false; size],
+            image: vec![Color::White.rgb(); size],
+            background_color: C::WHITE,
+            refresh_duration,
+            busy_until: None,
+            chromatic_passes: ChromaticPasses::default(),
+        }
+    }
+
+    /// Change how long a refresh keeps the display busy
+    pub fn set_refresh_duration(&mut self, refresh_duration: Duration) {
+        self.refresh_duration = refresh_duration;
+    }
+
+    /// Last displayed image, RGB pixels stored row by row
+    pub fn image(&self) -> &[[u8; 3]] {
+        &self.image
+    }
+
+    /// The sink receiving the frames
+    pub fn sink(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    fn show(&mut self) {
+        let white = Color::White.rgb();
+        let black = Color::Black.rgb();
+        let chromatic = TriColor::Chromatic.rgb();
+        for (i, pixel) in self.image.iter_mut().enumerate() {
+            *pixel = if self.chromatic[i] {
+                chromatic
+            } else if self.achromatic[i] {
+                white
+            } else {
+                black
+            };
+        }
+        self.sink.show(WIDTH, HEIGHT, &self.image);
+        self.busy_until = Some(Instant::now() + self.refresh_duration);
+    }
+
+    // Yields until the refresh is over instead of sleeping, other futures keep running
+    async fn wait(&mut self) {
+        if let Some(busy_until) = self.busy_until.take() {
+            while Instant::now() < busy_until {
+                YieldNow(false).await;
+            }
+        }
+    }
+}
+
+// Copy a 1 bit per pixel window into a plane, bits outside of the display are dropped.
+// Returns false if the buffer doesn't match the size of the window.
+#[allow(clippy::too_many_arguments)]
+fn blit(
+    plane: &mut [bool],
+    plane_width: u32,
+    plane_height: u32,
+    buffer: &[u8],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> bool {
+    let line = (width as usize).div_ceil(8);
+    if buffer.len() != line * height as usize {
+        return false;
+    }
+    for row in 0..height.min(plane_height.saturating_sub(y)) {
+        for col in 0..width.min(plane_width.saturating_sub(x)) {
+            let byte = buffer[row as usize * line + col as usize / 8];
+            plane[((y + row) * plane_width + x + col) as usize] = byte & (0x80 >> (col % 8)) != 0;
+        }
+    }
+    true
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, C, S, SPI>
+    ErrorType<SPI, NoHardware, NoHardware, NoHardware> for PreviewEpd<WIDTH, HEIGHT, C, S>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+{
+    type Error = ErrorKind<SPI, NoHardware, NoHardware, NoHardware>;
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, C, S, SPI>
+    WaveshareDisplay<SPI, NoHardware, NoHardware, NoHardware> for PreviewEpd<WIDTH, HEIGHT, C, S>
+where
+    C: PreviewColor,
+    S: PreviewSink + Default,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+{
+    type DisplayColor = C;
+
+    async fn sleep(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait().await;
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: C) {
+        self.background_color = color;
+    }
+
+    fn background_color(&self) -> &C {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.update_partial_frame(spi, buffer, 0, 0, WIDTH, HEIGHT)
+            .await
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.wait().await;
+        if blit(
+            &mut self.achromatic,
+            WIDTH,
+            HEIGHT,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        ) {
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidArgument)
+        }
+    }
+
+    fn partial_alignment(&self) -> PartialAlignment {
+        PartialAlignment::PIXEL
+    }
+
+    async fn display_frame(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait().await;
+        self.show();
+        Ok(())
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait().await;
+        let rgb = self.background_color.rgb();
+        self.achromatic.fill(rgb != Color::Black.rgb());
+        self.chromatic.fill(rgb == TriColor::Chromatic.rgb());
+        Ok(())
+    }
+
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait().await;
+        Ok(())
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.busy_until
+            .is_some_and(|busy_until| busy_until > Instant::now())
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, C, S, SPI>
+    WaveshareConstructor<SPI, NoHardware, NoHardware, NoHardware>
+    for PreviewEpd<WIDTH, HEIGHT, C, S>
+where
+    C: PreviewColor,
+    S: PreviewSink + Default,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+{
+    async fn try_new(
+        _spi: &mut SPI,
+        _busy: NoHardware,
+        _dc: NoHardware,
+        _rst: NoHardware,
+        _delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, NoHardware, NoHardware, NoHardware>> {
+        Ok(Self::with_sink(S::default(), DEFAULT_REFRESH_DURATION))
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, S, SPI>
+    WaveshareThreeColorDisplay<SPI, NoHardware, NoHardware, NoHardware>
+    for PreviewEpd<WIDTH, HEIGHT, TriColor, S>
+where
+    S: PreviewSink + Default,
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, black).await?;
+        self.update_chromatic_frame(spi, chromatic).await
+    }
+
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, black).await
+    }
+
+    async fn update_chromatic_frame(
+        &mut self,
+        _spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait().await;
+        if blit(
+            &mut self.chromatic,
+            WIDTH,
+            HEIGHT,
+            chromatic,
+            0,
+            0,
+            WIDTH,
+            HEIGHT,
+        ) {
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidArgument)
+        }
+    }
+
+    async fn refresh_and_wait(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.display_frame(spi).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.chromatic_passes
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        &mut self.chromatic_passes
+    }
+}
+
+/// Writes every frame to a PNG file, replacing the previous one, needs the `preview-png` feature
+#[cfg(feature = "preview-png")]
+pub struct PngSink {
+    path: PathBuf,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "preview-png")]
+impl PngSink {
+    /// Create a sink writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            error: None,
+        }
+    }
+
+    /// Returns the error of the last failed write, if any
+    pub fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
+}
+
+#[cfg(feature = "preview-png")]
+impl Default for PngSink {
+    /// Writes to `preview.png` in the working directory
+    fn default() -> Self {
+        Self::new("preview.png")
+    }
+}
+
+#[cfg(feature = "preview-png")]
+impl PreviewSink for PngSink {
+    fn show(&mut self, width: u32, height: u32, pixels: &[[u8; 3]]) {
+        let written = encode_png(width, height, pixels)
+            .map_err(std::io::Error::from)
+            .and_then(|png| std::fs::write(&self.path, png));
+        if let Err(error) = written {
+            self.error = Some(error);
+        }
+    }
+}
+
+/// Encodes `width` * `height` RGB pixels, stored row by row, as a PNG image
+#[cfg(feature = "preview-png")]
+pub fn encode_png(
+    width: u32,
+    height: u32,
+    pixels: &[[u8; 3]],
+) -> Result<Vec<u8>, png::EncodingError> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let data: Vec<u8> = pixels.iter().flatten().copied().collect();
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// Window showing the frames, needs the `preview-window` feature
+#[cfg(feature = "preview-window")]
+pub mod window {
+    use super::{PreviewSink, Vec};
+
+    /// Shows every frame in a desktop window
+    pub struct WindowSink {
+        window: minifb::Window,
+        buffer: Vec<u32>,
+    }
+
+    impl WindowSink {
+        /// Open a window with the size of the display
+        pub fn new(title: &str, width: u32, height: u32) -> Result<Self, minifb::Error> {
+            let window = minifb::Window::new(
+                title,
+                width as usize,
+                height as usize,
+                minifb::WindowOptions::default(),
+            )?;
+            Ok(Self {
+                window,
+                buffer: Vec::new(),
+            })
+        }
+    }
+
+    impl PreviewSink for WindowSink {
+        fn show(&mut self, width: u32, height: u32, pixels: &[[u8; 3]]) {
+            self.buffer.clear();
+            self.buffer.extend(
+                pixels
+                    .iter()
+                    .map(|[r, g, b]| (*r as u32) << 16 | (*g as u32) << 8 | *b as u32),
+            );
+            // a closed window just stops showing frames
+            let _ = self
+                .window
+                .update_with_buffer(&self.buffer, width as usize, height as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::block_on;
+
+    #[derive(Default)]
+    struct LastFrame {
+        frames: u32,
+        pixels: Vec<[u8; 3]>,
+    }
+
+    impl PreviewSink for LastFrame {
+        fn show(&mut self, _width: u32, _height: u32, pixels: &[[u8; 3]]) {
+            self.frames += 1;
+            self.pixels = pixels.to_vec();
+        }
+    }
+
+    const W: [u8; 3] = [0xff, 0xff, 0xff];
+    const B: [u8; 3] = [0x00, 0x00, 0x00];
+    const R: [u8; 3] = [0xff, 0x00, 0x00];
+
+    #[test]
+    fn partial_update_composites_into_frame() {
+        let mut epd =
+            PreviewEpd::<16, 2, Color, _>::with_sink(LastFrame::default(), Duration::ZERO);
+        let spi = &mut NoHardware;
+
+        block_on(epd.update_frame(spi, &[0xFF, 0xFF, 0x00, 0x00])).unwrap();
+        // 4 black pixels at x = 2..6 on the first row
+        block_on(epd.update_partial_frame(spi, &[0x0F], 2, 0, 4, 1)).unwrap();
+        assert!(epd.sink().frames == 0);
+        block_on(epd.display_frame(spi)).unwrap();
+
+        let frame = &epd.sink().pixels;
+        assert_eq!(frame.len(), 32);
+        assert_eq!(&frame[..8], &[W, W, B, B, B, B, W, W]);
+        assert!(frame[8..16].iter().all(|&p| p == W));
+        assert!(frame[16..].iter().all(|&p| p == B));
+    }
+
+    #[test]
+    fn chromatic_overrides_achromatic() {
+        let mut epd =
+            PreviewEpd::<8, 1, TriColor, _>::with_sink(LastFrame::default(), Duration::ZERO);
+        let spi = &mut NoHardware;
+
+        block_on(epd.update_color_frame(spi, &[0xF0], &[0x3C])).unwrap();
+        block_on(epd.display_frame(spi)).unwrap();
+        assert_eq!(epd.image(), &[W, W, R, R, R, R, B, B]);
+    }
+
+    #[test]
+    fn wrong_buffer_size() {
+        let mut epd =
+            PreviewEpd::<16, 2, Color, _>::with_sink(LastFrame::default(), Duration::ZERO);
+        assert!(matches!(
+            block_on(epd.update_frame(&mut NoHardware, &[0xFF])),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn refresh_keeps_display_busy() {
+        let duration = Duration::from_millis(20);
+        let mut epd = PreviewEpd::<8, 1, Color, _>::with_sink(LastFrame::default(), duration);
+        let spi = &mut NoHardware;
+
+        let start = Instant::now();
+        block_on(epd.display_frame(spi)).unwrap();
+        block_on(epd.wait_until_idle(spi)).unwrap();
+        assert!(start.elapsed() >= duration);
+    }
+
+    #[test]
+    fn busy_display_yields() {
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Waker};
+
+        let duration = Duration::from_secs(3600);
+        let mut epd = PreviewEpd::<8, 1, Color, _>::with_sink(LastFrame::default(), duration);
+        let spi = &mut NoHardware;
+
+        block_on(epd.display_frame(spi)).unwrap();
+        let mut wait = pin!(epd.wait_until_idle(spi));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(wait.as_mut().poll(&mut cx).is_pending());
+    }
+
+    #[cfg(feature = "preview-png")]
+    #[test]
+    fn png_round_trip() {
+        let png = encode_png(2, 2, &[W, B, R, W]).unwrap();
+        let mut decoder = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut data = vec![0; decoder.output_buffer_size()];
+        let info = decoder.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(&data[..info.buffer_size()], [W, B, R, W].concat());
+        assert!(encode_png(2, 2, &[W]).is_err());
+    }
+
+    #[cfg(feature = "preview-png")]
+    #[test]
+    fn png_sink_writes_file() {
+        let path = std::env::temp_dir().join("epd_waveshare_preview_test.png");
+        let mut epd = PreviewEpd::<2, 2, Color, _>::with_sink(PngSink::new(&path), Duration::ZERO);
+        let spi = &mut NoHardware;
+
+        block_on(epd.update_and_display_frame(spi, &[0x80, 0x40])).unwrap();
+        assert!(epd.sink().take_error().is_none());
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            encode_png(2, 2, &[W, B, B, W]).unwrap()
+        );
+        let _ = std::fs::remove_file(path);
+    }
+}
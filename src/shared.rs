@@ -0,0 +1,99 @@
+//! Sharing one display between tasks
+//!
+//! A [`SharedEpd`] keeps the driver in an [`embassy_sync`] mutex, so e.g. a UI task and a
+//! sensor task can both update the display. Every access runs to the end before the next one
+//! starts, a refresh started by one task is never interrupted by a frame of another.
+//!
+//! Waiting tasks get the display in the order chosen by the executor, run the tasks with the
+//! highest priority on a higher priority executor to have them served first.
+//!
+//! The raw mutex decides what the tasks may be: `NoopRawMutex` for tasks on the same executor,
+//! `ThreadModeRawMutex` for thread mode tasks, and `CriticalSectionRawMutex` for tasks on
+//! interrupt executors, which needs a `critical-section` implementation for the target.
+//!
+//! ```rust, ignore
+//! static EPD: StaticCell<SharedEpd<Epd2in9<...>, CriticalSectionRawMutex>> = StaticCell::new();
+//!
+//! epd.with_display(async |epd| {
+//!     epd.update_and_display_frame(&mut spi, display.buffer()).await
+//! })
+//! .await?;
+//! ```
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+
+/// Driver shared between tasks, see the [module documentation](self)
+pub struct SharedEpd<D, M: RawMutex> {
+    epd: Mutex<M, D>,
+}
+
+impl<D, M: RawMutex> SharedEpd<D, M> {
+    /// Wraps a driver
+    pub const fn new(epd: D) -> Self {
+        SharedEpd {
+            epd: Mutex::new(epd),
+        }
+    }
+
+    /// Waits until no other task uses the display and runs `f` with it
+    pub async fn with_display<R>(&self, f: impl AsyncFnOnce(&mut D) -> R) -> R {
+        let mut epd = self.epd.lock().await;
+        f(&mut epd).await
+    }
+
+    /// Runs `f` with the display if no other task uses it, returns `None` otherwise
+    pub async fn try_with_display<R>(&self, f: impl AsyncFnOnce(&mut D) -> R) -> Option<R> {
+        let mut epd = self.epd.try_lock().ok()?;
+        Some(f(&mut epd).await)
+    }
+
+    /// The driver, without locking as the display is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut D {
+        self.epd.get_mut()
+    }
+
+    /// Returns the driver
+    pub fn into_inner(self) -> D {
+        self.epd.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in9bc::{self, command::Command, Epd2in9bc};
+    use crate::test_utils::*;
+    use crate::traits::{WaveshareConstructor, WaveshareDisplay};
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    #[test]
+    fn exclusive_access() {
+        let mut spi = RecordingSpi::default();
+        let epd = block_on(Epd2in9bc::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        let shared = SharedEpd::<_, NoopRawMutex>::new(epd);
+        spi.take();
+
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        let busy = block_on(shared.with_display(async |epd| {
+            epd.update_and_display_frame(&mut spi, &buffer)
+                .await
+                .unwrap();
+            // another task can't get in while the refresh runs
+            block_on(shared.try_with_display(async |_| {})).is_none()
+        }));
+        assert!(busy);
+        assert!(contains(&spi.take(), &[Command::DisplayRefresh as u8]));
+
+        assert_eq!(
+            block_on(shared.try_with_display(async |epd| epd.width())),
+            Some(epd2in9bc::WIDTH)
+        );
+    }
+}
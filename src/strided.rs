@@ -0,0 +1,193 @@
+//! Windows of a larger buffer, sent without copying them first
+//!
+//! Partial updates take the window as rows of bytes. A [`StridedSlice`] borrows these rows from
+//! the full frame buffer, e.g. with [`Display::window`](crate::graphics::Display::window), and
+//! the drivers walk them with the stride of the frame:
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::{graphics::DisplayRegion, strided::StridedSlice};
+//!
+//! let window = display.window(DisplayRegion::new(64, 0, 64, 300)).unwrap();
+//! epd.update_partial_frame_strided(&mut spi, window, 64, 0, 64, 300).await?;
+//! // a window that is already contiguous
+//! epd.update_partial_frame_strided(&mut spi, StridedSlice::from(&buffer[..]), 64, 0, 64, 300).await?;
+//! ```
+
+/// `rows` rows of `row_len` bytes, each starting `stride` bytes after the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StridedSlice<'a> {
+    data: &'a [u8],
+    row_len: usize,
+    stride: usize,
+    rows: usize,
+}
+
+/// The rows of a [`StridedSlice`] don't fit its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrideError {
+    /// The stride is shorter than a row, so the rows would overlap
+    Overlap,
+    /// The data ends before the last row
+    TooShort,
+}
+
+impl<'a> StridedSlice<'a> {
+    /// Takes `rows` rows of `row_len` bytes from `data`, the first one at the start
+    ///
+    /// The data only has to reach the end of the last row, not a whole stride after it.
+    pub const fn new(
+        data: &'a [u8],
+        row_len: usize,
+        stride: usize,
+        rows: usize,
+    ) -> Result<Self, StrideError> {
+        if stride < row_len {
+            return Err(StrideError::Overlap);
+        }
+        if rows > 0 {
+            let end = match (rows - 1).checked_mul(stride) {
+                Some(start) => start.checked_add(row_len),
+                None => None,
+            };
+            match end {
+                Some(end) if end <= data.len() => {}
+                _ => return Err(StrideError::TooShort),
+            }
+        }
+        Ok(StridedSlice {
+            data,
+            row_len,
+            stride,
+            rows,
+        })
+    }
+
+    /// Takes the window of `width` bytes and `height` rows starting at byte `x` of row `y` of a
+    /// buffer with rows of `buffer_row_len` bytes
+    pub fn window(
+        buffer: &'a [u8],
+        buffer_row_len: usize,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, StrideError> {
+        if x.checked_add(width).is_none_or(|end| end > buffer_row_len) {
+            return Err(StrideError::Overlap);
+        }
+        let start = y
+            .checked_mul(buffer_row_len)
+            .and_then(|start| start.checked_add(x))
+            .filter(|&start| start <= buffer.len())
+            .ok_or(StrideError::TooShort)?;
+        Self::new(&buffer[start..], width, buffer_row_len, height)
+    }
+
+    /// The data the rows are taken from, starting with the first row
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Length of a row
+    pub const fn row_len(&self) -> usize {
+        self.row_len
+    }
+
+    /// Distance between the starts of two rows
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Number of rows
+    pub const fn row_count(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of bytes in all rows, without the gaps between them
+    pub const fn len(&self) -> usize {
+        self.row_len * self.rows
+    }
+
+    /// Whether there are no bytes in the rows
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bytes as one slice, if there are no gaps between the rows
+    pub fn as_contiguous(&self) -> Option<&'a [u8]> {
+        (self.stride == self.row_len || self.rows <= 1).then(|| &self.data[..self.len()])
+    }
+
+    /// The `index`th byte of the rows without their gaps
+    ///
+    /// Panics if `index` isn't less than [`len`](Self::len).
+    pub fn byte(&self, index: usize) -> u8 {
+        assert!(index < self.len());
+        self.data[index / self.row_len * self.stride + index % self.row_len]
+    }
+
+    /// The rows, top down
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &'a [u8]> + ExactSizeIterator + 'a {
+        let (data, row_len, stride) = (self.data, self.row_len, self.stride);
+        (0..self.rows).map(move |row| &data[row * stride..][..row_len])
+    }
+}
+
+/// A single row, to pass an already contiguous window
+impl<'a> From<&'a [u8]> for StridedSlice<'a> {
+    fn from(data: &'a [u8]) -> Self {
+        StridedSlice {
+            data,
+            row_len: data.len(),
+            stride: data.len(),
+            rows: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_of_a_buffer() {
+        let buffer: [u8; 20] = core::array::from_fn(|i| i as u8);
+        let window = StridedSlice::window(&buffer, 5, 1, 1, 3, 3).unwrap();
+        assert_eq!(window.len(), 9);
+        assert!(window
+            .rows()
+            .eq([&[6, 7, 8][..], &[11, 12, 13], &[16, 17, 18]]));
+        assert_eq!(window.byte(4), 12);
+        assert_eq!(window.as_contiguous(), None);
+
+        // the last row doesn't need a whole stride
+        let last = StridedSlice::window(&buffer, 5, 0, 3, 5, 1).unwrap();
+        assert_eq!(last.as_contiguous(), Some(&buffer[15..]));
+        assert!(StridedSlice::new(&buffer[..14], 3, 5, 3).is_ok());
+
+        assert_eq!(
+            StridedSlice::new(&buffer, 6, 5, 2),
+            Err(StrideError::Overlap)
+        );
+        assert_eq!(
+            StridedSlice::new(&buffer[..12], 3, 5, 3),
+            Err(StrideError::TooShort)
+        );
+        assert_eq!(
+            StridedSlice::window(&buffer, 5, 3, 0, 3, 1),
+            Err(StrideError::Overlap)
+        );
+        assert_eq!(
+            StridedSlice::window(&buffer, 5, 0, 4, 1, 1),
+            Err(StrideError::TooShort)
+        );
+        assert_eq!(
+            StridedSlice::new(&buffer, 1, usize::MAX, 3),
+            Err(StrideError::TooShort)
+        );
+
+        let contiguous = StridedSlice::from(&buffer[..]);
+        assert_eq!(contiguous.as_contiguous(), Some(&buffer[..]));
+        assert_eq!(contiguous.rows().len(), 1);
+    }
+}
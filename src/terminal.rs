@@ -0,0 +1,467 @@
+//! Text console on a display, e.g. for debugging units in the field
+//!
+//! [`EpdTerminal`] renders appended text into a [`Display`] with a monospaced font, wraps
+//! lines longer than the display and keeps track of the cursor. Writing only draws into the
+//! display buffer, [`flush`](EpdTerminal::flush) then sends the lines that changed since the
+//! last flush with a partial update and refreshes them with the quick look-up table.
+//!
+//! Once the screen is full the terminal starts over on a blank page, which gets a full refresh
+//! to get rid of the ghosting built up by the quick refreshes.
+//!
+//! Besides `\n` and `\r`, control characters are ignored. Everything else is passed to the
+//! font, characters it doesn't have are replaced by its replacement character.
+//!
+//! ```rust, ignore
+//! let mut terminal = EpdTerminal::new(&mut epd, &mut display, &FONT_6X10).with_margins(4, 4);
+//! writeln!(terminal, "boot #{} on {}", boots, VERSION).ok();
+//! terminal.flush(&mut spi).await?;
+//! ```
+//!
+//! [`Display`]: crate::graphics::Display
+use core::convert::Infallible;
+use core::fmt::{self, Debug, Display};
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyleBuilder};
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::color::Color;
+use crate::graphics::{self, DisplayRotation, VarDisplay};
+use crate::interface::{BusySignal, ResetControl};
+use crate::traits::{RefreshLut, WaveshareDisplay};
+
+/// Black/white display buffer a terminal can render into
+pub trait TerminalDisplay: DrawTarget<Color = Color, Error = Infallible> {
+    /// The buffer as expected by the display
+    fn frame(&self) -> &[u8];
+
+    /// The current rotation
+    fn rotation(&self) -> DisplayRotation;
+
+    /// Width and height of the display without rotation
+    fn unrotated_size(&self) -> Size {
+        let size = self.bounding_box().size;
+        match TerminalDisplay::rotation(self) {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => size,
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(size.height, size.width)
+            }
+        }
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: usize>
+    TerminalDisplay for graphics::Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, Color>
+{
+    fn frame(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        graphics::Display::rotation(self)
+    }
+}
+
+impl TerminalDisplay for VarDisplay<'_, Color> {
+    fn frame(&self) -> &[u8] {
+        self.buffer()
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        VarDisplay::rotation(self)
+    }
+}
+
+/// What a [`flush`](EpdTerminal::flush) sent to the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// Nothing changed since the last flush
+    Nothing,
+    /// The rows `y..y + height` of the buffer were updated and refreshed with the quick
+    /// look-up table
+    Lines {
+        /// First row of the buffer, without rotation
+        y: u32,
+        /// Number of rows
+        height: u32,
+    },
+    /// A new page was started and the whole display got a full refresh
+    Page,
+}
+
+/// Console appending text to a display, see the [module documentation](self)
+pub struct EpdTerminal<'a, EPD, D> {
+    epd: &'a mut EPD,
+    display: &'a mut D,
+    font: &'a MonoFont<'a>,
+    margin_x: u32,
+    margin_y: u32,
+    line_spacing: u32,
+    column: u32,
+    row: u32,
+    /// First and last text row changed since the last flush
+    dirty: Option<(u32, u32)>,
+    new_page: bool,
+    lut: Option<RefreshLut>,
+}
+
+impl<'a, EPD, D> EpdTerminal<'a, EPD, D>
+where
+    D: TerminalDisplay,
+{
+    /// Creates a terminal writing to `display` with `font`, starting on a blank page
+    pub fn new(epd: &'a mut EPD, display: &'a mut D, font: &'a MonoFont<'a>) -> Self {
+        let mut terminal = EpdTerminal {
+            epd,
+            display,
+            font,
+            margin_x: 0,
+            margin_y: 0,
+            line_spacing: 0,
+            column: 0,
+            row: 0,
+            dirty: None,
+            new_page: false,
+            lut: None,
+        };
+        terminal.start_page();
+        terminal
+    }
+
+    /// Leaves `horizontal` pixels free on the left and right and `vertical` ones on the top
+    /// and bottom
+    pub fn with_margins(mut self, horizontal: u32, vertical: u32) -> Self {
+        self.margin_x = horizontal;
+        self.margin_y = vertical;
+        self
+    }
+
+    /// Adds `spacing` pixels between two lines
+    pub fn with_line_spacing(mut self, spacing: u32) -> Self {
+        self.line_spacing = spacing;
+        self
+    }
+
+    /// Number of characters in a line
+    pub fn columns(&self) -> u32 {
+        let width = self.display.bounding_box().size.width;
+        let advance = self.font.character_size.width + self.font.character_spacing;
+        (width.saturating_sub(2 * self.margin_x) + self.font.character_spacing) / advance.max(1)
+    }
+
+    /// Number of lines on a page
+    pub fn rows(&self) -> u32 {
+        let height = self.display.bounding_box().size.height;
+        (height.saturating_sub(2 * self.margin_y) + self.line_spacing) / self.line_height()
+    }
+
+    /// Column and row the next character is written to
+    ///
+    /// The row equals [`rows`](Self::rows) after a line break on the last line, the next
+    /// character starts a new page.
+    pub fn cursor(&self) -> (u32, u32) {
+        (self.column, self.row)
+    }
+
+    /// The display the text is rendered into
+    pub fn display(&self) -> &D {
+        self.display
+    }
+
+    /// Clears the display and moves the cursor to the top left corner
+    ///
+    /// The next [`flush`](Self::flush) does a full refresh.
+    pub fn clear(&mut self) {
+        self.start_page();
+    }
+
+    fn line_height(&self) -> u32 {
+        (self.font.character_size.height + self.line_spacing).max(1)
+    }
+
+    fn start_page(&mut self) {
+        self.display.clear(Color::White).ok();
+        self.column = 0;
+        self.row = 0;
+        self.dirty = None;
+        self.new_page = true;
+    }
+
+    fn line_break(&mut self) {
+        self.column = 0;
+        self.row += 1;
+    }
+
+    fn put_char(&mut self, c: char) {
+        let columns = self.columns();
+        if columns == 0 || self.rows() == 0 {
+            return;
+        }
+        if self.column >= columns {
+            self.line_break();
+        }
+        if self.row >= self.rows() {
+            self.start_page();
+        }
+
+        let advance = self.font.character_size.width + self.font.character_spacing;
+        let position = Point::new(
+            (self.margin_x + self.column * advance) as i32,
+            (self.margin_y + self.row * self.line_height()) as i32,
+        );
+        let style = MonoTextStyleBuilder::new()
+            .font(self.font)
+            .text_color(Color::Black)
+            .background_color(Color::White)
+            .build();
+        let mut encoded = [0; 4];
+        Text::with_baseline(c.encode_utf8(&mut encoded), position, style, Baseline::Top)
+            .draw(self.display)
+            .ok();
+
+        self.dirty = Some(match self.dirty {
+            Some((first, last)) => (first.min(self.row), last.max(self.row)),
+            None => (self.row, self.row),
+        });
+        self.column += 1;
+    }
+
+    /// Rows of the buffer covered by the text rows `first..=last`
+    fn buffer_rows(&self, first: u32, last: u32) -> (u32, u32) {
+        let size = self.display.bounding_box().size;
+        let top = self.margin_y + first * self.line_height();
+        let bottom = (self.margin_y + (last + 1) * self.line_height()).min(size.height);
+        match TerminalDisplay::rotation(self.display) {
+            DisplayRotation::Rotate0 => (top, bottom - top),
+            DisplayRotation::Rotate180 => (size.height - bottom, bottom - top),
+            // the lines are columns of the buffer
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                (0, self.display.unrotated_size().height)
+            }
+        }
+    }
+
+    /// Sends the changes since the last flush to the display and refreshes it
+    ///
+    /// Changed lines get a partial update and a quick refresh, a new page a full refresh.
+    pub async fn flush<SPI, BUSY, DC, RST>(&mut self, spi: &mut SPI) -> Result<Flush, EPD::Error>
+    where
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST>,
+    {
+        if self.new_page {
+            self.select_lut(spi, RefreshLut::Full).await?;
+            self.epd
+                .update_and_display_frame(spi, self.display.frame())
+                .await?;
+            self.new_page = false;
+            self.dirty = None;
+            return Ok(Flush::Page);
+        }
+
+        let Some((first, last)) = self.dirty else {
+            return Ok(Flush::Nothing);
+        };
+        self.select_lut(spi, RefreshLut::Quick).await?;
+        let (y, height) = self.buffer_rows(first, last);
+        let width = self.display.unrotated_size().width;
+        let line = graphics::line_bytes(width, 1);
+        let rows = &self.display.frame()[y as usize * line..(y + height) as usize * line];
+        self.epd
+            .update_partial_frame(spi, rows, 0, y, width, height)
+            .await?;
+        self.epd.display_frame(spi).await?;
+        self.dirty = None;
+        Ok(Flush::Lines { y, height })
+    }
+
+    async fn select_lut<SPI, BUSY, DC, RST>(
+        &mut self,
+        spi: &mut SPI,
+        lut: RefreshLut,
+    ) -> Result<(), EPD::Error>
+    where
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+        EPD: WaveshareDisplay<SPI, BUSY, DC, RST>,
+    {
+        if self.lut != Some(lut) {
+            self.epd.set_lut(spi, Some(lut)).await?;
+            self.lut = Some(lut);
+        }
+        Ok(())
+    }
+}
+
+impl<EPD, D> fmt::Write for EpdTerminal<'_, EPD, D>
+where
+    D: TerminalDisplay,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => self.line_break(),
+                '\r' => self.column = 0,
+                c if c.is_control() => {}
+                c => self.put_char(c),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd2in9::{Display2in9, Epd2in9, HEIGHT, WIDTH};
+    use crate::test_utils::{block_on, IdleBusy, NoopPin, RecordingSpi};
+    use crate::traits::WaveshareConstructor;
+    use core::fmt::Write;
+    use embedded_graphics::mono_font::ascii::FONT_4X6;
+    use std::vec::Vec;
+
+    type Epd = Epd2in9<RecordingSpi, IdleBusy, NoopPin, NoopPin>;
+
+    // Leaves a text area of 16 x 24 pixels in the middle of the panel
+    const MARGIN_X: u32 = (WIDTH - 16) / 2;
+    const MARGIN_Y: u32 = (HEIGHT - 24) / 2;
+
+    // `DisplayUpdateControl2`, `MasterActivation` and `Nop` of the refresh
+    const REFRESH: [u8; 4] = [0x22, 0xC4, 0x20, 0xFF];
+
+    fn new_epd(spi: &mut RecordingSpi) -> Epd {
+        let epd = block_on(Epd2in9::new(spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+        epd
+    }
+
+    // Whether any pixel of the rows `y..y + height` is black
+    fn has_ink(display: &Display2in9, y: u32, height: u32) -> bool {
+        let row = WIDTH as usize / 8;
+        display.buffer()[y as usize * row..(y + height) as usize * row]
+            .iter()
+            .any(|&byte| byte != 0xFF)
+    }
+
+    fn refreshes(written: &[u8]) -> usize {
+        written
+            .windows(REFRESH.len())
+            .filter(|window| *window == REFRESH)
+            .count()
+    }
+
+    #[test]
+    fn layout() {
+        let spi = &mut RecordingSpi::default();
+        let (mut epd, mut display) = (new_epd(spi), Display2in9::default());
+        let terminal = EpdTerminal::new(&mut epd, &mut display, &FONT_4X6)
+            .with_margins(MARGIN_X + 2, MARGIN_Y + 1)
+            .with_line_spacing(1);
+        // 12 pixels for characters 4 wide, 22 pixels for lines 7 high
+        assert_eq!((terminal.columns(), terminal.rows()), (3, 3));
+    }
+
+    #[test]
+    fn transcript() {
+        let spi = &mut RecordingSpi::default();
+        let (mut epd, mut display) = (new_epd(spi), Display2in9::default());
+        let mut terminal =
+            EpdTerminal::new(&mut epd, &mut display, &FONT_4X6).with_margins(MARGIN_X, MARGIN_Y);
+        assert_eq!((terminal.columns(), terminal.rows()), (4, 4));
+
+        let mut flushes = Vec::new();
+        let mut step = |terminal: &mut EpdTerminal<Epd, Display2in9>, text: &str| {
+            terminal.write_str(text).unwrap();
+            flushes.push(block_on(terminal.flush(spi)).unwrap());
+        };
+
+        step(&mut terminal, "");
+        step(&mut terminal, "");
+        step(&mut terminal, "ab\r");
+        assert_eq!(terminal.cursor(), (0, 0));
+        step(&mut terminal, "\nabcdef");
+        // wrapped onto the third line
+        assert_eq!(terminal.cursor(), (2, 2));
+        step(&mut terminal, "\u{7}\n");
+        assert_eq!(terminal.cursor(), (0, 3));
+        step(&mut terminal, "xy\n");
+        assert_eq!(terminal.cursor(), (0, 4));
+        // starts a new page
+        step(&mut terminal, "é");
+        assert_eq!(terminal.cursor(), (1, 0));
+
+        assert_eq!(
+            flushes,
+            [
+                Flush::Page,
+                Flush::Nothing,
+                Flush::Lines {
+                    y: MARGIN_Y,
+                    height: 6
+                },
+                Flush::Lines {
+                    y: MARGIN_Y + 6,
+                    height: 12
+                },
+                Flush::Nothing,
+                Flush::Lines {
+                    y: MARGIN_Y + 18,
+                    height: 6
+                },
+                Flush::Page,
+            ]
+        );
+        let display = terminal.display();
+        assert!(has_ink(display, MARGIN_Y, 6));
+        assert!(!has_ink(display, MARGIN_Y + 6, 18));
+        assert_eq!(refreshes(&spi.take()), 5);
+    }
+
+    #[test]
+    fn lines_of_a_rotated_display() {
+        let spi = &mut RecordingSpi::default();
+        let (mut epd, mut display) = (new_epd(spi), Display2in9::default());
+        display.set_rotation(DisplayRotation::Rotate180);
+        let mut terminal = EpdTerminal::new(&mut epd, &mut display, &FONT_4X6);
+        block_on(terminal.flush(spi)).unwrap();
+
+        write!(terminal, "x").unwrap();
+        assert_eq!(
+            block_on(terminal.flush(spi)).unwrap(),
+            Flush::Lines {
+                y: HEIGHT - 6,
+                height: 6
+            }
+        );
+        assert!(has_ink(terminal.display(), HEIGHT - 6, 6));
+
+        let (mut epd, mut display) = (new_epd(spi), Display2in9::default());
+        display.set_rotation(DisplayRotation::Rotate90);
+        let mut terminal = EpdTerminal::new(&mut epd, &mut display, &FONT_4X6);
+        block_on(terminal.flush(spi)).unwrap();
+        write!(terminal, "x").unwrap();
+        assert_eq!(
+            block_on(terminal.flush(spi)).unwrap(),
+            Flush::Lines {
+                y: 0,
+                height: HEIGHT
+            }
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Helpers shared by the unit tests of the drivers
+extern crate std;
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::{Operation, SpiDevice};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::interface::BusySignal;
+
+/// Drive a future to completion, all test doubles below are always ready
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Spi device that records every written byte, commands and data alike, and every delay
+#[derive(Default)]
+pub(crate) struct RecordingSpi {
+    pub(crate) written: Vec<u8>,
+    /// Length of every write
+    pub(crate) writes: Vec<usize>,
+    /// Delays in nanoseconds
+    pub(crate) delays: Vec<u32>,
+}
+
+impl RecordingSpi {
+    /// Returns and forgets all bytes written so far
+    pub(crate) fn take(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.written)
+    }
+}
+
+impl embedded_hal::spi::ErrorType for RecordingSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for RecordingSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter() {
+            match operation {
+                Operation::Write(data) => {
+                    self.written.extend_from_slice(data);
+                    self.writes.push(data.len());
+                }
+                Operation::DelayNs(ns) => self.delays.push(*ns),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spi device that records every written byte and answers reads with the queued responses
+#[derive(Default)]
+pub(crate) struct ScriptedSpi {
+    pub(crate) written: Vec<u8>,
+    pub(crate) responses: VecDeque<Vec<u8>>,
+    pub(crate) reads: usize,
+}
+
+impl ScriptedSpi {
+    /// Queues the answer to the next read
+    pub(crate) fn answer(&mut self, response: &[u8]) {
+        self.responses.push_back(response.to_vec());
+    }
+
+    /// Returns and forgets all bytes written so far
+    pub(crate) fn take(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.written)
+    }
+}
+
+impl embedded_hal::spi::ErrorType for ScriptedSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for ScriptedSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(data) => self.written.extend_from_slice(data),
+                Operation::Read(buffer) => {
+                    let response = self.responses.pop_front().expect("unexpected read");
+                    buffer.copy_from_slice(&response);
+                    self.reads += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 16 bit spi device that records every written word and answers transfers with the inverted word
+#[derive(Default)]
+pub(crate) struct RecordingWordSpi {
+    pub(crate) written: Vec<u16>,
+}
+
+impl RecordingWordSpi {
+    /// Returns and forgets all bytes written so far, the upper half of a word first
+    pub(crate) fn take_bytes(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.written)
+            .into_iter()
+            .flat_map(u16::to_be_bytes)
+            .collect()
+    }
+}
+
+impl embedded_hal::spi::ErrorType for RecordingWordSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice<u16> for RecordingWordSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u16>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Write(words) => self.written.extend_from_slice(words),
+                Operation::TransferInPlace(words) => {
+                    self.written.extend_from_slice(words);
+                    words.iter_mut().for_each(|word| *word = !*word);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output pin that ignores everything
+pub(crate) struct NoopPin;
+
+impl embedded_hal::digital::ErrorType for NoopPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoopPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Output pin counting how often it was pulled low
+#[derive(Clone, Default)]
+pub(crate) struct CountingPin(pub(crate) Rc<Cell<u32>>);
+
+impl embedded_hal::digital::ErrorType for CountingPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for CountingPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set(self.0.get() + 1);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Busy signal of a display that is never busy
+pub(crate) struct IdleBusy;
+
+impl BusySignal for IdleBusy {
+    type Error = Infallible;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+        false
+    }
+}
+
+/// Busy signal of a display that is never busy, counting how often it was waited for
+#[derive(Clone, Default)]
+pub(crate) struct CountingBusy(pub(crate) Rc<Cell<u32>>);
+
+impl BusySignal for CountingBusy {
+    type Error = Infallible;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        self.0.set(self.0.get() + 1);
+        Ok(())
+    }
+
+    fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BusyError;
+
+impl core::fmt::Display for BusyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "busy line failed")
+    }
+}
+
+/// Busy signal failing while the shared flag is set
+#[derive(Clone, Default)]
+pub(crate) struct FailingBusy(pub(crate) Rc<Cell<bool>>);
+
+impl BusySignal for FailingBusy {
+    type Error = BusyError;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        if self.0.get() {
+            Err(BusyError)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_busy(&mut self, _is_busy_low: bool) -> bool {
+        false
+    }
+}
+
+/// Busy line at the level in the shared flag, `true` for high, that never needs waiting for
+pub(crate) struct BusyLevel(pub(crate) Rc<Cell<bool>>);
+
+impl BusySignal for BusyLevel {
+    type Error = Infallible;
+
+    async fn wait_idle(&mut self, _is_busy_low: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_busy(&mut self, is_busy_low: bool) -> bool {
+        self.0.get() != is_busy_low
+    }
+}
+
+/// Spi device toggling the shared level of a [`BusyLevel`] on the first 0x12 written, like a
+/// controller reacting to DRF or SWRESET
+pub(crate) struct ProbedSpi {
+    pub(crate) level: Rc<Cell<bool>>,
+    pub(crate) reacted: bool,
+}
+
+impl ProbedSpi {
+    /// Controller whose busy line idles at `idle_high`
+    pub(crate) fn new(idle_high: bool) -> Self {
+        ProbedSpi {
+            level: Rc::new(Cell::new(idle_high)),
+            reacted: false,
+        }
+    }
+
+    /// Busy signal of the controller
+    pub(crate) fn busy(&self) -> BusyLevel {
+        BusyLevel(self.level.clone())
+    }
+}
+
+impl embedded_hal::spi::ErrorType for ProbedSpi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for ProbedSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter() {
+            if let Operation::Write(data) = operation {
+                if !self.reacted && data.contains(&0x12) {
+                    self.reacted = true;
+                    self.level.set(!self.level.get());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether `needle` was sent as one contiguous sequence
+pub(crate) fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Zeroed buffer for a full frame of the given size
+pub(crate) fn blank_buffer(width: u32, height: u32) -> Vec<u8> {
+    std::vec![0; crate::buffer_len(width as usize, height as usize)]
+}
+
+/// Generates a test that configures a driver, cycles it through `sleep` and `wake_up` and checks
+/// that waking up, the following `update_and_display_frame`, `clear_frame` and `sleep` send every
+/// `expected` byte sequence
+///
+/// The frame is 0xAA but for a leading 0x0F, so it can't be mistaken for a cleared one and shows
+/// the order its bytes are sent in.
+macro_rules! user_config_survives_sleep {
+    ($name:ident, $epd:ident, |$driver:ident, $spi:ident| $configure:block, $expected:expr) => {
+        #[test]
+        fn $name() {
+            use crate::test_utils::*;
+            use crate::traits::WaveshareDisplay;
+
+            let mut $spi = RecordingSpi::default();
+            let mut $driver =
+                block_on($epd::new(&mut $spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+            $configure
+
+            block_on($driver.sleep(&mut $spi)).unwrap();
+            $spi.take();
+
+            block_on($driver.wake_up(&mut $spi)).unwrap();
+            let mut buffer = blank_buffer($driver.width(), $driver.height());
+            buffer.fill(0xAA);
+            buffer[0] = 0x0F;
+            block_on($driver.update_and_display_frame(&mut $spi, &buffer)).unwrap();
+            block_on($driver.clear_frame(&mut $spi)).unwrap();
+            block_on($driver.sleep(&mut $spi)).unwrap();
+            let written = $spi.take();
+
+            for expected in $expected {
+                let expected: &[u8] = expected.as_ref();
+                assert!(
+                    contains(&written, expected),
+                    "{:02x?} was not sent after waking up",
+                    expected
+                );
+            }
+        }
+    };
+}
+pub(crate) use user_config_survives_sleep;
@@ -1,19 +1,23 @@
 use core::fmt::{Debug, Display};
 use core::marker::Sized;
-use embedded_hal::digital::{InputPin, OutputPin};
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
 
-use crate::error::ErrorKind;
+use crate::cancel::{CancelToken, Outcome};
+use crate::color::Color;
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, BusyWait, IoStats, ResetControl, Timing, Watchdog, WordSpi};
+use crate::strided::StridedSlice;
 
 pub trait Error<SPI, BUSY, DC, RST>: core::fmt::Debug
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     fn kind(&self) -> &ErrorKind<SPI, BUSY, DC, RST>;
@@ -23,11 +27,11 @@ impl<SPI, BUSY, DC, RST> Error<SPI, BUSY, DC, RST> for core::convert::Infallible
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     fn kind(&self) -> &ErrorKind<SPI, BUSY, DC, RST> {
@@ -39,11 +43,11 @@ pub trait ErrorType<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     type Error: Error<SPI, BUSY, DC, RST>;
@@ -71,17 +75,17 @@ pub(crate) trait InternalWiAdditions<SPI, BUSY, DC, RST>:
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// This initialises the EPD and powers it up
     ///
     /// This function is already called from
-    ///  - [new()](WaveshareDisplay::new())
+    ///  - [new()](WaveshareConstructor::new())
     ///  - [`wake_up`]
     ///
     ///
@@ -97,11 +101,11 @@ pub trait WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>:
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// Transmit data to the SRAM of the EPD
@@ -114,6 +118,18 @@ where
         chromatic: &[u8],
     ) -> Result<(), Self::Error>;
 
+    /// Same as [`update_color_frame`](Self::update_color_frame), taking the chromatic layer first
+    ///
+    /// For call sites that have the layers in this order, so they don't end up swapped.
+    async fn update_color_frame_swapped(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_color_frame(spi, black, chromatic).await
+    }
+
     /// Update only the black/white data of the display.
     ///
     /// This must be finished by calling `update_chromatic_frame`.
@@ -132,6 +148,361 @@ where
         spi: &mut SPI,
         chromatic: &[u8],
     ) -> Result<(), Self::Error>;
+
+    /// Update only the chromatic data of a window of the display
+    ///
+    /// CHROMATIC needs to be of size: width / 8 * height ! Like for
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame) the controller may need
+    /// `x` and `width` to be multiples of 8. The rest of both RAMs stays as it is.
+    ///
+    /// The default implementation returns [`ErrorKind::InvalidArgument`], for controllers
+    /// without partial windows.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_chromatic_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _chromatic: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        Err(ErrorKind::InvalidArgument.into())
+    }
+
+    /// Refreshes the display once and waits until the refresh has finished
+    ///
+    /// One pass of [`display_frame_repeated`](Self::display_frame_repeated). The default
+    /// forwards to [`display_frame`](WaveshareDisplay::display_frame), drivers whose
+    /// `display_frame` doesn't wait after the refresh wait for the busy line afterwards as well.
+    async fn refresh_and_wait(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.display_frame(spi).await
+    }
+
+    /// Pass count of the driver, backing
+    /// [`chromatic_refresh_passes`](Self::chromatic_refresh_passes)
+    fn chromatic_passes(&self) -> ChromaticPasses;
+
+    /// Pass count of the driver, backing
+    /// [`set_chromatic_refresh_passes`](Self::set_chromatic_refresh_passes)
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses;
+
+    /// Number of refreshes done by
+    /// [`update_and_display_color_frame`](Self::update_and_display_color_frame), 1 by default
+    fn chromatic_refresh_passes(&self) -> u8 {
+        self.chromatic_passes().get()
+    }
+
+    /// Sets the number of refreshes done by
+    /// [`update_and_display_color_frame`](Self::update_and_display_color_frame)
+    ///
+    /// `passes` is clamped to `1..=`[`MAX_CHROMATIC_REFRESH_PASSES`], see
+    /// [`display_frame_repeated`](Self::display_frame_repeated) for the trade-off.
+    fn set_chromatic_refresh_passes(&mut self, passes: u8) {
+        *self.chromatic_passes_mut() = ChromaticPasses::new(passes);
+    }
+
+    /// Builder variant of [`set_chromatic_refresh_passes`](Self::set_chromatic_refresh_passes)
+    fn with_chromatic_refresh_passes(mut self, passes: u8) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_chromatic_refresh_passes(passes);
+        self
+    }
+
+    /// Transmits both layers and refreshes
+    /// [`chromatic_refresh_passes`](Self::chromatic_refresh_passes) times
+    async fn update_and_display_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_color_frame(spi, black, chromatic).await?;
+        let passes = self.chromatic_refresh_passes();
+        self.display_frame_repeated(spi, passes).await
+    }
+
+    /// Displays the frame data from SRAM `times` times in a row
+    ///
+    /// On aged panels the chromatic particles often don't fully saturate in a single refresh.
+    /// Running the refresh again on the same SRAM content noticeably improves the red/yellow density.
+    ///
+    /// Every pass is a complete refresh cycle, so this multiplies both the refresh time and
+    /// the wear on the panel. `times` is therefore clamped to [`MAX_CHROMATIC_REFRESH_PASSES`].
+    /// A value of 0 does nothing.
+    ///
+    /// This function waits until the device isn't busy anymore after every pass, see
+    /// [`refresh_and_wait`](Self::refresh_and_wait).
+    async fn display_frame_repeated(
+        &mut self,
+        spi: &mut SPI,
+        times: u8,
+    ) -> Result<(), Self::Error> {
+        self.display_frame_repeated_cancellable(spi, times, &CancelToken::never())
+            .await
+            .map(|_| ())
+    }
+
+    /// Like [`display_frame_repeated`](Self::display_frame_repeated), checking `cancel` before
+    /// every pass
+    ///
+    /// A cancelled refresh reports the number of finished passes.
+    async fn display_frame_repeated_cancellable(
+        &mut self,
+        spi: &mut SPI,
+        times: u8,
+        cancel: &CancelToken<'_>,
+    ) -> Result<Outcome<()>, Self::Error> {
+        for pass in 0..times.min(MAX_CHROMATIC_REFRESH_PASSES) {
+            if cancel.is_cancelled() {
+                return Ok(Outcome::Cancelled {
+                    completed: pass.into(),
+                });
+            }
+            self.refresh_and_wait(spi).await?;
+        }
+        Ok(Outcome::Done(()))
+    }
+}
+
+/// Maximum number of refresh passes done by [`WaveshareThreeColorDisplay::display_frame_repeated`]
+pub const MAX_CHROMATIC_REFRESH_PASSES: u8 = 3;
+
+/// Number of refreshes of
+/// [`update_and_display_color_frame`](WaveshareThreeColorDisplay::update_and_display_color_frame),
+/// kept by every three color driver
+///
+/// Always in `1..=`[`MAX_CHROMATIC_REFRESH_PASSES`], 1 by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChromaticPasses(u8);
+
+impl ChromaticPasses {
+    /// Clamps `passes` to `1..=`[`MAX_CHROMATIC_REFRESH_PASSES`]
+    pub const fn new(passes: u8) -> Self {
+        if passes == 0 {
+            ChromaticPasses(1)
+        } else if passes > MAX_CHROMATIC_REFRESH_PASSES {
+            ChromaticPasses(MAX_CHROMATIC_REFRESH_PASSES)
+        } else {
+            ChromaticPasses(passes)
+        }
+    }
+
+    /// Number of passes
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for ChromaticPasses {
+    fn default() -> Self {
+        ChromaticPasses(1)
+    }
+}
+
+/// Granularity of the windows of [`update_partial_frame`](WaveshareDisplay::update_partial_frame)
+///
+/// `x` and the width of a window need to be multiples of `x`, `y` and the height multiples
+/// of `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialAlignment {
+    /// Columns, in pixels
+    pub x: u32,
+    /// Rows, in pixels
+    pub y: u32,
+}
+
+impl PartialAlignment {
+    /// Windows starting and ending on a byte of a row, what most controllers need
+    pub const BYTE: Self = PartialAlignment { x: 8, y: 1 };
+
+    /// Any window
+    pub const PIXEL: Self = PartialAlignment { x: 1, y: 1 };
+
+    /// Whether the window starts and ends on the alignment
+    pub const fn is_aligned(self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        x.is_multiple_of(self.x)
+            && width.is_multiple_of(self.x)
+            && y.is_multiple_of(self.y)
+            && height.is_multiple_of(self.y)
+    }
+
+    /// The smallest aligned window covering the window, as `(x, y, width, height)`
+    pub const fn expand(self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let (x_start, y_start) = (x - x % self.x, y - y % self.y);
+        let x_end = (x + width).div_ceil(self.x) * self.x;
+        let y_end = (y + height).div_ceil(self.y) * self.y;
+        (x_start, y_start, x_end - x_start, y_end - y_start)
+    }
+}
+
+/// Number of gray levels of a frame for [`WaveshareGrayscaleDisplay`]
+///
+/// Pixels are packed from the most significant bits on, every row starts at a new byte and
+/// a level of 0 is black:
+///
+/// | Levels | Bits per pixel | White |
+/// | --- | --- | --- |
+/// | `Two` | 1, as for [`update_frame`](WaveshareDisplay::update_frame) | 1 |
+/// | `Four` | 2, see [`crate::gray`] | `0b11` |
+/// | `Sixteen` | 4 | `0xF` |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrayscaleLevels {
+    /// Black and white
+    Two,
+    /// Black, dark gray, light gray and white
+    Four,
+    /// Sixteen levels from black to white
+    Sixteen,
+}
+
+impl GrayscaleLevels {
+    /// Number of bits of a pixel
+    pub const fn bits_per_pixel(self) -> u32 {
+        match self {
+            GrayscaleLevels::Two => 1,
+            GrayscaleLevels::Four => 2,
+            GrayscaleLevels::Sixteen => 4,
+        }
+    }
+
+    /// Length of a frame of `width` x `height` pixels
+    pub const fn buffer_len(self, width: u32, height: u32) -> usize {
+        (width as usize * self.bits_per_pixel() as usize).div_ceil(8) * height as usize
+    }
+}
+
+/// Functions to interact with panels showing more than two gray levels
+pub trait WaveshareGrayscaleDisplay<SPI, BUSY, DC, RST>:
+    WaveshareDisplay<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Whether the panel can show frames with `levels`
+    fn supports_levels(&self, levels: GrayscaleLevels) -> bool;
+
+    /// Transmits a frame with `levels` gray levels to the SRAM of the EPD
+    ///
+    /// Switches the display to the waveform for `levels`, it is shown by the next
+    /// [`display_frame`](WaveshareDisplay::display_frame). BUFFER needs to be of size:
+    /// [`GrayscaleLevels::buffer_len`] ! Levels the panel doesn't support return
+    /// [`ErrorKind::InvalidArgument`].
+    async fn update_grayscale_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        levels: GrayscaleLevels,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Constructors of the drivers
+///
+/// Separate from [`WaveshareDisplay`], which is also implemented for mutable references to a
+/// driver.
+pub trait WaveshareConstructor<SPI, BUSY, DC, RST>:
+    WaveshareDisplay<SPI, BUSY, DC, RST> + Sized
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Creates a new driver from a SPI peripheral, Busy signal, DC and RST Pin
+    ///
+    /// `delay_us` is the number of us the idle loop should sleep on.
+    /// Setting it to 0 implies busy waiting.
+    /// Setting it to None means a default value is used.
+    ///
+    /// This already initialises the device.
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        Self::try_new(spi, busy, dc, rst, delay_us)
+            .await
+            .map_err(InitError::into_error)
+    }
+
+    /// Same as [`new`](WaveshareConstructor::new), but hands back the pins if the initialisation fails
+    ///
+    /// This allows retrying on HALs where the pins can only be taken once:
+    ///
+    /// ```rust, ignore
+    /// let epd = match Epd2in9bc::try_new(&mut spi, busy, dc, rst, None).await {
+    ///     Ok(epd) => epd,
+    ///     Err(InitError { busy, dc, rst, .. }) => Epd2in9bc::new(&mut spi, busy, dc, rst, None).await?,
+    /// };
+    /// ```
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>>;
+}
+
+/// Creating a driver for a 16 bit SPI device wrapped in a [`WordSpi`]
+///
+/// Only controllers that ignore the padding byte of odd transfers can be driven with 16 bit
+/// words, the others have no [`WORD_PADDING`](WordSpiConstructor::WORD_PADDING).
+pub trait WordSpiConstructor<SPI, BUSY, DC, RST>:
+    WaveshareConstructor<WordSpi<SPI>, BUSY, DC, RST>
+    + ErrorType<WordSpi<SPI>, BUSY, DC, RST, Error = ErrorKind<WordSpi<SPI>, BUSY, DC, RST>>
+where
+    SPI: SpiDevice<u16>,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Byte the controller ignores both as command and as excess parameter, `None` if there is none
+    const WORD_PADDING: Option<u8>;
+
+    /// Same as [`try_new`](WaveshareConstructor::try_new) for a [`WordSpi`]
+    ///
+    /// Fails with [`ErrorKind::InvalidArgument`] without touching the display if `spi` doesn't pad
+    /// with [`WORD_PADDING`](WordSpiConstructor::WORD_PADDING), in particular if the controller
+    /// can't be used with 16 bit words at all.
+    async fn try_new_word(
+        spi: &mut WordSpi<SPI>,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        if Self::WORD_PADDING != Some(spi.padding()) {
+            return Err(InitError {
+                error: ErrorKind::InvalidArgument,
+                busy,
+                dc,
+                rst,
+            });
+        }
+        Self::try_new(spi, busy, dc, rst, delay_us).await
+    }
 }
 
 /// All the functions to interact with the EPDs
@@ -181,32 +552,15 @@ pub trait WaveshareDisplay<SPI, BUSY, DC, RST>: ErrorType<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// The Color Type used by the Display
     type DisplayColor;
-    /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
-    ///
-    /// `delay_us` is the number of us the idle loop should sleep on.
-    /// Setting it to 0 implies busy waiting.
-    /// Setting it to None means a default value is used.
-    ///
-    /// This already initialises the device.
-    async fn new(
-        spi: &mut SPI,
-        busy: BUSY,
-        dc: DC,
-        rst: RST,
-        delay_us: Option<u32>,
-    ) -> Result<Self, Self::Error>
-    where
-        Self: Sized;
-
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
@@ -248,6 +602,117 @@ where
         height: u32,
     ) -> Result<(), Self::Error>;
 
+    /// Alignment the windows of [`update_partial_frame`](WaveshareDisplay::update_partial_frame)
+    /// need, byte aligned columns by default
+    ///
+    /// Drivers without partial updates keep the default.
+    fn partial_alignment(&self) -> PartialAlignment {
+        PartialAlignment::BYTE
+    }
+
+    /// Programs the partial window and starts the transmission of its data
+    ///
+    /// Splits [`update_partial_frame`](WaveshareDisplay::update_partial_frame) into steps, e.g.
+    /// to stream the window from a slow source: the window is set once, its data is written in
+    /// as many parts as needed with [`write_partial_data`](WaveshareDisplay::write_partial_data)
+    /// and [`end_partial_update`](WaveshareDisplay::end_partial_update) closes the transmission.
+    /// No other command may be sent in between.
+    ///
+    /// The default implementation returns [`ErrorKind::InvalidArgument`], for drivers that can't
+    /// split the update.
+    async fn set_partial_window(
+        &mut self,
+        _spi: &mut SPI,
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        Err(ErrorKind::InvalidArgument.into())
+    }
+
+    /// Writes the next part of the data of the window set with
+    /// [`set_partial_window`](WaveshareDisplay::set_partial_window)
+    ///
+    /// The parts are the rows of the window one after the other, like the buffer of
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame), and may split rows.
+    async fn write_partial_data(&mut self, _spi: &mut SPI, _data: &[u8]) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        Err(ErrorKind::InvalidArgument.into())
+    }
+
+    /// Closes the transmission started with
+    /// [`set_partial_window`](WaveshareDisplay::set_partial_window)
+    async fn end_partial_update(&mut self, _spi: &mut SPI) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        Err(ErrorKind::InvalidArgument.into())
+    }
+
+    /// Transmits partial data taken from the rows of `window`, see [`strided`](crate::strided)
+    ///
+    /// Same as [`update_partial_frame`](WaveshareDisplay::update_partial_frame), but the window
+    /// can be borrowed from a larger buffer instead of being copied into a contiguous one.
+    /// WINDOW needs to hold width / 8 * height bytes, without the gaps between its rows!
+    ///
+    /// The default implementation passes contiguous windows on and returns
+    /// [`ErrorKind::InvalidArgument`] for others, drivers override it to walk the rows.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_frame_strided(
+        &mut self,
+        spi: &mut SPI,
+        window: StridedSlice<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        match window.as_contiguous() {
+            Some(buffer) => {
+                self.update_partial_frame(spi, buffer, x, y, width, height)
+                    .await
+            }
+            None => Err(ErrorKind::InvalidArgument.into()),
+        }
+    }
+
+    /// Transmits `stripe_height` full rows of a frame, starting at row `y_start`
+    ///
+    /// Allows streaming a frame in stripes when there is no memory for a full buffer.
+    /// The stripes have to be sent in order starting at row 0, followed by
+    /// [`end_stripe`](WaveshareDisplay::end_stripe) once the last one was sent.
+    ///
+    /// STRIPE needs to be of size: width / 8 * stripe_height !
+    ///
+    /// The default implementation sends every stripe as a partial frame, drivers that
+    /// can keep the frame transmission open override it. On drivers without partial
+    /// updates it returns [`ErrorKind::InvalidArgument`], like their `update_partial_frame`.
+    async fn update_frame_stripe(
+        &mut self,
+        spi: &mut SPI,
+        stripe: &[u8],
+        y_start: u32,
+        stripe_height: u32,
+    ) -> Result<(), Self::Error> {
+        let width = self.width();
+        self.update_partial_frame(spi, stripe, 0, y_start, width, stripe_height)
+            .await
+    }
+
+    /// Finishes a frame sent with [`update_frame_stripe`](WaveshareDisplay::update_frame_stripe)
+    async fn end_stripe(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
@@ -260,6 +725,27 @@ where
         buffer: &[u8],
     ) -> Result<(), Self::Error>;
 
+    /// Sends the frame and starts the refresh, without waiting for the refresh to finish
+    ///
+    /// The display stays busy for the duration of the refresh, call
+    /// [`wait_until_idle`](WaveshareDisplay::wait_until_idle) or
+    /// [`flush`](WaveshareDisplay::flush) before the next operation. There is no `delay`
+    /// parameter, like everywhere in this crate the drivers delay with the SPI device.
+    ///
+    /// The default implementation calls
+    /// [`update_and_display_frame`](WaveshareDisplay::update_and_display_frame) and so blocks
+    /// until the refresh has finished. Every driver overrides it except for the 1in02,
+    /// 5in65f and 7in3f, whose refresh powers the panel on and off again around it, and the
+    /// 2in13 V2 with the quick look-up tables, which writes the base of the next refresh
+    /// after it.
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_and_display_frame(spi, buffer).await
+    }
+
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
@@ -283,6 +769,731 @@ where
     ///
     /// You can call this to make sure a frame is displayed before goin further
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Checks the busy signal once and returns right away, true while the display is busy
+    ///
+    /// The polarity of the busy line of the driver is taken care of. Unlike
+    /// [`wait_until_idle`](WaveshareDisplay::wait_until_idle) this sends nothing, so drivers
+    /// that ask the controller for its status before reading the line may lag behind by a check.
+    fn is_busy(&mut self) -> bool;
+
+    /// Whether a refresh started by
+    /// [`update_and_display_frame_no_wait`](WaveshareDisplay::update_and_display_frame_no_wait)
+    /// may still be running
+    ///
+    /// Drivers whose refresh always waits for the display never have an update pending.
+    fn update_pending(&self) -> bool {
+        false
+    }
+
+    /// Waits until the display is idle if an update is pending, returns right away otherwise
+    ///
+    /// Cheap to call as a precaution, e.g. before putting the display to sleep.
+    async fn flush(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.update_pending() {
+            self.wait_until_idle(spi).await?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the panel has the controller generation this driver is written for
+    ///
+    /// Resets the controller, watches a reaction that tells the generations apart and
+    /// initialises it again. Fails with [`ErrorKind::PanelMismatch`] naming the suspected
+    /// generation only if the observation is unambiguous, a panel that doesn't react at all
+    /// passes. Drivers without such an observation always pass.
+    async fn sanity_check(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Reads critical registers back after writing them and writes them again if they differ
+    ///
+    /// Off by default. Only drivers of controllers with register readback support it, like
+    /// the 2in13 V2. The default implementation rejects turning it on with
+    /// [`ErrorKind::InvalidArgument`], e.g. for the UC81xx family, which can't read registers.
+    fn verify_register_writes(&mut self, verify: bool) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        if verify {
+            Err(ErrorKind::InvalidArgument.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the controller was reset behind the back of the driver and needs a `wake_up`
+    ///
+    /// Happens when another display on the same [`ResetLine`](crate::ResetLine) was reset,
+    /// or after [`on_external_reset`](Self::on_external_reset).
+    fn needs_init(&self) -> bool {
+        false
+    }
+
+    /// Tells the driver that its controller was reset by other means, e.g. a supervisor chip
+    ///
+    /// Forgets the cached state of the controller, like loaded look-up tables, and
+    /// [`needs_init`](Self::needs_init) reports true until the next `wake_up`.
+    fn on_external_reset(&mut self) {}
+
+    /// The timing parameters, see [`Timing`]
+    fn timing(&self) -> Timing {
+        Timing::default()
+    }
+
+    /// Replaces the timing parameters, taking effect from the next delay or busy wait
+    ///
+    /// Allows e.g. switching to a fine grained timer only while a refresh is running.
+    fn set_timing(&mut self, _timing: Timing) {}
+
+    /// Bytes written to the controller since the driver was created or the last
+    /// [`reset_io_stats`](Self::reset_io_stats), split into command and data bytes
+    ///
+    /// Counting costs an addition per write. Drivers without the counters report zero.
+    fn io_stats(&self) -> IoStats {
+        IoStats::default()
+    }
+
+    /// Sets the counters of [`io_stats`](Self::io_stats) back to zero
+    fn reset_io_stats(&mut self) {}
+
+    /// Changes how long to sleep between two checks of the busy signal while polling
+    fn set_busy_poll_interval(&mut self, us: u32) {
+        let timing = self.timing();
+        self.set_timing(Timing {
+            busy_poll_interval_us: us,
+            ..timing
+        });
+    }
+
+    /// Changes how to wait for the busy signal
+    fn set_wait_strategy(&mut self, busy_wait: BusyWait) {
+        let timing = self.timing();
+        self.set_timing(Timing {
+            busy_wait,
+            ..timing
+        });
+    }
+
+    /// Gives up waiting for the busy signal after `us` microseconds, see
+    /// [`Timing::busy_timeout_us`]
+    fn set_busy_timeout(&mut self, us: Option<u32>) {
+        let timing = self.timing();
+        self.set_timing(Timing {
+            busy_timeout_us: us,
+            ..timing
+        });
+    }
+
+    /// Scales the fixed delays of the command sequences
+    fn set_delay_scale_percent(&mut self, percent: u32) {
+        let timing = self.timing();
+        self.set_timing(Timing {
+            delay_scale_percent: percent,
+            ..timing
+        });
+    }
+
+    /// Rounds the fixed delays of the command sequences up to a multiple of `us`, e.g. the tick
+    /// of the delay implementation
+    fn set_delay_granularity(&mut self, us: u32) {
+        let timing = self.timing();
+        self.set_timing(Timing {
+            delay_granularity_us: us,
+            ..timing
+        });
+    }
+
+    /// Calls a hook regularly during long waits and transmissions, e.g. to feed a hardware
+    /// watchdog, see [`Watchdog`]
+    fn set_watchdog(&mut self, watchdog: Option<Watchdog>) {
+        let timing = self.timing();
+        self.set_timing(Timing { watchdog, ..timing });
+    }
+}
+
+impl<SPI, BUSY, DC, RST, D> ErrorType<SPI, BUSY, DC, RST> for &mut D
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: ErrorType<SPI, BUSY, DC, RST>,
+{
+    type Error = D::Error;
+}
+
+/// Allows passing a driver by mutable reference to functions generic over [`WaveshareDisplay`]
+///
+/// A reference can't create the driver it points to, so it doesn't implement
+/// [`WaveshareConstructor`].
+impl<SPI, BUSY, DC, RST, D> WaveshareDisplay<SPI, BUSY, DC, RST> for &mut D
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareDisplay<SPI, BUSY, DC, RST>,
+{
+    type DisplayColor = D::DisplayColor;
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::sleep(self, spi).await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::wake_up(self, spi).await
+    }
+
+    fn set_background_color(&mut self, color: Self::DisplayColor) {
+        D::set_background_color(self, color)
+    }
+
+    fn background_color(&self) -> &Self::DisplayColor {
+        D::background_color(self)
+    }
+
+    fn width(&self) -> u32 {
+        D::width(self)
+    }
+
+    fn height(&self) -> u32 {
+        D::height(self)
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        D::update_frame(self, spi, buffer).await
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        D::update_partial_frame(self, spi, buffer, x, y, width, height).await
+    }
+
+    fn partial_alignment(&self) -> PartialAlignment {
+        D::partial_alignment(self)
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        D::set_partial_window(self, spi, x, y, width, height).await
+    }
+
+    async fn write_partial_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        D::write_partial_data(self, spi, data).await
+    }
+
+    async fn end_partial_update(&mut self, spi: &mut SPI) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        D::end_partial_update(self, spi).await
+    }
+
+    async fn update_partial_frame_strided(
+        &mut self,
+        spi: &mut SPI,
+        window: StridedSlice<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        D::update_partial_frame_strided(self, spi, window, x, y, width, height).await
+    }
+
+    async fn update_frame_stripe(
+        &mut self,
+        spi: &mut SPI,
+        stripe: &[u8],
+        y_start: u32,
+        stripe_height: u32,
+    ) -> Result<(), Self::Error> {
+        D::update_frame_stripe(self, spi, stripe, y_start, stripe_height).await
+    }
+
+    async fn end_stripe(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::end_stripe(self, spi).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::display_frame(self, spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        D::update_and_display_frame(self, spi, buffer).await
+    }
+
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        D::update_and_display_frame_no_wait(self, spi, buffer).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::clear_frame(self, spi).await
+    }
+
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        D::set_lut(self, spi, refresh_rate).await
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::wait_until_idle(self, spi).await
+    }
+
+    fn is_busy(&mut self) -> bool {
+        D::is_busy(self)
+    }
+
+    fn update_pending(&self) -> bool {
+        D::update_pending(self)
+    }
+
+    async fn flush(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::flush(self, spi).await
+    }
+
+    async fn sanity_check(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        D::sanity_check(self, spi).await
+    }
+
+    fn verify_register_writes(&mut self, verify: bool) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        D::verify_register_writes(self, verify)
+    }
+
+    fn needs_init(&self) -> bool {
+        D::needs_init(self)
+    }
+
+    fn on_external_reset(&mut self) {
+        D::on_external_reset(self)
+    }
+
+    fn timing(&self) -> Timing {
+        D::timing(self)
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        D::set_timing(self, timing)
+    }
+
+    fn io_stats(&self) -> IoStats {
+        D::io_stats(self)
+    }
+
+    fn reset_io_stats(&mut self) {
+        D::reset_io_stats(self)
+    }
+}
+
+/// Hash of the last frame sent by [`WaveshareDisplayExt::refresh_if_changed`]
+///
+/// Keep one per display, the default doesn't match any frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameHash(Option<u32>);
+
+impl FrameHash {
+    /// FNV-1a hash of a frame buffer
+    pub const fn of(buffer: &[u8]) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        let mut i = 0;
+        while i < buffer.len() {
+            hash ^= buffer[i] as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+            i += 1;
+        }
+        hash
+    }
+
+    /// Forget the last frame, e.g. after the display was cleared by other means
+    pub fn invalidate(&mut self) {
+        self.0 = None;
+    }
+}
+
+/// Refresh done by [`WaveshareDisplayExt::smart_update`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefreshType {
+    /// The frame didn't change, nothing was sent
+    Unchanged,
+    /// Only the rows with changes were sent and displayed
+    Partial,
+    /// The whole frame was sent and displayed
+    Full,
+}
+
+/// Higher level functions for every [`WaveshareDisplay`]
+///
+/// Drawing text is done with embedded-graphics on a [`Display`](crate::graphics::Display)
+/// buffer, this crate has no font of its own.
+pub trait WaveshareDisplayExt<SPI, BUSY, DC, RST>: WaveshareDisplay<SPI, BUSY, DC, RST>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+{
+    /// Transmits a 1 bit per pixel image to the given position and displays it
+    ///
+    /// IMAGE needs to be of size: width / 8 * height !
+    async fn display_image_1bpp(
+        &mut self,
+        spi: &mut SPI,
+        image: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.update_partial_frame(spi, image, x, y, width, height)
+            .await?;
+        self.display_frame(spi).await
+    }
+
+    /// Same as [`update_and_display_frame`](WaveshareDisplay::update_and_display_frame),
+    /// returning the bytes it wrote, see [`io_stats`](WaveshareDisplay::io_stats)
+    async fn update_and_display_frame_counted(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<IoStats, Self::Error> {
+        let before = self.io_stats();
+        self.update_and_display_frame(spi, buffer).await?;
+        Ok(self.io_stats().since(before))
+    }
+
+    /// Updates and displays `buffer` unless it is the frame that was sent last
+    ///
+    /// Returns whether the display was refreshed.
+    async fn refresh_if_changed(
+        &mut self,
+        spi: &mut SPI,
+        last: &mut FrameHash,
+        buffer: &[u8],
+    ) -> Result<bool, Self::Error> {
+        let hash = FrameHash::of(buffer);
+        if last.0 == Some(hash) {
+            return Ok(false);
+        }
+        // A failed update leaves the content of the display unknown
+        last.invalidate();
+        self.update_and_display_frame(spi, buffer).await?;
+        last.0 = Some(hash);
+        Ok(true)
+    }
+
+    /// Updates and displays `buffer` with a partial update if few pixels changed
+    ///
+    /// `previous` holds the frame that is on the display and is compared with `buffer`. If
+    /// less than `partial_threshold` percent of the pixels differ, the rows from the first to
+    /// the last changed one are sent as a partial frame, otherwise the whole frame is. Drivers
+    /// rejecting the partial frame with [`ErrorKind::InvalidArgument`], e.g. because they have
+    /// no partial updates, get the whole frame as well. On success `buffer` is copied to
+    /// `previous`. Returns the refresh that was done.
+    ///
+    /// The previous frame is kept by the caller instead of the driver: the drivers don't own a
+    /// frame buffer and this crate doesn't allocate.
+    ///
+    /// Both buffers need to be of size: width / 8 * height !
+    async fn smart_update(
+        &mut self,
+        spi: &mut SPI,
+        previous: &mut [u8],
+        buffer: &[u8],
+        partial_threshold: f32,
+    ) -> Result<RefreshType, Self::Error>
+    where
+        Self: ErrorType<SPI, BUSY, DC, RST, Error = ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let row_len = crate::buffer_len(self.width() as usize, 1);
+        if buffer.len() != previous.len() || buffer.len() != row_len * self.height() as usize {
+            return Err(ErrorKind::InvalidArgument);
+        }
+
+        let mut changed = 0u64;
+        let mut rows = None;
+        for (y, (old, new)) in previous
+            .chunks(row_len)
+            .zip(buffer.chunks(row_len))
+            .enumerate()
+        {
+            let row_changed: u32 = old.iter().zip(new).map(|(a, b)| (a ^ b).count_ones()).sum();
+            if row_changed > 0 {
+                changed += u64::from(row_changed);
+                rows = Some(rows.map_or((y, y), |(first, _)| (first, y)));
+            }
+        }
+        let Some((first, last)) = rows else {
+            return Ok(RefreshType::Unchanged);
+        };
+
+        let pixels = u64::from(self.width()) * u64::from(self.height());
+        let mut refresh = RefreshType::Full;
+        if (changed as f32) < pixels as f32 * partial_threshold / 100.0 {
+            let window = &buffer[first * row_len..(last + 1) * row_len];
+            let width = self.width();
+            match self
+                .update_partial_frame(
+                    spi,
+                    window,
+                    0,
+                    first as u32,
+                    width,
+                    (last - first + 1) as u32,
+                )
+                .await
+            {
+                Ok(()) => {
+                    self.display_frame(spi).await?;
+                    refresh = RefreshType::Partial;
+                }
+                Err(ErrorKind::InvalidArgument) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        if refresh == RefreshType::Full {
+            self.update_and_display_frame(spi, buffer).await?;
+        }
+        previous.copy_from_slice(buffer);
+        Ok(refresh)
+    }
+
+    /// Updates and displays `buffer` with the refresh mode `policy` recommends for it
+    ///
+    /// Analyzes the 1 bit per pixel frame with [`FrameCharacter`](crate::graphics::FrameCharacter)
+    /// and returns the chosen mode. Drivers with a quick mode offer a `REFRESH_POLICY`.
+    #[cfg(feature = "graphics")]
+    async fn update_and_display_frame_with_policy(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        policy: &crate::graphics::RefreshPolicy,
+    ) -> Result<RefreshLut, Self::Error> {
+        let frame = crate::graphics::FrameCharacter::of_buffer(buffer, self.width(), self.height());
+        let lut = policy.recommend(&frame);
+        self.set_lut(spi, Some(lut)).await?;
+        self.update_and_display_frame(spi, buffer).await?;
+        Ok(lut)
+    }
+
+    /// Updates the frame from run-length encoded data
+    ///
+    /// RLE_DATA is a sequence of `[count, value]` byte pairs, each repeating `value`
+    /// `count` times. It is decoded while transmitting, in stripes of whole rows (see
+    /// [`update_frame_stripe`](WaveshareDisplay::update_frame_stripe)), so no full
+    /// buffer is needed. The pairs have to decode to exactly width / 8 * height bytes,
+    /// which is checked before anything is sent.
+    async fn update_frame_rle(&mut self, spi: &mut SPI, rle_data: &[u8]) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let row_len = crate::buffer_len(self.width() as usize, 1);
+        let len: usize = rle_data.chunks(2).map(|pair| pair[0] as usize).sum();
+        if !rle_data.len().is_multiple_of(2)
+            || row_len > STRIPE_LEN
+            || len != row_len * self.height() as usize
+        {
+            return Err(ErrorKind::InvalidArgument.into());
+        }
+
+        let stripe_rows = STRIPE_LEN / row_len;
+        let mut stripe = [0u8; STRIPE_LEN];
+        let mut filled = 0;
+        let mut y = 0;
+        for pair in rle_data.chunks(2) {
+            let (mut count, value) = (pair[0] as usize, pair[1]);
+            while count > 0 {
+                let n = count.min(stripe_rows * row_len - filled);
+                stripe[filled..filled + n].fill(value);
+                filled += n;
+                count -= n;
+                // only whole rows are sent, the last stripe may be shorter
+                if filled == stripe_rows * row_len || y * row_len + filled == len {
+                    let rows = filled / row_len;
+                    self.update_frame_stripe(spi, &stripe[..filled], y as u32, rows as u32)
+                        .await?;
+                    y += rows;
+                    filled = 0;
+                }
+            }
+        }
+        self.end_stripe(spi).await
+    }
+
+    /// Updates the frame with the color `pixel_fn` returns for every pixel `(x, y)`
+    ///
+    /// The pixels are packed into a buffer of a few rows and sent in stripes (see
+    /// [`update_frame_stripe`](WaveshareDisplay::update_frame_stripe)), so procedural content
+    /// like patterns or fractals needs no full buffer.
+    async fn update_frame_from_fn(
+        &mut self,
+        spi: &mut SPI,
+        pixel_fn: impl Fn(u32, u32) -> Color,
+    ) -> Result<(), Self::Error>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DisplayColor = Color>,
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let (width, height) = (self.width(), self.height());
+        let row_len = crate::buffer_len(width as usize, 1);
+        if row_len > STRIPE_LEN {
+            return Err(ErrorKind::InvalidArgument.into());
+        }
+
+        let stripe_rows = (STRIPE_LEN / row_len) as u32;
+        let mut stripe = [0u8; STRIPE_LEN];
+        let mut y = 0;
+        while y < height {
+            let rows = stripe_rows.min(height - y);
+            let stripe = &mut stripe[..row_len * rows as usize];
+            for (row, bytes) in stripe.chunks_mut(row_len).enumerate() {
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    // the padding bits at the end of a row stay white
+                    *byte = 0xFF;
+                    for bit in 0..8 {
+                        let x = i as u32 * 8 + bit;
+                        if x < width && pixel_fn(x, y + row as u32) == Color::Black {
+                            *byte &= !(0x80 >> bit);
+                        }
+                    }
+                }
+            }
+            self.update_frame_stripe(spi, stripe, y, rows).await?;
+            y += rows;
+        }
+        self.end_stripe(spi).await
+    }
+
+    /// Refreshes the whole display black, white and black again
+    ///
+    /// Showing the same content for months can leave a faint ghost of it. Driving every pixel
+    /// through both colors clears the residual charge, call this now and then (e.g. once a day)
+    /// on always-on displays and redraw the content afterwards. The frames are sent with
+    /// [`update_frame_from_fn`](Self::update_frame_from_fn) and refreshed with the LUT in use,
+    /// switch to the full LUT first if a quick one is set.
+    async fn screen_saver_refresh(&mut self, spi: &mut SPI) -> Result<(), Self::Error>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DisplayColor = Color>,
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        for color in [Color::Black, Color::White, Color::Black] {
+            self.update_frame_from_fn(spi, |_, _| color).await?;
+            self.display_frame(spi).await?;
+        }
+        Ok(())
+    }
+
+    /// Measures the throughput of the transfers to the controller, in bytes per second
+    ///
+    /// Sends at least `byte_count` bytes of white rows (whole rows, starting over at the top
+    /// after a full frame) into the frame RAM through
+    /// [`update_frame_stripe`](WaveshareDisplay::update_frame_stripe), the same code path as
+    /// [`update_frame_rle`](Self::update_frame_rle). The result includes the overhead of the
+    /// driver, e.g. one transaction per byte, so it estimates the time of a frame better than
+    /// the SPI clock. `clock` returns the current time in microseconds.
+    ///
+    /// Overwrites the frame RAM without refreshing, send the frame again before the next
+    /// refresh.
+    async fn benchmark_spi_transfer(
+        &mut self,
+        spi: &mut SPI,
+        clock: impl Fn() -> u64,
+        byte_count: u32,
+    ) -> Result<u32, Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let (width, height) = (self.width(), self.height());
+        let row_len = crate::buffer_len(width as usize, 1);
+        if row_len == 0 || row_len > STRIPE_LEN || height == 0 {
+            return Err(ErrorKind::InvalidArgument.into());
+        }
+
+        let stripe_rows = (STRIPE_LEN / row_len) as u32;
+        let stripe = [0xFF; STRIPE_LEN];
+        let mut sent = 0u64;
+        let start = clock();
+        while sent < u64::from(byte_count) {
+            let mut y = 0;
+            while y < height && sent < u64::from(byte_count) {
+                let rows = stripe_rows.min(height - y);
+                self.update_frame_stripe(spi, &stripe[..row_len * rows as usize], y, rows)
+                    .await?;
+                y += rows;
+                sent += (row_len * rows as usize) as u64;
+            }
+            self.end_stripe(spi).await?;
+        }
+        let elapsed = clock().wrapping_sub(start).max(1);
+        Ok((sent * 1_000_000 / elapsed).min(u32::MAX.into()) as u32)
+    }
+}
+
+/// Size of the stripe buffer [`WaveshareDisplayExt::update_frame_rle`],
+/// [`WaveshareDisplayExt::update_frame_from_fn`] and
+/// [`WaveshareDisplayExt::benchmark_spi_transfer`] fill
+const STRIPE_LEN: usize = 512;
+
+impl<SPI, BUSY, DC, RST, D> WaveshareDisplayExt<SPI, BUSY, DC, RST> for D
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareDisplay<SPI, BUSY, DC, RST>,
+{
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -333,11 +1544,11 @@ pub trait QuickRefresh<SPI, BUSY, DC, RST>: ErrorType<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
-    BUSY: InputPin + Wait,
+    BUSY: BusySignal,
     BUSY::Error: Copy + Debug + Display,
     DC: OutputPin,
     DC::Error: Copy + Debug + Display,
-    RST: OutputPin,
+    RST: ResetControl,
     RST::Error: Copy + Debug + Display,
 {
     /// Updates the old frame.
@@ -391,3 +1602,305 @@ where
         height: u32,
     ) -> Result<(), Self::Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd2in7b::Epd2in7b;
+    use crate::epd2in9::Epd2in9;
+    use crate::test_utils::{
+        blank_buffer, block_on, contains, BusyLevel, IdleBusy, NoopPin, RecordingSpi,
+    };
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn partial_alignment_expands_windows() {
+        let byte = PartialAlignment::BYTE;
+        assert!(byte.is_aligned(8, 3, 16, 5));
+        assert!(!byte.is_aligned(4, 0, 8, 1));
+        assert!(!byte.is_aligned(8, 0, 12, 1));
+        assert_eq!(byte.expand(13, 3, 6, 5), (8, 3, 16, 5));
+        assert_eq!(byte.expand(16, 0, 8, 1), (16, 0, 8, 1));
+
+        let tiles = PartialAlignment { x: 8, y: 8 };
+        assert_eq!(tiles.expand(1, 7, 1, 2), (0, 0, 8, 16));
+        assert_eq!(PartialAlignment::PIXEL.expand(1, 7, 1, 2), (1, 7, 1, 2));
+    }
+
+    async fn clear(
+        mut display: impl WaveshareDisplay<RecordingSpi, IdleBusy, NoopPin, NoopPin>,
+        spi: &mut RecordingSpi,
+    ) -> u32 {
+        display.clear_frame(spi).await.unwrap();
+        display.width()
+    }
+
+    #[test]
+    fn display_by_mutable_reference() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        assert_eq!(block_on(clear(&mut epd, &mut spi)), epd.width());
+        assert!(!spi.take().is_empty());
+    }
+
+    #[test]
+    fn refresh_only_changed_frames() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let mut last = FrameHash::default();
+        let mut frame = blank_buffer(epd.width(), epd.height());
+        spi.take();
+
+        assert!(block_on(epd.refresh_if_changed(&mut spi, &mut last, &frame)).unwrap());
+        assert!(!spi.take().is_empty());
+        assert!(!block_on(epd.refresh_if_changed(&mut spi, &mut last, &frame)).unwrap());
+        assert!(spi.take().is_empty());
+
+        frame[3] = 0xAA;
+        assert!(block_on(epd.refresh_if_changed(&mut spi, &mut last, &frame)).unwrap());
+        last.invalidate();
+        assert!(block_on(epd.refresh_if_changed(&mut spi, &mut last, &frame)).unwrap());
+    }
+
+    #[test]
+    fn smart_update_refreshes_the_changed_rows() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let row_len = crate::buffer_len(epd.width() as usize, 1);
+        let mut previous = blank_buffer(epd.width(), epd.height());
+        let mut frame = previous.clone();
+        spi.take();
+
+        assert_eq!(
+            block_on(epd.smart_update(&mut spi, &mut previous, &frame, 10.0)).unwrap(),
+            RefreshType::Unchanged
+        );
+        assert!(spi.take().is_empty());
+
+        // a few pixels in rows 2 and 4
+        frame[2 * row_len + 1] = 0x0F;
+        frame[4 * row_len] = 0x7F;
+        let window = frame[2 * row_len..5 * row_len].to_vec();
+        assert_eq!(
+            block_on(epd.smart_update(&mut spi, &mut previous, &frame, 10.0)).unwrap(),
+            RefreshType::Partial
+        );
+        assert_eq!(previous, frame);
+        let smart = spi.take();
+        block_on(epd.update_partial_frame(&mut spi, &window, 0, 2, epd.width(), 3)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(smart, spi.take());
+
+        frame.fill(0xFF);
+        assert_eq!(
+            block_on(epd.smart_update(&mut spi, &mut previous, &frame, 10.0)).unwrap(),
+            RefreshType::Full
+        );
+        let smart = spi.take();
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(smart, spi.take());
+
+        assert!(matches!(
+            block_on(epd.smart_update(&mut spi, &mut previous[1..], &frame, 10.0)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+    }
+
+    #[test]
+    fn smart_update_falls_back_to_a_full_update() {
+        use crate::epd5in83_v2::Epd5in83;
+
+        // no partial updates
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd5in83::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let mut previous = blank_buffer(epd.width(), epd.height());
+        let mut frame = previous.clone();
+        frame[0] = 0x7F;
+        spi.take();
+
+        assert_eq!(
+            block_on(epd.smart_update(&mut spi, &mut previous, &frame, 10.0)).unwrap(),
+            RefreshType::Full
+        );
+        assert_eq!(previous, frame);
+        let smart = spi.take();
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(smart, spi.take());
+    }
+
+    #[test]
+    fn busy_polarity_of_the_driver() {
+        let level = Rc::new(Cell::new(true));
+        let mut spi = RecordingSpi::default();
+        // busy low
+        let mut epd_2in7b = block_on(Epd2in7b::new(
+            &mut spi,
+            BusyLevel(level.clone()),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        // busy high
+        let mut epd_2in9 = block_on(Epd2in9::new(
+            &mut spi,
+            BusyLevel(level.clone()),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+
+        assert!(!epd_2in7b.is_busy());
+        assert!(epd_2in9.is_busy());
+        level.set(false);
+        assert!(epd_2in7b.is_busy());
+        assert!(!epd_2in9.is_busy());
+    }
+
+    #[test]
+    fn screen_saver_cycles_black_and_white() {
+        let mut spi = RecordingSpi::default();
+        let mut epd =
+            block_on(Epd2in7b::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        spi.take();
+
+        block_on(epd.screen_saver_refresh(&mut spi)).unwrap();
+        let cycle = spi.take();
+        for color in [Color::Black, Color::White, Color::Black] {
+            block_on(epd.update_frame_from_fn(&mut spi, |_, _| color)).unwrap();
+            block_on(epd.display_frame(&mut spi)).unwrap();
+        }
+        assert_eq!(cycle, spi.take());
+        assert!(contains(&cycle, &[0x00; 16]));
+        assert!(contains(&cycle, &[0xFF; 16]));
+    }
+
+    /// Checks that `update_and_display_frame_no_wait` sends the same bytes as
+    /// `update_and_display_frame`, saving `waits_saved` busy waits and leaving a pending update
+    /// for `flush` if `pending`
+    fn check_refresh_without_waiting<D>(waits_saved: u32, pending: bool)
+    where
+        D: WaveshareConstructor<RecordingSpi, crate::test_utils::CountingBusy, NoopPin, NoopPin>,
+        D::Error: core::fmt::Debug,
+    {
+        let busy = crate::test_utils::CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(D::new(&mut spi, busy.clone(), NoopPin, NoopPin, Some(0))).unwrap();
+        let buffer = blank_buffer(epd.width(), epd.height());
+        spi.take();
+
+        busy.0.set(0);
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        let (waiting, waits) = (spi.take(), busy.0.get());
+        assert!(!epd.update_pending());
+
+        busy.0.set(0);
+        block_on(epd.update_and_display_frame_no_wait(&mut spi, &buffer)).unwrap();
+        assert_eq!(spi.take(), waiting);
+        assert_eq!(busy.0.get() + waits_saved, waits);
+        assert_eq!(epd.update_pending(), pending);
+
+        busy.0.set(0);
+        block_on(epd.flush(&mut spi)).unwrap();
+        assert_eq!(busy.0.get(), u32::from(pending));
+        assert!(!epd.update_pending());
+    }
+
+    #[test]
+    fn refresh_without_waiting() {
+        // waits before the refresh command only
+        check_refresh_without_waiting::<Epd2in7b<_, _, _, _>>(0, true);
+        // waits after the refresh command
+        check_refresh_without_waiting::<crate::epd4in2b::Epd4in2b<_, _, _, _>>(1, true);
+        check_refresh_without_waiting::<crate::epd2in9d::Epd2in9d<_, _, _, _>>(1, true);
+        // powers the panel off after the refresh
+        check_refresh_without_waiting::<crate::epd1in02::Epd1in02<_, _, _, _>>(0, false);
+    }
+
+    #[test]
+    fn repeated_refresh_waits_once_per_pass() {
+        use crate::epd1in54b::{command::Command as Command1in54b, Epd1in54b};
+        use crate::epd2in7b::command::Command as Command2in7b;
+        use crate::test_utils::CountingBusy;
+
+        // the 2in7b waits after the refresh command, the 1in54b only before it
+        let busy = CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in7b::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+        busy.0.set(0);
+        block_on(epd.display_frame_repeated(&mut spi, 3)).unwrap();
+        assert_eq!(spi.take(), [Command2in7b::DisplayRefresh as u8; 3]);
+        assert_eq!(busy.0.get(), 3);
+
+        let busy = CountingBusy::default();
+        let mut epd = block_on(Epd1in54b::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap();
+        spi.take();
+        busy.0.set(0);
+        block_on(epd.display_frame_repeated(&mut spi, 3)).unwrap();
+        assert_eq!(spi.take(), [Command1in54b::DisplayRefresh as u8; 3]);
+        assert_eq!(busy.0.get(), 6);
+    }
+
+    #[test]
+    fn color_frame_refreshes_the_configured_passes() {
+        use crate::epd2in7b::{self, command::Command};
+        use crate::test_utils::CountingBusy;
+
+        let black = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+        let chromatic = blank_buffer(epd2in7b::WIDTH, epd2in7b::HEIGHT);
+        let busy = CountingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = block_on(Epd2in7b::new(
+            &mut spi,
+            busy.clone(),
+            NoopPin,
+            NoopPin,
+            Some(0),
+        ))
+        .unwrap()
+        .with_chromatic_refresh_passes(2);
+        assert_eq!(epd.chromatic_refresh_passes(), 2);
+
+        spi.take();
+        busy.0.set(0);
+        block_on(epd.update_color_frame(&mut spi, &black, &chromatic)).unwrap();
+        let mut expected = spi.take();
+        let waits = busy.0.get();
+        expected.extend([Command::DisplayRefresh as u8; 2]);
+
+        busy.0.set(0);
+        block_on(epd.update_and_display_color_frame(&mut spi, &black, &chromatic)).unwrap();
+        assert_eq!(spi.take(), expected);
+        assert_eq!(busy.0.get(), waits + 2);
+
+        epd.set_chromatic_refresh_passes(0);
+        assert_eq!(epd.chromatic_refresh_passes(), 1);
+        epd.set_chromatic_refresh_passes(u8::MAX);
+        assert_eq!(epd.chromatic_refresh_passes(), MAX_CHROMATIC_REFRESH_PASSES);
+    }
+}
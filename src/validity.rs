@@ -0,0 +1,960 @@
+//! Tracking whether the panel still shows what the driver sent to it
+//!
+//! Failed refreshes, SPI errors in the middle of a frame or a reset leave the panel showing
+//! something the driver can no longer vouch for. Partial updates build on the content of the
+//! panel and the RAM of the controller, so afterwards a full refresh is needed.
+//!
+//! [`Tracked`] wraps any driver and keeps its [`ImageValidity`] up to date. All transitions are
+//! defined by [`ImageValidity::next`].
+//!
+//! For three color panels it also knows whether the black/white RAM of the controller still
+//! holds the last full frame, so only the chromatic RAM needs to be sent for a new refresh, see
+//! [`Tracked::refresh_with_existing_achromatic`].
+//!
+#![doc = include_str!("../docs/image_validity.md")]
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::error::{ErrorKind, InitError};
+use crate::interface::{BusySignal, IoStats, ResetControl, Timing};
+use crate::strided::StridedSlice;
+use crate::traits::{
+    ChromaticPasses, ErrorType, GrayscaleLevels, PartialAlignment, RefreshLut,
+    WaveshareConstructor, WaveshareDisplay, WaveshareGrayscaleDisplay, WaveshareThreeColorDisplay,
+};
+
+/// What is known about the image on the panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageValidity {
+    /// The panel shows the last frame that was displayed
+    Valid,
+    /// The content of the panel or of the controller RAM isn't known, e.g. after a reset
+    Unknown,
+    /// A refresh failed, the panel may show a partially driven image
+    Corrupted,
+}
+
+/// Operations affecting the [`ImageValidity`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageEvent {
+    /// The driver was created, resetting the controller
+    Reset,
+    /// The display was put to sleep
+    Sleep,
+    /// The display woke up, the controller RAM is lost
+    WakeUp,
+    /// A refresh with the full look-up table succeeded
+    FullRefresh,
+    /// A refresh with the quick look-up table succeeded
+    PartialRefresh,
+    /// A transfer to the controller RAM or a register failed
+    TransferFailed,
+    /// A refresh failed or didn't finish
+    RefreshFailed,
+}
+
+impl ImageEvent {
+    /// Every event
+    pub const ALL: [ImageEvent; 7] = [
+        ImageEvent::Reset,
+        ImageEvent::Sleep,
+        ImageEvent::WakeUp,
+        ImageEvent::FullRefresh,
+        ImageEvent::PartialRefresh,
+        ImageEvent::TransferFailed,
+        ImageEvent::RefreshFailed,
+    ];
+}
+
+impl ImageValidity {
+    /// Every state
+    pub const ALL: [ImageValidity; 3] = [
+        ImageValidity::Valid,
+        ImageValidity::Unknown,
+        ImageValidity::Corrupted,
+    ];
+
+    /// The transition table
+    pub const fn next(self, event: ImageEvent) -> Self {
+        use ImageEvent::*;
+        use ImageValidity::*;
+        match (self, event) {
+            (_, Reset) => Unknown,
+            (state, Sleep) => state,
+            (Valid, WakeUp) => Unknown,
+            (state, WakeUp) => state,
+            (_, FullRefresh) => Valid,
+            // A partial refresh only changes the updated pixels
+            (state, PartialRefresh) => state,
+            // The panel is untouched but the RAM doesn't match it anymore
+            (Valid, TransferFailed) => Unknown,
+            (state, TransferFailed) => state,
+            (_, RefreshFailed) => Corrupted,
+        }
+    }
+
+    /// Whether the next refresh needs to be a full one
+    pub const fn needs_full_refresh(self) -> bool {
+        !matches!(self, ImageValidity::Valid)
+    }
+}
+
+/// Driver wrapper keeping track of the [`ImageValidity`]
+///
+/// Refreshes count as partial while the quick look-up table is selected with
+/// [`set_lut`](WaveshareDisplay::set_lut). A controller that [needs another
+/// initialisation](WaveshareDisplay::needs_init), e.g. after another display on a shared
+/// [`ResetLine`](crate::ResetLine) was reset, gets a `wake_up` before the next operation.
+pub struct Tracked<D> {
+    epd: D,
+    validity: ImageValidity,
+    quick_lut: bool,
+    /// Whether the black/white RAM holds a whole frame sent since the last reset
+    achromatic_known: bool,
+}
+
+impl<D> Tracked<D> {
+    /// Wraps a driver, the content of its panel is unknown
+    ///
+    /// [`WaveshareConstructor::new`] creates the driver and wraps it in one go.
+    pub fn wrap(epd: D) -> Self {
+        Tracked {
+            epd,
+            validity: ImageValidity::Unknown,
+            quick_lut: false,
+            achromatic_known: false,
+        }
+    }
+
+    /// What is known about the image on the panel
+    pub fn image_validity(&self) -> ImageValidity {
+        self.validity
+    }
+
+    /// Marks the panel content as unknown, e.g. after a brown-out detected by the application
+    pub fn invalidate(&mut self) {
+        self.validity = self.validity.next(ImageEvent::Reset);
+        self.achromatic_known = false;
+    }
+
+    /// The wrapped driver
+    ///
+    /// Operations on it aren't tracked.
+    pub fn inner(&mut self) -> &mut D {
+        &mut self.epd
+    }
+
+    /// Returns the wrapped driver
+    pub fn into_inner(self) -> D {
+        self.epd
+    }
+
+    /// Applies `ok` if the operation succeeded and `err` if it failed
+    fn track<T, E>(
+        &mut self,
+        result: Result<T, E>,
+        ok: Option<ImageEvent>,
+        err: ImageEvent,
+    ) -> Result<T, E> {
+        let event = if result.is_ok() { ok } else { Some(err) };
+        if let Some(event) = event {
+            self.validity = self.validity.next(event);
+        }
+        result
+    }
+
+    /// Keeps track of the black/white RAM, `whole` if a successful operation wrote all of it
+    fn track_achromatic<T, E>(&mut self, result: &Result<T, E>, whole: bool) {
+        match result {
+            Ok(_) => self.achromatic_known |= whole,
+            Err(_) => self.achromatic_known = false,
+        }
+    }
+
+    fn refresh_event(&self) -> ImageEvent {
+        if self.quick_lut {
+            ImageEvent::PartialRefresh
+        } else {
+            ImageEvent::FullRefresh
+        }
+    }
+}
+
+impl<D> Tracked<D> {
+    /// Runs a `wake_up` if the controller was reset behind the back of the driver
+    ///
+    /// The look-up table selected before is gone, like the RAM content.
+    async fn init_if_needed<SPI, BUSY, DC, RST>(&mut self, spi: &mut SPI) -> Result<(), D::Error>
+    where
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+        D: WaveshareDisplay<SPI, BUSY, DC, RST>,
+    {
+        if !self.epd.needs_init() {
+            return Ok(());
+        }
+        self.quick_lut = false;
+        self.validity = self.validity.next(ImageEvent::Reset);
+        self.achromatic_known = false;
+        self.epd.wake_up(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, D> ErrorType<SPI, BUSY, DC, RST> for Tracked<D>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: ErrorType<SPI, BUSY, DC, RST>,
+{
+    type Error = D::Error;
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareDisplay<SPI, BUSY, DC, RST> for Tracked<D>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareDisplay<SPI, BUSY, DC, RST>,
+{
+    type DisplayColor = D::DisplayColor;
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let result = self.epd.sleep(spi).await;
+        self.track(result, Some(ImageEvent::Sleep), ImageEvent::TransferFailed)
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let result = self.epd.wake_up(spi).await;
+        self.achromatic_known = false;
+        self.track(result, Some(ImageEvent::WakeUp), ImageEvent::Reset)
+    }
+
+    fn set_background_color(&mut self, color: Self::DisplayColor) {
+        self.epd.set_background_color(color)
+    }
+
+    fn background_color(&self) -> &Self::DisplayColor {
+        self.epd.background_color()
+    }
+
+    fn width(&self) -> u32 {
+        self.epd.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.epd.height()
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_frame(spi, buffer).await;
+        self.track_achromatic(&result, true);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self
+            .epd
+            .update_partial_frame(spi, buffer, x, y, width, height)
+            .await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    fn partial_alignment(&self) -> PartialAlignment {
+        self.epd.partial_alignment()
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.set_partial_window(spi, x, y, width, height).await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn write_partial_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let result = self.epd.write_partial_data(spi, data).await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn end_partial_update(&mut self, spi: &mut SPI) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let result = self.epd.end_partial_update(spi).await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_partial_frame_strided(
+        &mut self,
+        spi: &mut SPI,
+        window: StridedSlice<'_>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.init_if_needed(spi).await?;
+        let result = self
+            .epd
+            .update_partial_frame_strided(spi, window, x, y, width, height)
+            .await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_frame_stripe(
+        &mut self,
+        spi: &mut SPI,
+        stripe: &[u8],
+        y_start: u32,
+        stripe_height: u32,
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self
+            .epd
+            .update_frame_stripe(spi, stripe, y_start, stripe_height)
+            .await;
+        self.track_achromatic(&result, false);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn end_stripe(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.end_stripe(spi).await;
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.display_frame(spi).await;
+        self.track(
+            result,
+            Some(self.refresh_event()),
+            ImageEvent::RefreshFailed,
+        )
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_and_display_frame(spi, buffer).await;
+        self.track_achromatic(&result, true);
+        self.track(
+            result,
+            Some(self.refresh_event()),
+            ImageEvent::RefreshFailed,
+        )
+    }
+
+    async fn update_and_display_frame_no_wait(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_and_display_frame_no_wait(spi, buffer).await;
+        self.track_achromatic(&result, true);
+        self.track(
+            result,
+            Some(self.refresh_event()),
+            ImageEvent::RefreshFailed,
+        )
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.clear_frame(spi).await;
+        self.track_achromatic(&result, true);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.set_lut(spi, refresh_rate).await;
+        if result.is_ok() {
+            if let Some(refresh_rate) = refresh_rate {
+                self.quick_lut = refresh_rate == RefreshLut::Quick;
+            }
+        }
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let result = self.epd.wait_until_idle(spi).await;
+        self.track(result, None, ImageEvent::RefreshFailed)
+    }
+
+    fn is_busy(&mut self) -> bool {
+        self.epd.is_busy()
+    }
+
+    fn update_pending(&self) -> bool {
+        self.epd.update_pending()
+    }
+
+    fn verify_register_writes(&mut self, verify: bool) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.epd.verify_register_writes(verify)
+    }
+
+    fn needs_init(&self) -> bool {
+        self.epd.needs_init()
+    }
+
+    fn on_external_reset(&mut self) {
+        self.epd.on_external_reset();
+        self.quick_lut = false;
+        self.validity = self.validity.next(ImageEvent::Reset);
+        self.achromatic_known = false;
+    }
+
+    fn timing(&self) -> Timing {
+        self.epd.timing()
+    }
+
+    fn set_timing(&mut self, timing: Timing) {
+        self.epd.set_timing(timing)
+    }
+
+    fn io_stats(&self) -> IoStats {
+        self.epd.io_stats()
+    }
+
+    fn reset_io_stats(&mut self) {
+        self.epd.reset_io_stats()
+    }
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareConstructor<SPI, BUSY, DC, RST> for Tracked<D>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareConstructor<SPI, BUSY, DC, RST>,
+{
+    async fn try_new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay_us: Option<u32>,
+    ) -> Result<Self, InitError<Self::Error, BUSY, DC, RST>> {
+        Ok(Tracked::wrap(
+            D::try_new(spi, busy, dc, rst, delay_us).await?,
+        ))
+    }
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST> for Tracked<D>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_color_frame(spi, black, chromatic).await;
+        self.track_achromatic(&result, true);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_achromatic_frame(spi, black).await;
+        self.track_achromatic(&result, true);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_chromatic_frame(spi, chromatic).await;
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.init_if_needed(spi).await?;
+        let result = self
+            .epd
+            .update_partial_chromatic_frame(spi, chromatic, x, y, width, height)
+            .await;
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+
+    async fn refresh_and_wait(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.refresh_and_wait(spi).await;
+        self.track(
+            result,
+            Some(self.refresh_event()),
+            ImageEvent::RefreshFailed,
+        )
+    }
+
+    fn chromatic_passes(&self) -> ChromaticPasses {
+        self.epd.chromatic_passes()
+    }
+
+    fn chromatic_passes_mut(&mut self) -> &mut ChromaticPasses {
+        self.epd.chromatic_passes_mut()
+    }
+}
+
+impl<SPI, BUSY, DC, RST, D> WaveshareGrayscaleDisplay<SPI, BUSY, DC, RST> for Tracked<D>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: BusySignal,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: ResetControl,
+    RST::Error: Copy + Debug + Display,
+    D: WaveshareGrayscaleDisplay<SPI, BUSY, DC, RST>,
+{
+    fn supports_levels(&self, levels: GrayscaleLevels) -> bool {
+        self.epd.supports_levels(levels)
+    }
+
+    async fn update_grayscale_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        levels: GrayscaleLevels,
+    ) -> Result<(), Self::Error> {
+        self.init_if_needed(spi).await?;
+        let result = self.epd.update_grayscale_frame(spi, buffer, levels).await;
+        if result.is_ok() && levels != GrayscaleLevels::Two {
+            // the gray waveform replaced the quick one
+            self.quick_lut = false;
+        }
+        self.track_achromatic(&result, true);
+        self.track(result, None, ImageEvent::TransferFailed)
+    }
+}
+
+/// Refreshes sending only the chromatic RAM
+///
+/// The refresh of all three color controllers of this crate reads the black/white and the
+/// chromatic RAM and leaves both as they are, so a frame sent once can be combined with new
+/// chromatic content as often as needed:
+///
+/// | Driver | RAMs read by the refresh |
+/// | --- | --- |
+/// | [1in54b](crate::epd1in54b), [1in54c](crate::epd1in54c) | DTM1 and DTM2 |
+/// | [2in13bc](crate::epd2in13bc), [2in9bc](crate::epd2in9bc) | DTM1 and DTM2 |
+/// | [2in7b](crate::epd2in7b) | DTM1 and DTM2 |
+/// | [5in83b V2](crate::epd5in83b_v2), [7in5b V2](crate::epd7in5b_v2) | DTM1 and DTM2, in KWR mode |
+/// | [2in66b](crate::epd2in66b) | black/white RAM (0x24) and red RAM (0x26) |
+impl<D> Tracked<D> {
+    /// Sends the chromatic plane and refreshes, keeping the black/white RAM as it is
+    ///
+    /// Halves the traffic for content like a red ticker over a static black layout. Fails with
+    /// [`ErrorKind::AchromaticStale`] without sending anything unless the black/white RAM holds
+    /// a whole frame, sent with `update_frame`, `update_and_display_frame`, `clear_frame`,
+    /// `update_color_frame` or `update_achromatic_frame` since the last reset or wake up.
+    pub async fn refresh_with_existing_achromatic<SPI, BUSY, DC, RST>(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), D::Error>
+    where
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+        D: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>,
+        D::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.init_if_needed(spi).await?;
+        if !self.achromatic_known {
+            return Err(ErrorKind::AchromaticStale.into());
+        }
+        self.update_chromatic_frame(spi, chromatic).await?;
+        self.display_frame(spi).await
+    }
+
+    /// Like [`refresh_with_existing_achromatic`](Tracked::refresh_with_existing_achromatic),
+    /// sending only a window of the chromatic plane
+    ///
+    /// See [`update_partial_chromatic_frame`](WaveshareThreeColorDisplay::update_partial_chromatic_frame)
+    /// for the window, the rest of the chromatic RAM keeps its content. The refresh still covers
+    /// the whole panel, like every refresh of the chromatic particles.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn refresh_partial_with_existing_achromatic<SPI, BUSY, DC, RST>(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), D::Error>
+    where
+        SPI: SpiDevice,
+        SPI::Error: Copy + Debug + Display,
+        BUSY: BusySignal,
+        BUSY::Error: Copy + Debug + Display,
+        DC: OutputPin,
+        DC::Error: Copy + Debug + Display,
+        RST: ResetControl,
+        RST::Error: Copy + Debug + Display,
+        D: WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>,
+        D::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        self.init_if_needed(spi).await?;
+        if !self.achromatic_known {
+            return Err(ErrorKind::AchromaticStale.into());
+        }
+        self.update_partial_chromatic_frame(spi, chromatic, x, y, width, height)
+            .await?;
+        self.display_frame(spi).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::epd2in9bc::command::Command;
+    use crate::epd2in9bc::{self, Epd2in9bc};
+    use crate::interface::ResetLine;
+    use crate::test_utils::{
+        blank_buffer, block_on, contains, CountingPin, FailingBusy, IdleBusy, NoopPin, RecordingSpi,
+    };
+    use std::format;
+    use std::string::String;
+    use std::vec::Vec;
+    use ImageEvent::*;
+    use ImageValidity::*;
+
+    /// Renders the transitions of [`ImageValidity::next`] as a mermaid state diagram
+    fn state_diagram() -> String {
+        use core::fmt::Write;
+
+        let mut text = String::from(
+            "## States\n\n\
+             Generated from [`ImageValidity::next`] by the `state_diagram_is_up_to_date` test, \
+             regenerate it with `UPDATE_GOLDENS=1 cargo test state_diagram`.\n\n\
+             ```mermaid\nstateDiagram-v2\n    [*] --> Unknown: Tracked::wrap\n",
+        );
+        for from in ImageValidity::ALL {
+            for to in ImageValidity::ALL {
+                let events: Vec<_> = ImageEvent::ALL
+                    .iter()
+                    .filter(|&&event| from.next(event) == to)
+                    .map(|event| format!("{:?}", event))
+                    .collect();
+                if !events.is_empty() {
+                    writeln!(text, "    {:?} --> {:?}: {}", from, to, events.join(", ")).unwrap();
+                }
+            }
+        }
+        for state in ImageValidity::ALL {
+            if state.needs_full_refresh() {
+                writeln!(text, "    note right of {:?}: needs a full refresh", state).unwrap();
+            }
+        }
+        text.push_str("```\n");
+        text
+    }
+
+    #[test]
+    fn state_diagram_is_up_to_date() {
+        crate::golden::check_text("docs/image_validity.md", &state_diagram());
+    }
+
+    #[test]
+    fn transition_table() {
+        let table = [
+            (Valid, Reset, Unknown),
+            (Unknown, Reset, Unknown),
+            (Corrupted, Reset, Unknown),
+            (Valid, Sleep, Valid),
+            (Unknown, Sleep, Unknown),
+            (Corrupted, Sleep, Corrupted),
+            (Valid, WakeUp, Unknown),
+            (Unknown, WakeUp, Unknown),
+            (Corrupted, WakeUp, Corrupted),
+            (Valid, FullRefresh, Valid),
+            (Unknown, FullRefresh, Valid),
+            (Corrupted, FullRefresh, Valid),
+            (Valid, PartialRefresh, Valid),
+            (Unknown, PartialRefresh, Unknown),
+            (Corrupted, PartialRefresh, Corrupted),
+            (Valid, TransferFailed, Unknown),
+            (Unknown, TransferFailed, Unknown),
+            (Corrupted, TransferFailed, Corrupted),
+            (Valid, RefreshFailed, Corrupted),
+            (Unknown, RefreshFailed, Corrupted),
+            (Corrupted, RefreshFailed, Corrupted),
+        ];
+        assert_eq!(table.len(), 3 * 7);
+        for (state, event, expected) in table {
+            assert_eq!(state.next(event), expected, "{:?} + {:?}", state, event);
+            assert_eq!(expected.needs_full_refresh(), expected != Valid);
+        }
+    }
+
+    #[test]
+    fn tracked_driver() {
+        let busy = FailingBusy::default();
+        let mut spi = RecordingSpi::default();
+        let mut epd = Tracked::wrap(
+            block_on(Epd2in9bc::new(
+                &mut spi,
+                busy.clone(),
+                NoopPin,
+                NoopPin,
+                Some(0),
+            ))
+            .unwrap(),
+        );
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        assert_eq!(epd.image_validity(), Unknown);
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert_eq!(epd.image_validity(), Unknown);
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert_eq!(epd.image_validity(), Valid);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(epd.image_validity(), Valid);
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(epd.image_validity(), Unknown);
+
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        busy.0.set(true);
+        assert!(block_on(epd.display_frame(&mut spi)).is_err());
+        assert_eq!(epd.image_validity(), Corrupted);
+    }
+
+    #[test]
+    fn shared_reset_line() {
+        let pin = CountingPin::default();
+        let line = ResetLine::new(pin.clone());
+        let mut spi = RecordingSpi::default();
+        let mut first: Tracked<Epd2in9bc<_, _, _, _>> = block_on(Tracked::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            line.handle(),
+            Some(0),
+        ))
+        .unwrap();
+        let mut second: Tracked<Epd2in9bc<_, _, _, _>> = block_on(Tracked::new(
+            &mut spi,
+            IdleBusy,
+            NoopPin,
+            line.handle(),
+            Some(0),
+        ))
+        .unwrap();
+        // the second display was reset by the pulse of the first one
+        assert_eq!(pin.0.get(), 1);
+        assert!(!first.needs_init());
+        assert!(!second.needs_init());
+
+        block_on(second.wake_up(&mut spi)).unwrap();
+        assert_eq!(pin.0.get(), 2);
+        assert!(first.needs_init());
+        assert!(!second.needs_init());
+        spi.take();
+
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        block_on(first.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        let written = spi.take();
+        assert!(contains(&written, &[Command::PowerOn as u8]));
+        assert_eq!(pin.0.get(), 2);
+        assert!(!first.needs_init());
+        assert!(!second.needs_init());
+        assert_eq!(first.image_validity(), Valid);
+    }
+
+    #[test]
+    fn external_reset() {
+        let mut spi = RecordingSpi::default();
+        let mut epd = Tracked::wrap(
+            block_on(Epd2in9bc::new(
+                &mut spi,
+                IdleBusy,
+                NoopPin,
+                NoopPin,
+                Some(0),
+            ))
+            .unwrap(),
+        );
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        spi.take();
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert!(!contains(&spi.take(), &[Command::PowerOn as u8]));
+        assert_eq!(epd.image_validity(), Unknown);
+
+        epd.on_external_reset();
+        assert!(epd.needs_init());
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert!(contains(&spi.take(), &[Command::PowerOn as u8]));
+        assert!(!epd.needs_init());
+        // the quick look-up table was lost with the reset
+        assert_eq!(epd.image_validity(), Valid);
+    }
+
+    #[test]
+    fn chromatic_only_refresh() {
+        use crate::epd2in7b::Epd2in7b;
+
+        let mut spi = RecordingSpi::default();
+        let mut epd: Tracked<Epd2in9bc<_, _, _, _>> =
+            block_on(Tracked::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        let buffer = blank_buffer(epd2in9bc::WIDTH, epd2in9bc::HEIGHT);
+        spi.take();
+
+        // nothing known about the black/white RAM after the reset
+        assert!(matches!(
+            block_on(epd.refresh_with_existing_achromatic(&mut spi, &buffer)),
+            Err(ErrorKind::AchromaticStale)
+        ));
+        assert!(spi.take().is_empty());
+
+        block_on(epd.update_color_frame(&mut spi, &buffer, &buffer)).unwrap();
+        spi.take();
+        block_on(epd.refresh_with_existing_achromatic(&mut spi, &buffer)).unwrap();
+        let written = spi.take();
+        assert_eq!(written[0], Command::DataStartTransmission2 as u8);
+        assert!(!written.contains(&(Command::DataStartTransmission1 as u8)));
+        assert!(contains(&written, &[Command::DisplayRefresh as u8]));
+        assert_eq!(epd.image_validity(), Valid);
+
+        // the controller has no partial windows
+        assert!(matches!(
+            block_on(epd.refresh_partial_with_existing_achromatic(&mut spi, &[0; 2], 0, 0, 8, 2)),
+            Err(ErrorKind::InvalidArgument)
+        ));
+
+        // the RAM is lost with deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(matches!(
+            block_on(epd.refresh_with_existing_achromatic(&mut spi, &buffer)),
+            Err(ErrorKind::AchromaticStale)
+        ));
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert!(block_on(epd.refresh_with_existing_achromatic(&mut spi, &buffer)).is_ok());
+        epd.on_external_reset();
+        assert!(matches!(
+            block_on(epd.refresh_with_existing_achromatic(&mut spi, &buffer)),
+            Err(ErrorKind::AchromaticStale)
+        ));
+
+        // a window of the chromatic RAM only
+        let mut spi = RecordingSpi::default();
+        let mut epd: Tracked<Epd2in7b<_, _, _, _>> =
+            block_on(Tracked::new(&mut spi, IdleBusy, NoopPin, NoopPin, Some(0))).unwrap();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        spi.take();
+        block_on(epd.refresh_partial_with_existing_achromatic(&mut spi, &[0; 6], 8, 24, 24, 2))
+            .unwrap();
+        let written = spi.take();
+        assert_eq!(written[..9], [0x15, 0, 8, 0, 24, 0, 24, 0, 2]);
+        assert!(!written.contains(&0x10) && !written.contains(&0x14));
+        assert!(contains(&written, &[0x12]));
+    }
+}
@@ -0,0 +1,588 @@
+//! Frames prepared on a host, in the byte format sent to the panel
+//!
+//! Most controllers take the 1 bit per pixel buffer of a [`Display`](crate::graphics::Display)
+//! as is, others like the [7in5](crate::epd7in5) expect a different format on the wire. A
+//! [`Panel`] knows how its driver transforms a buffer, [`Panel::wire_bytes`] is the code path
+//! used by the drivers themselves.
+//!
+//! The `epd-wire` tool (feature `cli`) converts images to such frames on the host, prefixed with
+//! a small header. Constrained targets then only embed the file and check it against their
+//! panel, at compile time if used in a constant:
+//!
+//! ```rust, ignore
+//! use epd_waveshare_async::{epd7in5::*, wire::{self, WireFrame}};
+//!
+//! const SPLASH: WireFrame = WireFrame::expect(include_bytes!("splash.bin"), &wire::EPD7IN5);
+//!
+//! epd.update_frame_wire(&mut spi, SPLASH).await?;
+//! // panels that take the buffer as is, like the 4in2
+//! epd4in2.update_frame(&mut spi, SPLASH_4IN2.data()).await?;
+//! ```
+//!
+//! # Header
+//!
+//! | Bytes | Content |
+//! | :---: | --- |
+//! | 0..4 | [`MAGIC`] |
+//! | 4 | [`FORMAT_VERSION`] |
+//! | 5..8 | reserved, 0 |
+//! | 8..20 | panel id, the name of the driver module padded with 0 |
+//! | 20..24 | length of the following data, little endian |
+//!
+//! # Output order
+//!
+//! The same module can come with the glass mounted the other way round relative to the flex.
+//! Instead of transforming every frame, drivers supporting it take a [`WireOrder`] and send the
+//! rows bottom up or the bytes of each row in reverse. The order applies to the bytes of the
+//! 1 bit per pixel buffer, before the driver's own transformation: every byte keeps its pixel
+//! order, the 7in5 still expands it first pixel first. Partial windows are reordered within the
+//! window and moved to their place in the reordered frame, see [`WireOrder::remap_window`].
+
+use crate::color::Color;
+use crate::power::{self, PowerProfile};
+use crate::strided::StridedSlice;
+
+/// First bytes of every frame
+pub const MAGIC: [u8; 4] = *b"EPDW";
+/// Version of the header and the data formats, bumped whenever one of them changes
+pub const FORMAT_VERSION: u8 = 1;
+/// Length of the header in front of the data
+pub const HEADER_LEN: usize = 24;
+/// Longest panel id fitting in the header
+pub const MAX_ID_LEN: usize = 12;
+
+/// How a driver sends a 1 bit per pixel buffer to its controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireTransform {
+    /// The buffer is sent as is
+    Packed,
+    /// Every pixel is sent as a 4 bit nibble, see [`crate::epd7in5::consts`]
+    Nibbles,
+}
+
+/// Order of the rows of a frame on the wire
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RowOrder {
+    /// First row first, as in the buffer
+    #[default]
+    TopDown,
+    /// Last row first
+    BottomUp,
+}
+
+/// Order of the bytes within a row on the wire
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ByteOrderInRow {
+    /// First byte first, as in the buffer
+    #[default]
+    Forward,
+    /// Last byte first, the pixels within a byte keep their order
+    Reverse,
+}
+
+/// Order a driver sends the bytes of a buffer in, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WireOrder {
+    /// Order of the rows
+    pub rows: RowOrder,
+    /// Order of the bytes within a row
+    pub bytes: ByteOrderInRow,
+}
+
+impl WireOrder {
+    /// Whether the buffer is sent as is
+    pub const fn is_identity(&self) -> bool {
+        matches!(self.rows, RowOrder::TopDown) && matches!(self.bytes, ByteOrderInRow::Forward)
+    }
+
+    /// Index in a buffer with rows of `row_len` bytes of the byte sent as the `index`th one
+    pub const fn source_index(&self, index: usize, row_len: usize, rows: usize) -> usize {
+        let (row, column) = (index / row_len, index % row_len);
+        let row = match self.rows {
+            RowOrder::TopDown => row,
+            RowOrder::BottomUp => rows - 1 - row,
+        };
+        let column = match self.bytes {
+            ByteOrderInRow::Forward => column,
+            ByteOrderInRow::Reverse => row_len - 1 - column,
+        };
+        row * row_len + column
+    }
+
+    /// Position of a window on a panel of `panel_width` × `panel_height` pixels, once the
+    /// frame is sent in this order
+    ///
+    /// Reversed bytes mirror the window at whole bytes, so `x` and `width` need to be
+    /// multiples of 8 like for the controllers taking such windows. Returns the new `x` and `y`,
+    /// the size stays the same.
+    pub const fn remap_window(
+        &self,
+        panel_width: u32,
+        panel_height: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> (u32, u32) {
+        let x = match self.bytes {
+            ByteOrderInRow::Forward => x,
+            ByteOrderInRow::Reverse => {
+                (panel_width.div_ceil(8) * 8).saturating_sub(x.saturating_add(width))
+            }
+        };
+        let y = match self.rows {
+            RowOrder::TopDown => y,
+            RowOrder::BottomUp => panel_height.saturating_sub(y.saturating_add(height)),
+        };
+        (x, y)
+    }
+}
+
+/// A black and white panel and the format of its frames on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Panel {
+    /// Name of the driver module, e.g. `epd4in2`
+    pub id: &'static str,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Transformation of the buffer done by the driver
+    pub transform: WireTransform,
+}
+
+macro_rules! panels {
+    ($($name:ident: $driver:ident, $transform:ident;)*) => {
+        $(
+            #[doc = concat!("The panel of [`", stringify!($driver), "`](crate::", stringify!($driver), ")")]
+            pub const $name: Panel = Panel {
+                id: stringify!($driver),
+                width: crate::$driver::WIDTH,
+                height: crate::$driver::HEIGHT,
+                transform: WireTransform::$transform,
+            };
+        )*
+
+        /// Every supported black and white panel
+        pub const PANELS: &[Panel] = &[$($name),*];
+    };
+}
+
+panels! {
+    EPD1IN02: epd1in02, Packed;
+    EPD1IN54: epd1in54, Packed;
+    EPD1IN54_V2: epd1in54_v2, Packed;
+    EPD2IN13_V2: epd2in13_v2, Packed;
+    EPD2IN13_V3: epd2in13_v3, Packed;
+    EPD2IN9: epd2in9, Packed;
+    EPD2IN9_V2: epd2in9_v2, Packed;
+    EPD2IN9D: epd2in9d, Packed;
+    EPD3IN7: epd3in7, Packed;
+    EPD3IN71: epd3in71, Packed;
+    EPD4IN2: epd4in2, Packed;
+    EPD5IN83_V2: epd5in83_v2, Packed;
+    EPD7IN5: epd7in5, Nibbles;
+    EPD7IN5_HD: epd7in5_hd, Packed;
+    EPD7IN5_V2: epd7in5_v2, Packed;
+}
+
+const _: () = {
+    let mut index = 0;
+    while index < PANELS.len() {
+        assert!(PANELS[index].id.len() <= MAX_ID_LEN);
+        index += 1;
+    }
+};
+
+impl Panel {
+    /// Looks up a panel by its id
+    pub fn find(id: &str) -> Option<&'static Panel> {
+        PANELS.iter().find(|panel| panel.id == id)
+    }
+
+    /// Length of a 1 bit per pixel buffer of the panel
+    pub const fn buffer_len(&self) -> usize {
+        crate::buffer_len(self.width as usize, self.height as usize)
+    }
+
+    /// Length of a frame on the wire, without the header
+    pub const fn wire_len(&self) -> usize {
+        match self.transform {
+            WireTransform::Packed => self.buffer_len(),
+            WireTransform::Nibbles => self.buffer_len() * 4,
+        }
+    }
+
+    /// Typical power consumption of the panel
+    pub fn power_profile(&self) -> Option<&'static PowerProfile> {
+        power::profile(self.id)
+    }
+
+    /// The bytes the driver sends for `buffer`
+    pub fn wire_bytes<'a>(&self, buffer: &'a [u8]) -> WireBytes<'a> {
+        WireBytes::new(self.transform, buffer)
+    }
+
+    /// The bytes the driver sends for `buffer` with the output order `order`
+    pub fn wire_bytes_ordered<'a>(&self, buffer: &'a [u8], order: WireOrder) -> WireBytes<'a> {
+        WireBytes::ordered(
+            self.transform,
+            buffer,
+            self.width.div_ceil(8) as usize,
+            order,
+        )
+    }
+
+    /// The header of a frame of this panel
+    pub fn header(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = FORMAT_VERSION;
+        header[8..8 + self.id.len()].copy_from_slice(self.id.as_bytes());
+        header[20..].copy_from_slice(&(self.wire_len() as u32).to_le_bytes());
+        header
+    }
+}
+
+/// Iterator over the bytes sent for a buffer, see [`Panel::wire_bytes`]
+#[derive(Debug, Clone)]
+pub struct WireBytes<'a> {
+    transform: WireTransform,
+    buffer: &'a [u8],
+    order: WireOrder,
+    row_len: usize,
+    // distance between the rows in the buffer, the same as row_len unless sending a window
+    stride: usize,
+    rows: usize,
+    // bytes sent from the buffer, the rows without their gaps and a trailing partial row
+    len: usize,
+    // index of the next byte of the buffer, in the order sent
+    next: usize,
+    // remaining pixels of the current byte, for transformations sending several bytes per byte
+    current: u8,
+    pending: u8,
+}
+
+impl<'a> WireBytes<'a> {
+    /// Transforms `buffer`
+    pub fn new(transform: WireTransform, buffer: &'a [u8]) -> Self {
+        Self::ordered(transform, buffer, buffer.len(), WireOrder::default())
+    }
+
+    /// Transforms `buffer` made of rows of `row_len` bytes, sent in `order`
+    ///
+    /// Reads the buffer in place, a trailing partial row is sent as is.
+    pub fn ordered(
+        transform: WireTransform,
+        buffer: &'a [u8],
+        row_len: usize,
+        order: WireOrder,
+    ) -> Self {
+        let row_len = row_len.max(1);
+        WireBytes {
+            transform,
+            buffer,
+            order,
+            row_len,
+            stride: row_len,
+            rows: buffer.len() / row_len,
+            len: buffer.len(),
+            next: 0,
+            current: 0,
+            pending: 0,
+        }
+    }
+
+    /// Transforms the rows of `window`, sent in `order` within the window
+    ///
+    /// Walks the rows in place, skipping the gaps between them.
+    pub fn strided(transform: WireTransform, window: StridedSlice<'a>, order: WireOrder) -> Self {
+        WireBytes {
+            transform,
+            buffer: window.data(),
+            order,
+            row_len: window.row_len().max(1),
+            stride: window.stride(),
+            rows: window.row_count(),
+            len: window.len(),
+            next: 0,
+            current: 0,
+            pending: 0,
+        }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let index = self.next;
+        if index >= self.len {
+            return None;
+        }
+        self.next += 1;
+        if index >= self.rows * self.row_len {
+            return Some(self.buffer[index]);
+        }
+        let index = self.order.source_index(index, self.row_len, self.rows);
+        Some(self.buffer[index / self.row_len * self.stride + index % self.row_len])
+    }
+}
+
+impl Iterator for WireBytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        match self.transform {
+            WireTransform::Packed => self.next_byte(),
+            WireTransform::Nibbles => {
+                use crate::epd7in5::consts::nibble;
+
+                if self.pending == 0 {
+                    self.current = self.next_byte()?;
+                    self.pending = 4;
+                }
+                // two pixels per byte, the first one in the upper nibble
+                let first = nibble(Color::from(self.current >> 7));
+                let second = nibble(Color::from(self.current >> 6 & 1));
+                self.current <<= 2;
+                self.pending -= 1;
+                Some(first << 4 | second)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.next;
+        let len = match self.transform {
+            WireTransform::Packed => remaining,
+            WireTransform::Nibbles => remaining * 4 + self.pending as usize,
+        };
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for WireBytes<'_> {}
+
+/// A frame that doesn't fit a panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+    /// The frame is shorter than its header or its length
+    TooShort,
+    /// The frame doesn't start with [`MAGIC`]
+    NoFrame,
+    /// The frame was written with another [`FORMAT_VERSION`]
+    Version(u8),
+    /// The frame was made for another panel
+    WrongPanel,
+    /// The length in the header doesn't match the panel
+    Length,
+}
+
+/// A frame with its header, e.g. embedded with `include_bytes!`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireFrame<'a> {
+    id: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> WireFrame<'a> {
+    /// Reads the header of `bytes`
+    pub const fn parse(bytes: &'a [u8]) -> Result<Self, WireError> {
+        let Some((header, data)) = bytes.split_at_checked(HEADER_LEN) else {
+            return Err(WireError::TooShort);
+        };
+        if header[0] != MAGIC[0]
+            || header[1] != MAGIC[1]
+            || header[2] != MAGIC[2]
+            || header[3] != MAGIC[3]
+        {
+            return Err(WireError::NoFrame);
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(WireError::Version(header[4]));
+        }
+
+        let (_, id) = header.split_at(8);
+        let (mut id, len) = id.split_at(MAX_ID_LEN);
+        while let [rest @ .., 0] = id {
+            id = rest;
+        }
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let Some((data, _)) = data.split_at_checked(len) else {
+            return Err(WireError::TooShort);
+        };
+        Ok(WireFrame { id, data })
+    }
+
+    /// Checks that the frame was made for `panel`
+    pub const fn check(self, panel: &Panel) -> Result<Self, WireError> {
+        let id = panel.id.as_bytes();
+        if id.len() != self.id.len() {
+            return Err(WireError::WrongPanel);
+        }
+        let mut index = 0;
+        while index < id.len() {
+            if id[index] != self.id[index] {
+                return Err(WireError::WrongPanel);
+            }
+            index += 1;
+        }
+        if self.data.len() != panel.wire_len() {
+            return Err(WireError::Length);
+        }
+        Ok(self)
+    }
+
+    /// Parses and checks `bytes`, panicking if they don't hold a frame for `panel`
+    ///
+    /// In a constant the check happens at compile time.
+    pub const fn expect(bytes: &'a [u8], panel: &Panel) -> Self {
+        match WireFrame::parse(bytes) {
+            Ok(frame) => match frame.check(panel) {
+                Ok(frame) => frame,
+                Err(WireError::WrongPanel) => panic!("the frame was made for another panel"),
+                Err(_) => panic!("the frame has the wrong length for the panel"),
+            },
+            Err(WireError::Version(_)) => panic!("the frame has another format version"),
+            Err(_) => panic!("not a frame"),
+        }
+    }
+
+    /// Id of the panel the frame was made for
+    pub fn panel_id(&self) -> &'a str {
+        core::str::from_utf8(self.id).unwrap_or("")
+    }
+
+    /// The bytes to send to the panel
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn nibbles_match_the_7in5_encoding() {
+        let wire: [u8; 8] = {
+            let mut wire = [0; 8];
+            for (target, byte) in wire
+                .iter_mut()
+                .zip(EPD7IN5.wire_bytes(&[0b1000_0001, 0xFF]))
+            {
+                *target = byte;
+            }
+            wire
+        };
+        assert_eq!(wire, [0x30, 0x00, 0x00, 0x03, 0x33, 0x33, 0x33, 0x33]);
+        assert_eq!(EPD7IN5.wire_bytes(&[0; 3]).len(), 12);
+        assert_eq!(EPD7IN5.wire_len(), 640 * 384 / 2);
+        assert!(EPD4IN2.wire_bytes(&[1, 2, 3]).eq([1, 2, 3]));
+    }
+
+    #[test]
+    fn every_order_on_a_small_frame() {
+        // 16 × 3 pixels, two bytes per row
+        let buffer = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        let panel = Panel {
+            id: "test",
+            width: 16,
+            height: 3,
+            transform: WireTransform::Packed,
+        };
+        let cases = [
+            (
+                RowOrder::TopDown,
+                ByteOrderInRow::Forward,
+                [1, 2, 3, 4, 5, 6],
+            ),
+            (
+                RowOrder::TopDown,
+                ByteOrderInRow::Reverse,
+                [2, 1, 4, 3, 6, 5],
+            ),
+            (
+                RowOrder::BottomUp,
+                ByteOrderInRow::Forward,
+                [5, 6, 3, 4, 1, 2],
+            ),
+            (
+                RowOrder::BottomUp,
+                ByteOrderInRow::Reverse,
+                [6, 5, 4, 3, 2, 1],
+            ),
+        ];
+        for (rows, bytes, expected) in cases {
+            let order = WireOrder { rows, bytes };
+            let wire = panel.wire_bytes_ordered(&buffer, order);
+            assert_eq!(wire.len(), 6);
+            assert!(wire.eq(expected), "{:?}", order);
+
+            // the nibble expansion works on the reordered bytes
+            let nibbles = Panel {
+                transform: WireTransform::Nibbles,
+                ..panel
+            };
+            let expanded = nibbles.wire_bytes_ordered(&buffer, order);
+            assert_eq!(expanded.len(), 24);
+            assert!(expanded.eq(expected
+                .iter()
+                .flat_map(|&byte| EPD7IN5.wire_bytes(&[byte]).collect::<std::vec::Vec<_>>())));
+        }
+        assert!(WireOrder::default().is_identity());
+    }
+
+    #[test]
+    fn windows_follow_the_order() {
+        let reversed = WireOrder {
+            rows: RowOrder::BottomUp,
+            bytes: ByteOrderInRow::Reverse,
+        };
+        // 20 pixels wide rows take 3 bytes, the window mirrors at 24
+        assert_eq!(reversed.remap_window(20, 10, 8, 2, 8, 3), (8, 5));
+        assert_eq!(reversed.remap_window(20, 10, 0, 0, 24, 10), (0, 0));
+        assert_eq!(
+            WireOrder::default().remap_window(20, 10, 8, 2, 8, 3),
+            (8, 2)
+        );
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let panel = Panel {
+            id: "epd2in9",
+            width: 16,
+            height: 2,
+            transform: WireTransform::Packed,
+        };
+        let mut bytes = [0xAA; HEADER_LEN + 4];
+        bytes[..HEADER_LEN].copy_from_slice(&panel.header());
+
+        let frame = WireFrame::parse(&bytes).unwrap();
+        assert_eq!(frame.panel_id(), "epd2in9");
+        assert_eq!(frame.data(), [0xAA; 4]);
+        assert_eq!(frame.check(&panel), Ok(frame));
+        assert_eq!(frame.check(&EPD2IN9), Err(WireError::Length));
+        assert_eq!(frame.check(&EPD2IN9D), Err(WireError::WrongPanel));
+
+        assert_eq!(
+            WireFrame::parse(&bytes[..HEADER_LEN + 3]),
+            Err(WireError::TooShort)
+        );
+        bytes[4] = FORMAT_VERSION + 1;
+        assert_eq!(
+            WireFrame::parse(&bytes),
+            Err(WireError::Version(FORMAT_VERSION + 1))
+        );
+        bytes[0] = b'X';
+        assert_eq!(WireFrame::parse(&bytes), Err(WireError::NoFrame));
+    }
+
+    #[test]
+    fn panels_are_unique() {
+        for (index, panel) in PANELS.iter().enumerate() {
+            assert_eq!(Panel::find(panel.id), Some(&PANELS[index]));
+        }
+        assert_eq!(Panel::find("epd0in00"), None);
+    }
+}